@@ -0,0 +1,101 @@
+//! The `bench` subcommand: replay a quote corpus against the verifier and report latencies.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use dcap_qvl::collateral::{get_collateral_from_pcs_sync, get_collateral_sync};
+use dcap_qvl::formats::{parse_envelope, Envelope};
+use dcap_qvl::verify::verify;
+
+/// Load a corpus directory of envelopes and quote files, fetching collateral for bare quotes once.
+pub fn load_corpus(dir: &Path) -> Result<Vec<Envelope>> {
+    let pccs_url = std::env::var("PCCS_URL").unwrap_or_default();
+    let mut paths = std::fs::read_dir(dir)
+        .context("Failed to read corpus directory")?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    paths.sort();
+    let mut corpus = Vec::new();
+    for path in paths.iter().filter(|path| path.is_file()) {
+        let data = std::fs::read(path).context("Failed to read corpus file")?;
+        if let Ok(envelope) = parse_envelope(&data) {
+            corpus.push(envelope);
+            continue;
+        }
+        let quote = super::hex_decode(&data, false)
+            .with_context(|| format!("Failed to decode {}", path.display()))?;
+        let timeout = Duration::from_secs(60);
+        let collateral = if pccs_url.is_empty() {
+            get_collateral_from_pcs_sync(&quote, timeout)
+        } else {
+            get_collateral_sync(&pccs_url, &quote, timeout)
+        }
+        .with_context(|| format!("Failed to get collateral for {}", path.display()))?;
+        corpus.push(Envelope { quote, collateral });
+    }
+    if corpus.is_empty() {
+        bail!("Corpus is empty");
+    }
+    Ok(corpus)
+}
+
+/// Verify `requests` quotes from the corpus round robin at up to `rate` per second and print
+/// the latency distribution.
+pub async fn run(
+    corpus: Vec<Envelope>,
+    requests: usize,
+    rate: Option<f64>,
+    now: u64,
+) -> Result<()> {
+    if requests == 0 {
+        bail!("At least one request is needed");
+    }
+    let corpus = Arc::new(corpus);
+    let interval = rate
+        .filter(|rate| *rate > 0.0)
+        .map(|rate| Duration::from_secs_f64(1.0 / rate));
+    let started = Instant::now();
+    let mut tasks = Vec::with_capacity(requests);
+    for index in 0..requests {
+        if let Some(interval) = interval {
+            // Schedule on an absolute timeline so slow iterations don't lower the rate
+            tokio::time::sleep_until((started + interval.mul_f64(index as f64)).into()).await;
+        }
+        let corpus = corpus.clone();
+        tasks.push(tokio::task::spawn_blocking(move || {
+            let envelope = &corpus[index % corpus.len()];
+            let start = Instant::now();
+            let result = verify(&envelope.quote, &envelope.collateral, now);
+            (start.elapsed(), result.is_ok())
+        }));
+    }
+    let mut latencies = Vec::with_capacity(requests);
+    let mut failures = 0;
+    for task in tasks {
+        let (latency, ok) = task.await.context("Failed to join verification task")?;
+        latencies.push(latency);
+        if !ok {
+            failures += 1;
+        }
+    }
+    let elapsed = started.elapsed();
+    latencies.sort();
+
+    let percentile = |p: usize| latencies[(latencies.len() - 1) * p / 100];
+    let report = serde_json::json!({
+        "requests": requests,
+        "failures": failures,
+        "elapsed_ms": elapsed.as_secs_f64() * 1e3,
+        "throughput": requests as f64 / elapsed.as_secs_f64(),
+        "latency_ms": {
+            "p50": percentile(50).as_secs_f64() * 1e3,
+            "p90": percentile(90).as_secs_f64() * 1e3,
+            "p99": percentile(99).as_secs_f64() * 1e3,
+            "max": percentile(100).as_secs_f64() * 1e3,
+        },
+    });
+    println!("{report}");
+    Ok(())
+}
@@ -0,0 +1,49 @@
+//! Lenient decoding of quotes in the textual forms partner systems send them in.
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+use base64::Engine as _;
+
+/// JSON fields a wrapped quote is looked up in, in order.
+const QUOTE_FIELDS: [&str; 4] = ["quote", "evidence", "tdx_quote", "sgx_quote"];
+
+/// Decode a textual quote: hex (with `0x` prefix and embedded whitespace allowed), base64 or
+/// base64url, a `data:` URI, or a JSON string or object with a `quote` field holding any of these.
+pub fn decode_text(input: &str) -> Result<Vec<u8>> {
+    let input = input.trim();
+    if input.starts_with('{') || input.starts_with('"') {
+        let value: serde_json::Value =
+            serde_json::from_str(input).context("Failed to parse JSON wrapped quote")?;
+        let quote = match &value {
+            serde_json::Value::String(quote) => quote,
+            _ => QUOTE_FIELDS
+                .iter()
+                .find_map(|field| value.get(field)?.as_str())
+                .context("No quote field in JSON")?,
+        };
+        return decode_text(quote);
+    }
+    if let Some(uri) = input.strip_prefix("data:") {
+        let (media_type, data) = uri.split_once(',').context("Invalid data URI")?;
+        if !media_type.ends_with(";base64") {
+            return decode_text(data);
+        }
+        return decode_base64(data);
+    }
+    let compact = input.split_whitespace().collect::<String>();
+    let hex_input = compact.strip_prefix("0x").unwrap_or(&compact);
+    if hex_input.len() % 2 == 0 && hex_input.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return hex::decode(hex_input).context("Failed to decode hex quote");
+    }
+    decode_base64(&compact)
+}
+
+fn decode_base64(input: &str) -> Result<Vec<u8>> {
+    let input = input.split_whitespace().collect::<String>();
+    for engine in [STANDARD, URL_SAFE, STANDARD_NO_PAD, URL_SAFE_NO_PAD] {
+        if let Ok(data) = engine.decode(&input) {
+            return Ok(data);
+        }
+    }
+    bail!("Quote is neither hex nor base64")
+}
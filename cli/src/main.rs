@@ -3,14 +3,40 @@
 //! dcap-qvl decode-quote [--hex] <quote_file>
 //!
 //! cargo run collateral 1.bin
+//! cargo run collateral --fmspc 00906ED50000 --tee sgx
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use anyhow::{Context as _, Result};
-use clap::{Args, Parser, Subcommand};
-use dcap_qvl::collateral::{get_collateral, get_collateral_from_pcs};
-use dcap_qvl::quote::Quote;
-use dcap_qvl::verify::verify;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use dcap_qvl::canonical;
+use dcap_qvl::collateral::{
+    get_collateral_cached_sync, get_collateral_for_fmspc_with_options_sync,
+    get_collateral_with_options_sync, pcs_url_for, verify_online_blocking, CollateralCache,
+    CollateralOptions, CollateralProvider, OnlineOptions, TcbUpdate, ThimConfig,
+};
+use dcap_qvl::eventlog::{verify_rtmrs, EventLog};
+use dcap_qvl::formats::parse_envelope;
+use dcap_qvl::mock::MockQuoteBuilder;
+use dcap_qvl::platform_family::platform_family;
+use dcap_qvl::qe_auth::QeAuthDataRegistry;
+use dcap_qvl::quote::{ClassifiedQuote, Quote, TeeType};
+use dcap_qvl::ra_tls;
+use dcap_qvl::record::{Detail, VerdictRecord};
+use dcap_qvl::store::FileStore;
+use dcap_qvl::verify::{verify_collateral, verify_traced, VerifiedReport, VerifyOptions};
+use dcap_qvl::QuoteCollateralV3;
+use output::{OutputArgs, SaveArgs};
+
+#[cfg(feature = "bench")]
+mod bench;
+mod input;
+mod output;
+mod ratls;
+mod remote;
+mod self_test;
+mod support;
 
 #[derive(Parser)]
 struct Cli {
@@ -24,8 +50,33 @@ enum Commands {
     Decode(DecodeQuoteArgs),
     /// Verify a quote file
     Verify(VerifyQuoteArgs),
+    /// Print a single field of a quote as hex, for scripts
+    Extract(ExtractFieldArgs),
     /// Get quote collateral
     Collateral(CollateralQuoteArgs),
+    /// Zero report_data and QE auth data so the quote can be shared publicly
+    Redact(RedactQuoteArgs),
+    /// Output the SHA-256 digests of the canonical bytes of a quote and of its collateral, to use
+    /// as their identifiers
+    Canonicalize(CanonicalizeArgs),
+    /// Verify the quote in the RA-TLS certificate of a TLS endpoint
+    RatlsProbe(RatlsProbeArgs),
+    /// Extract the quote from an RA-TLS certificate file
+    ExtractQuote(ExtractQuoteArgs),
+    /// Verify the quote of an RA-TLS certificate file and its binding to the certificate key
+    VerifyCert(VerifyCertArgs),
+    /// Replay a quote corpus against the verifier and report latency percentiles
+    #[cfg(feature = "bench")]
+    Bench(BenchArgs),
+    /// Package a quote, its collateral, the verification outcome and environment info into a
+    /// tarball to attach to bug reports
+    SupportBundle(SupportBundleArgs),
+    /// Generate a synthetic quote and its collateral, signed by a throwaway root CA, to test
+    /// pipelines without TEE hardware
+    MockQuote(MockQuoteArgs),
+    /// Run known-answer tests of signature verification and of parsing and verifying the bundled
+    /// sample quotes, exiting non-zero on any mismatch
+    SelfTest,
 }
 
 #[derive(Args)]
@@ -33,15 +84,105 @@ struct DecodeQuoteArgs {
     /// Indicate the quote file is in hex format
     #[arg(long)]
     hex: bool,
+    /// Output the raw header and body of quotes of unknown TEE types instead of failing
+    #[arg(long)]
+    allow_unknown_tee: bool,
+    /// Also output the SGX extension of the PCK certificate: FMSPC, PCE ID, CPUSVN, TCB
+    /// components and SGX type
+    #[arg(long)]
+    pck: bool,
+    #[command(flatten)]
+    output: OutputArgs,
     /// The quote file
     quote_file: PathBuf,
 }
 
+#[derive(Args)]
+struct ExtractFieldArgs {
+    /// Indicate the quote file is in hex format
+    #[arg(long)]
+    hex: bool,
+    /// The field to print. Fails if the quote's TEE type has no such field
+    #[arg(long, value_enum)]
+    field: QuoteField,
+    /// The quote file
+    quote_file: PathBuf,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+#[value(rename_all = "snake_case")]
+enum QuoteField {
+    MrEnclave,
+    MrSigner,
+    MrTd,
+    Rtmr0,
+    Rtmr1,
+    Rtmr2,
+    Rtmr3,
+    ReportData,
+    Fmspc,
+}
+
 #[derive(Args)]
 struct VerifyQuoteArgs {
     /// Indicate the quote file is in hex format
     #[arg(long)]
     hex: bool,
+    /// Indicate the file is a JSON envelope bundling the quote with its collateral
+    #[arg(long, conflicts_with_all = ["hex", "tcb_update", "tcb_eval_num"])]
+    envelope: bool,
+    /// Verify against collateral from this JSON file, as written by the `collateral` command,
+    /// instead of fetching it. No network access is needed
+    #[arg(long, conflicts_with_all = ["envelope", "tcb_update", "tcb_eval_num"])]
+    collateral: Option<PathBuf>,
+    /// Where to get the collateral from
+    #[arg(long, value_enum, conflicts_with_all = ["envelope", "collateral"])]
+    provider: Option<Provider>,
+    /// Check that this CC event log replays to the RTMRs of the TDX quote
+    #[arg(long, conflicts_with = "envelope")]
+    event_log: Option<PathBuf>,
+    /// Fail unless the collateral has at least this tcbEvaluationDataNumber
+    #[arg(long, conflicts_with = "envelope")]
+    min_tcb_evaluation_data_number: Option<u32>,
+    /// Cache collateral fetched from PCS or PCCS in this directory, reusing it until its
+    /// nextUpdate
+    #[arg(long, conflicts_with_all = ["envelope", "collateral", "tcb_update", "tcb_eval_num"])]
+    cache_dir: Option<PathBuf>,
+    #[command(flatten)]
+    tcb_evaluation: TcbEvaluationArgs,
+    /// Write the collateral the quote was verified against to this JSON file, which
+    /// `--collateral` reads back. Written only if the quote verifies
+    #[arg(long)]
+    emit_collateral: Option<PathBuf>,
+    /// Fail unless the report data is this hex value, zero padded to 64 bytes
+    #[arg(long)]
+    report_data_hex: Option<String>,
+    #[command(flatten)]
+    output: OutputArgs,
+    #[command(flatten)]
+    save: SaveArgs,
+    /// Output a record of the verdict, with the evidence behind it if `full`, instead of the
+    /// report. The text format is unaffected
+    #[arg(long, value_enum)]
+    detail: Option<DetailArg>,
+    /// Print the verification steps, how long each took and which failed, to stderr
+    #[arg(short, long)]
+    verbose: bool,
+    /// Trust this root CA certificate, PEM or DER encoded, instead of the Intel SGX root CA. For
+    /// test deployments, like quotes from `mock-quote`
+    #[arg(long, conflicts_with = "envelope")]
+    root_ca: Option<PathBuf>,
+    /// Verify through the verification service at this URL instead: post the quote and check
+    /// the signed report it answers with, see `--remote-key` and `--remote-measurement`
+    #[arg(long, conflicts_with_all = ["envelope", "collateral", "cache_dir", "root_ca", "verbose"])]
+    remote: Option<String>,
+    /// Trust remote reports signed with this PEM encoded public key
+    #[arg(long, requires = "remote")]
+    remote_key: Option<PathBuf>,
+    /// Trust remote reports signed by an attested verifier with this hex encoded MRENCLAVE or
+    /// MRTD, verifying its quote with collateral from `--provider`
+    #[arg(long, requires = "remote", conflicts_with = "remote_key")]
+    remote_measurement: Option<String>,
     /// The quote file
     quote_file: PathBuf,
 }
@@ -51,101 +192,811 @@ struct CollateralQuoteArgs {
     /// Indicate the quote file is in hex format
     #[arg(long)]
     hex: bool,
+    /// Fetch the collateral for this hex encoded FMSPC instead of a quote's
+    #[arg(long, conflicts_with_all = ["quote_file", "hex"], requires = "tee")]
+    fmspc: Option<String>,
+    /// The TEE type of the FMSPC
+    #[arg(long, value_enum, requires = "fmspc")]
+    tee: Option<Tee>,
+    /// Check the certificate chains and signatures of the fetched collateral
+    #[arg(long)]
+    validate: bool,
+    /// Where to get the collateral from
+    #[arg(long, value_enum)]
+    provider: Option<Provider>,
+    #[command(flatten)]
+    tcb_evaluation: TcbEvaluationArgs,
+    /// The quote file
+    #[arg(required_unless_present = "fmspc")]
+    quote_file: Option<PathBuf>,
+}
+
+/// The TCB evaluation to fetch collateral at. Defaults to the standard update of PCS or PCCS
+#[derive(Args)]
+struct TcbEvaluationArgs {
+    /// Which update of the TCB info and QE identity to fetch. `early` fetches those of an
+    /// upcoming TCB recovery ahead of its effective date
+    #[arg(long, value_enum)]
+    tcb_update: Option<TcbUpdateArg>,
+    /// Fetch the TCB info and QE identity of this tcbEvaluationDataNumber
+    #[arg(long, conflicts_with = "tcb_update")]
+    tcb_eval_num: Option<u32>,
+}
+
+impl TcbEvaluationArgs {
+    fn options(&self) -> CollateralOptions {
+        CollateralOptions {
+            update: match self.tcb_update {
+                Some(TcbUpdateArg::Early) => TcbUpdate::Early,
+                Some(TcbUpdateArg::Standard) | None => TcbUpdate::Standard,
+            },
+            tcb_evaluation_data_number: self.tcb_eval_num,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TcbUpdateArg {
+    Standard,
+    Early,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DetailArg {
+    Verdict,
+    Full,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Tee {
+    Sgx,
+    Tdx,
+}
+
+/// A collateral provider. Defaults to PCCS at `PCCS_URL` if set, Intel PCS otherwise.
+#[derive(Clone, Copy, ValueEnum)]
+enum Provider {
+    /// Intel PCS
+    Pcs,
+    /// The PCCS server at `PCCS_URL`
+    Pccs,
+    /// Azure THIM, at `THIM_URL` if set, with the client ID in `THIM_CLIENT_ID`
+    Azure,
+}
+
+fn collateral_provider(provider: Option<Provider>) -> Result<CollateralProvider> {
+    let env = |name| std::env::var(name).ok().filter(|value| !value.is_empty());
+    Ok(match (provider, env("PCCS_URL")) {
+        (Some(Provider::Pcs), _) | (None, None) => CollateralProvider::Pcs,
+        (Some(Provider::Pccs) | None, Some(pccs_url)) => CollateralProvider::Pccs(pccs_url),
+        (Some(Provider::Pccs), None) => anyhow::bail!("PCCS_URL is not set"),
+        (Some(Provider::Azure), _) => CollateralProvider::AzureThim(ThimConfig {
+            base_url: env("THIM_URL"),
+            client_id: env("THIM_CLIENT_ID"),
+        }),
+    })
+}
+
+#[derive(Args)]
+struct RedactQuoteArgs {
+    /// Indicate the quote file is in hex format
+    #[arg(long)]
+    hex: bool,
+    /// The quote file
+    quote_file: PathBuf,
+    /// The output file, written in the same format as the input
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+#[derive(Args)]
+struct CanonicalizeArgs {
+    /// Indicate the quote file is in hex format
+    #[arg(long)]
+    hex: bool,
+    /// Also digest the collateral in this JSON file, as written by the `collateral` command
+    #[arg(long)]
+    collateral: Option<PathBuf>,
     /// The quote file
     quote_file: PathBuf,
 }
 
+#[derive(Args)]
+struct RatlsProbeArgs {
+    /// The endpoint address, host:port
+    addr: String,
+}
+
+#[derive(Args)]
+struct ExtractQuoteArgs {
+    /// Write the quote in hex format
+    #[arg(long)]
+    hex: bool,
+    /// The certificate file, PEM or DER encoded
+    cert_file: PathBuf,
+    /// The output file
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+#[derive(Args)]
+struct VerifyCertArgs {
+    /// The certificate file, PEM or DER encoded
+    cert_file: PathBuf,
+    /// Verify against collateral from this JSON file, as written by the `collateral` command,
+    /// instead of fetching it
+    #[arg(long)]
+    collateral: Option<PathBuf>,
+    /// Where to get the collateral from
+    #[arg(long, value_enum, conflicts_with = "collateral")]
+    provider: Option<Provider>,
+    /// Trust this root CA certificate, PEM or DER encoded, instead of the Intel SGX root CA. For
+    /// test deployments, like certificates of quotes from `mock-quote`
+    #[arg(long)]
+    root_ca: Option<PathBuf>,
+}
+
+#[cfg(feature = "bench")]
+#[derive(Args)]
+struct BenchArgs {
+    /// Directory of quote files and JSON envelopes. Collateral for bare quotes is fetched once
+    corpus: PathBuf,
+    /// The number of verifications to run
+    #[arg(short = 'n', long, default_value_t = 1000)]
+    requests: usize,
+    /// The target rate in verifications per second. Unlimited if not set
+    #[arg(long)]
+    rate: Option<f64>,
+    /// The verification time in seconds since the Unix epoch. Defaults to the current time
+    #[arg(long)]
+    now: Option<u64>,
+}
+
+#[derive(Args)]
+struct MockQuoteArgs {
+    /// The TEE the quote is of
+    #[arg(long, value_enum)]
+    tee: Tee,
+    /// The hex encoded MRENCLAVE of an SGX quote
+    #[arg(long)]
+    mr_enclave: Option<String>,
+    /// The hex encoded MRSIGNER of an SGX quote
+    #[arg(long)]
+    mr_signer: Option<String>,
+    /// The hex encoded MRTD of a TDX quote
+    #[arg(long)]
+    mrtd: Option<String>,
+    /// The hex encoded report data, zero padded to 64 bytes
+    #[arg(long)]
+    report_data_hex: Option<String>,
+    /// The TCB status the collateral gives the platform, as in Intel's TCB info
+    #[arg(long, default_value = "UpToDate")]
+    tcb_status: String,
+    /// Write the quote in hex format
+    #[arg(long)]
+    hex: bool,
+    /// The quote output file
+    #[arg(long)]
+    out: PathBuf,
+    /// The collateral output file, in the JSON format `verify --collateral` reads
+    #[arg(long)]
+    collateral_out: PathBuf,
+    /// Write the DER encoded root CA to this file, for `verify --root-ca`
+    #[arg(long)]
+    root_ca_out: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct SupportBundleArgs {
+    /// Indicate the quote file is in hex format
+    #[arg(long)]
+    hex: bool,
+    /// Zero report_data and QE auth data of the bundled quote so the bundle can be shared
+    /// publicly. Verification still uses the original quote
+    #[arg(long)]
+    redact: bool,
+    /// The quote file
+    quote_file: PathBuf,
+    /// The output file
+    #[arg(short, long, default_value = "support-bundle.tar")]
+    output: PathBuf,
+}
+
 fn hex_decode(input: &[u8], is_hex: bool) -> Result<Vec<u8>> {
-    if is_hex {
-        let input = input.strip_prefix(b"0x").unwrap_or(input);
-        let input = input.strip_suffix(b"\n").unwrap_or(input);
-        hex::decode(input).context("Failed to decode quote file")
-    } else {
-        Ok(input.to_vec())
+    // Binary quotes carry random keys and signatures, so are practically never valid UTF-8
+    match std::str::from_utf8(input) {
+        Ok(text) => input::decode_text(text).context("Failed to decode quote file"),
+        Err(_) if is_hex => anyhow::bail!("Quote file is not valid hex"),
+        Err(_) => Ok(input.to_vec()),
     }
 }
 
 fn command_decode_quote(args: DecodeQuoteArgs) -> Result<()> {
     let quote = std::fs::read(args.quote_file).context("Failed to read quote file")?;
     let quote = hex_decode(&quote, args.hex)?;
+    if args.allow_unknown_tee {
+        if let ClassifiedQuote::UnknownTee(unknown) =
+            Quote::classify(&quote).context("Failed to parse quote")?
+        {
+            let header = hex::encode(unknown.header);
+            let body = hex::encode(unknown.body);
+            let json = serde_json::json!({
+                "unknown_tee": {
+                    "tee_type": unknown.tee_type,
+                    "header": header,
+                    "body": body,
+                },
+            });
+            return args.output.write(&json, || {
+                format!(
+                    "Unknown TEE type {:#x}\nHeader: {header}\nBody: {body}\n",
+                    unknown.tee_type
+                )
+            });
+        }
+    }
     let decoded_quote = Quote::parse(&quote).context("Failed to parse quote")?;
-    let json = serde_json::to_string(&decoded_quote).context("Failed to serialize quote")?;
-    println!("{}", json);
+    let anomalies = Quote::anomalies(&quote).context("Failed to check quote anomalies")?;
+    let mut json = serde_json::to_value(&decoded_quote).context("Failed to serialize quote")?;
+    json["anomalies"] =
+        serde_json::to_value(&anomalies).context("Failed to serialize anomalies")?;
+    json["qe_auth_data"] = hex::encode(decoded_quote.qe_auth_data()).into();
+    json["platform_family"] = platform_family_of(&decoded_quote).into();
+    if args.pck {
+        let pck = decoded_quote
+            .pck_extension()
+            .context("Failed to parse PCK certificate")?;
+        json["pck"] = serde_json::to_value(&pck).context("Failed to serialize PCK extension")?;
+    }
+    if let Some(interpreted) = QeAuthDataRegistry::with_defaults().interpret(&decoded_quote) {
+        json["qe_auth_data_interpreted"] = match interpreted {
+            Ok(value) => value,
+            Err(err) => serde_json::json!({ "error": format!("{err:#}") }),
+        };
+    }
+    args.output.write(&json, || {
+        output::quote_text(
+            &decoded_quote,
+            &anomalies,
+            platform_family_of(&decoded_quote),
+        )
+    })
+}
+
+fn command_extract_field(args: ExtractFieldArgs) -> Result<()> {
+    let quote = std::fs::read(args.quote_file).context("Failed to read quote file")?;
+    let quote = hex_decode(&quote, args.hex)?;
+    let quote = Quote::parse(&quote).context("Failed to parse quote")?;
+    let sgx = || quote.report.as_sgx().context("Not an SGX quote");
+    let td = || quote.report.as_td10().context("Not a TDX quote");
+    let value = match args.field {
+        QuoteField::MrEnclave => hex::encode(sgx()?.mr_enclave),
+        QuoteField::MrSigner => hex::encode(sgx()?.mr_signer),
+        QuoteField::MrTd => hex::encode(td()?.mr_td),
+        QuoteField::Rtmr0 => hex::encode(td()?.rt_mr0),
+        QuoteField::Rtmr1 => hex::encode(td()?.rt_mr1),
+        QuoteField::Rtmr2 => hex::encode(td()?.rt_mr2),
+        QuoteField::Rtmr3 => hex::encode(td()?.rt_mr3),
+        QuoteField::ReportData => quote.report.report_data().to_string(),
+        QuoteField::Fmspc => quote.fmspc()?.to_string(),
+    };
+    println!("{value}");
     Ok(())
 }
 
-async fn command_verify_quote(args: VerifyQuoteArgs) -> Result<()> {
+/// The platform family of the quote's FMSPC, if it is known.
+fn platform_family_of(quote: &Quote) -> Option<&'static str> {
+    platform_family(&quote.fmspc().ok()?)
+}
+
+fn command_redact_quote(args: RedactQuoteArgs) -> Result<()> {
     let quote = std::fs::read(args.quote_file).context("Failed to read quote file")?;
     let quote = hex_decode(&quote, args.hex)?;
-    let pccs_url = std::env::var("PCCS_URL").unwrap_or_default();
-    let collateral = if pccs_url.is_empty() {
-        eprintln!("Getting collateral from PCS...");
-        get_collateral_from_pcs(&quote, std::time::Duration::from_secs(60)).await?
+    let redacted = Quote::redact(&quote).context("Failed to redact quote")?;
+    let out = if args.hex {
+        hex::encode(redacted).into_bytes()
+    } else {
+        redacted
+    };
+    std::fs::write(args.output, out).context("Failed to write redacted quote")?;
+    eprintln!("Quote redacted");
+    Ok(())
+}
+
+fn command_canonicalize(args: CanonicalizeArgs) -> Result<()> {
+    let quote = std::fs::read(args.quote_file).context("Failed to read quote file")?;
+    let quote = hex_decode(&quote, args.hex)?;
+    let canonical_quote = canonical::canonical_quote(&quote)?;
+    let mut json = serde_json::json!({
+        "quote_sha256": hex::encode(canonical::quote_digest(&quote)?),
+        "quote_len": canonical_quote.len(),
+    });
+    if let Some(collateral) = args.collateral {
+        let collateral =
+            std::fs::read_to_string(collateral).context("Failed to read collateral file")?;
+        let collateral = QuoteCollateralV3::from_json(&collateral)?;
+        json["collateral_sha256"] = hex::encode(canonical::collateral_digest(&collateral)?).into();
+    }
+    println!("{}", serde_json::to_string(&json).unwrap());
+    Ok(())
+}
+
+fn command_verify_quote(args: VerifyQuoteArgs) -> Result<()> {
+    let quote = std::fs::read(args.quote_file).context("Failed to read quote file")?;
+    if args.envelope {
+        let envelope = parse_envelope(&quote).context("Failed to parse envelope")?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        let report = verify_locally(
+            &envelope.quote,
+            &envelope.collateral,
+            now,
+            &VerifyOptions::default(),
+            args.verbose,
+        )?;
+        check_report_data(args.report_data_hex.as_deref(), &report)?;
+        emit_collateral(args.emit_collateral.as_deref(), &envelope.collateral)?;
+        output_report(
+            &args.output,
+            &args.save,
+            args.detail,
+            &envelope.quote,
+            &report,
+        )?;
+        eprintln!("Quote verified");
+        return Ok(());
+    }
+    let quote = hex_decode(&quote, args.hex)?;
+    if let Some(url) = &args.remote {
+        let provider = collateral_provider(args.provider)?;
+        let trust = match (&args.remote_key, &args.remote_measurement) {
+            (Some(key_file), _) => remote::Trust::Key(key_file),
+            (None, Some(measurement)) => remote::Trust::Measurement(measurement, &provider),
+            (None, None) => anyhow::bail!("--remote needs --remote-key or --remote-measurement"),
+        };
+        let report = remote::verify_remote(url, &quote, trust)?;
+        check_report_data(args.report_data_hex.as_deref(), &report)?;
+        output_report(&args.output, &args.save, args.detail, &quote, &report)?;
+        eprintln!("Quote verified remotely");
+        return Ok(());
+    }
+    if let Some(event_log) = args.event_log {
+        let event_log = std::fs::read(event_log).context("Failed to read event log file")?;
+        let event_log = EventLog::parse(&event_log).context("Failed to parse event log")?;
+        let decoded_quote = Quote::parse(&quote).context("Failed to parse quote")?;
+        verify_rtmrs(&decoded_quote, &event_log)?;
+        eprintln!("Event log matches RTMR0 to RTMR3");
+    }
+    let verify_options = VerifyOptions {
+        min_tcb_evaluation_data_number: args.min_tcb_evaluation_data_number,
+        root_ca: args.root_ca.as_deref().map(read_cert).transpose()?,
+        ..Default::default()
+    };
+    if let Some(collateral) = args.collateral {
+        let collateral =
+            std::fs::read_to_string(collateral).context("Failed to read collateral file")?;
+        let collateral = QuoteCollateralV3::from_json(&collateral)?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        let report = verify_locally(&quote, &collateral, now, &verify_options, args.verbose)?;
+        check_report_data(args.report_data_hex.as_deref(), &report)?;
+        emit_collateral(args.emit_collateral.as_deref(), &collateral)?;
+        output_report(&args.output, &args.save, args.detail, &quote, &report)?;
+        eprintln!("Quote verified");
+        return Ok(());
+    }
+    // Online verification runs in the library, fetch the collateral here instead to trace it
+    let online = !args.verbose || args.cache_dir.is_some();
+    let pccs_url = match collateral_provider(args.provider)? {
+        CollateralProvider::Pcs if online => {
+            eprintln!("Getting collateral from PCS...");
+            None
+        }
+        CollateralProvider::Pccs(pccs_url) if online => {
+            eprintln!("Getting collateral from {pccs_url}");
+            Some(pccs_url)
+        }
+        provider => {
+            let timeout = std::time::Duration::from_secs(60);
+            let collateral =
+                collateral_for_quote(&provider, &quote, timeout, &args.tcb_evaluation.options())?;
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs();
+            let report = verify_locally(&quote, &collateral, now, &verify_options, args.verbose)?;
+            check_report_data(args.report_data_hex.as_deref(), &report)?;
+            emit_collateral(args.emit_collateral.as_deref(), &collateral)?;
+            output_report(&args.output, &args.save, args.detail, &quote, &report)?;
+            eprintln!("Quote verified");
+            return Ok(());
+        }
+    };
+    let (report, collateral) = match args.cache_dir {
+        Some(cache_dir) => {
+            let store = FileStore::open(cache_dir).context("Failed to open cache directory")?;
+            let cache = CollateralCache::with_store(Arc::new(store));
+            let pccs_url = match &pccs_url {
+                Some(pccs_url) => pccs_url.as_str(),
+                None => pcs_url_for(Quote::parse(&quote)?.header.is_sgx()),
+            };
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs();
+            let timeout = std::time::Duration::from_secs(60);
+            let collateral = get_collateral_cached_sync(&cache, pccs_url, &quote, now, timeout)
+                .context("Failed to get collateral")?;
+            let report = verify_locally(&quote, &collateral, now, &verify_options, args.verbose)?;
+            (report, collateral)
+        }
+        None => verify_online_quote(
+            &quote,
+            pccs_url,
+            args.tcb_evaluation.options(),
+            verify_options,
+        )?,
+    };
+    check_report_data(args.report_data_hex.as_deref(), &report)?;
+    emit_collateral(args.emit_collateral.as_deref(), &collateral)?;
+    output_report(&args.output, &args.save, args.detail, &quote, &report)?;
+    eprintln!("Quote verified");
+    Ok(())
+}
+
+/// Verify a quote against collateral, printing the steps run to stderr if `verbose`.
+fn verify_locally(
+    quote: &[u8],
+    collateral: &QuoteCollateralV3,
+    now: u64,
+    options: &VerifyOptions,
+    verbose: bool,
+) -> Result<VerifiedReport> {
+    let (result, trace) = verify_traced(quote, collateral, now, options);
+    if verbose {
+        for step in &trace.steps {
+            let outcome = if step.passed { "ok" } else { "FAILED" };
+            let duration = step
+                .duration_us
+                .map(|us| format!(" ({us} us)"))
+                .unwrap_or_default();
+            eprintln!("{:<20} {outcome}{duration}", step.step.as_str());
+        }
+    }
+    result.context("Failed to verify quote")
+}
+
+/// Output a verified report, with the platform family of the quote if it parses, or its record
+/// in the given detail, and save it if asked to.
+fn output_report(
+    output: &OutputArgs,
+    save: &SaveArgs,
+    detail: Option<DetailArg>,
+    quote: &[u8],
+    report: &VerifiedReport,
+) -> Result<()> {
+    let decoded_quote = Quote::parse(quote).ok();
+    let platform_family = decoded_quote.as_ref().and_then(platform_family_of);
+    let json = match detail {
+        Some(detail) => {
+            let detail = match detail {
+                DetailArg::Verdict => Detail::Verdict,
+                DetailArg::Full => Detail::Full,
+            };
+            serde_json::to_value(VerdictRecord::new(report, detail))
+                .context("Failed to serialize record")?
+        }
+        None => {
+            let mut json = serde_json::to_value(report).context("Failed to serialize report")?;
+            if decoded_quote.is_some() {
+                json["platform_family"] = platform_family.into();
+            }
+            json
+        }
+    };
+    output.write(&json, || output::verified_text(report, platform_family))?;
+    save.save(&json)
+}
+
+fn verify_online_quote(
+    quote: &[u8],
+    pccs_url: Option<String>,
+    collateral_options: CollateralOptions,
+    verify_options: VerifyOptions,
+) -> Result<(VerifiedReport, QuoteCollateralV3)> {
+    let options = OnlineOptions {
+        pccs_url,
+        timeout: Some(std::time::Duration::from_secs(60)),
+        now: None,
+        collateral: collateral_options,
+        verify: verify_options,
+        ..Default::default()
+    };
+    let online = verify_online_blocking(quote, &options).context("Failed to verify quote")?;
+    Ok((online.report, online.collateral))
+}
+
+/// Check the report data of a verified report, if asked to.
+fn check_report_data(expected_hex: Option<&str>, report: &VerifiedReport) -> Result<()> {
+    let Some(expected_hex) = expected_hex else {
+        return Ok(());
+    };
+    let expected = hex::decode(expected_hex).context("Invalid report data hex")?;
+    report.verify_report_data(&expected)
+}
+
+/// Write the collateral a quote was verified against, if asked to, atomically.
+fn emit_collateral(path: Option<&Path>, collateral: &QuoteCollateralV3) -> Result<()> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+    let json = serde_json::to_vec(collateral).context("Failed to serialize collateral")?;
+    output::write_atomic(path, &json).context("Failed to write collateral")?;
+    eprintln!("Collateral written to {}", path.display());
+    Ok(())
+}
+
+/// Read a PEM or DER encoded certificate file as DER.
+fn read_cert(path: &Path) -> Result<Vec<u8>> {
+    let cert = std::fs::read(path).context("Failed to read certificate file")?;
+    if cert.starts_with(b"-----BEGIN") {
+        Ok(pem::parse(&cert)
+            .context("Failed to parse PEM certificate")?
+            .into_contents())
+    } else {
+        Ok(cert)
+    }
+}
+
+fn command_extract_quote(args: ExtractQuoteArgs) -> Result<()> {
+    let cert = read_cert(&args.cert_file)?;
+    let quote = ra_tls::extract_quote(&cert).context("Failed to extract quote")?;
+    let out = if args.hex {
+        hex::encode(quote).into_bytes()
     } else {
-        eprintln!("Getting collateral from {pccs_url}");
-        get_collateral(&pccs_url, &quote, std::time::Duration::from_secs(60)).await?
+        quote
+    };
+    std::fs::write(args.output, out).context("Failed to write quote")?;
+    eprintln!("Quote extracted");
+    Ok(())
+}
+
+fn command_verify_cert(args: VerifyCertArgs) -> Result<()> {
+    let cert = read_cert(&args.cert_file)?;
+    let collateral = match args.collateral {
+        Some(collateral) => {
+            let collateral =
+                std::fs::read_to_string(collateral).context("Failed to read collateral file")?;
+            QuoteCollateralV3::from_json(&collateral)?
+        }
+        None => {
+            let quote = ra_tls::extract_quote(&cert).context("Failed to extract quote")?;
+            let provider = collateral_provider(args.provider)?;
+            collateral_for_quote(
+                &provider,
+                &quote,
+                std::time::Duration::from_secs(60),
+                &CollateralOptions::default(),
+            )?
+        }
     };
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)?
         .as_secs();
-    let report = verify(&quote, &collateral, now)
-        .ok()
-        .context("Failed to verify quote")?;
+    let verify_options = VerifyOptions {
+        root_ca: args.root_ca.as_deref().map(read_cert).transpose()?,
+        ..Default::default()
+    };
+    let report = ra_tls::verify_ra_tls_cert_with_options(&cert, &collateral, now, &verify_options)?;
     println!("{}", serde_json::to_string(&report).unwrap());
-    eprintln!("Quote verified");
+    eprintln!("RA-TLS certificate verified");
     Ok(())
 }
 
-#[derive(Debug)]
-pub struct QuoteCollateralV3Json {
-    tcb_info_issuer_chain: String,
-    tcb_info: String,
-    tcb_info_signature: String,
-    qe_identity_issuer_chain: String,
-    qe_identity: String,
-    qe_identity_signature: String,
+fn command_ratls_probe(args: RatlsProbeArgs) -> Result<()> {
+    let cert = ratls::fetch_peer_cert(&args.addr)?;
+    let quote = ra_tls::extract_quote(&cert).context("Failed to extract quote")?;
+    let options = OnlineOptions {
+        pccs_url: std::env::var("PCCS_URL").ok().filter(|url| !url.is_empty()),
+        timeout: Some(std::time::Duration::from_secs(60)),
+        now: None,
+        ..Default::default()
+    };
+    let report = verify_online_blocking(&quote, &options)
+        .context("Failed to verify quote")?
+        .report;
+    ra_tls::check_key_binding(&cert, &report.report).context("Failed to check key binding")?;
+    println!("{}", serde_json::to_string(&report).unwrap());
+    eprintln!("RA-TLS certificate verified");
+    Ok(())
 }
 
-async fn command_collateral_quote(args: CollateralQuoteArgs) -> Result<()> {
-    let quote = std::fs::read(args.quote_file).context("Failed to read quote file")?;
-    let quote = hex_decode(&quote, true)?;
+#[cfg(feature = "bench")]
+fn command_bench(args: BenchArgs) -> Result<()> {
+    let corpus = bench::load_corpus(&args.corpus)?;
+    let now = match args.now {
+        Some(now) => now,
+        None => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs(),
+    };
+    eprintln!("Replaying {} quotes", corpus.len());
+    tokio::runtime::Runtime::new()
+        .context("Failed to start runtime")?
+        .block_on(bench::run(corpus, args.requests, args.rate, now))
+}
 
+fn command_support_bundle(args: SupportBundleArgs) -> Result<()> {
+    let quote = std::fs::read(args.quote_file).context("Failed to read quote file")?;
+    let quote = hex_decode(&quote, args.hex)?;
     let pccs_url = std::env::var("PCCS_URL").unwrap_or_default();
-    let collateral = if pccs_url.is_empty() {
-        eprintln!("Getting collateral from PCS...");
-        get_collateral_from_pcs(&quote, std::time::Duration::from_secs(60)).await?
-    } else {
-        eprintln!("Getting collateral from {pccs_url}");
-        get_collateral(&pccs_url, &quote, std::time::Duration::from_secs(60)).await?
+    let options = support::BundleOptions {
+        pccs_url: &pccs_url,
+        redact: args.redact,
+        now: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs(),
     };
+    support::write_bundle(&quote, &options, &args.output)?;
+    eprintln!("Support bundle written to {}", args.output.display());
+    Ok(())
+}
 
-    let json = QuoteCollateralV3Json {
-        tcb_info_issuer_chain: collateral.tcb_info_issuer_chain,
-        tcb_info: collateral.tcb_info,
-        tcb_info_signature: hex::encode(&collateral.tcb_info_signature),
-        qe_identity_issuer_chain: collateral.qe_identity_issuer_chain,
-        qe_identity: collateral.qe_identity,
-        qe_identity_signature: hex::encode(&collateral.qe_identity_signature),
+fn command_mock_quote(args: MockQuoteArgs) -> Result<()> {
+    let tee = match args.tee {
+        Tee::Sgx => TeeType::Sgx,
+        Tee::Tdx => TeeType::Tdx,
     };
+    let mut builder = MockQuoteBuilder::new(tee).tcb_status(args.tcb_status.parse()?);
+    if let Some(mr_enclave) = &args.mr_enclave {
+        builder = builder.mr_enclave(hex_array(mr_enclave).context("Invalid MRENCLAVE")?);
+    }
+    if let Some(mr_signer) = &args.mr_signer {
+        builder = builder.mr_signer(hex_array(mr_signer).context("Invalid MRSIGNER")?);
+    }
+    if let Some(mrtd) = &args.mrtd {
+        builder = builder.mr_td(hex_array(mrtd).context("Invalid MRTD")?);
+    }
+    if let Some(report_data_hex) = &args.report_data_hex {
+        let report_data = hex::decode(report_data_hex).context("Invalid report data hex")?;
+        if report_data.len() > 64 {
+            anyhow::bail!("Report data is longer than 64 bytes");
+        }
+        let mut padded = [0u8; 64];
+        padded[..report_data.len()].copy_from_slice(&report_data);
+        builder = builder.report_data(padded);
+    }
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    let mock = builder.build(now)?;
+    let quote = if args.hex {
+        hex::encode(&mock.quote).into_bytes()
+    } else {
+        mock.quote
+    };
+    std::fs::write(&args.out, quote).context("Failed to write quote")?;
+    let collateral =
+        serde_json::to_vec(&mock.collateral).context("Failed to serialize collateral")?;
+    std::fs::write(&args.collateral_out, collateral).context("Failed to write collateral")?;
+    if let Some(root_ca_out) = &args.root_ca_out {
+        std::fs::write(root_ca_out, &mock.root_ca).context("Failed to write root CA")?;
+    }
+    eprintln!("Mock quote written to {}", args.out.display());
+    Ok(())
+}
 
-    let out_str = format!("{:?}", json).to_string();
-    let (_, out) = out_str.split_at(22);
-
-    let _ = std::fs::write("quote_collateral.json", out);
+/// Decode a hex string of exactly `N` bytes.
+fn hex_array<const N: usize>(hex: &str) -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    hex::decode_to_slice(hex, &mut bytes)
+        .with_context(|| format!("Expected {} hex characters", 2 * N))?;
+    Ok(bytes)
+}
 
+fn command_collateral_quote(args: CollateralQuoteArgs) -> Result<()> {
+    let provider = collateral_provider(args.provider)?;
+    let timeout = std::time::Duration::from_secs(60);
+    let options = args.tcb_evaluation.options();
+    let collateral = if let (Some(fmspc), Some(tee)) = (&args.fmspc, args.tee) {
+        let fmspc = hex::decode(fmspc).context("FMSPC must be hex encoded")?;
+        if fmspc.len() != 6 {
+            anyhow::bail!("FMSPC must be 6 bytes");
+        }
+        let pccs_url = match &provider {
+            CollateralProvider::Pcs => {
+                eprintln!("Getting collateral from PCS...");
+                pcs_url_for(matches!(tee, Tee::Sgx))
+            }
+            CollateralProvider::Pccs(pccs_url) => {
+                eprintln!("Getting collateral from {pccs_url}");
+                pccs_url
+            }
+            CollateralProvider::AzureThim(_) => {
+                anyhow::bail!("Azure THIM collateral can only be fetched for a quote")
+            }
+        };
+        get_collateral_for_fmspc_with_options_sync(
+            pccs_url,
+            &hex::encode_upper(fmspc),
+            timeout,
+            &options,
+        )?
+    } else {
+        let quote_file = args.quote_file.context("Missing quote file")?;
+        let quote = std::fs::read(quote_file).context("Failed to read quote file")?;
+        let quote = hex_decode(&quote, true)?;
+        collateral_for_quote(&provider, &quote, timeout, &options)?
+    };
+    if args.validate {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs();
+        verify_collateral(&collateral, now).context("Fetched collateral is invalid")?;
+        eprintln!("Collateral validated");
+    }
+    std::fs::write("quote_collateral.json", collateral.to_json()?)
+        .context("Failed to write collateral")?;
+    eprintln!("Collateral written to quote_collateral.json");
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn collateral_for_quote(
+    provider: &CollateralProvider,
+    quote: &[u8],
+    timeout: std::time::Duration,
+    options: &CollateralOptions,
+) -> Result<QuoteCollateralV3> {
+    let pccs_url = match provider {
+        CollateralProvider::Pcs => {
+            eprintln!("Getting collateral from PCS...");
+            pcs_url_for(Quote::parse(quote)?.header.is_sgx())
+        }
+        CollateralProvider::Pccs(pccs_url) => {
+            eprintln!("Getting collateral from {pccs_url}");
+            pccs_url
+        }
+        CollateralProvider::AzureThim(_) => {
+            eprintln!("Getting collateral from Azure THIM...");
+            if *options != CollateralOptions::default() {
+                anyhow::bail!("Azure THIM only serves the standard TCB evaluation");
+            }
+            return provider.get_collateral_sync(quote, timeout);
+        }
+    };
+    get_collateral_with_options_sync(pccs_url, quote, timeout, options)
+}
+
+fn main() -> Result<()> {
     let cli = Cli::parse();
     match cli.command {
         Commands::Decode(args) => command_decode_quote(args).context("Failed to decode quote"),
-        Commands::Verify(args) => command_verify_quote(args)
-            .await
-            .context("Failed to verify quote"),
-        Commands::Collateral(args) => command_collateral_quote(args)
-            .await
-            .context("Failed to decode quote"),
+        Commands::Verify(args) => command_verify_quote(args).context("Failed to verify quote"),
+        Commands::Extract(args) => command_extract_field(args).context("Failed to extract field"),
+        Commands::Collateral(args) => {
+            command_collateral_quote(args).context("Failed to decode quote")
+        }
+        Commands::Redact(args) => command_redact_quote(args).context("Failed to redact quote"),
+        Commands::Canonicalize(args) => {
+            command_canonicalize(args).context("Failed to canonicalize quote")
+        }
+        Commands::RatlsProbe(args) => {
+            command_ratls_probe(args).context("Failed to probe RA-TLS endpoint")
+        }
+        Commands::ExtractQuote(args) => {
+            command_extract_quote(args).context("Failed to extract quote")
+        }
+        Commands::VerifyCert(args) => {
+            command_verify_cert(args).context("Failed to verify RA-TLS certificate")
+        }
+        #[cfg(feature = "bench")]
+        Commands::Bench(args) => command_bench(args).context("Failed to run benchmark"),
+        Commands::SupportBundle(args) => {
+            command_support_bundle(args).context("Failed to write support bundle")
+        }
+        Commands::MockQuote(args) => {
+            command_mock_quote(args).context("Failed to generate mock quote")
+        }
+        Commands::SelfTest => self_test::run().context("Self-test failed"),
     }
 }
@@ -0,0 +1,285 @@
+//! Rendering command results as JSON, YAML or a text report for operators.
+
+use std::fmt::{Display, Write as _};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context as _, Result};
+use clap::{Args, ValueEnum};
+use dcap_qvl::quote::{Anomaly, Quote, Report, TDReport10};
+use dcap_qvl::signed_report::{SignatureScheme, SignedReport};
+use dcap_qvl::verify::VerifiedReport;
+use rustls::pki_types::{PrivateKeyDer, PrivatePkcs1KeyDer, PrivatePkcs8KeyDer, PrivateSec1KeyDer};
+use serde_json::Value;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    /// Pretty printed JSON
+    Json,
+    Yaml,
+    /// A text report with the measurements hex encoded
+    Text,
+}
+
+#[derive(Args)]
+pub struct OutputArgs {
+    /// The output format. Defaults to JSON on a single line
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+    /// Write the output to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+impl OutputArgs {
+    /// Write a result in the chosen format. `text` renders the text report, and is only called
+    /// for it.
+    pub fn write(&self, json: &Value, text: impl FnOnce() -> String) -> Result<()> {
+        let mut out = match self.format {
+            None => serde_json::to_string(json).context("Failed to serialize JSON")?,
+            Some(Format::Json) => {
+                serde_json::to_string_pretty(json).context("Failed to serialize JSON")?
+            }
+            Some(Format::Yaml) => {
+                serde_yaml::to_string(json).context("Failed to serialize YAML")?
+            }
+            Some(Format::Text) => text(),
+        };
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        match &self.output {
+            Some(path) => {
+                write_atomic(path, out.as_bytes()).context("Failed to write output")?;
+                eprintln!("Output written to {}", path.display());
+            }
+            None => print!("{out}"),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+pub struct SaveArgs {
+    /// Also save the JSON report to this file. It is written next to the file and renamed into
+    /// place, so the file is either missing or complete
+    #[arg(long)]
+    save_report: Option<PathBuf>,
+    /// Sign the saved report with this PEM encoded PKCS#8, SEC1 or PKCS#1 private key, saving a
+    /// signed report document carrying the report, its signature and the public key instead
+    #[arg(long, requires = "save_report")]
+    sign_key: Option<PathBuf>,
+    /// Attach this quote of the verifier itself to the signed report, for a verifier running in
+    /// a TEE. Its report data must start with the SHA-256 of the signing key's
+    /// SubjectPublicKeyInfo
+    #[arg(long, requires = "sign_key")]
+    verifier_quote: Option<PathBuf>,
+}
+
+impl SaveArgs {
+    /// Save a result as pretty printed JSON, signed into a [`SignedReport`] if asked to.
+    pub fn save(&self, json: &Value) -> Result<()> {
+        let Some(path) = &self.save_report else {
+            return Ok(());
+        };
+        let mut report = serde_json::to_vec_pretty(json).context("Failed to serialize JSON")?;
+        report.push(b'\n');
+        let contents = match &self.sign_key {
+            Some(key) => {
+                let mut signed = sign(key, report)?;
+                if let Some(quote_file) = &self.verifier_quote {
+                    signed.verifier_quote = Some(verifier_quote(quote_file, &signed)?);
+                }
+                let mut signed = signed.to_json()?;
+                signed.push(b'\n');
+                signed
+            }
+            None => report,
+        };
+        write_atomic(path, &contents).context("Failed to write report")?;
+        eprintln!("Report written to {}", path.display());
+        Ok(())
+    }
+}
+
+/// Sign a report with the private key in a PEM file.
+fn sign(key_file: &Path, report: Vec<u8>) -> Result<SignedReport> {
+    let pem = std::fs::read(key_file).context("Failed to read signing key file")?;
+    let pem = pem::parse(pem).context("Failed to parse signing key PEM")?;
+    let key = match pem.tag() {
+        "PRIVATE KEY" => PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(pem.into_contents())),
+        "EC PRIVATE KEY" => PrivateKeyDer::Sec1(PrivateSec1KeyDer::from(pem.into_contents())),
+        "RSA PRIVATE KEY" => PrivateKeyDer::Pkcs1(PrivatePkcs1KeyDer::from(pem.into_contents())),
+        tag => bail!("Unsupported signing key PEM block {tag}"),
+    };
+    let key = rustls::crypto::ring::sign::any_supported_type(&key)
+        .map_err(|err| anyhow!("Unsupported signing key: {err}"))?;
+    let public_key = key
+        .public_key()
+        .context("Failed to get the public key of the signing key")?;
+    let signer = key
+        .choose_scheme(&[
+            rustls::SignatureScheme::ECDSA_NISTP256_SHA256,
+            rustls::SignatureScheme::ECDSA_NISTP384_SHA384,
+            rustls::SignatureScheme::ED25519,
+            rustls::SignatureScheme::RSA_PSS_SHA256,
+        ])
+        .context("No signature scheme for the signing key")?;
+    let scheme = match signer.scheme() {
+        rustls::SignatureScheme::ECDSA_NISTP256_SHA256 => SignatureScheme::EcdsaNistp256Sha256,
+        rustls::SignatureScheme::ECDSA_NISTP384_SHA384 => SignatureScheme::EcdsaNistp384Sha384,
+        rustls::SignatureScheme::ED25519 => SignatureScheme::Ed25519,
+        rustls::SignatureScheme::RSA_PSS_SHA256 => SignatureScheme::RsaPssSha256,
+        scheme => bail!("Unexpected signature scheme {scheme:?}"),
+    };
+    let signature = signer
+        .sign(&report)
+        .map_err(|err| anyhow!("Failed to sign report: {err}"))?;
+    Ok(SignedReport {
+        report,
+        scheme,
+        signature,
+        public_key: public_key.as_ref().to_vec(),
+        verifier_quote: None,
+    })
+}
+
+/// Read the verifier's quote, checking it binds the key of a signed report.
+fn verifier_quote(quote_file: &Path, signed: &SignedReport) -> Result<Vec<u8>> {
+    let quote = std::fs::read(quote_file).context("Failed to read verifier quote file")?;
+    let quote = crate::hex_decode(&quote, false)?;
+    let report_data = Quote::parse(&quote)
+        .context("Failed to parse verifier quote")?
+        .report
+        .report_data();
+    if report_data.as_bytes()[..32] != signed.key_hash() {
+        bail!("Verifier quote does not bind the signing key");
+    }
+    Ok(quote)
+}
+
+/// Write a file next to its destination, sync it and rename it into place, so the destination
+/// is either missing, as before, or complete, even if the process is killed while writing.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let mut file = std::fs::File::create(&tmp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// The text report of a decoded quote.
+pub fn quote_text(quote: &Quote, anomalies: &[Anomaly], platform_family: Option<&str>) -> String {
+    let mut out = String::new();
+    field(&mut out, "Quote version", quote.header.version);
+    field(&mut out, "TEE", tee_name(&quote.report));
+    field(&mut out, "QE SVN", quote.header.qe_svn);
+    field(&mut out, "PCE SVN", quote.header.pce_svn);
+    if let Ok(fmspc) = quote.fmspc() {
+        field(&mut out, "FMSPC", fmspc);
+    }
+    if let Some(family) = platform_family {
+        field(&mut out, "Platform family", family);
+    }
+    let anomalies = anomalies
+        .iter()
+        .map(|anomaly| format!("{anomaly:?}"))
+        .collect::<Vec<_>>();
+    field(&mut out, "Anomalies", list(&anomalies));
+    report_fields(&mut out, &quote.report);
+    out
+}
+
+/// The text report of a verified quote.
+pub fn verified_text(report: &VerifiedReport, platform_family: Option<&str>) -> String {
+    let mut out = String::new();
+    field(&mut out, "TCB status", report.status);
+    field(&mut out, "Advisories", list(&report.advisory_ids));
+    let warnings = report
+        .warnings
+        .iter()
+        .map(|warning| format!("{warning:?}"))
+        .collect::<Vec<_>>();
+    field(&mut out, "Warnings", list(&warnings));
+    if let Some(supplemental) = &report.supplemental {
+        field(&mut out, "FMSPC", supplemental.fmspc);
+        field(
+            &mut out,
+            "TCB eval number",
+            supplemental.tcb_evaluation_data_number,
+        );
+        if let Some(tcb_date) = &supplemental.tcb_date {
+            field(&mut out, "TCB date", tcb_date);
+        }
+    }
+    if let Some(family) = platform_family {
+        field(&mut out, "Platform family", family);
+    }
+    field(&mut out, "TEE", tee_name(&report.report));
+    report_fields(&mut out, &report.report);
+    out
+}
+
+fn tee_name(report: &Report) -> &'static str {
+    match report {
+        Report::SgxEnclave(_) => "SGX",
+        Report::TD10(_) => "TDX 1.0",
+        Report::TD15(_) => "TDX 1.5",
+        _ => "unknown",
+    }
+}
+
+/// The measurements and attributes of an enclave or TD report.
+fn report_fields(out: &mut String, report: &Report) {
+    field(out, "Debug", if report.is_debug() { "yes" } else { "no" });
+    match report {
+        Report::SgxEnclave(enclave) => {
+            field(out, "MRENCLAVE", hex::encode(enclave.mr_enclave));
+            field(out, "MRSIGNER", hex::encode(enclave.mr_signer));
+            field(out, "ISV prod ID", enclave.isv_prod_id);
+            field(out, "ISV SVN", enclave.isv_svn);
+            field(out, "Attributes", hex::encode(enclave.attributes));
+            field(out, "Report data", hex::encode(enclave.report_data));
+        }
+        Report::TD10(td) => td_fields(out, td, None),
+        Report::TD15(td) => td_fields(out, &td.base, Some(&td.mr_service_td)),
+        _ => {}
+    }
+}
+
+fn td_fields(out: &mut String, td: &TDReport10, mr_service_td: Option<&[u8; 48]>) {
+    field(out, "MRTD", hex::encode(td.mr_td));
+    for (index, rtmr) in [td.rt_mr0, td.rt_mr1, td.rt_mr2, td.rt_mr3]
+        .iter()
+        .enumerate()
+    {
+        field(out, &format!("RTMR{index}"), hex::encode(rtmr));
+    }
+    field(out, "MRSEAM", hex::encode(td.mr_seam));
+    field(out, "MRCONFIGID", hex::encode(td.mr_config_id));
+    field(out, "MROWNER", hex::encode(td.mr_owner));
+    field(out, "MROWNERCONFIG", hex::encode(td.mr_owner_config));
+    if let Some(mr_service_td) = mr_service_td {
+        field(out, "MRSERVICETD", hex::encode(mr_service_td));
+    }
+    field(out, "TD attributes", hex::encode(td.td_attributes));
+    field(out, "XFAM", hex::encode(td.xfam));
+    field(out, "Report data", hex::encode(td.report_data));
+}
+
+fn list(items: &[String]) -> String {
+    if items.is_empty() {
+        "none".into()
+    } else {
+        items.join(", ")
+    }
+}
+
+fn field(out: &mut String, name: &str, value: impl Display) {
+    let name = format!("{name}:");
+    let _ = writeln!(out, "{name:<17}{value}");
+}
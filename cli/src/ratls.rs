@@ -0,0 +1,93 @@
+//! Fetching the certificate of an RA-TLS endpoint.
+
+use std::io::Write;
+use std::net::TcpStream;
+use std::sync::Arc;
+
+use anyhow::{Context as _, Result};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{ClientConfig, ClientConnection, DigitallySignedStruct, SignatureScheme};
+
+/// Accepts any certificate, since RA-TLS certificates are self-signed and trusted through their
+/// quote instead. Handshake signatures are still checked, so the peer holds the certificate key.
+#[derive(Debug)]
+struct AcceptAnyCert(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Connect to `addr` (`host:port`) and return the DER encoded leaf certificate of the peer.
+pub fn fetch_peer_cert(addr: &str) -> Result<Vec<u8>> {
+    let host = addr
+        .rsplit_once(':')
+        .map(|(host, _)| host)
+        .context("Address must be host:port")?;
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .context("Failed to configure TLS")?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyCert(provider)))
+        .with_no_client_auth();
+    let server_name = ServerName::try_from(host.to_string()).context("Invalid host name")?;
+    let mut conn =
+        ClientConnection::new(Arc::new(config), server_name).context("Failed to start TLS")?;
+    let mut sock = TcpStream::connect(addr).context("Failed to connect")?;
+    while conn.is_handshaking() {
+        conn.complete_io(&mut sock)
+            .context("TLS handshake failed")?;
+    }
+    let cert = conn
+        .peer_certificates()
+        .and_then(|certs| certs.first())
+        .context("Peer sent no certificate")?
+        .to_vec();
+    conn.send_close_notify();
+    let _ = conn.write_tls(&mut sock);
+    let _ = sock.flush();
+    Ok(cert)
+}
@@ -0,0 +1,89 @@
+//! Verifying quotes through a remote verification service, for machines without PCS access.
+//!
+//! The client posts the raw quote as `application/octet-stream` to the service URL, which answers
+//! with a [`SignedReport`] whose report is the JSON of the quote's [`VerifiedReport`], as
+//! `verify --save-report --sign-key` saves it. The report is trusted if it is signed by the pinned
+//! key, or by an attested verifier: one whose own quote, attached to the report, verifies against
+//! collateral fetched here and has the expected measurement.
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{bail, Context as _, Result};
+use dcap_qvl::collateral::{CollateralOptions, CollateralProvider};
+use dcap_qvl::quote::Quote;
+use dcap_qvl::signed_report::SignedReport;
+use dcap_qvl::verify::{VerifiedReport, VerifyOptions};
+
+/// Who the remote verifier must be.
+pub enum Trust<'a> {
+    /// The PEM file of the public key it signs reports with
+    Key(&'a Path),
+    /// The hex encoded MRENCLAVE or MRTD of an attested verifier, whose quote collateral comes
+    /// from this provider
+    Measurement(&'a str, &'a CollateralProvider),
+}
+
+/// Verify a quote through the service at `url`, returning the report it signed.
+pub fn verify_remote(url: &str, quote: &[u8], trust: Trust) -> Result<VerifiedReport> {
+    let timeout = Duration::from_secs(60);
+    eprintln!("Verifying quote with {url}");
+    let response = reqwest::blocking::Client::builder()
+        .timeout(timeout)
+        .build()
+        .context("Failed to create HTTP client")?
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+        .body(quote.to_vec())
+        .send()
+        .context("Failed to reach the verification service")?
+        .error_for_status()
+        .context("Verification service failed")?
+        .bytes()
+        .context("Failed to read the verification service response")?;
+    let signed = SignedReport::from_json(&response)?;
+    let json = match trust {
+        Trust::Key(key_file) => {
+            let pem = std::fs::read(key_file).context("Failed to read remote key file")?;
+            let pem = pem::parse(pem).context("Failed to parse remote key PEM")?;
+            if pem.tag() != "PUBLIC KEY" {
+                bail!("Remote key PEM block is {}, not PUBLIC KEY", pem.tag());
+            }
+            signed.verify_with_key(pem.contents())?
+        }
+        Trust::Measurement(measurement, provider) => {
+            let expected = hex::decode(measurement).context("Invalid remote measurement hex")?;
+            let verifier_quote = signed
+                .verifier_quote
+                .as_deref()
+                .context("Report is not signed by an attested verifier")?;
+            let collateral = crate::collateral_for_quote(
+                provider,
+                verifier_quote,
+                timeout,
+                &CollateralOptions::default(),
+            )?;
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)?
+                .as_secs();
+            let (json, verifier) =
+                signed.verify_chained(&collateral, now, &VerifyOptions::default())?;
+            if verifier.report.measurement().as_bytes() != expected {
+                bail!("Remote verifier has an unexpected measurement");
+            }
+            eprintln!(
+                "Report signed by an attested verifier, TCB status {}",
+                verifier.status
+            );
+            json
+        }
+    };
+    let report: VerifiedReport =
+        serde_json::from_value(json).context("Failed to decode the remote report")?;
+    // A report signed for another quote would pass otherwise
+    let quote = Quote::parse(quote).context("Failed to parse quote")?;
+    if serde_json::to_value(&report.report)? != serde_json::to_value(&quote.report)? {
+        bail!("Remote report is not of this quote");
+    }
+    Ok(report)
+}
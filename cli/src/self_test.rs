@@ -0,0 +1,142 @@
+//! The `self-test` subcommand: known-answer tests of the crypto and parsing stack, for startup
+//! checks in regulated deployments.
+//!
+//! The tests only use the bundled sample quotes and collateral, verified at fixed times, so they
+//! need no network access and give the same answers on every run.
+
+use anyhow::{bail, ensure, Context, Result};
+use dcap_qvl::quote::Quote;
+use dcap_qvl::signature::{RingVerifier, SignatureVerifier};
+use dcap_qvl::verify::{verify, TcbStatus};
+use dcap_qvl::QuoteCollateralV3;
+
+const SGX_QUOTE: &[u8] = include_bytes!("../../sample/sgx_quote");
+const SGX_COLLATERAL: &[u8] = include_bytes!("../../sample/sgx_quote_collateral.json");
+const SGX_NOW: u64 = 1699301000;
+const TDX_QUOTE: &[u8] = include_bytes!("../../sample/tdx_quote");
+const TDX_COLLATERAL: &[u8] = include_bytes!("../../sample/tdx_quote_collateral.json");
+const TDX_NOW: u64 = 1741852249;
+
+/// A known-answer test, failing with how the answer differed.
+type KnownAnswerTest = fn() -> Result<()>;
+
+const TESTS: [(&str, KnownAnswerTest); 7] = [
+    ("ecdsa_p256_verify", ecdsa_p256_verify),
+    ("ecdsa_p256_reject", ecdsa_p256_reject),
+    ("sgx_quote_parse", sgx_quote_parse),
+    ("sgx_quote_verify", sgx_quote_verify),
+    ("tdx_quote_verify", tdx_quote_verify),
+    ("tampered_quote_reject", tampered_quote_reject),
+    ("expired_collateral_reject", expired_collateral_reject),
+];
+
+/// Run every test, printing its outcome to stderr, and fail if any did.
+pub fn run() -> Result<()> {
+    let mut failed = 0;
+    for (name, test) in TESTS {
+        match test() {
+            Ok(()) => eprintln!("{name:<26} ok"),
+            Err(err) => {
+                failed += 1;
+                eprintln!("{name:<26} FAILED: {err:#}");
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} of {} self-tests failed", TESTS.len());
+    }
+    eprintln!("All {} self-tests passed", TESTS.len());
+    Ok(())
+}
+
+/// The attestation key of a quote, the bytes it signed and its signature.
+fn quote_signature(raw_quote: &[u8]) -> Result<([u8; 64], Vec<u8>, [u8; 64])> {
+    let layout = Quote::layout(raw_quote)?;
+    let key = raw_quote[layout.attestation_key.range()].try_into()?;
+    let signature = raw_quote[layout.ecdsa_signature.range()].try_into()?;
+    Ok((key, raw_quote[layout.signed.range()].to_vec(), signature))
+}
+
+fn ecdsa_p256_verify() -> Result<()> {
+    let (key, message, signature) = quote_signature(SGX_QUOTE)?;
+    RingVerifier.verify_p256_sha256(&key, &message, &signature)
+}
+
+fn ecdsa_p256_reject() -> Result<()> {
+    let (key, mut message, signature) = quote_signature(SGX_QUOTE)?;
+    message[0] ^= 1;
+    ensure!(
+        RingVerifier
+            .verify_p256_sha256(&key, &message, &signature)
+            .is_err(),
+        "Signature over altered message verified"
+    );
+    Ok(())
+}
+
+fn sgx_quote_parse() -> Result<()> {
+    let quote = Quote::parse(SGX_QUOTE)?;
+    ensure!(quote.header.version == 3, "Unexpected quote version");
+    let report = quote.as_sgx().context("Not an SGX quote")?;
+    ensure!(
+        hex::encode(report.mr_enclave)
+            == "33d8736db756ed4997e04ba358d27833188f1932ff7b1d156904d3f560452fbb",
+        "Unexpected MRENCLAVE"
+    );
+    ensure!(
+        quote.fmspc()?.to_string() == "00A067110000",
+        "Unexpected FMSPC"
+    );
+    Ok(())
+}
+
+fn sgx_quote_verify() -> Result<()> {
+    let collateral: QuoteCollateralV3 = serde_json::from_slice(SGX_COLLATERAL)?;
+    let report = verify(SGX_QUOTE, &collateral, SGX_NOW)?;
+    ensure!(
+        report.status == TcbStatus::ConfigurationAndSwHardeningNeeded,
+        "Unexpected TCB status {:?}",
+        report.status
+    );
+    ensure!(
+        report.advisory_ids == ["INTEL-SA-00289", "INTEL-SA-00615"],
+        "Unexpected advisories {:?}",
+        report.advisory_ids
+    );
+    Ok(())
+}
+
+fn tdx_quote_verify() -> Result<()> {
+    let collateral: QuoteCollateralV3 = serde_json::from_slice(TDX_COLLATERAL)?;
+    let report = verify(TDX_QUOTE, &collateral, TDX_NOW)?;
+    ensure!(
+        report.status == TcbStatus::UpToDate,
+        "Unexpected TCB status {:?}",
+        report.status
+    );
+    let fmspc = Quote::parse(TDX_QUOTE)?.fmspc()?;
+    ensure!(fmspc.to_string() == "B0C06F000000", "Unexpected FMSPC");
+    Ok(())
+}
+
+fn tampered_quote_reject() -> Result<()> {
+    let collateral: QuoteCollateralV3 = serde_json::from_slice(SGX_COLLATERAL)?;
+    let layout = Quote::layout(SGX_QUOTE)?;
+    let mut quote = SGX_QUOTE.to_vec();
+    quote[layout.report_data.offset] ^= 1;
+    ensure!(
+        verify(&quote, &collateral, SGX_NOW).is_err(),
+        "Quote with altered report data verified"
+    );
+    Ok(())
+}
+
+fn expired_collateral_reject() -> Result<()> {
+    let collateral: QuoteCollateralV3 = serde_json::from_slice(SGX_COLLATERAL)?;
+    let ten_years_later = SGX_NOW + 10 * 365 * 24 * 3600;
+    ensure!(
+        verify(SGX_QUOTE, &collateral, ten_years_later).is_err(),
+        "Quote verified against expired collateral"
+    );
+    Ok(())
+}
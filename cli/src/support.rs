@@ -0,0 +1,112 @@
+//! The `support-bundle` subcommand: package everything needed to triage a verification issue.
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use dcap_qvl::collateral::{CollateralOptions, CollateralProvider};
+use dcap_qvl::quote::Quote;
+use dcap_qvl::verify::verify;
+use serde_json::{json, Value};
+
+/// Options of a support bundle.
+pub struct BundleOptions<'a> {
+    pub pccs_url: &'a str,
+    /// Store the quote with its report data and QE auth data zeroed
+    pub redact: bool,
+    pub now: u64,
+}
+
+/// Fetch collateral for `quote`, verify it and write a tarball with the quote, the collateral,
+/// the verification outcome and environment info to `output`.
+///
+/// Failures to fetch or verify are recorded in the bundle rather than returned, since those are
+/// what the bundle is for.
+pub fn write_bundle(quote: &[u8], options: &BundleOptions<'_>, output: &Path) -> Result<()> {
+    let mut files: Vec<(&str, Vec<u8>)> = Vec::new();
+
+    let stored_quote = if options.redact {
+        Quote::redact(quote).context("Failed to redact quote")?
+    } else {
+        quote.to_vec()
+    };
+    files.push(("quote.bin", stored_quote));
+    let decoded = match Quote::parse(quote) {
+        Ok(decoded) => {
+            // The decoded quote would show what redaction removed
+            let decoded = if options.redact {
+                Value::Null
+            } else {
+                serde_json::to_value(&decoded)?
+            };
+            json!({ "quote": decoded, "anomalies": Quote::anomalies(quote)? })
+        }
+        Err(err) => json!({ "error": format!("{err:#}") }),
+    };
+    files.push(("decoded.json", to_json(&decoded)?));
+
+    let provider = if options.pccs_url.is_empty() {
+        CollateralProvider::Pcs
+    } else {
+        CollateralProvider::Pccs(options.pccs_url.into())
+    };
+    let collateral = super::collateral_for_quote(
+        &provider,
+        quote,
+        Duration::from_secs(60),
+        &CollateralOptions::default(),
+    );
+    let verification = match &collateral {
+        Ok(collateral) => {
+            files.push((
+                "collateral.json",
+                to_json(&serde_json::to_value(collateral)?)?,
+            ));
+            match verify(quote, collateral, options.now) {
+                Ok(report) => json!({ "ok": true, "report": report }),
+                Err(err) => json!({ "ok": false, "trace": error_trace(&err) }),
+            }
+        }
+        Err(err) => json!({ "ok": false, "collateral_trace": error_trace(err) }),
+    };
+    files.push(("verification.json", to_json(&verification)?));
+
+    let collateral_source = if options.pccs_url.is_empty() {
+        "PCS"
+    } else {
+        options.pccs_url
+    };
+    let environment = json!({
+        "dcap_qvl_version": dcap_qvl::VERSION,
+        "cli_version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "now": options.now,
+        "collateral_source": collateral_source,
+        "redacted": options.redact,
+    });
+    files.push(("environment.json", to_json(&environment)?));
+
+    let file = std::fs::File::create(output).context("Failed to create bundle file")?;
+    let mut archive = tar::Builder::new(file);
+    for (name, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(options.now);
+        archive
+            .append_data(&mut header, name, &data[..])
+            .with_context(|| format!("Failed to add {name} to bundle"))?;
+    }
+    archive.finish().context("Failed to write bundle")?;
+    Ok(())
+}
+
+/// The error and its chain of causes, outermost first.
+fn error_trace(err: &anyhow::Error) -> Vec<String> {
+    err.chain().map(|cause| cause.to_string()).collect()
+}
+
+fn to_json(value: &Value) -> Result<Vec<u8>> {
+    serde_json::to_vec_pretty(value).context("Failed to serialize bundle file")
+}
@@ -0,0 +1,350 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+
+use std::sync::Arc;
+
+use assert_cmd::Command;
+use dcap_qvl::mock::{MockQuoteBuilder, MockRaTlsCert};
+use dcap_qvl::quote::TeeType;
+use dcap_qvl::signed_report::SignedReport;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use tempfile::TempDir;
+
+fn dcap_qvl() -> Command {
+    Command::cargo_bin("dcap-qvl").unwrap()
+}
+
+fn sample(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../sample")
+        .join(name)
+}
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+/// Generate a mock SGX quote in `dir`, returning the paths of the quote, its collateral and the
+/// root CA they verify against.
+fn mock_quote(dir: &Path) -> (PathBuf, PathBuf, PathBuf) {
+    let (quote, collateral, root_ca) = (
+        dir.join("quote.bin"),
+        dir.join("collateral.json"),
+        dir.join("root_ca.der"),
+    );
+    dcap_qvl()
+        .args(["mock-quote", "--tee", "sgx", "--out"])
+        .arg(&quote)
+        .arg("--collateral-out")
+        .arg(&collateral)
+        .arg("--root-ca-out")
+        .arg(&root_ca)
+        .assert()
+        .success();
+    (quote, collateral, root_ca)
+}
+
+#[test]
+fn extract_prints_a_field_and_fails_on_a_missing_one() {
+    dcap_qvl()
+        .args(["extract", "--field", "fmspc"])
+        .arg(sample("sgx_quote"))
+        .assert()
+        .success()
+        .stdout("00A067110000\n");
+    dcap_qvl()
+        .args(["extract", "--field", "mr_td"])
+        .arg(sample("sgx_quote"))
+        .assert()
+        .failure();
+}
+
+#[test]
+fn self_test_passes() {
+    dcap_qvl().arg("self-test").assert().success();
+}
+
+#[test]
+fn saves_the_report_signed_into_one_document() {
+    let dir = TempDir::new().unwrap();
+    let (quote, collateral, root_ca) = mock_quote(dir.path());
+    let report = dir.path().join("report.json");
+    dcap_qvl()
+        .arg("verify")
+        .arg("--collateral")
+        .arg(&collateral)
+        .arg("--root-ca")
+        .arg(&root_ca)
+        .arg("--save-report")
+        .arg(&report)
+        .arg("--sign-key")
+        .arg(fixture("report_key.pem"))
+        .arg(&quote)
+        .assert()
+        .success();
+
+    // Renamed into place, with no temporary file or detached signature left behind
+    let mut files: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        ["collateral.json", "quote.bin", "report.json", "root_ca.der"]
+    );
+
+    let signed = SignedReport::from_json(&std::fs::read(&report).unwrap()).unwrap();
+    let public_key = pem::parse(std::fs::read(fixture("report_key.pub.pem")).unwrap()).unwrap();
+    let json = signed.verify_with_key(public_key.contents()).unwrap();
+    assert_eq!(json["status"], "UpToDate");
+
+    let mut tampered = signed.clone();
+    tampered.report[0] ^= 1;
+    assert!(tampered.verify_signature().is_err());
+}
+
+#[test]
+fn sign_key_requires_save_report() {
+    dcap_qvl()
+        .args(["verify", "--sign-key"])
+        .arg(fixture("report_key.pem"))
+        .arg(sample("sgx_quote"))
+        .assert()
+        .failure();
+}
+
+/// Serve `body` to every request on a local port, as a verification service would, returning
+/// its URL.
+fn serve(body: Vec<u8>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/verify", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut reader = BufReader::new(stream.unwrap());
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(len) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = len.trim().parse().unwrap();
+                }
+            }
+            let mut request = vec![0; content_length];
+            reader.read_exact(&mut request).unwrap();
+            let mut stream = reader.into_inner();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(&body).unwrap();
+        }
+    });
+    url
+}
+
+#[test]
+fn verifies_through_a_remote_verifier() {
+    let dir = TempDir::new().unwrap();
+    let (quote, collateral, root_ca) = mock_quote(dir.path());
+    let report = dir.path().join("report.json");
+    dcap_qvl()
+        .arg("verify")
+        .arg("--collateral")
+        .arg(&collateral)
+        .arg("--root-ca")
+        .arg(&root_ca)
+        .arg("--save-report")
+        .arg(&report)
+        .arg("--sign-key")
+        .arg(fixture("report_key.pem"))
+        .arg(&quote)
+        .assert()
+        .success();
+    let url = serve(std::fs::read(&report).unwrap());
+
+    dcap_qvl()
+        .args(["verify", "--remote", &url, "--remote-key"])
+        .arg(fixture("report_key.pub.pem"))
+        .arg(&quote)
+        .assert()
+        .success();
+    // Signed by the trusted key, but for another quote
+    dcap_qvl()
+        .args(["verify", "--remote", &url, "--remote-key"])
+        .arg(fixture("report_key.pub.pem"))
+        .arg(sample("sgx_quote"))
+        .assert()
+        .failure();
+    // Signed by a key other than the trusted one
+    dcap_qvl()
+        .args(["verify", "--remote", &url, "--remote-key"])
+        .arg(fixture("other_key.pub.pem"))
+        .arg(&quote)
+        .assert()
+        .failure();
+}
+
+/// Generate a mock RA-TLS certificate of a TD, writing it PEM encoded in `dir` with the
+/// collateral and root CA of its quote, returning their paths.
+fn mock_ra_tls_cert(dir: &Path) -> (MockRaTlsCert, PathBuf, PathBuf, PathBuf) {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mock = MockQuoteBuilder::new(TeeType::Tdx)
+        .mr_td([0x33; 48])
+        .build_ra_tls_cert(now)
+        .unwrap();
+    let (cert, collateral, root_ca) = (
+        dir.join("cert.pem"),
+        dir.join("collateral.json"),
+        dir.join("root_ca.der"),
+    );
+    let pem = pem::Pem::new("CERTIFICATE", mock.cert.clone());
+    std::fs::write(&cert, pem::encode(&pem)).unwrap();
+    std::fs::write(
+        &collateral,
+        serde_json::to_vec(&mock.quote.collateral).unwrap(),
+    )
+    .unwrap();
+    std::fs::write(&root_ca, &mock.quote.root_ca).unwrap();
+    (mock, cert, collateral, root_ca)
+}
+
+#[test]
+fn verifies_ra_tls_certificates() {
+    let dir = TempDir::new().unwrap();
+    let (_, cert, collateral, root_ca) = mock_ra_tls_cert(dir.path());
+    let output = dcap_qvl()
+        .arg("verify-cert")
+        .arg("--collateral")
+        .arg(&collateral)
+        .arg("--root-ca")
+        .arg(&root_ca)
+        .arg(&cert)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let report: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(report["status"], "UpToDate");
+
+    // Mock quotes don't chain to the Intel SGX root CA
+    dcap_qvl()
+        .arg("verify-cert")
+        .arg("--collateral")
+        .arg(&collateral)
+        .arg(&cert)
+        .assert()
+        .failure();
+    // The root CA carries no quote
+    dcap_qvl()
+        .arg("verify-cert")
+        .arg("--collateral")
+        .arg(&collateral)
+        .arg("--root-ca")
+        .arg(&root_ca)
+        .arg(&root_ca)
+        .assert()
+        .failure();
+}
+
+#[test]
+fn extracts_the_quote_of_a_certificate() {
+    let dir = TempDir::new().unwrap();
+    let (mock, cert, _, root_ca) = mock_ra_tls_cert(dir.path());
+    let quote = dir.path().join("quote.bin");
+    dcap_qvl()
+        .arg("extract-quote")
+        .arg(&cert)
+        .arg("--output")
+        .arg(&quote)
+        .assert()
+        .success();
+    assert_eq!(std::fs::read(&quote).unwrap(), mock.quote.quote);
+
+    let quote_hex = dir.path().join("quote.hex");
+    dcap_qvl()
+        .args(["extract-quote", "--hex"])
+        .arg(&cert)
+        .arg("--output")
+        .arg(&quote_hex)
+        .assert()
+        .success();
+    assert_eq!(
+        std::fs::read_to_string(&quote_hex).unwrap(),
+        hex::encode(&mock.quote.quote)
+    );
+
+    dcap_qvl()
+        .arg("extract-quote")
+        .arg(&root_ca)
+        .arg("--output")
+        .arg(&quote)
+        .assert()
+        .failure();
+}
+
+/// Serve TLS with the certificate and key of `mock` on a local port, returning its address.
+fn serve_tls(mock: &MockRaTlsCert) -> String {
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .unwrap()
+    .with_no_client_auth()
+    .with_single_cert(
+        vec![CertificateDer::from(mock.cert.clone())],
+        PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(mock.key.clone())),
+    )
+    .unwrap();
+    let config = Arc::new(config);
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut conn = rustls::ServerConnection::new(config.clone()).unwrap();
+            while conn.is_handshaking() {
+                if conn.complete_io(&mut stream).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    addr
+}
+
+#[test]
+fn probes_ra_tls_endpoints() {
+    let dir = TempDir::new().unwrap();
+    let (mock, _, _, _) = mock_ra_tls_cert(dir.path());
+    let addr = serve_tls(&mock);
+    // The quote is extracted from the served certificate, then its collateral can't be fetched
+    // from the unreachable PCCS
+    let output = dcap_qvl()
+        .args(["ratls-probe", &addr])
+        .env("PCCS_URL", "http://127.0.0.1:1/sgx/certification/v4")
+        .assert()
+        .failure()
+        .get_output()
+        .stderr
+        .clone();
+    let stderr = String::from_utf8(output).unwrap();
+    assert!(stderr.contains("Failed to verify quote"), "{stderr}");
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    drop(listener);
+    dcap_qvl().args(["ratls-probe", &addr]).assert().failure();
+}
@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.20;
+
+/// @title Reference verifier for proofs produced by `dcap_qvl::evm::prove`.
+/// @notice The PCK certificate chain and collateral are checked off-chain by dcap-qvl. This
+/// contract re-checks the quote signatures against an allowlisted PCK key and a TCB assertion
+/// allowlisted for that key. Assertions commit to the PCK key, see `dcap_qvl::evm::tcb_assertion`.
+/// Requires the P-256 verification precompile (RIP-7212) at address 0x100. Compile with via-IR.
+contract DcapProofVerifier {
+    address private constant P256_VERIFY = address(0x100);
+    uint256 private constant QE_REPORT_DATA_OFFSET = 320;
+
+    address public owner;
+    mapping(bytes32 => bool) public trustedPckKeys;
+    /// The TCB assertions trusted for each PCK key, by the hash of the key.
+    mapping(bytes32 => mapping(bytes32 => bool)) public trustedTcbAssertions;
+
+    constructor() {
+        owner = msg.sender;
+    }
+
+    modifier onlyOwner() {
+        require(msg.sender == owner, "not owner");
+        _;
+    }
+
+    function setPckKey(bytes32 x, bytes32 y, bool trusted) external onlyOwner {
+        trustedPckKeys[keccak256(abi.encode(x, y))] = trusted;
+    }
+
+    function setTcbAssertion(bytes32 x, bytes32 y, bytes32 assertion, bool trusted) external onlyOwner {
+        trustedTcbAssertions[keccak256(abi.encode(x, y))][assertion] = trusted;
+    }
+
+    /// @notice Verify an ABI encoded proof, returning the signed quote header and body.
+    function verify(bytes calldata proof) external view returns (bytes memory signedQuote) {
+        bytes32 quoteSigR;
+        bytes32 quoteSigS;
+        bytes32 attestationKeyX;
+        bytes32 attestationKeyY;
+        bytes memory qeReport;
+        bytes32 qeReportSigR;
+        bytes32 qeReportSigS;
+        bytes memory qeAuthData;
+        bytes32 pckKeyX;
+        bytes32 pckKeyY;
+        bytes32 tcbAssertion;
+        (
+            signedQuote,
+            quoteSigR,
+            quoteSigS,
+            attestationKeyX,
+            attestationKeyY,
+            qeReport,
+            qeReportSigR,
+            qeReportSigS,
+            qeAuthData,
+            pckKeyX,
+            pckKeyY,
+            tcbAssertion
+        ) = abi.decode(
+            proof,
+            (bytes, bytes32, bytes32, bytes32, bytes32, bytes, bytes32, bytes32, bytes, bytes32, bytes32, bytes32)
+        );
+
+        bytes32 pckKeyHash = keccak256(abi.encode(pckKeyX, pckKeyY));
+        require(trustedPckKeys[pckKeyHash], "untrusted PCK key");
+        require(trustedTcbAssertions[pckKeyHash][tcbAssertion], "untrusted TCB assertion");
+
+        // The QE report is signed by the PCK key.
+        require(_p256Verify(sha256(qeReport), qeReportSigR, qeReportSigS, pckKeyX, pckKeyY), "invalid QE report signature");
+
+        // The QE report binds the attestation key and QE auth data.
+        bytes32 qeHash = sha256(abi.encodePacked(attestationKeyX, attestationKeyY, qeAuthData));
+        bytes32 reportData;
+        assembly {
+            reportData := mload(add(add(qeReport, 32), QE_REPORT_DATA_OFFSET))
+        }
+        require(reportData == qeHash, "QE report hash mismatch");
+
+        // The quote is signed by the attestation key.
+        require(
+            _p256Verify(sha256(signedQuote), quoteSigR, quoteSigS, attestationKeyX, attestationKeyY),
+            "invalid quote signature"
+        );
+    }
+
+    function _p256Verify(bytes32 hash, bytes32 r, bytes32 s, bytes32 x, bytes32 y) private view returns (bool) {
+        (bool ok, bytes memory result) = P256_VERIFY.staticcall(abi.encode(hash, r, s, x, y));
+        return ok && result.length == 32 && abi.decode(result, (uint256)) == 1;
+    }
+}
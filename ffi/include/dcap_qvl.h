@@ -0,0 +1,105 @@
+/* Generated by cbindgen from ffi/src/lib.rs, do not edit. */
+
+#ifndef DCAP_QVL_H
+#define DCAP_QVL_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The call succeeded.
+ */
+#define DCAP_QVL_OK 0
+
+/**
+ * A pointer argument is null, or a string argument is not UTF-8.
+ */
+#define DCAP_QVL_ERR_INVALID_ARGUMENT 1
+
+/**
+ * The quote could not be decoded.
+ */
+#define DCAP_QVL_ERR_INVALID_QUOTE 2
+
+/**
+ * The collateral JSON could not be decoded.
+ */
+#define DCAP_QVL_ERR_INVALID_COLLATERAL 3
+
+/**
+ * The quote or collateral failed a check.
+ */
+#define DCAP_QVL_ERR_VERIFICATION_FAILED 4
+
+/**
+ * An unexpected internal failure, such as a panic.
+ */
+#define DCAP_QVL_ERR_INTERNAL 5
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Verify a quote against its collateral.
+ *
+ * # Arguments
+ *
+ * * `quote` - The raw quote, `quote_len` bytes long
+ * * `collateral_json` - The NUL terminated collateral, in the JSON encoding of
+ *   `QuoteCollateralV3`
+ * * `now` - The verification time, in seconds since the UNIX epoch
+ * * `out_report_json` - Receives the verified report as JSON, or the error message
+ *
+ * # Returns
+ *
+ * `DCAP_QVL_OK`, or the error code. Unless `out_report_json` is null, `*out_report_json` is
+ * set to a string owned by the caller, to free with `dcap_qvl_string_free`.
+ *
+ * # Safety
+ *
+ * `quote` must point to `quote_len` readable bytes, `collateral_json` to a NUL terminated
+ * string, and `out_report_json` to a writable pointer.
+ */
+int32_t dcap_qvl_verify(const uint8_t *quote,
+                        size_t quote_len,
+                        const char *collateral_json,
+                        uint64_t now,
+                        char **out_report_json);
+
+/**
+ * Decode a quote, without verifying it.
+ *
+ * # Arguments
+ *
+ * * `quote` - The raw quote, `quote_len` bytes long
+ * * `out_quote_json` - Receives the decoded quote as JSON, or the error message
+ *
+ * # Returns
+ *
+ * `DCAP_QVL_OK`, or the error code. Unless `out_quote_json` is null, `*out_quote_json` is set
+ * to a string owned by the caller, to free with `dcap_qvl_string_free`.
+ *
+ * # Safety
+ *
+ * `quote` must point to `quote_len` readable bytes, and `out_quote_json` to a writable pointer.
+ */
+int32_t dcap_qvl_parse_quote(const uint8_t *quote, size_t quote_len, char **out_quote_json);
+
+/**
+ * Free a string returned by the library. Null is ignored.
+ *
+ * # Safety
+ *
+ * `s` must be null or a string returned by the library, not freed already.
+ */
+void dcap_qvl_string_free(char *s);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* DCAP_QVL_H */
@@ -0,0 +1,179 @@
+//! C bindings of `dcap-qvl`.
+//!
+//! All results are returned as JSON strings, allocated by the library and owned by the caller,
+//! who frees them with [`dcap_qvl_string_free`]. The functions return one of the `DCAP_QVL_*`
+//! status codes, which are stable: new codes may be added, existing ones keep their value. On
+//! failure the output string holds the error message instead, so it must be freed either way.
+//!
+//! The C header is `include/dcap_qvl.h`, generated by `cbindgen --config cbindgen.toml --output
+//! include/dcap_qvl.h`.
+
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use dcap_qvl::quote::Quote;
+use dcap_qvl::verify::verify;
+use dcap_qvl::QuoteCollateralV3;
+
+/// The call succeeded.
+pub const DCAP_QVL_OK: i32 = 0;
+/// A pointer argument is null, or a string argument is not UTF-8.
+pub const DCAP_QVL_ERR_INVALID_ARGUMENT: i32 = 1;
+/// The quote could not be decoded.
+pub const DCAP_QVL_ERR_INVALID_QUOTE: i32 = 2;
+/// The collateral JSON could not be decoded.
+pub const DCAP_QVL_ERR_INVALID_COLLATERAL: i32 = 3;
+/// The quote or collateral failed a check.
+pub const DCAP_QVL_ERR_VERIFICATION_FAILED: i32 = 4;
+/// An unexpected internal failure, such as a panic.
+pub const DCAP_QVL_ERR_INTERNAL: i32 = 5;
+
+/// A failed call, with its status code and message.
+struct Error {
+    code: i32,
+    message: String,
+}
+
+impl Error {
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Verify a quote against its collateral.
+///
+/// # Arguments
+///
+/// * `quote` - The raw quote, `quote_len` bytes long
+/// * `collateral_json` - The NUL terminated collateral, in the JSON encoding of
+///   `QuoteCollateralV3`
+/// * `now` - The verification time, in seconds since the UNIX epoch
+/// * `out_report_json` - Receives the verified report as JSON, or the error message
+///
+/// # Returns
+///
+/// `DCAP_QVL_OK`, or the error code. Unless `out_report_json` is null, `*out_report_json` is
+/// set to a string owned by the caller, to free with `dcap_qvl_string_free`.
+///
+/// # Safety
+///
+/// `quote` must point to `quote_len` readable bytes, `collateral_json` to a NUL terminated
+/// string, and `out_report_json` to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn dcap_qvl_verify(
+    quote: *const u8,
+    quote_len: usize,
+    collateral_json: *const c_char,
+    now: u64,
+    out_report_json: *mut *mut c_char,
+) -> i32 {
+    run(out_report_json, || {
+        let quote = bytes_arg(quote, quote_len, "quote")?;
+        let collateral_json = str_arg(collateral_json, "collateral_json")?;
+        let collateral = QuoteCollateralV3::from_json(collateral_json)
+            .map_err(|err| Error::new(DCAP_QVL_ERR_INVALID_COLLATERAL, format!("{err:#}")))?;
+        if let Err(err) = Quote::parse(quote) {
+            return Err(Error::new(DCAP_QVL_ERR_INVALID_QUOTE, format!("{err:#}")));
+        }
+        let report = verify(quote, &collateral, now)
+            .map_err(|err| Error::new(DCAP_QVL_ERR_VERIFICATION_FAILED, format!("{err:#}")))?;
+        to_json(&report)
+    })
+}
+
+/// Decode a quote, without verifying it.
+///
+/// # Arguments
+///
+/// * `quote` - The raw quote, `quote_len` bytes long
+/// * `out_quote_json` - Receives the decoded quote as JSON, or the error message
+///
+/// # Returns
+///
+/// `DCAP_QVL_OK`, or the error code. Unless `out_quote_json` is null, `*out_quote_json` is set
+/// to a string owned by the caller, to free with `dcap_qvl_string_free`.
+///
+/// # Safety
+///
+/// `quote` must point to `quote_len` readable bytes, and `out_quote_json` to a writable pointer.
+#[no_mangle]
+pub unsafe extern "C" fn dcap_qvl_parse_quote(
+    quote: *const u8,
+    quote_len: usize,
+    out_quote_json: *mut *mut c_char,
+) -> i32 {
+    run(out_quote_json, || {
+        let quote = bytes_arg(quote, quote_len, "quote")?;
+        let quote = Quote::parse(quote)
+            .map_err(|err| Error::new(DCAP_QVL_ERR_INVALID_QUOTE, format!("{err:#}")))?;
+        to_json(&quote)
+    })
+}
+
+/// Free a string returned by the library. Null is ignored.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by the library, not freed already.
+#[no_mangle]
+pub unsafe extern "C" fn dcap_qvl_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Run `f`, storing its result or error message in `out` and returning the status code.
+unsafe fn run(out: *mut *mut c_char, f: impl FnOnce() -> Result<String, Error>) -> i32 {
+    if out.is_null() {
+        return DCAP_QVL_ERR_INVALID_ARGUMENT;
+    }
+    *out = ptr::null_mut();
+    let (code, output) = match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(json)) => (DCAP_QVL_OK, json),
+        Ok(Err(err)) => (err.code, err.message),
+        Err(_) => (DCAP_QVL_ERR_INTERNAL, "Panicked".into()),
+    };
+    // Neither JSON nor the error messages contain NUL, but don't trust them to
+    let output = CString::new(output.replace('\0', "")).unwrap_or_default();
+    *out = output.into_raw();
+    code
+}
+
+unsafe fn bytes_arg<'a>(data: *const u8, len: usize, name: &str) -> Result<&'a [u8], Error> {
+    if data.is_null() {
+        return Err(Error::new(
+            DCAP_QVL_ERR_INVALID_ARGUMENT,
+            format!("{name} is null"),
+        ));
+    }
+    Ok(slice::from_raw_parts(data, len))
+}
+
+unsafe fn str_arg<'a>(s: *const c_char, name: &str) -> Result<&'a str, Error> {
+    if s.is_null() {
+        return Err(Error::new(
+            DCAP_QVL_ERR_INVALID_ARGUMENT,
+            format!("{name} is null"),
+        ));
+    }
+    CStr::from_ptr(s).to_str().map_err(|_| {
+        Error::new(
+            DCAP_QVL_ERR_INVALID_ARGUMENT,
+            format!("{name} is not UTF-8"),
+        )
+    })
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, Error> {
+    serde_json::to_string(value).map_err(|err| {
+        Error::new(
+            DCAP_QVL_ERR_INTERNAL,
+            format!("Failed to serialize JSON: {err}"),
+        )
+    })
+}
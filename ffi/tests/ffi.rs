@@ -0,0 +1,52 @@
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+use dcap_qvl_ffi::{
+    dcap_qvl_parse_quote, dcap_qvl_string_free, dcap_qvl_verify, DCAP_QVL_ERR_INVALID_QUOTE,
+    DCAP_QVL_ERR_VERIFICATION_FAILED, DCAP_QVL_OK,
+};
+
+/// Take the string returned by the library, freeing it.
+fn take(s: *mut c_char) -> String {
+    assert!(!s.is_null());
+    let owned = unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_owned();
+    unsafe { dcap_qvl_string_free(s) };
+    owned
+}
+
+fn verify(quote: &[u8], now: u64) -> (i32, String) {
+    let collateral =
+        CString::new(&include_bytes!("../../sample/tdx_quote_collateral.json")[..]).unwrap();
+    let mut out = ptr::null_mut();
+    let code = unsafe {
+        dcap_qvl_verify(
+            quote.as_ptr(),
+            quote.len(),
+            collateral.as_ptr(),
+            now,
+            &mut out,
+        )
+    };
+    (code, take(out))
+}
+
+#[test]
+fn could_verify_over_ffi() {
+    let quote = include_bytes!("../../sample/tdx_quote");
+    let (code, report) = verify(quote, 1741852249);
+    assert_eq!(code, DCAP_QVL_OK, "{report}");
+    let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+    assert_eq!(report["status"], "UpToDate");
+
+    let (code, message) = verify(quote, 0);
+    assert_eq!(code, DCAP_QVL_ERR_VERIFICATION_FAILED, "{message}");
+
+    let (code, _) = verify(&quote[..10], 1741852249);
+    assert_eq!(code, DCAP_QVL_ERR_INVALID_QUOTE);
+
+    let mut out = ptr::null_mut();
+    let code = unsafe { dcap_qvl_parse_quote(quote.as_ptr(), quote.len(), &mut out) };
+    assert_eq!(code, DCAP_QVL_OK);
+    let decoded: serde_json::Value = serde_json::from_str(&take(out)).unwrap();
+    assert!(decoded["header"].is_object());
+}
@@ -0,0 +1,57 @@
+//! Thin wrapper for verifying DCAP quotes inside zkVM guests (Risc0, SP1).
+//!
+//! The guest reads a SCALE encoded [`GuestInput`], calls [`run`] and commits the encoded
+//! [`GuestOutput`] to the journal. Verification is deterministic: the time comes from the input
+//! and nothing is fetched or randomized. For example with Risc0:
+//!
+//! ```ignore
+//! risc0_zkvm::guest::entry!(main);
+//!
+//! fn main() {
+//!     let input: Vec<u8> = risc0_zkvm::guest::env::read();
+//!     let output = dcap_qvl_guest::run(&input).expect("quote verification failed");
+//!     risc0_zkvm::guest::env::commit_slice(&output);
+//! }
+//! ```
+
+use anyhow::{Context, Result};
+use dcap_qvl::verify::{verify, VerifiedReport};
+use dcap_qvl::QuoteCollateralV3;
+use scale::{Decode, Encode};
+
+/// The private input of the guest.
+#[derive(Encode, Decode, Debug, Clone)]
+pub struct GuestInput {
+    pub quote: Vec<u8>,
+    pub collateral: QuoteCollateralV3,
+    /// The verification time in seconds since the Unix epoch
+    pub now: u64,
+}
+
+/// The public output of the guest.
+#[derive(Encode, Decode, Debug, Clone)]
+pub struct GuestOutput {
+    /// SHA-256 of the verified quote
+    pub quote_hash: [u8; 32],
+    pub now: u64,
+    pub report: VerifiedReport,
+}
+
+/// Decode the input, verify the quote and encode the output.
+pub fn run(input: &[u8]) -> Result<Vec<u8>> {
+    let input = GuestInput::decode(&mut &input[..]).context("Failed to decode guest input")?;
+    verify_input(&input).map(|output| output.encode())
+}
+
+/// Verify the quote of the input.
+pub fn verify_input(input: &GuestInput) -> Result<GuestOutput> {
+    let report = verify(&input.quote, &input.collateral, input.now)?;
+    let digest = ring::digest::digest(&ring::digest::SHA256, &input.quote);
+    let mut quote_hash = [0u8; 32];
+    quote_hash.copy_from_slice(digest.as_ref());
+    Ok(GuestOutput {
+        quote_hash,
+        now: input.now,
+        report,
+    })
+}
@@ -0,0 +1,406 @@
+//! Quotes appraised by Intel's Quote Appraisal Engine.
+//!
+//! Newer DCAP releases can emit, next to a quote, an appraisal token: a JWS signed by a key whose
+//! certificate chains up to the Intel SGX Root CA, carrying the appraisal results as claims.
+//! [`verify_appraised_quote`] checks the token and maps its claims into a [`VerifiedReport`], so
+//! such quotes can be handled like ones verified against collateral.
+//!
+//! The quote itself is not checked against collateral, the token vouches for its TCB. Its
+//! signatures are checked up to the root CA though: the PCK certificate chain, the QE report and
+//! the attestation key signing the quote. The token is bound to the quote by its report data, and
+//! every report field it claims must match the quote. The TCB status is the one claimed for the
+//! platform, merged with the one claimed for its QE if any.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine as _;
+use serde_json::Value;
+use webpki::types::CertificateDer;
+
+use crate::commitment::CollateralCommitment;
+use crate::constants::DCAP_SERVER_ROOTS;
+use crate::quote::{Quote, Report};
+use crate::signature::{self, RingVerifier, SignatureAlgorithm};
+use crate::trace::{Steps, VerifyTrace};
+use crate::utils;
+use crate::verify::{
+    check_quote_signatures, merge_module_status, quote_warnings, Revocation, SignedQuote,
+    TcbStatus, VerifiedReport, VerifyOptions,
+};
+
+const REPORT_DATA_CLAIMS: [&str; 4] = [
+    "tdx_reportdata",
+    "tdx_report_data",
+    "sgx_reportdata",
+    "sgx_report_data",
+];
+
+/// Verify an appraisal token and map its claims about `raw_quote` into a report.
+///
+/// # Arguments
+///
+/// * `raw_quote` - The raw quote the token was issued for
+/// * `token` - The appraisal token in JWS compact serialization
+/// * `now` - The current time in seconds since the Unix epoch
+///
+/// # Returns
+///
+/// * `Ok(VerifiedReport)` - The report, whose collateral commitment root is the SHA-256 of the
+///   token and has no leaves
+/// * `Err(Error)` - The error
+pub fn verify_appraised_quote(raw_quote: &[u8], token: &str, now: u64) -> Result<VerifiedReport> {
+    verify_appraised_quote_with_options(raw_quote, token, now, &VerifyOptions::default())
+}
+
+/// Verify an appraisal token like [`verify_appraised_quote`], trusting the root CA of `options`
+/// if set, e.g. a test CA, for both the token and the quote. Expired certificates are accepted as
+/// [`VerifyOptions::accept_expired_cert`] says, the other options don't apply to tokens.
+pub fn verify_appraised_quote_with_options(
+    raw_quote: &[u8],
+    token: &str,
+    now: u64,
+    options: &VerifyOptions,
+) -> Result<VerifiedReport> {
+    let claims = verify_token_with_options(token, now, options)?;
+    let quote = Quote::parse(raw_quote).context("Failed to parse quote")?;
+    // Tokens carry no CRLs, checking revocation is up to the appraisal
+    let revocation = Revocation {
+        root_ca_crl: None,
+        pck_crl: None,
+        require: false,
+        accept_expired: options.accept_expired_cert.clone(),
+        root_ca: options.root_ca.clone(),
+    };
+    let mut trace = VerifyTrace::default();
+    let mut steps = Steps::new(&mut trace);
+    let SignedQuote {
+        report,
+        chain_warnings,
+        ..
+    } = check_quote_signatures(
+        raw_quote,
+        quote,
+        None,
+        now,
+        &revocation,
+        &RingVerifier,
+        &mut steps,
+    )
+    .context("Failed to verify quote signatures")?;
+    steps.finish();
+
+    let (report_data_claim, fields) = report_fields(&report);
+    if claims_named(&claims, &REPORT_DATA_CLAIMS).is_empty() {
+        bail!("Appraisal token has no report data claim");
+    }
+    // Tokens may appraise several quotes, take the claims of the result about this one only
+    let result = select_result(&claims, &hex::encode(report_data_claim))
+        .context("Appraisal token is for a different quote")?;
+    for (name, value) in fields {
+        for claimed in claims_named(result, &[name]) {
+            if !claim_matches(claimed, &value) {
+                bail!("Appraisal token claim {name} does not match the quote");
+            }
+        }
+    }
+
+    // Like Intel's QVL, the platform is no more up to date than its QE
+    let (status, advisory_ids) = match unique_claim(result, "platform_tcb")? {
+        Some(platform) => {
+            let (mut status, mut advisory_ids) = tcb_claims(platform)?;
+            if let Some(qe) = unique_claim(result, "qe_tcb")? {
+                let (qe_status, qe_advisory_ids) = tcb_claims(qe)?;
+                status = merge_module_status(status, qe_status);
+                for id in qe_advisory_ids {
+                    if !advisory_ids.contains(&id) {
+                        advisory_ids.push(id);
+                    }
+                }
+            }
+            (status, advisory_ids)
+        }
+        None => tcb_claims(result)?,
+    };
+    let mut warnings = chain_warnings;
+    warnings.extend(quote_warnings(raw_quote, &report, status)?);
+    let digest = ring::digest::digest(&ring::digest::SHA256, token.as_bytes());
+    let mut root = [0u8; 32];
+    root.copy_from_slice(digest.as_ref());
+    Ok(VerifiedReport {
+        status,
+        advisory_ids,
+        report,
+        collateral_commitment: CollateralCommitment {
+            root,
+            leaves: Vec::new(),
+        },
+        warnings,
+        platform_id: None,
+        supplemental: None,
+        provenance: Vec::new(),
+    })
+}
+
+/// Check the signature and `x5c` certificate chain of an appraisal token and return its claims.
+pub fn verify_token(token: &str, now: u64) -> Result<Value> {
+    verify_token_with_options(token, now, &VerifyOptions::default())
+}
+
+/// Check an appraisal token like [`verify_token`], trusting the root CA of `options` if set.
+pub fn verify_token_with_options(token: &str, now: u64, options: &VerifyOptions) -> Result<Value> {
+    let Jws {
+        signing_input,
+        header,
+        claims,
+        signature,
+    } = decode_jws(token)?;
+
+    let certs = header
+        .get("x5c")
+        .and_then(Value::as_array)
+        .context("Token header has no x5c chain")?
+        .iter()
+        .map(|cert| {
+            let cert = cert.as_str().context("Invalid x5c entry")?;
+            Ok(CertificateDer::from(STANDARD.decode(cert)?))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let leaf = certs.first().context("Token x5c chain is empty")?;
+    let leaf_cert = webpki::EndEntityCert::try_from(leaf)
+        .context("Failed to parse token signing certificate")?;
+    let root_ca = options.root_ca.as_deref().map(CertificateDer::from);
+    let custom_roots;
+    let roots: &[webpki::types::TrustAnchor] = match &root_ca {
+        Some(root_ca) => {
+            custom_roots = [webpki::anchor_from_trusted_cert(root_ca)
+                .map_err(|_| anyhow!("Failed to parse root CA certificate"))?];
+            &custom_roots
+        }
+        None => DCAP_SERVER_ROOTS,
+    };
+    utils::verify_certificate_chain(&leaf_cert, &certs[1..], roots, now * 1000, &[], false)?;
+    // PCK certificates chain to the same root, but their keys belong to individual platforms
+    if utils::get_intel_extension(leaf).is_ok() {
+        bail!("Appraisal token is signed by a PCK certificate");
+    }
+
+    let (algorithm, signing_key) = utils::get_signing_key(leaf)?;
+    if header.get("alg").and_then(Value::as_str) != Some(jws_algorithm(algorithm)) {
+        bail!("Token algorithm does not match its signing key");
+    }
+    signature::verify_with_algorithm(
+        &RingVerifier,
+        algorithm,
+        &signing_key,
+        signing_input.as_bytes(),
+        &signature,
+    )
+    .context("Appraisal token signature is invalid")?;
+
+    if let Some(exp) = claims.get("exp").and_then(Value::as_u64) {
+        if now > exp {
+            bail!("Appraisal token expired");
+        }
+    }
+    Ok(claims)
+}
+
+/// A decoded JWS in compact serialization.
+pub(crate) struct Jws<'a> {
+    /// The `header.payload` part the signature is over
+    pub signing_input: &'a str,
+    pub header: Value,
+    pub claims: Value,
+    pub signature: Vec<u8>,
+}
+
+/// Decode a JWS in compact serialization without checking its signature.
+pub(crate) fn decode_jws(token: &str) -> Result<Jws<'_>> {
+    let (signing_input, signature) = token
+        .trim()
+        .rsplit_once('.')
+        .context("Token is not a compact JWS")?;
+    let (header, payload) = signing_input
+        .split_once('.')
+        .context("Token is not a compact JWS")?;
+    let header = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header)?)
+        .context("Failed to decode token header")?;
+    let claims = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload)?)
+        .context("Failed to decode token claims")?;
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .context("Failed to decode token signature")?;
+    Ok(Jws {
+        signing_input,
+        header,
+        claims,
+        signature,
+    })
+}
+
+/// The JWS `alg` name of a signature algorithm.
+pub(crate) fn jws_algorithm(algorithm: SignatureAlgorithm) -> &'static str {
+    match algorithm {
+        SignatureAlgorithm::EcdsaP256Sha256 => "ES256",
+        SignatureAlgorithm::EcdsaP384Sha384 => "ES384",
+    }
+}
+
+/// The report data of a report, and the claims about its fields with their values.
+fn report_fields(report: &Report) -> (&[u8], Vec<(&'static str, Vec<u8>)>) {
+    let td_fields = |td: &crate::quote::TDReport10| {
+        vec![
+            ("tdx_tee_tcb_svn", td.tee_tcb_svn.to_vec()),
+            ("tdx_mrseam", td.mr_seam.to_vec()),
+            ("tdx_mrsignerseam", td.mr_signer_seam.to_vec()),
+            ("tdx_seam_attributes", td.seam_attributes.to_vec()),
+            ("tdx_td_attributes", td.td_attributes.to_vec()),
+            ("tdx_xfam", td.xfam.to_vec()),
+            ("tdx_mrtd", td.mr_td.to_vec()),
+            ("tdx_mrconfigid", td.mr_config_id.to_vec()),
+            ("tdx_mrowner", td.mr_owner.to_vec()),
+            ("tdx_mrownerconfig", td.mr_owner_config.to_vec()),
+            ("tdx_rtmr0", td.rt_mr0.to_vec()),
+            ("tdx_rtmr1", td.rt_mr1.to_vec()),
+            ("tdx_rtmr2", td.rt_mr2.to_vec()),
+            ("tdx_rtmr3", td.rt_mr3.to_vec()),
+            ("tdx_reportdata", td.report_data.to_vec()),
+            ("tdx_report_data", td.report_data.to_vec()),
+        ]
+    };
+    match report {
+        Report::SgxEnclave(report) => (
+            &report.report_data,
+            vec![
+                ("sgx_cpusvn", report.cpu_svn.to_vec()),
+                ("sgx_miscselect", report.misc_select.to_le_bytes().to_vec()),
+                ("sgx_attributes", report.attributes.to_vec()),
+                ("sgx_mrenclave", report.mr_enclave.to_vec()),
+                ("sgx_mrsigner", report.mr_signer.to_vec()),
+                ("sgx_isvprodid", report.isv_prod_id.to_le_bytes().to_vec()),
+                ("sgx_isvsvn", report.isv_svn.to_le_bytes().to_vec()),
+                ("sgx_reportdata", report.report_data.to_vec()),
+                ("sgx_report_data", report.report_data.to_vec()),
+            ],
+        ),
+        Report::TD10(report) => (&report.report_data, td_fields(report)),
+        Report::TD15(report) => {
+            let mut fields = td_fields(&report.base);
+            fields.push(("tdx_tee_tcb_svn2", report.tee_tcb_svn2.to_vec()));
+            fields.push(("tdx_mrservicetd", report.mr_service_td.to_vec()));
+            (&report.base.report_data, fields)
+        }
+    }
+}
+
+/// Whether a claim is a report field: its hex encoding, or its little endian value as a number.
+fn claim_matches(claim: &Value, field: &[u8]) -> bool {
+    match claim {
+        Value::String(claimed) => claimed.eq_ignore_ascii_case(&hex::encode(field)),
+        Value::Number(claimed) => {
+            field.len() <= 8
+                && claimed.as_u64()
+                    == Some(
+                        field
+                            .iter()
+                            .rev()
+                            .fold(0u64, |value, byte| value << 8 | u64::from(*byte)),
+                    )
+        }
+        _ => false,
+    }
+}
+
+/// Select the result about the quote of `report_data`, hex encoded: the innermost list entry
+/// whose claims hold that report data, or all claims if they are not a list of results.
+fn select_result<'a>(claims: &'a Value, report_data: &str) -> Option<&'a Value> {
+    fn holds(value: &Value, report_data: &str) -> bool {
+        match value {
+            Value::Object(map) => {
+                REPORT_DATA_CLAIMS.iter().any(|name| {
+                    map.get(*name)
+                        .and_then(Value::as_str)
+                        .is_some_and(|claimed| claimed.eq_ignore_ascii_case(report_data))
+                }) || map.values().any(|value| holds(value, report_data))
+            }
+            Value::Array(values) => values.iter().any(|value| holds(value, report_data)),
+            _ => false,
+        }
+    }
+
+    if !holds(claims, report_data) {
+        return None;
+    }
+    let mut result = claims;
+    let mut value = claims;
+    loop {
+        let next = match value {
+            Value::Object(map) => map.values().find(|value| holds(value, report_data)),
+            Value::Array(values) => {
+                let entry = values.iter().find(|value| holds(value, report_data));
+                result = entry.unwrap_or(result);
+                entry
+            }
+            _ => None,
+        };
+        match next {
+            Some(next) => value = next,
+            None => return Some(result),
+        }
+    }
+}
+
+/// Find the claims named one of `names`, at any depth.
+fn claims_named<'a>(value: &'a Value, names: &[&str]) -> Vec<&'a Value> {
+    fn collect<'a>(value: &'a Value, names: &[&str], found: &mut Vec<&'a Value>) {
+        match value {
+            Value::Object(map) => {
+                for (name, value) in map {
+                    if names.contains(&name.as_str()) {
+                        found.push(value);
+                    } else {
+                        collect(value, names, found);
+                    }
+                }
+            }
+            Value::Array(values) => values.iter().for_each(|value| collect(value, names, found)),
+            _ => {}
+        }
+    }
+
+    let mut found = Vec::new();
+    collect(value, names, &mut found);
+    found
+}
+
+/// The TCB status and advisory ids claimed in `claims`, each claimed at most once.
+fn tcb_claims(claims: &Value) -> Result<(TcbStatus, Vec<String>)> {
+    let status = match unique_claim(claims, "tcb_status")? {
+        Some(Value::String(status)) => status
+            .parse()
+            .context("Invalid tcb_status claim in appraisal token")?,
+        Some(_) => bail!("Invalid tcb_status claim in appraisal token"),
+        None => bail!("Appraisal token has no tcb_status claim"),
+    };
+    let advisory_ids = match unique_claim(claims, "advisory_ids")? {
+        Some(Value::Array(ids)) => ids
+            .iter()
+            .map(|id| id.as_str().map(String::from))
+            .collect::<Option<Vec<_>>>()
+            .context("Invalid advisory_ids claim in appraisal token")?,
+        Some(_) => bail!("Invalid advisory_ids claim in appraisal token"),
+        None => Vec::new(),
+    };
+    Ok((status, advisory_ids))
+}
+
+/// The claim named `name`, failing if it occurs more than once.
+fn unique_claim<'a>(value: &'a Value, name: &str) -> Result<Option<&'a Value>> {
+    match claims_named(value, &[name])[..] {
+        [] => Ok(None),
+        [claim] => Ok(Some(claim)),
+        _ => bail!("Appraisal token has several {name} claims for the quote"),
+    }
+}
@@ -0,0 +1,176 @@
+//! A content-addressed archive of fetched collateral, for audit trails.
+//!
+//! [`fetch_and_archive`] stores every collateral response body under its SHA-256, along with a
+//! [`FetchRecord`] of its source URL, response headers and fetch time. The records of one fetch
+//! form a [`Manifest`], itself archived by hash, whose digest identifies the fetch. Since the
+//! collateral is rebuilt from the archived bytes, [`replay`] reproduces a past verification
+//! bit-exactly.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use core::time::Duration;
+use serde::{Deserialize, Serialize};
+
+use crate::collateral::{collateral_from_responses, fetch_responses, EgressPolicy, RawResponse};
+use crate::quote::Quote;
+use crate::verify::{verify, VerifiedReport};
+use crate::QuoteCollateralV3;
+
+/// The SHA-256 digest addressing an archived blob.
+pub type Digest = [u8; 32];
+
+/// Where and when an archived response was fetched.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FetchRecord {
+    /// The URL the response was fetched from
+    pub url: String,
+    /// The response headers, in the order they were received
+    pub headers: Vec<(String, String)>,
+    /// The fetch time in seconds since the Unix epoch
+    pub fetched_at: u64,
+    /// The digest of the response body
+    #[serde(with = "serde_bytes")]
+    pub body: Digest,
+}
+
+/// The records of the responses making up one collateral fetch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub tcb_info: FetchRecord,
+    pub qe_identity: FetchRecord,
+}
+
+/// A directory of blobs named by the hex SHA-256 of their contents.
+#[derive(Debug, Clone)]
+pub struct CollateralArchive {
+    root: PathBuf,
+}
+
+impl CollateralArchive {
+    /// Open the archive at `root`, creating the directory if needed.
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(&root).context("Failed to create archive directory")?;
+        Ok(Self { root })
+    }
+
+    /// Store a blob and return its digest. Storing the same blob again is a no-op.
+    pub fn put(&self, data: &[u8]) -> Result<Digest> {
+        let digest = sha256(data);
+        let path = self.root.join(hex::encode(digest));
+        if !path.exists() {
+            // Write to a temporary file first so a crash never leaves a truncated blob
+            let tmp = self.root.join(format!("{}.tmp", hex::encode(digest)));
+            std::fs::write(&tmp, data).context("Failed to write archive blob")?;
+            std::fs::rename(&tmp, &path).context("Failed to write archive blob")?;
+        }
+        Ok(digest)
+    }
+
+    /// Load a blob, checking it still matches its digest.
+    pub fn get(&self, digest: &Digest) -> Result<Vec<u8>> {
+        let data = std::fs::read(self.root.join(hex::encode(digest)))
+            .with_context(|| format!("Blob {} is not archived", hex::encode(digest)))?;
+        if sha256(&data) != *digest {
+            bail!("Archived blob {} is corrupted", hex::encode(digest));
+        }
+        Ok(data)
+    }
+
+    /// Load the manifest of a fetch.
+    pub fn manifest(&self, id: &Digest) -> Result<Manifest> {
+        serde_json::from_slice(&self.get(id)?).context("Failed to decode manifest")
+    }
+
+    /// Rebuild the collateral of a fetch from the archived responses.
+    pub fn collateral(&self, id: &Digest) -> Result<QuoteCollateralV3> {
+        let manifest = self.manifest(id)?;
+        collateral_from_responses(
+            &self.response(&manifest.tcb_info)?,
+            &self.response(&manifest.qe_identity)?,
+        )
+    }
+
+    fn archive(&self, response: &RawResponse) -> Result<FetchRecord> {
+        Ok(FetchRecord {
+            url: response.url.clone(),
+            headers: response.headers.clone(),
+            fetched_at: response.fetched_at.context("Missing fetch time")?,
+            body: self.put(response.body.as_bytes())?,
+        })
+    }
+
+    fn response(&self, record: &FetchRecord) -> Result<RawResponse> {
+        let body = String::from_utf8(self.get(&record.body)?)
+            .context("Archived response body is not UTF-8")?;
+        Ok(RawResponse {
+            url: record.url.clone(),
+            headers: record.headers.clone(),
+            body,
+            fetched_at: Some(record.fetched_at),
+        })
+    }
+}
+
+/// Fetch the collateral for a quote and archive the responses it was built from.
+///
+/// # Arguments
+///
+/// * `archive` - The archive to store the responses in
+/// * `pccs_url` - The base URL of PCCS server. (e.g. `https://pccs.example.com/sgx/certification/v4`)
+/// * `quote` - The raw quote to fetch collateral for
+/// * `timeout` - The timeout for each request
+///
+/// # Returns
+///
+/// * `Ok((Digest, QuoteCollateralV3))` - The manifest digest identifying the fetch, and the collateral
+/// * `Err(Error)` - The error
+pub async fn fetch_and_archive(
+    archive: &CollateralArchive,
+    pccs_url: &str,
+    quote: &[u8],
+    timeout: Duration,
+) -> Result<(Digest, QuoteCollateralV3)> {
+    let fmspc = Quote::parse(quote)?
+        .fmspc()
+        .context("Failed to get FMSPC")?;
+    let (tcb_info, qe_identity) = fetch_responses(
+        pccs_url,
+        &hex::encode_upper(fmspc),
+        timeout,
+        &EgressPolicy::default(),
+    )
+    .await?;
+    let collateral = collateral_from_responses(&tcb_info, &qe_identity)?;
+    let manifest = Manifest {
+        tcb_info: archive.archive(&tcb_info)?,
+        qe_identity: archive.archive(&qe_identity)?,
+    };
+    let manifest = serde_json::to_vec(&manifest).context("Failed to encode manifest")?;
+    Ok((archive.put(&manifest)?, collateral))
+}
+
+/// Verify a quote again against the archived collateral of a past fetch.
+///
+/// # Arguments
+///
+/// * `archive` - The archive holding the fetch
+/// * `id` - The manifest digest returned by [`fetch_and_archive`]
+/// * `quote` - The raw quote that was verified
+/// * `now` - The time of the original verification in seconds since the Unix epoch
+pub fn replay(
+    archive: &CollateralArchive,
+    id: &Digest,
+    quote: &[u8],
+    now: u64,
+) -> Result<VerifiedReport> {
+    verify(quote, &archive.collateral(id)?, now)
+}
+
+fn sha256(data: &[u8]) -> Digest {
+    let digest = ring::digest::digest(&ring::digest::SHA256, data);
+    let mut out = [0u8; 32];
+    out.copy_from_slice(digest.as_ref());
+    out
+}
@@ -0,0 +1,279 @@
+//! Verifying many quotes.
+//!
+//! Long running verifications can be bounded by a [`Deadline`] and stopped early with a
+//! [`CancelToken`], so request handlers embedding the crate can enforce end-to-end timeouts.
+//!
+//! [`verify_pairs`] verifies quotes held in memory with their collateral one by one.
+//! [`verify_stream`] pulls quotes from an iterator, e.g. one reading an archive, and verifies them
+//! on a bounded number of threads while keeping the quote bytes in flight under a budget.
+//! [`verify_batch`] checks the collateral once for many quotes of the same platforms, and with the
+//! `rayon` feature verifies them in parallel.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+use std::sync::{mpsc, Condvar, Mutex, PoisonError};
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::signature::RingVerifier;
+use crate::verify::{verify, CheckedCollateral, VerifiedReport, VerifyOptions};
+use crate::QuoteCollateralV3;
+
+/// A flag to stop an operation early, shared between the caller and the operation.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the operations holding this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// When an operation has to be done by, and how to stop it early.
+#[derive(Debug, Clone, Default)]
+pub struct Deadline {
+    /// The instant the operation must finish by.
+    pub at: Option<Instant>,
+    /// A token to cancel the operation.
+    pub cancel: Option<CancelToken>,
+}
+
+impl Deadline {
+    /// A deadline `timeout` from now.
+    pub fn after(timeout: Duration) -> Self {
+        Self {
+            at: Some(Instant::now() + timeout),
+            cancel: None,
+        }
+    }
+
+    /// Fail with [`DeadlineError`] if the operation was cancelled or the deadline passed.
+    pub fn check(&self) -> Result<()> {
+        if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            return Err(DeadlineError::Cancelled.into());
+        }
+        if self.at.is_some_and(|at| Instant::now() >= at) {
+            return Err(DeadlineError::Exceeded.into());
+        }
+        Ok(())
+    }
+
+    /// The time left before the deadline, at most `timeout`.
+    pub fn remaining(&self, timeout: Duration) -> Result<Duration> {
+        self.check()?;
+        Ok(match self.at {
+            Some(at) => timeout.min(at.saturating_duration_since(Instant::now())),
+            None => timeout,
+        })
+    }
+}
+
+/// The error of an operation stopped by its [`Deadline`].
+///
+/// Callers can tell it apart from the failures of the operation itself with
+/// `err.root_cause().downcast_ref::<DeadlineError>()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadlineError {
+    Cancelled,
+    Exceeded,
+}
+
+impl core::fmt::Display for DeadlineError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DeadlineError::Cancelled => f.write_str("Operation cancelled"),
+            DeadlineError::Exceeded => f.write_str("Deadline exceeded"),
+        }
+    }
+}
+
+impl core::error::Error for DeadlineError {}
+
+/// Verify quotes one by one, stopping at the deadline.
+///
+/// Quotes not verified before the deadline or cancellation get an error result, so the results
+/// line up with the input.
+///
+/// # Arguments
+///
+/// * `items` - The raw quotes with their collateral.
+/// * `now` - The current time in seconds since the Unix epoch
+/// * `deadline` - When to stop verifying.
+pub fn verify_pairs<'a>(
+    items: impl IntoIterator<Item = (&'a [u8], &'a QuoteCollateralV3)>,
+    now: u64,
+    deadline: &Deadline,
+) -> Vec<Result<VerifiedReport>> {
+    items
+        .into_iter()
+        .map(|(quote, collateral)| {
+            deadline.check()?;
+            verify(quote, collateral, now)
+        })
+        .collect()
+}
+
+/// Verify quotes sharing the same collateral.
+///
+/// The collateral's certificate chains and signatures are checked, and its TCB info and QE
+/// identity parsed, once for all the quotes instead of once per quote. With the `rayon` feature
+/// the quotes are verified in parallel on the global rayon thread pool.
+///
+/// # Arguments
+///
+/// * `quotes` - The raw quotes
+/// * `collateral` - The collateral of all the quotes, e.g. of platforms with the same FMSPC
+/// * `now` - The current time in seconds since the Unix epoch
+/// * `options` - The options each quote is verified with, as by
+///   [`verify_with_options`](crate::verify::verify_with_options)
+///
+/// # Returns
+///
+/// * `Ok(Vec<Result<VerifiedReport>>)` - The result of each quote, in the order of `quotes`
+/// * `Err(Error)` - The collateral is invalid, so no quote was verified
+pub fn verify_batch(
+    quotes: &[&[u8]],
+    collateral: &QuoteCollateralV3,
+    now: u64,
+    options: &VerifyOptions,
+) -> Result<Vec<Result<VerifiedReport>>> {
+    let checked = CheckedCollateral::new(collateral, now, options, &RingVerifier)?;
+    let verify = |quote: &&[u8]| checked.verify(quote, now, &RingVerifier);
+    #[cfg(feature = "rayon")]
+    let results = quotes.par_iter().map(verify).collect();
+    #[cfg(not(feature = "rayon"))]
+    let results = quotes.iter().map(verify).collect();
+    Ok(results)
+}
+
+/// Options for [`verify_stream`].
+#[derive(Debug, Clone)]
+pub struct StreamOptions {
+    /// The number of verification threads.
+    pub concurrency: usize,
+    /// Quotes larger than this are rejected without being verified.
+    pub max_quote_size: usize,
+    /// The most quote bytes queued or being verified at once. Reading from the iterator pauses
+    /// while the budget is used up.
+    pub max_in_flight_bytes: usize,
+}
+
+impl Default for StreamOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: std::thread::available_parallelism().map_or(4, Into::into),
+            max_quote_size: 64 * 1024,
+            max_in_flight_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Verify a stream of quotes with bounded concurrency and memory.
+///
+/// Results are passed to `on_result` with the index of the quote in the stream as soon as they
+/// are ready, so they arrive out of order. The collateral is shared by reference counting and not
+/// counted against the budget.
+///
+/// # Arguments
+///
+/// * `items` - The raw quotes with their collateral.
+/// * `now` - The current time in seconds since the Unix epoch
+/// * `options` - The concurrency and memory limits.
+/// * `deadline` - When to stop verifying.
+/// * `on_result` - Called from the verification threads with each result.
+pub fn verify_stream<I>(
+    items: I,
+    now: u64,
+    options: &StreamOptions,
+    deadline: &Deadline,
+    on_result: impl Fn(usize, Result<VerifiedReport>) + Sync,
+) where
+    I: IntoIterator<Item = (Vec<u8>, Arc<QuoteCollateralV3>)>,
+{
+    let concurrency = options.concurrency.max(1);
+    let budget = Budget::new(options.max_in_flight_bytes);
+    let (tx, rx) = mpsc::sync_channel::<(usize, Vec<u8>, Arc<QuoteCollateralV3>)>(concurrency);
+    let rx = Mutex::new(rx);
+    std::thread::scope(|scope| {
+        for _ in 0..concurrency {
+            scope.spawn(|| loop {
+                let job = rx.lock().unwrap_or_else(PoisonError::into_inner).recv();
+                let Ok((index, quote, collateral)) = job else {
+                    break;
+                };
+                let result = deadline
+                    .check()
+                    .and_then(|_| verify(&quote, &collateral, now));
+                budget.release(quote.len());
+                drop(quote);
+                on_result(index, result);
+            });
+        }
+        for (index, (quote, collateral)) in items.into_iter().enumerate() {
+            if quote.len() > options.max_quote_size {
+                on_result(index, Err(anyhow!("Quote is too large")));
+                continue;
+            }
+            if let Err(err) = deadline.check() {
+                on_result(index, Err(err));
+                continue;
+            }
+            budget.acquire(quote.len());
+            if tx.send((index, quote, collateral)).is_err() {
+                break;
+            }
+        }
+        drop(tx);
+    });
+}
+
+/// A byte budget shared between the reader and the verification threads.
+struct Budget {
+    max: usize,
+    used: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl Budget {
+    fn new(max: usize) -> Self {
+        Self {
+            max,
+            used: Mutex::new(0),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Wait until `size` bytes fit in the budget. A single item larger than the whole budget is
+    /// let through once nothing else is in flight.
+    fn acquire(&self, size: usize) {
+        let mut used = self.used.lock().unwrap_or_else(PoisonError::into_inner);
+        while *used > 0 && *used + size > self.max {
+            used = self
+                .freed
+                .wait(used)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        *used += size;
+    }
+
+    fn release(&self, size: usize) {
+        let mut used = self.used.lock().unwrap_or_else(PoisonError::into_inner);
+        *used -= size;
+        self.freed.notify_all();
+    }
+}
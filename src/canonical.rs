@@ -0,0 +1,129 @@
+//! JSON Canonicalization Scheme (RFC 8785) serialization.
+//!
+//! Reports and collateral serialized with [`to_string`] are byte-for-byte stable, so their
+//! hashes can be committed on-chain or compared across implementations. [`quote_digest`] and
+//! [`collateral_digest`] build on it to give stable identifiers for quotes and collateral.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use anyhow::{bail, Context, Result};
+use scale::Decode;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::quote::Quote;
+use crate::QuoteCollateralV3;
+
+/// Serialize a value into its RFC 8785 canonical JSON form.
+///
+/// Only integral numbers are supported since none of the types in this crate carry floats.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String> {
+    let value = serde_json::to_value(value).context("Failed to serialize value")?;
+    let mut out = String::new();
+    write_value(&value, &mut out)?;
+    Ok(out)
+}
+
+/// Serialize a value into its RFC 8785 canonical JSON form as bytes.
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    to_string(value).map(String::into_bytes)
+}
+
+/// The bytes of a quote, without anything trailing its structure.
+///
+/// Quote providers often return the quote in a fixed size buffer, padded with zeros.
+pub fn canonical_quote(raw_quote: &[u8]) -> Result<&[u8]> {
+    let mut rest = raw_quote;
+    Quote::decode(&mut rest)
+        .map_err(anyhow::Error::msg)
+        .context("Failed to decode quote")?;
+    Ok(&raw_quote[..raw_quote.len() - rest.len()])
+}
+
+/// The canonical JSON of collateral.
+///
+/// The provenance, which tells where the collateral was fetched from rather than what it is, is
+/// left out, and the issuer chains are re-encoded as PEM with LF line endings. The signed
+/// documents and CRLs are kept as they are.
+pub fn canonical_collateral(collateral: &QuoteCollateralV3) -> Result<Vec<u8>> {
+    let collateral = QuoteCollateralV3 {
+        tcb_info_issuer_chain: normalize_chain(&collateral.tcb_info_issuer_chain)
+            .context("Invalid TCB info issuer chain")?,
+        qe_identity_issuer_chain: normalize_chain(&collateral.qe_identity_issuer_chain)
+            .context("Invalid QE identity issuer chain")?,
+        provenance: Vec::new(),
+        ..collateral.clone()
+    };
+    to_vec(&collateral)
+}
+
+/// The SHA-256 digest of the [canonical bytes](canonical_quote) of a quote.
+pub fn quote_digest(raw_quote: &[u8]) -> Result<[u8; 32]> {
+    Ok(sha256(canonical_quote(raw_quote)?))
+}
+
+/// The SHA-256 digest of the [canonical JSON](canonical_collateral) of collateral.
+pub fn collateral_digest(collateral: &QuoteCollateralV3) -> Result<[u8; 32]> {
+    Ok(sha256(&canonical_collateral(collateral)?))
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(ring::digest::digest(&ring::digest::SHA256, data).as_ref());
+    digest
+}
+
+fn normalize_chain(chain: &str) -> Result<String> {
+    let certs = pem::parse_many(chain).context("Failed to parse certs")?;
+    let config = pem::EncodeConfig::new().set_line_ending(pem::LineEnding::LF);
+    Ok(pem::encode_many_config(&certs, config))
+}
+
+fn write_value(value: &Value, out: &mut String) -> Result<()> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => {
+            if let Some(n) = n.as_u64() {
+                out.push_str(&n.to_string());
+            } else if let Some(n) = n.as_i64() {
+                out.push_str(&n.to_string());
+            } else {
+                bail!("Non-integral numbers are not supported");
+            }
+        }
+        Value::String(s) => write_string(s, out)?,
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            // Keys are sorted by their UTF-16 code units.
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(key, out)?;
+                out.push(':');
+                write_value(value, out)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+fn write_string(s: &str, out: &mut String) -> Result<()> {
+    // serde_json escapes strings exactly as RFC 8785 requires.
+    out.push_str(&serde_json::to_string(s).context("Failed to serialize string")?);
+    Ok(())
+}
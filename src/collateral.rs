@@ -1,30 +1,464 @@
-use alloc::string::{String, ToString};
-use anyhow::{anyhow, Context, Result};
+//! Fetching collateral from PCS, PCCS and Azure THIM.
+//!
+//! The fetching logic runs over any [`HttpClient`], so enclave-side code and custom TLS stacks
+//! can bring their own transport through the `*_with_client` functions. The `report` feature
+//! provides [`ReqwestClient`] and the functions that use it, like [`get_collateral`].
+//!
+//! The blocking functions, like [`get_collateral_sync`], are the same `*_with_client`
+//! implementations run by [`block_on`] over a blocking client, so they behave exactly as their
+//! async counterparts. Blocking custom clients can be run the same way.
+//!
+//! The independent requests for a platform are issued concurrently. The convenience functions
+//! retry transient failures per [`RetryPolicy::default`] within their timeout, taken as the budget
+//! of the whole fetch; `*_with_client` callers get the same with a [`RetryingClient`]. A failed
+//! fetch carries a [`FetchError`] naming the element of the collateral that failed.
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail, Context, Result};
+use core::future::Future;
 use scale::Decode;
+use serde::{Deserialize, Serialize};
 
+#[cfg(not(feature = "js"))]
+use crate::batch::{Deadline, DeadlineError};
+use crate::formats::parse_collateral;
+use crate::limits::SizeLimits;
+use crate::pcs::{self, PckCa};
+use crate::qe_identity::QeIdentity;
 use crate::quote::{Header, Quote};
-use crate::verify::VerifiedReport;
-use crate::QuoteCollateralV3;
+use crate::store::{CacheStore, LruStore};
+use crate::tcb_info::TcbInfo;
+use crate::types::Fmspc;
+use crate::utils;
+#[cfg(feature = "report")]
+use crate::verify::{verify_with_options, VerifiedReport, VerifyOptions};
+use crate::{CollateralKind, CollateralSource, QuoteCollateralV3};
+
+pub use crate::pcs::{CollateralOptions, TcbUpdate};
 
 #[cfg(feature = "js")]
 use wasm_bindgen::prelude::*;
 
-#[cfg(not(feature = "js"))]
 use core::time::Duration;
+#[cfg(feature = "report")]
 use std::time::SystemTime;
 
-fn get_header(resposne: &reqwest::Response, name: &str) -> Result<String> {
-    let value = resposne
-        .headers()
-        .get(name)
-        .ok_or_else(|| anyhow!("Missing {name}"))?
-        .to_str()?;
-    let value = urlencoding::decode(value)?;
-    Ok(value.into_owned())
+#[cfg(all(feature = "report", not(feature = "js")))]
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The response headers kept in the [`CollateralSource`] of fetched collateral, those that
+/// identify the response for forensic review.
+pub const PROVENANCE_HEADERS: &[&str] = &[
+    "Date",
+    "Last-Modified",
+    "ETag",
+    "Age",
+    "Request-ID",
+    "x-ms-request-id",
+];
+
+/// A response of an [`HttpClient`].
+#[derive(Debug, Clone, Default)]
+pub struct HttpResponse {
+    pub status: u16,
+    /// The response headers, with their values as sent
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// The HTTP transport collateral is fetched over, e.g. reqwest, ureq or an in-enclave fetcher.
+///
+/// Implementations send a GET request to the URL and return the response whatever its status.
+/// Timeouts, redirects, TLS and egress restrictions are up to them. Blocking clients can return
+/// [`core::future::ready`].
+pub trait HttpClient {
+    /// Send a GET request to `url`.
+    fn get(&self, url: &str) -> impl Future<Output = Result<HttpResponse>>;
+
+    /// Wait before a [`RetryingClient`] retries a request. By default retries follow at once, for
+    /// clients without a timer.
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> {
+        let _ = duration;
+        core::future::ready(())
+    }
+}
+
+/// The error of fetching an element of the collateral.
+///
+/// Fetch errors carry it, so callers can tell which element failed, e.g. to fetch it from another
+/// provider, with `err.downcast_ref::<FetchError>()`.
+#[derive(Debug)]
+pub struct FetchError {
+    /// The element that failed to fetch
+    pub kind: CollateralKind,
+    pub url: String,
+    source: anyhow::Error,
+}
+
+impl FetchError {
+    fn new(kind: CollateralKind, url: &str, source: anyhow::Error) -> Self {
+        Self {
+            kind,
+            url: url.into(),
+            source,
+        }
+    }
+}
+
+impl core::fmt::Display for FetchError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self.kind {
+            CollateralKind::TcbInfo => "TCB info",
+            CollateralKind::QeIdentity => "QE identity",
+            CollateralKind::RootCaCrl => "root CA CRL",
+            CollateralKind::PckCrl => "PCK CRL",
+        };
+        write!(f, "Failed to fetch {name} from {}", self.url)
+    }
+}
+
+impl std::error::Error for FetchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+/// How a [`RetryingClient`] retries requests failing transiently, i.e. with a transport error or
+/// HTTP status 408, 429 or 5xx. Requests denied by the [`EgressPolicy`] or stopped by the
+/// [`Deadline`] are not retried.
+///
+/// The backoffs are waited with [`HttpClient::sleep`], whose default doesn't wait: custom clients
+/// not overriding it retry immediately, each attempt right after the one before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The attempts per request, the first included. 1 disables retries
+    pub attempts: u32,
+    /// The wait before the first retry, doubled for each further one
+    pub initial_backoff: Duration,
+    /// The longest wait between attempts
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// 3 attempts, backing off 250 milliseconds, then 500.
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(4),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Send each request once.
+    pub fn none() -> Self {
+        Self {
+            attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// The wait before retry number `retry`, counted from 0.
+    fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u32.checked_shl(retry).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// An [`HttpClient`] retrying the transient failures of another per a [`RetryPolicy`].
+#[derive(Debug, Clone)]
+pub struct RetryingClient<C> {
+    client: C,
+    policy: RetryPolicy,
+    #[cfg(not(feature = "js"))]
+    deadline: Deadline,
+}
+
+impl<C: HttpClient> RetryingClient<C> {
+    /// Retry the requests of `client` per `policy`.
+    pub fn new(client: C, policy: RetryPolicy) -> Self {
+        Self {
+            client,
+            policy,
+            #[cfg(not(feature = "js"))]
+            deadline: Deadline::default(),
+        }
+    }
+
+    /// Stop retrying at the deadline: no backoff is waited past it, the last failure is returned
+    /// instead.
+    #[cfg(not(feature = "js"))]
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// The last result of a transiently failing request, noting the attempts if retried.
+    fn give_up(result: Result<HttpResponse>, attempts: u32, reason: &str) -> Result<HttpResponse> {
+        if attempts == 1 {
+            return result;
+        }
+        result.with_context(|| format!("{reason} after {attempts} attempts"))
+    }
+
+    /// Whether a request may succeed if retried: not if the policy denied it or time ran out.
+    fn is_transient(result: &Result<HttpResponse>) -> bool {
+        match result {
+            Ok(response) => matches!(response.status, 408 | 429 | 500..=599),
+            Err(err) => !err.chain().any(|err| {
+                #[cfg(feature = "report")]
+                if err.is::<EgressDenied>() {
+                    return true;
+                }
+                #[cfg(not(feature = "js"))]
+                if err.is::<DeadlineError>() {
+                    return true;
+                }
+                false
+            }),
+        }
+    }
+}
+
+impl<C: HttpClient> HttpClient for RetryingClient<C> {
+    async fn get(&self, url: &str) -> Result<HttpResponse> {
+        let mut attempts = 0;
+        loop {
+            let result = self.client.get(url).await;
+            attempts += 1;
+            if !Self::is_transient(&result) {
+                return result;
+            }
+            if attempts >= self.policy.attempts {
+                return Self::give_up(result, attempts, "Gave up");
+            }
+            let backoff = self.policy.backoff(attempts - 1);
+            #[cfg(not(feature = "js"))]
+            if !matches!(self.deadline.remaining(backoff), Ok(left) if left >= backoff) {
+                return Self::give_up(result, attempts, "Deadline reached");
+            }
+            self.sleep(backoff).await;
+        }
+    }
+
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> {
+        self.client.sleep(duration)
+    }
+}
+
+/// The default [`HttpClient`], over reqwest.
+#[cfg(feature = "report")]
+#[derive(Debug, Clone)]
+pub struct ReqwestClient {
+    client: reqwest::Client,
+    egress: EgressPolicy,
+    #[cfg(not(feature = "js"))]
+    timeout: Duration,
+    #[cfg(not(feature = "js"))]
+    deadline: Deadline,
+}
+
+#[cfg(feature = "report")]
+impl ReqwestClient {
+    /// A client with the given request timeout, within an egress policy.
+    pub fn new(
+        #[cfg(not(feature = "js"))] timeout: Duration,
+        egress: &EgressPolicy,
+    ) -> Result<Self> {
+        let builder = reqwest::Client::builder();
+        #[cfg(not(feature = "js"))]
+        let builder = {
+            let builder = builder
+                .danger_accept_invalid_certs(true)
+                .timeout(timeout)
+                .redirect(egress.redirect_policy());
+            match egress.proxy()? {
+                Some(proxy) => builder.proxy(proxy),
+                None => builder,
+            }
+        };
+        Ok(Self {
+            client: builder.build()?,
+            egress: egress.clone(),
+            #[cfg(not(feature = "js"))]
+            timeout,
+            #[cfg(not(feature = "js"))]
+            deadline: Deadline::default(),
+        })
+    }
+
+    /// Bound each request by the time left before the deadline, besides the request timeout.
+    #[cfg(not(feature = "js"))]
+    pub fn with_deadline(mut self, deadline: Deadline) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// A client retrying per the default [`RetryPolicy`], with `timeout` as the budget of all
+    /// requests.
+    fn retrying(
+        #[cfg(not(feature = "js"))] timeout: Duration,
+        egress: &EgressPolicy,
+    ) -> Result<RetryingClient<Self>> {
+        Self::retrying_until(
+            #[cfg(not(feature = "js"))]
+            timeout,
+            egress,
+            #[cfg(not(feature = "js"))]
+            Deadline::after(timeout),
+            RetryPolicy::default(),
+        )
+    }
+
+    /// A client retrying per `policy`, with `timeout` as the request timeout and stopping at
+    /// `deadline`.
+    fn retrying_until(
+        #[cfg(not(feature = "js"))] timeout: Duration,
+        egress: &EgressPolicy,
+        #[cfg(not(feature = "js"))] deadline: Deadline,
+        policy: RetryPolicy,
+    ) -> Result<RetryingClient<Self>> {
+        let client = Self::new(
+            #[cfg(not(feature = "js"))]
+            timeout,
+            egress,
+        )?;
+        #[cfg(not(feature = "js"))]
+        let client = RetryingClient::new(client.with_deadline(deadline.clone()), policy)
+            .with_deadline(deadline);
+        #[cfg(feature = "js")]
+        let client = RetryingClient::new(client, policy);
+        Ok(client)
+    }
+}
+
+#[cfg(feature = "report")]
+impl HttpClient for ReqwestClient {
+    async fn get(&self, url: &str) -> Result<HttpResponse> {
+        self.egress.check(url)?;
+        let request = self.client.get(url);
+        #[cfg(not(feature = "js"))]
+        let request = request.timeout(self.deadline.remaining(self.timeout)?);
+        let response = request.send().await?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+            })
+            .collect();
+        let body = response.bytes().await?.to_vec();
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    /// Waits on the shared timer thread, so backoffs work on any async runtime.
+    #[cfg(not(feature = "js"))]
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> {
+        timer::sleep(duration)
+    }
+}
+
+/// A single thread waking the backoffs of every [`ReqwestClient`], started on first use.
+#[cfg(all(feature = "report", not(feature = "js")))]
+mod timer {
+    use core::future::Future;
+    use core::time::Duration;
+    use std::collections::BinaryHeap;
+    use std::sync::mpsc::{self, RecvTimeoutError};
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    use futures::channel::oneshot;
+
+    struct Timer {
+        due: Instant,
+        done: oneshot::Sender<()>,
+    }
+
+    // Ordered by due time, the earliest greatest, so the max-heap pops it first
+    impl Ord for Timer {
+        fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+            other.due.cmp(&self.due)
+        }
+    }
+
+    impl PartialOrd for Timer {
+        fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl PartialEq for Timer {
+        fn eq(&self, other: &Self) -> bool {
+            self.due == other.due
+        }
+    }
+
+    impl Eq for Timer {}
+
+    static TIMERS: OnceLock<mpsc::Sender<Timer>> = OnceLock::new();
+
+    /// Resolve once `duration` has passed.
+    pub(super) fn sleep(duration: Duration) -> impl Future<Output = ()> {
+        let (done, wait) = oneshot::channel();
+        let timers = TIMERS.get_or_init(|| {
+            let (timers, pending) = mpsc::channel();
+            std::thread::Builder::new()
+                .name("dcap-qvl-timer".into())
+                .spawn(move || run(pending))
+                .expect("Failed to spawn timer thread");
+            timers
+        });
+        let timer = Timer {
+            due: Instant::now() + duration,
+            done,
+        };
+        // The thread never exits, so the timer always arrives
+        let _ = timers.send(timer);
+        async move {
+            let _ = wait.await;
+        }
+    }
+
+    fn run(pending: mpsc::Receiver<Timer>) {
+        let mut timers = BinaryHeap::new();
+        loop {
+            let received = match timers.peek() {
+                Some(Timer { due, .. }) => {
+                    pending.recv_timeout(due.saturating_duration_since(Instant::now()))
+                }
+                None => pending.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(timer) => timers.push(timer),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            let now = Instant::now();
+            while timers.peek().is_some_and(|timer| timer.due <= now) {
+                if let Some(timer) = timers.pop() {
+                    let _ = timer.done.send(());
+                }
+            }
+        }
+    }
 }
 
 /// Get collateral given DCAP quote and base URL of PCCS server URL.
 ///
+/// Besides the TCB info and QE identity, this fetches the root CA CRL and the CRL of the CA that
+/// issued the PCK certificate of the quote, so verification rejects revoked certificates.
+///
 /// # Arguments
 ///
 /// * `pccs_url` - The base URL of PCCS server. (e.g. `https://pccs.example.com/sgx/certification/v4`)
@@ -35,82 +469,536 @@ fn get_header(resposne: &reqwest::Response, name: &str) -> Result<String> {
 ///
 /// * `Ok(QuoteCollateralV3)` - The quote collateral
 /// * `Err(Error)` - The error
+#[cfg(feature = "report")]
 pub async fn get_collateral(
     pccs_url: &str,
-    mut quote: &[u8],
+    quote: &[u8],
+    #[cfg(not(feature = "js"))] timeout: Duration,
+) -> Result<QuoteCollateralV3> {
+    let client = ReqwestClient::retrying(
+        #[cfg(not(feature = "js"))]
+        timeout,
+        &EgressPolicy::default(),
+    )?;
+    get_collateral_with_client(&client, pccs_url, quote).await
+}
+
+/// Get collateral given DCAP quote and base URL of PCCS server URL, at the TCB evaluation
+/// selected by `options`.
+///
+/// Fails if a specific `tcbEvaluationDataNumber` is requested and the server returns another.
+/// See [`get_collateral`] for the other arguments.
+#[cfg(feature = "report")]
+pub async fn get_collateral_with_options(
+    pccs_url: &str,
+    quote: &[u8],
     #[cfg(not(feature = "js"))] timeout: Duration,
+    options: &CollateralOptions,
+) -> Result<QuoteCollateralV3> {
+    let client = ReqwestClient::retrying(
+        #[cfg(not(feature = "js"))]
+        timeout,
+        &EgressPolicy::default(),
+    )?;
+    get_collateral_with_client_and_options(&client, pccs_url, quote, options).await
+}
+
+/// Get collateral given DCAP quote and base URL of PCCS server URL, over the given client.
+///
+/// See [`get_collateral`].
+pub async fn get_collateral_with_client<C: HttpClient>(
+    client: &C,
+    pccs_url: &str,
+    quote: &[u8],
+) -> Result<QuoteCollateralV3> {
+    get_collateral_with_client_and_options(client, pccs_url, quote, &CollateralOptions::default())
+        .await
+}
+
+/// Get collateral given DCAP quote and base URL of PCCS server URL, over the given client and at
+/// the TCB evaluation selected by `options`.
+///
+/// See [`get_collateral_with_options`].
+pub async fn get_collateral_with_client_and_options<C: HttpClient>(
+    client: &C,
+    pccs_url: &str,
+    mut quote: &[u8],
+    options: &CollateralOptions,
 ) -> Result<QuoteCollateralV3> {
     let quote = Quote::decode(&mut quote)?;
     let fmspc = hex::encode_upper(quote.fmspc().context("Failed to get FMSPC")?);
-    let builder = reqwest::Client::builder();
+    collateral_with_client(client, pccs_url, &fmspc, Some(pck_ca(&quote)?), options).await
+}
+
+/// The CA that issued the PCK certificate of a quote.
+pub(crate) fn pck_ca(quote: &Quote) -> Result<PckCa> {
+    let issuer = pck_cert_issuer(quote)?.to_string();
+    if issuer.contains("Platform CA") {
+        Ok(PckCa::Platform)
+    } else if issuer.contains("Processor CA") {
+        Ok(PckCa::Processor)
+    } else {
+        bail!("Unknown PCK certificate issuer: {issuer}")
+    }
+}
+
+/// The issuer of the PCK certificate of a quote.
+fn pck_cert_issuer(quote: &Quote) -> Result<x509_cert::name::Name> {
+    let certs = utils::extract_raw_certs(quote.raw_cert_chain()?)?;
+    let leaf = certs
+        .first()
+        .context("Certificate chain is empty in quote")?;
+    let cert: x509_cert::Certificate =
+        der::Decode::from_der(leaf).context("Failed to decode PCK certificate")?;
+    Ok(cert.tbs_certificate.issuer)
+}
+
+/// Restrictions on where collateral requests may go, for verifiers with locked-down egress.
+#[cfg(feature = "report")]
+#[derive(Debug, Clone, Default)]
+pub struct EgressPolicy {
+    /// The hosts collateral may be fetched from, redirects included. Any host if `None`.
+    pub allowed_hosts: Option<Vec<String>>,
+    /// The proxy all requests go through, e.g. `socks5h://127.0.0.1:1080` or
+    /// `http://proxy:3128`. Use `socks5h` to have the proxy resolve host names.
     #[cfg(not(feature = "js"))]
-    let builder = builder.danger_accept_invalid_certs(true).timeout(timeout);
-    let client = builder.build()?;
-    let base_url = pccs_url.trim_end_matches('/');
+    pub proxy: Option<String>,
+}
 
-    let tcb_info_issuer_chain;
-    let raw_tcb_info;
-    {
-        let resposne = client
-            .get(format!("{base_url}/tcb?fmspc={fmspc}"))
-            .send()
-            .await?;
-        tcb_info_issuer_chain = get_header(&resposne, "SGX-TCB-Info-Issuer-Chain")
-            .or(get_header(&resposne, "TCB-Info-Issuer-Chain"))?;
-        raw_tcb_info = resposne.text().await?;
+#[cfg(feature = "report")]
+impl EgressPolicy {
+    fn is_allowed(&self, host: Option<&str>) -> bool {
+        match (&self.allowed_hosts, host) {
+            (None, _) => true,
+            (Some(allowed), Some(host)) => allowed.iter().any(|h| h.eq_ignore_ascii_case(host)),
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Fail if `url` points to a host outside the allowlist.
+    fn check(&self, url: &str) -> Result<()> {
+        let url = reqwest::Url::parse(url).context("Invalid collateral URL")?;
+        if !self.is_allowed(url.host_str()) {
+            let host = url.host_str().unwrap_or_default().into();
+            return Err(EgressDenied { host }.into());
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "js"))]
+    fn redirect_policy(&self) -> reqwest::redirect::Policy {
+        let policy = self.clone();
+        reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= 10 {
+                attempt.error("Too many redirects")
+            } else if policy.is_allowed(attempt.url().host_str()) {
+                attempt.follow()
+            } else {
+                let host = attempt.url().host_str().unwrap_or_default().into();
+                attempt.error(EgressDenied { host })
+            }
+        })
+    }
+
+    #[cfg(not(feature = "js"))]
+    fn proxy(&self) -> Result<Option<reqwest::Proxy>> {
+        self.proxy
+            .as_deref()
+            .map(|url| reqwest::Proxy::all(url).context("Invalid proxy URL"))
+            .transpose()
+    }
+}
+
+/// The error of a request to a host outside the [`EgressPolicy`] allowlist, redirects included.
+///
+/// It is never retried. Callers can tell it apart from transport failures with
+/// `err.chain().any(|err| err.is::<EgressDenied>())`.
+#[cfg(feature = "report")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EgressDenied {
+    pub host: String,
+}
+
+#[cfg(feature = "report")]
+impl core::fmt::Display for EgressDenied {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "Host {:?} is not allowed by the egress policy",
+            self.host
+        )
+    }
+}
+
+#[cfg(feature = "report")]
+impl std::error::Error for EgressDenied {}
+
+/// Get collateral given the FMSPC of a platform and base URL of PCCS server URL, without a quote.
+///
+/// Without a quote the issuer of the PCK certificate is unknown, so the collateral carries the
+/// root CA CRL but no PCK CRL.
+///
+/// # Arguments
+///
+/// * `pccs_url` - The base URL of PCCS server. (e.g. `https://pccs.example.com/sgx/certification/v4`)
+/// * `fmspc` - The hex encoded FMSPC of the platform. (e.g. `00906ED50000`)
+/// * `timeout` - The timeout for the request. (e.g. `Duration::from_secs(10)`)
+///
+/// # Returns
+///
+/// * `Ok(QuoteCollateralV3)` - The quote collateral
+/// * `Err(Error)` - The error
+#[cfg(feature = "report")]
+pub async fn get_collateral_for_fmspc(
+    pccs_url: &str,
+    fmspc: &str,
+    #[cfg(not(feature = "js"))] timeout: Duration,
+) -> Result<QuoteCollateralV3> {
+    get_collateral_with_policy(
+        pccs_url,
+        fmspc,
+        None,
+        #[cfg(not(feature = "js"))]
+        timeout,
+        &EgressPolicy::default(),
+    )
+    .await
+}
+
+/// Get collateral given the FMSPC of a platform, over the given client.
+///
+/// See [`get_collateral_for_fmspc`].
+pub async fn get_collateral_for_fmspc_with_client<C: HttpClient>(
+    client: &C,
+    pccs_url: &str,
+    fmspc: &str,
+) -> Result<QuoteCollateralV3> {
+    get_collateral_for_fmspc_with_client_and_options(
+        client,
+        pccs_url,
+        fmspc,
+        &CollateralOptions::default(),
+    )
+    .await
+}
+
+/// Get collateral given the FMSPC of a platform, over the given client and at the TCB evaluation
+/// selected by `options`.
+///
+/// See [`get_collateral_for_fmspc`] and [`get_collateral_with_options`].
+pub async fn get_collateral_for_fmspc_with_client_and_options<C: HttpClient>(
+    client: &C,
+    pccs_url: &str,
+    fmspc: &str,
+    options: &CollateralOptions,
+) -> Result<QuoteCollateralV3> {
+    collateral_with_client(client, pccs_url, fmspc, None, options).await
+}
+
+/// Get collateral given the FMSPC of a platform and the CA issuing its PCK certificates, over
+/// the given client.
+///
+/// Unlike [`get_collateral_for_fmspc_with_client`], this fetches the PCK CRL too.
+pub async fn get_collateral_for_platform_with_client<C: HttpClient>(
+    client: &C,
+    pccs_url: &str,
+    fmspc: &str,
+    pck_ca: PckCa,
+) -> Result<QuoteCollateralV3> {
+    collateral_with_client(
+        client,
+        pccs_url,
+        fmspc,
+        Some(pck_ca),
+        &CollateralOptions::default(),
+    )
+    .await
+}
+
+/// Get collateral given the hex encoded FMSPC of a platform, within an egress policy.
+///
+/// The root CA CRL is always fetched, the PCK CRL only if the issuing CA is known.
+#[cfg(feature = "report")]
+pub(crate) async fn get_collateral_with_policy(
+    pccs_url: &str,
+    fmspc: &str,
+    pck_ca: Option<PckCa>,
+    #[cfg(not(feature = "js"))] timeout: Duration,
+    egress: &EgressPolicy,
+) -> Result<QuoteCollateralV3> {
+    egress.check(pccs_url)?;
+    let client = ReqwestClient::retrying(
+        #[cfg(not(feature = "js"))]
+        timeout,
+        egress,
+    )?;
+    collateral_with_client(
+        &client,
+        pccs_url,
+        fmspc,
+        pck_ca,
+        &CollateralOptions::default(),
+    )
+    .await
+}
+
+async fn collateral_with_client<C: HttpClient>(
+    client: &C,
+    pccs_url: &str,
+    fmspc: &str,
+    pck_ca: Option<PckCa>,
+    options: &CollateralOptions,
+) -> Result<QuoteCollateralV3> {
+    let pck_crl = async {
+        match pck_ca {
+            Some(ca) => {
+                let url = pcs::pck_crl(&pcs::sgx_base_url(pccs_url), ca);
+                fetch_crl(client, CollateralKind::PckCrl, &url)
+                    .await
+                    .map(Some)
+            }
+            None => Ok(None),
+        }
     };
-    let qe_identity_issuer_chain;
-    let raw_qe_identity;
-    {
-        let response = client.get(format!("{base_url}/qe/identity")).send().await?;
-        qe_identity_issuer_chain = get_header(&response, "SGX-Enclave-Identity-Issuer-Chain")?;
-        raw_qe_identity = response.text().await?;
+    let ((tcb_info, qe_identity), root_ca_crl, pck_crl) = futures::try_join!(
+        fetch_responses_with_client(client, pccs_url, fmspc, options),
+        fetch_crl(
+            client,
+            CollateralKind::RootCaCrl,
+            &pcs::root_ca_crl(pccs_url)
+        ),
+        pck_crl,
+    )?;
+    let mut collateral = collateral_from_responses(&tcb_info, &qe_identity)?;
+    check_tcb_evaluation_data_number(&collateral, options)?;
+
+    let (root_ca_crl, source) = root_ca_crl;
+    collateral.root_ca_crl = Some(root_ca_crl);
+    collateral.provenance.push(source);
+    if let Some((pck_crl, source)) = pck_crl {
+        collateral.pck_crl = Some(pck_crl);
+        collateral.provenance.push(source);
+    }
+    Ok(collateral)
+}
+
+/// Check the collateral is of the `tcbEvaluationDataNumber` requested, if any.
+fn check_tcb_evaluation_data_number(
+    collateral: &QuoteCollateralV3,
+    options: &CollateralOptions,
+) -> Result<()> {
+    let Some(requested) = options.tcb_evaluation_data_number else {
+        return Ok(());
     };
+    let tcb_info = serde_json::from_str::<TcbInfo>(&collateral.tcb_info)
+        .context("Failed to decode TcbInfo")?;
+    let qe_identity = serde_json::from_str::<QeIdentity>(&collateral.qe_identity)
+        .context("Failed to decode QeIdentity")?;
+    for (name, number) in [
+        ("TCB info", tcb_info.tcb_evaluation_data_number),
+        ("QE identity", qe_identity.tcb_evaluation_data_number),
+    ] {
+        if number != requested {
+            bail!("Requested tcbEvaluationDataNumber {requested}, got {name} of {number}");
+        }
+    }
+    Ok(())
+}
 
-    let tcb_info_json: serde_json::Value =
-        serde_json::from_str(&raw_tcb_info).context("TCB Info should be valid JSON")?;
-    let tcb_info = tcb_info_json["tcbInfo"].to_string();
-    let tcb_info_signature = tcb_info_json
-        .get("signature")
-        .context("TCB Info missing 'signature' field")?
-        .as_str()
-        .context("TCB Info signature must be a string")?;
-    let tcb_info_signature = hex::decode(tcb_info_signature)
-        .ok()
-        .context("TCB Info signature must be valid hex")?;
-
-    let qe_identity_json: serde_json::Value =
-        serde_json::from_str(&raw_qe_identity).context("QE Identity should be valid JSON")?;
-    let qe_identity = qe_identity_json
-        .get("enclaveIdentity")
-        .context("QE Identity missing 'enclaveIdentity' field")?
-        .to_string();
-    let qe_identity_signature = qe_identity_json
-        .get("signature")
-        .context("QE Identity missing 'signature' field")?
-        .as_str()
-        .context("QE Identity signature must be a string")?;
-    let qe_identity_signature = hex::decode(qe_identity_signature)
+/// Fetch a CRL, served DER, PEM or hex encoded, as DER.
+async fn fetch_crl<C: HttpClient>(
+    client: &C,
+    kind: CollateralKind,
+    url: &str,
+) -> Result<(Vec<u8>, CollateralSource)> {
+    let fetched_at = fetch_time();
+    let fetch = async {
+        let response = fetch_ok(client, url).await?;
+        let source = source(kind, url, &response.headers, fetched_at);
+        Ok::<_, anyhow::Error>((decode_crl(&response.body)?, source))
+    };
+    fetch
+        .await
+        .map_err(|err| FetchError::new(kind, url, err).into())
+}
+
+/// Send a GET request, failing unless it succeeds.
+async fn fetch_ok<C: HttpClient>(client: &C, url: &str) -> Result<HttpResponse> {
+    let response = client.get(url);
+    #[cfg(feature = "tracing")]
+    let response =
+        tracing::Instrument::instrument(response, tracing::debug_span!("collateral_fetch", url));
+    let response = response.await?;
+    #[cfg(feature = "tracing")]
+    tracing::debug!(url, status = response.status, "collateral fetched");
+    if !(200..300).contains(&response.status) {
+        bail!("{url} returned HTTP {}", response.status);
+    }
+    Ok(response)
+}
+
+/// The current time in seconds since the Unix epoch, `None` in browsers where `SystemTime` is
+/// unavailable.
+fn fetch_time() -> Option<u64> {
+    if cfg!(feature = "js") {
+        return None;
+    }
+    std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
         .ok()
-        .context("QE Identity signature must be valid hex")?;
+        .map(|elapsed| elapsed.as_secs())
+}
 
-    Ok(QuoteCollateralV3 {
+/// The provenance of a response, keeping the [`PROVENANCE_HEADERS`] only.
+fn source(
+    kind: CollateralKind,
+    url: &str,
+    headers: &[(String, String)],
+    fetched_at: Option<u64>,
+) -> CollateralSource {
+    let headers = headers
+        .iter()
+        .filter(|(name, _)| {
+            PROVENANCE_HEADERS
+                .iter()
+                .any(|kept| kept.eq_ignore_ascii_case(name))
+        })
+        .cloned()
+        .collect();
+    CollateralSource {
+        kind,
+        url: url.into(),
+        fetched_at,
+        headers,
+    }
+}
+
+fn decode_crl(body: &[u8]) -> Result<Vec<u8>> {
+    if body.first() == Some(&0x30) {
+        return Ok(body.to_vec());
+    }
+    let text = core::str::from_utf8(body)
+        .context("CRL is neither DER nor text")?
+        .trim();
+    if text.starts_with("-----BEGIN") {
+        return Ok(pem::parse(text)
+            .context("Failed to parse PEM CRL")?
+            .into_contents());
+    }
+    hex::decode(text).context("Failed to decode hex CRL")
+}
+
+/// A collateral response as served by PCCS.
+pub(crate) struct RawResponse {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub fetched_at: Option<u64>,
+}
+
+impl RawResponse {
+    async fn fetch<C: HttpClient>(client: &C, kind: CollateralKind, url: String) -> Result<Self> {
+        let fetched_at = fetch_time();
+        let response = fetch_ok(client, &url)
+            .await
+            .map_err(|err| FetchError::new(kind, &url, err))?;
+        let body = String::from_utf8(response.body).map_err(|err| {
+            FetchError::new(kind, &url, anyhow!("Response is not valid UTF-8: {err}"))
+        })?;
+        Ok(Self {
+            url,
+            headers: response.headers,
+            body,
+            fetched_at,
+        })
+    }
+
+    fn source(&self, kind: CollateralKind) -> CollateralSource {
+        source(kind, &self.url, &self.headers, self.fetched_at)
+    }
+
+    fn header(&self, name: &str) -> Result<String> {
+        let value = self
+            .headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+            .ok_or_else(|| anyhow!("Missing {name}"))?;
+        Ok(urlencoding::decode(value)?.into_owned())
+    }
+}
+
+/// Fetch the TCB info and QE identity responses for a platform.
+#[cfg(feature = "report")]
+pub(crate) async fn fetch_responses(
+    pccs_url: &str,
+    fmspc: &str,
+    #[cfg(not(feature = "js"))] timeout: Duration,
+    egress: &EgressPolicy,
+) -> Result<(RawResponse, RawResponse)> {
+    egress.check(pccs_url)?;
+    let client = ReqwestClient::retrying(
+        #[cfg(not(feature = "js"))]
+        timeout,
+        egress,
+    )?;
+    fetch_responses_with_client(&client, pccs_url, fmspc, &CollateralOptions::default()).await
+}
+
+async fn fetch_responses_with_client<C: HttpClient>(
+    client: &C,
+    pccs_url: &str,
+    fmspc: &str,
+    options: &CollateralOptions,
+) -> Result<(RawResponse, RawResponse)> {
+    let fmspc: Fmspc = fmspc.parse()?;
+    futures::try_join!(
+        RawResponse::fetch(
+            client,
+            CollateralKind::TcbInfo,
+            pcs::tcb_info_with_options(pccs_url, &fmspc, options),
+        ),
+        RawResponse::fetch(
+            client,
+            CollateralKind::QeIdentity,
+            pcs::qe_identity_with_options(pccs_url, options),
+        ),
+    )
+}
+
+/// Build the collateral from the TCB info and QE identity responses.
+pub(crate) fn collateral_from_responses(
+    tcb_info: &RawResponse,
+    qe_identity: &RawResponse,
+) -> Result<QuoteCollateralV3> {
+    let tcb_info_issuer_chain = tcb_info
+        .header("SGX-TCB-Info-Issuer-Chain")
+        .or(tcb_info.header("TCB-Info-Issuer-Chain"))
+        .map_err(|err| FetchError::new(CollateralKind::TcbInfo, &tcb_info.url, err))?;
+    let qe_identity_issuer_chain = qe_identity
+        .header("SGX-Enclave-Identity-Issuer-Chain")
+        .map_err(|err| FetchError::new(CollateralKind::QeIdentity, &qe_identity.url, err))?;
+    let mut collateral = parse_collateral(
         tcb_info_issuer_chain,
-        tcb_info,
-        tcb_info_signature,
+        &tcb_info.body,
         qe_identity_issuer_chain,
-        qe_identity,
-        qe_identity_signature,
-    })
+        &qe_identity.body,
+    )?;
+    collateral.provenance = vec![
+        tcb_info.source(CollateralKind::TcbInfo),
+        qe_identity.source(CollateralKind::QeIdentity),
+    ];
+    Ok(collateral)
 }
 
 fn pcs_url(quote: &[u8]) -> Result<&'static str> {
     let header = Header::decode(&mut &quote[..]).context("Failed to decode quote header")?;
-    if header.is_sgx() {
-        Ok("https://api.trustedservices.intel.com/sgx/certification/v4")
-    } else {
-        Ok("https://api.trustedservices.intel.com/tdx/certification/v4")
-    }
+    Ok(pcs_url_for(header.is_sgx()))
+}
+
+/// The base URL of Intel PCS for SGX or TDX collateral, see [`pcs::base_url`].
+pub fn pcs_url_for(is_sgx: bool) -> &'static str {
+    pcs::base_url(is_sgx)
 }
 
 /// Get collateral given DCAP quote from Intel PCS.
@@ -124,6 +1012,7 @@ fn pcs_url(quote: &[u8]) -> Result<&'static str> {
 ///
 /// * `Ok(QuoteCollateralV3)` - The quote collateral
 /// * `Err(Error)` - The error
+#[cfg(feature = "report")]
 pub async fn get_collateral_from_pcs(
     quote: &[u8],
     #[cfg(not(feature = "js"))] timeout: Duration,
@@ -137,6 +1026,218 @@ pub async fn get_collateral_from_pcs(
     .await
 }
 
+/// Get collateral given DCAP quote from Intel PCS, at the TCB evaluation selected by `options`.
+///
+/// See [`get_collateral_from_pcs`] and [`get_collateral_with_options`].
+#[cfg(feature = "report")]
+pub async fn get_collateral_from_pcs_with_options(
+    quote: &[u8],
+    #[cfg(not(feature = "js"))] timeout: Duration,
+    options: &CollateralOptions,
+) -> Result<QuoteCollateralV3> {
+    get_collateral_with_options(
+        pcs_url(quote)?,
+        quote,
+        #[cfg(not(feature = "js"))]
+        timeout,
+        options,
+    )
+    .await
+}
+
+/// The base URL of the global Azure THIM (Trusted Hardware Identity Management) cache.
+pub const AZURE_THIM_URL: &str = "https://global.acccache.azure.net";
+
+/// Where to reach Azure THIM.
+#[derive(Debug, Clone, Default)]
+pub struct ThimConfig {
+    /// The THIM host, e.g. a regional cache. [`AZURE_THIM_URL`] if not set.
+    pub base_url: Option<String>,
+    /// The client ID sent with each request, for THIM's request accounting
+    pub client_id: Option<String>,
+}
+
+impl ThimConfig {
+    /// The PCCS-style base URL for SGX or TDX collateral, e.g.
+    /// `https://global.acccache.azure.net/tdx/certification/v4`.
+    pub fn certification_url(&self, is_sgx: bool) -> String {
+        let host = self.base_url.as_deref().unwrap_or(AZURE_THIM_URL);
+        let tee = if is_sgx { "sgx" } else { "tdx" };
+        format!("{}/{tee}/certification/v4", host.trim_end_matches('/'))
+    }
+
+    fn with_client_id(&self, url: String) -> String {
+        match &self.client_id {
+            Some(client_id) => {
+                let separator = if url.contains('?') { '&' } else { '?' };
+                format!(
+                    "{url}{separator}clientid={}",
+                    urlencoding::encode(client_id)
+                )
+            }
+            None => url,
+        }
+    }
+}
+
+/// Get collateral given DCAP quote from Azure THIM, as on Azure confidential VMs.
+///
+/// THIM follows the PCCS URL layout below `/sgx/certification/v4` and `/tdx/certification/v4`.
+/// It either serves the signed documents as PCCS does, with the issuer chain in a header, or
+/// wraps them in a JSON object with the issuer chain in its `issuerChain` field and the document,
+/// as a string, in `tcbInfo` or `qeIdentity`. Both produce the same collateral. The root CA CRL
+/// comes from Intel, see [`pcs::ROOT_CA_CRL_URL`].
+///
+/// # Arguments
+///
+/// * `quote` - The raw quote to verify. Supported SGX and TDX quotes.
+/// * `config` - Where to reach THIM.
+/// * `timeout` - The timeout for the request. (e.g. `Duration::from_secs(10)`)
+///
+/// # Returns
+///
+/// * `Ok(QuoteCollateralV3)` - The quote collateral
+/// * `Err(Error)` - The error
+#[cfg(feature = "report")]
+pub async fn get_collateral_from_azure_thim(
+    quote: &[u8],
+    config: &ThimConfig,
+    #[cfg(not(feature = "js"))] timeout: Duration,
+) -> Result<QuoteCollateralV3> {
+    let client = ReqwestClient::retrying(
+        #[cfg(not(feature = "js"))]
+        timeout,
+        &EgressPolicy::default(),
+    )?;
+    get_collateral_from_azure_thim_with_client(&client, quote, config).await
+}
+
+/// Get collateral given DCAP quote from Azure THIM, over the given client.
+///
+/// See [`get_collateral_from_azure_thim`].
+pub async fn get_collateral_from_azure_thim_with_client<C: HttpClient>(
+    client: &C,
+    mut quote: &[u8],
+    config: &ThimConfig,
+) -> Result<QuoteCollateralV3> {
+    let quote = Quote::decode(&mut quote)?;
+    let fmspc = quote.fmspc().context("Failed to get FMSPC")?;
+    let base_url = config.certification_url(quote.header.is_sgx());
+
+    let tcb_info = config.with_client_id(pcs::tcb_info(&base_url, &fmspc));
+    let tcb_info = RawResponse::fetch(client, CollateralKind::TcbInfo, tcb_info).await?;
+    let (tcb_info_issuer_chain, raw_tcb_info) = unwrap_thim_response(
+        &tcb_info,
+        &["SGX-TCB-Info-Issuer-Chain", "TCB-Info-Issuer-Chain"],
+        "tcbInfo",
+    )
+    .context("Invalid TCB info response")?;
+    let qe_identity = config.with_client_id(pcs::qe_identity(&base_url));
+    let qe_identity = RawResponse::fetch(client, CollateralKind::QeIdentity, qe_identity).await?;
+    let (qe_identity_issuer_chain, raw_qe_identity) = unwrap_thim_response(
+        &qe_identity,
+        &["SGX-Enclave-Identity-Issuer-Chain"],
+        "qeIdentity",
+    )
+    .context("Invalid QE identity response")?;
+    let mut collateral = parse_collateral(
+        tcb_info_issuer_chain,
+        &raw_tcb_info,
+        qe_identity_issuer_chain,
+        &raw_qe_identity,
+    )?;
+    collateral.provenance = vec![
+        tcb_info.source(CollateralKind::TcbInfo),
+        qe_identity.source(CollateralKind::QeIdentity),
+    ];
+
+    let (root_ca_crl, source) =
+        fetch_crl(client, CollateralKind::RootCaCrl, pcs::ROOT_CA_CRL_URL).await?;
+    collateral.root_ca_crl = Some(root_ca_crl);
+    collateral.provenance.push(source);
+    let pck_crl = pcs::pck_crl(&config.certification_url(true), pck_ca(&quote)?);
+    let (pck_crl, source) = fetch_crl(
+        client,
+        CollateralKind::PckCrl,
+        &config.with_client_id(pck_crl),
+    )
+    .await?;
+    collateral.pck_crl = Some(pck_crl);
+    collateral.provenance.push(source);
+    Ok(collateral)
+}
+
+/// The issuer chain and signed document of a THIM response, plain or wrapped.
+fn unwrap_thim_response(
+    response: &RawResponse,
+    chain_headers: &[&str],
+    document_key: &str,
+) -> Result<(String, String)> {
+    if let Some(chain) = chain_headers
+        .iter()
+        .find_map(|name| response.header(name).ok())
+    {
+        return Ok((chain, response.body.clone()));
+    }
+    let wrapped: serde_json::Value =
+        serde_json::from_str(&response.body).context("Missing issuer chain")?;
+    let field = |key: &str| {
+        wrapped
+            .get(key)
+            .and_then(serde_json::Value::as_str)
+            .map(String::from)
+            .ok_or_else(|| anyhow!("Missing {key} in wrapped response"))
+    };
+    Ok((field("issuerChain")?, field(document_key)?))
+}
+
+/// A source of collateral.
+#[derive(Debug, Clone)]
+pub enum CollateralProvider {
+    /// Intel PCS
+    Pcs,
+    /// A PCCS server at this base URL
+    Pccs(String),
+    /// Azure THIM
+    AzureThim(ThimConfig),
+}
+
+impl CollateralProvider {
+    /// Get the collateral for a quote from this provider.
+    #[cfg(feature = "report")]
+    pub async fn get_collateral(
+        &self,
+        quote: &[u8],
+        #[cfg(not(feature = "js"))] timeout: Duration,
+    ) -> Result<QuoteCollateralV3> {
+        let client = ReqwestClient::retrying(
+            #[cfg(not(feature = "js"))]
+            timeout,
+            &EgressPolicy::default(),
+        )?;
+        self.get_collateral_with_client(&client, quote).await
+    }
+
+    /// Get the collateral for a quote from this provider, over the given client.
+    pub async fn get_collateral_with_client<C: HttpClient>(
+        &self,
+        client: &C,
+        quote: &[u8],
+    ) -> Result<QuoteCollateralV3> {
+        match self {
+            CollateralProvider::Pcs => {
+                get_collateral_with_client(client, pcs_url(quote)?, quote).await
+            }
+            CollateralProvider::Pccs(pccs_url) => {
+                get_collateral_with_client(client, pccs_url, quote).await
+            }
+            CollateralProvider::AzureThim(config) => {
+                get_collateral_from_azure_thim_with_client(client, quote, config).await
+            }
+        }
+    }
+}
+
 #[cfg(feature = "js")]
 #[wasm_bindgen]
 pub async fn get_collateral_js(quote: JsValue) -> Result<JsValue, JsValue> {
@@ -150,28 +1251,620 @@ pub async fn get_collateral_js(quote: JsValue) -> Result<JsValue, JsValue> {
 }
 
 /// Get collateral and verify the quote.
+#[cfg(feature = "report")]
 pub async fn get_collateral_and_verify(
     quote: &[u8],
     pccs_url: Option<&str>,
 ) -> Result<VerifiedReport> {
-    let pccs_url = pccs_url.unwrap_or_default();
-    let pccs_url = if pccs_url.is_empty() {
-        pcs_url(quote)?
-    } else {
-        pccs_url
+    let options = OnlineOptions {
+        pccs_url: pccs_url.map(Into::into),
+        ..Default::default()
     };
-    #[cfg(not(feature = "js"))]
-    let timeout = Duration::from_secs(120);
-    let collateral = get_collateral(
-        pccs_url,
-        quote,
+    Ok(verify_online(quote, &options).await?.report)
+}
+
+impl QuoteCollateralV3 {
+    /// The earliest `nextUpdate` of the TCB info and QE identity, in seconds since the Unix epoch.
+    pub fn next_update(&self) -> Result<u64> {
+        let tcb_info =
+            serde_json::from_str::<TcbInfo>(&self.tcb_info).context("Failed to decode TcbInfo")?;
+        let qe_identity = serde_json::from_str::<QeIdentity>(&self.qe_identity)
+            .context("Failed to decode QeIdentity")?;
+        let tcb_info_next_update = utils::parse_time(&tcb_info.next_update)?;
+        let qe_identity_next_update = utils::parse_time(&qe_identity.next_update)?;
+        Ok(tcb_info_next_update.min(qe_identity_next_update))
+    }
+}
+
+/// A cache of fetched collateral, keyed by FMSPC, TEE type and PCK CA.
+///
+/// Entries are served until the earliest `nextUpdate` of their TCB info and QE identity, so
+/// cached collateral is never older than PCS would serve it. Cache failures are logged and treated
+/// as misses, caching is best effort.
+#[derive(Clone)]
+pub struct CollateralCache {
+    store: Arc<dyn CacheStore>,
+    limits: SizeLimits,
+}
+
+impl core::fmt::Debug for CollateralCache {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CollateralCache")
+            .field("limits", &self.limits)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CollateralCacheEntry {
+    collateral: QuoteCollateralV3,
+    next_update: u64,
+}
+
+impl CollateralCache {
+    /// A cache of up to `capacity` entries in memory, evicting the least recently used.
+    pub fn in_memory(capacity: usize) -> Self {
+        Self::with_store(Arc::new(LruStore::new(capacity)))
+    }
+
+    /// A cache keeping its entries in `store`, like a [`FileStore`](crate::store::FileStore) to
+    /// persist them between runs.
+    pub fn with_store(store: Arc<dyn CacheStore>) -> Self {
+        Self {
+            store,
+            limits: SizeLimits::default(),
+        }
+    }
+
+    /// Reject quotes and collateral over these limits instead of the default ones.
+    pub fn with_limits(mut self, limits: SizeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The cached collateral for a quote, if still valid at `now`.
+    ///
+    /// Entries over the [`SizeLimits`] are treated as misses.
+    pub fn get(&self, quote: &[u8], now: u64) -> Result<Option<QuoteCollateralV3>> {
+        self.limits.check_quote(quote)?;
+        let key = cache_key(&Quote::parse(quote)?)?;
+        let value = match self.store.get(&key) {
+            Ok(value) => value,
+            Err(err) => {
+                log::warn!("Failed to read collateral cache: {err:?}");
+                None
+            }
+        };
+        let Some(value) = value else {
+            return Ok(None);
+        };
+        if value.len() > self.limits.max_cache_entry_size() {
+            log::warn!("Ignoring collateral cache entry of {} bytes", value.len());
+            return Ok(None);
+        }
+        match serde_json::from_slice::<CollateralCacheEntry>(&value) {
+            Ok(entry) if now < entry.next_update => {
+                if let Err(err) = self.limits.check_collateral(&entry.collateral) {
+                    log::warn!("Ignoring collateral cache entry: {err:?}");
+                    return Ok(None);
+                }
+                Ok(Some(entry.collateral))
+            }
+            Ok(_) => Ok(None),
+            Err(err) => {
+                log::warn!("Failed to decode collateral cache entry: {err:?}");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Cache the collateral fetched for a quote, failing with a
+    /// [`TooLarge`](crate::limits::TooLarge) if either is over the [`SizeLimits`].
+    pub fn put(&self, quote: &[u8], collateral: &QuoteCollateralV3) -> Result<()> {
+        self.limits.check_quote(quote)?;
+        self.limits.check_collateral(collateral)?;
+        let key = cache_key(&Quote::parse(quote)?)?;
+        let entry = CollateralCacheEntry {
+            collateral: collateral.clone(),
+            next_update: collateral.next_update()?,
+        };
+        let value = serde_json::to_vec(&entry).context("Failed to encode cache entry")?;
+        if let Err(err) = self.store.put(&key, &value) {
+            log::warn!("Failed to write collateral cache: {err:?}");
+        }
+        Ok(())
+    }
+}
+
+/// The cache key of the collateral of a quote. The PCK CA is part of it since the PCK CRL is.
+fn cache_key(quote: &Quote) -> Result<Vec<u8>> {
+    let tee = if quote.header.is_sgx() { "sgx" } else { "tdx" };
+    let fmspc = quote.fmspc().context("Failed to get FMSPC")?;
+    let ca = pck_ca(quote)?;
+    Ok(format!("collateral-cache/{tee}/{}/{fmspc}", ca.as_str()).into_bytes())
+}
+
+/// Get collateral given DCAP quote and base URL of PCCS server URL, from the cache if it holds
+/// collateral still valid at `now`, otherwise fetching and caching it.
+///
+/// # Arguments
+///
+/// * `cache` - The collateral cache
+/// * `pccs_url` - The base URL of PCCS server. (e.g. `https://pccs.example.com/sgx/certification/v4`)
+/// * `quote` - The raw quote to verify. Supported SGX and TDX quotes.
+/// * `now` - The current time in seconds since the Unix epoch
+/// * `timeout` - The timeout for the request. (e.g. `Duration::from_secs(10)`)
+///
+/// # Returns
+///
+/// * `Ok(QuoteCollateralV3)` - The quote collateral
+/// * `Err(Error)` - The error
+#[cfg(feature = "report")]
+pub async fn get_collateral_cached(
+    cache: &CollateralCache,
+    pccs_url: &str,
+    quote: &[u8],
+    now: u64,
+    #[cfg(not(feature = "js"))] timeout: Duration,
+) -> Result<QuoteCollateralV3> {
+    let client = ReqwestClient::retrying(
         #[cfg(not(feature = "js"))]
         timeout,
-    )
-    .await?;
-    let now = SystemTime::now()
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .context("Failed to get current time")?
-        .as_secs() as u64;
-    crate::verify::verify(quote, &collateral, now)
+        &EgressPolicy::default(),
+    )?;
+    get_collateral_cached_with_client(cache, &client, pccs_url, quote, now).await
+}
+
+/// Get collateral through the cache, over the given client.
+///
+/// See [`get_collateral_cached`].
+pub async fn get_collateral_cached_with_client<C: HttpClient>(
+    cache: &CollateralCache,
+    client: &C,
+    pccs_url: &str,
+    quote: &[u8],
+    now: u64,
+) -> Result<QuoteCollateralV3> {
+    if let Some(collateral) = cache.get(quote, now)? {
+        return Ok(collateral);
+    }
+    let collateral = get_collateral_with_client(client, pccs_url, quote).await?;
+    cache.put(quote, &collateral)?;
+    Ok(collateral)
+}
+
+/// Strip the collateral down to the smallest form that still verifies the given quote.
+///
+/// The issuer chains are re-encoded without redundant whitespace and without the root CA, which
+/// is pinned in the verifier. A PCK CRL of another CA than the one issuing the quote's PCK
+/// certificate is dropped, and so is the provenance.
+///
+/// The TCB info and QE identity are kept byte for byte, whitespace and TCB levels included: they
+/// are signed as encoded, so the TCB levels that don't apply to the quote can't be stripped
+/// without invalidating the signatures.
+///
+/// # Arguments
+///
+/// * `collateral` - The quote collateral to minimize.
+/// * `quote` - The raw quote the collateral is for.
+pub fn minimize(collateral: &QuoteCollateralV3, quote: &[u8]) -> Result<QuoteCollateralV3> {
+    let quote = Quote::parse(quote)?;
+    let fmspc = quote.fmspc().context("Failed to get FMSPC")?;
+    let tcb_info = serde_json::from_str::<TcbInfo>(&collateral.tcb_info)
+        .context("Failed to decode TcbInfo")?;
+    if !tcb_info.fmspc.eq_ignore_ascii_case(&hex::encode(fmspc)) {
+        bail!("Collateral is not for the FMSPC of the quote");
+    }
+    let pck_crl = match &collateral.pck_crl {
+        Some(crl) if is_pck_ca_crl(crl, &quote)? => Some(crl.clone()),
+        _ => None,
+    };
+    Ok(QuoteCollateralV3 {
+        tcb_info_issuer_chain: minimize_chain(&collateral.tcb_info_issuer_chain)?,
+        tcb_info: collateral.tcb_info.clone(),
+        tcb_info_signature: collateral.tcb_info_signature.clone(),
+        qe_identity_issuer_chain: minimize_chain(&collateral.qe_identity_issuer_chain)?,
+        qe_identity: collateral.qe_identity.clone(),
+        qe_identity_signature: collateral.qe_identity_signature.clone(),
+        root_ca_crl: collateral.root_ca_crl.clone(),
+        pck_crl,
+        provenance: Vec::new(),
+    })
+}
+
+/// Whether a CRL is the one of the CA issuing the PCK certificate of a quote, the only PCK CRL
+/// its verification checks.
+fn is_pck_ca_crl(crl: &[u8], quote: &Quote) -> Result<bool> {
+    let crl: x509_cert::crl::CertificateList =
+        der::Decode::from_der(crl).context("Failed to decode PCK CRL")?;
+    Ok(crl.tbs_cert_list.issuer == pck_cert_issuer(quote)?)
+}
+
+fn minimize_chain(chain: &str) -> Result<String> {
+    let mut certs = Vec::new();
+    for cert in pem::parse_many(chain).context("Failed to parse certs")? {
+        if !utils::is_self_signed(cert.contents())? {
+            certs.push(cert);
+        }
+    }
+    let config = pem::EncodeConfig::new().set_line_ending(pem::LineEnding::LF);
+    Ok(pem::encode_many_config(&certs, config))
+}
+
+/// Options for [`verify_online`] and [`verify_online_blocking`].
+#[cfg(feature = "report")]
+#[derive(Debug, Clone, Default)]
+pub struct OnlineOptions {
+    /// The base URL of PCCS server. Intel PCS is used if not set.
+    pub pccs_url: Option<String>,
+    /// The timeout of fetching the collateral, retries included. Defaults to 120 seconds.
+    #[cfg(not(feature = "js"))]
+    pub timeout: Option<Duration>,
+    /// The verification time in seconds since the Unix epoch. Defaults to the current time.
+    pub now: Option<u64>,
+    /// The end-to-end deadline, checked before each step and bounding each request timeout.
+    #[cfg(not(feature = "js"))]
+    pub deadline: Deadline,
+    /// How failing requests are retried
+    pub retry: RetryPolicy,
+    /// Restrictions on the hosts contacted and the proxy used
+    pub egress: EgressPolicy,
+    /// The TCB evaluation to fetch the collateral at
+    pub collateral: CollateralOptions,
+    /// The cache to serve the collateral from while valid, and to cache fetched collateral in.
+    ///
+    /// Entries are keyed by platform only, so share a cache between calls with the same
+    /// `collateral` options.
+    pub cache: Option<CollateralCache>,
+    /// The options the quote is verified with
+    pub verify: VerifyOptions,
+}
+
+/// The result of [`verify_online`] and [`verify_online_blocking`].
+#[cfg(feature = "report")]
+#[derive(Debug, Clone)]
+pub struct OnlineReport {
+    /// The verified report
+    pub report: VerifiedReport,
+    /// The collateral the quote was verified against
+    pub collateral: QuoteCollateralV3,
+}
+
+#[cfg(feature = "report")]
+impl OnlineOptions {
+    fn pccs_url<'a>(&'a self, quote: &[u8]) -> Result<&'a str> {
+        match self.pccs_url.as_deref() {
+            Some(url) if !url.is_empty() => Ok(url),
+            _ => pcs_url(quote),
+        }
+    }
+
+    #[cfg(not(feature = "js"))]
+    fn timeout(&self) -> Result<Duration> {
+        self.deadline
+            .remaining(self.timeout.unwrap_or(DEFAULT_TIMEOUT))
+    }
+
+    /// The deadline of fetching the collateral, the timeout from now unless the end-to-end
+    /// deadline is sooner.
+    #[cfg(not(feature = "js"))]
+    fn fetch_deadline(&self) -> Result<Deadline> {
+        Ok(Deadline {
+            at: Some(std::time::Instant::now() + self.timeout()?),
+            cancel: self.deadline.cancel.clone(),
+        })
+    }
+
+    fn now(&self) -> Result<u64> {
+        match self.now {
+            Some(now) => Ok(now),
+            None => Ok(SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .context("Failed to get current time")?
+                .as_secs()),
+        }
+    }
+}
+
+/// A blocking [`HttpClient`] over reqwest, whose futures are ready once returned.
+#[cfg(all(feature = "report", not(feature = "js")))]
+struct BlockingClient {
+    client: reqwest::blocking::Client,
+    egress: EgressPolicy,
+    timeout: Duration,
+    deadline: Deadline,
+}
+
+#[cfg(all(feature = "report", not(feature = "js")))]
+impl BlockingClient {
+    /// A client retrying per the default [`RetryPolicy`], with `timeout` as the budget of all
+    /// requests.
+    fn retrying(timeout: Duration, egress: &EgressPolicy) -> Result<RetryingClient<Self>> {
+        Self::retrying_until(
+            timeout,
+            egress,
+            Deadline::after(timeout),
+            RetryPolicy::default(),
+        )
+    }
+
+    /// A client retrying per `policy`, with `timeout` as the request timeout and stopping at
+    /// `deadline`.
+    fn retrying_until(
+        timeout: Duration,
+        egress: &EgressPolicy,
+        deadline: Deadline,
+        policy: RetryPolicy,
+    ) -> Result<RetryingClient<Self>> {
+        let client = Self::new(timeout, egress, deadline.clone())?;
+        Ok(RetryingClient::new(client, policy).with_deadline(deadline))
+    }
+
+    fn new(timeout: Duration, egress: &EgressPolicy, deadline: Deadline) -> Result<Self> {
+        let builder = reqwest::blocking::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .timeout(timeout)
+            .redirect(egress.redirect_policy());
+        let builder = match egress.proxy()? {
+            Some(proxy) => builder.proxy(proxy),
+            None => builder,
+        };
+        Ok(Self {
+            client: builder.build()?,
+            egress: egress.clone(),
+            timeout,
+            deadline,
+        })
+    }
+
+    fn fetch(&self, url: &str) -> Result<HttpResponse> {
+        self.egress.check(url)?;
+        let timeout = self.deadline.remaining(self.timeout)?;
+        let response = self.client.get(url).timeout(timeout).send()?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+            })
+            .collect();
+        let body = response.bytes()?.to_vec();
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+#[cfg(all(feature = "report", not(feature = "js")))]
+impl HttpClient for BlockingClient {
+    fn get(&self, url: &str) -> impl Future<Output = Result<HttpResponse>> {
+        core::future::ready(self.fetch(url))
+    }
+
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> {
+        std::thread::sleep(duration);
+        core::future::ready(())
+    }
+}
+
+/// Run a `*_with_client` future over a blocking [`HttpClient`] to completion.
+///
+/// Futures of blocking clients never wait, so this polls once without an async runtime.
+///
+/// # Panics
+///
+/// If the future is pending, i.e. the client is not blocking.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    use std::task::{Poll, Wake, Waker};
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut cx = std::task::Context::from_waker(&waker);
+    match core::pin::pin!(future).poll(&mut cx) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("block_on needs a blocking HttpClient"),
+    }
+}
+
+/// Get collateral given DCAP quote and base URL of PCCS server URL, without an async runtime.
+///
+/// This uses a blocking HTTP client, so it must not be called from within an async runtime. See
+/// [`get_collateral`] for the arguments.
+#[cfg(all(feature = "blocking", not(feature = "js")))]
+pub fn get_collateral_sync(
+    pccs_url: &str,
+    quote: &[u8],
+    timeout: Duration,
+) -> Result<QuoteCollateralV3> {
+    let client = BlockingClient::retrying(timeout, &EgressPolicy::default())?;
+    block_on(get_collateral_with_client(&client, pccs_url, quote))
+}
+
+/// Get collateral given DCAP quote and base URL of PCCS server URL, at the TCB evaluation
+/// selected by `options`, without an async runtime.
+///
+/// This uses a blocking HTTP client, so it must not be called from within an async runtime. See
+/// [`get_collateral_with_options`] for the arguments.
+#[cfg(all(feature = "blocking", not(feature = "js")))]
+pub fn get_collateral_with_options_sync(
+    pccs_url: &str,
+    quote: &[u8],
+    timeout: Duration,
+    options: &CollateralOptions,
+) -> Result<QuoteCollateralV3> {
+    let client = BlockingClient::retrying(timeout, &EgressPolicy::default())?;
+    block_on(get_collateral_with_client_and_options(
+        &client, pccs_url, quote, options,
+    ))
+}
+
+/// Get collateral through the cache, without an async runtime.
+///
+/// This uses a blocking HTTP client, so it must not be called from within an async runtime. See
+/// [`get_collateral_cached`] for the arguments.
+#[cfg(all(feature = "blocking", not(feature = "js")))]
+pub fn get_collateral_cached_sync(
+    cache: &CollateralCache,
+    pccs_url: &str,
+    quote: &[u8],
+    now: u64,
+    timeout: Duration,
+) -> Result<QuoteCollateralV3> {
+    let client = BlockingClient::retrying(timeout, &EgressPolicy::default())?;
+    block_on(get_collateral_cached_with_client(
+        cache, &client, pccs_url, quote, now,
+    ))
+}
+
+/// Get collateral given the FMSPC of a platform, without an async runtime.
+///
+/// This uses a blocking HTTP client, so it must not be called from within an async runtime. See
+/// [`get_collateral_for_fmspc`] for the arguments.
+#[cfg(all(feature = "blocking", not(feature = "js")))]
+pub fn get_collateral_for_fmspc_sync(
+    pccs_url: &str,
+    fmspc: &str,
+    timeout: Duration,
+) -> Result<QuoteCollateralV3> {
+    let client = BlockingClient::retrying(timeout, &EgressPolicy::default())?;
+    block_on(get_collateral_for_fmspc_with_client(
+        &client, pccs_url, fmspc,
+    ))
+}
+
+/// Get collateral given the FMSPC of a platform, at the TCB evaluation selected by `options`,
+/// without an async runtime.
+///
+/// This uses a blocking HTTP client, so it must not be called from within an async runtime. See
+/// [`get_collateral_for_fmspc`] and [`get_collateral_with_options`] for the arguments.
+#[cfg(all(feature = "blocking", not(feature = "js")))]
+pub fn get_collateral_for_fmspc_with_options_sync(
+    pccs_url: &str,
+    fmspc: &str,
+    timeout: Duration,
+    options: &CollateralOptions,
+) -> Result<QuoteCollateralV3> {
+    let client = BlockingClient::retrying(timeout, &EgressPolicy::default())?;
+    block_on(get_collateral_for_fmspc_with_client_and_options(
+        &client, pccs_url, fmspc, options,
+    ))
+}
+
+/// Get collateral given DCAP quote from Intel PCS, without an async runtime.
+///
+/// This uses a blocking HTTP client, so it must not be called from within an async runtime. See
+/// [`get_collateral_from_pcs`] for the arguments.
+#[cfg(all(feature = "blocking", not(feature = "js")))]
+pub fn get_collateral_from_pcs_sync(quote: &[u8], timeout: Duration) -> Result<QuoteCollateralV3> {
+    get_collateral_sync(pcs_url(quote)?, quote, timeout)
+}
+
+#[cfg(all(feature = "blocking", not(feature = "js")))]
+impl CollateralProvider {
+    /// Get the collateral for a quote from this provider, without an async runtime.
+    ///
+    /// This uses a blocking HTTP client, so it must not be called from within an async runtime.
+    pub fn get_collateral_sync(
+        &self,
+        quote: &[u8],
+        timeout: Duration,
+    ) -> Result<QuoteCollateralV3> {
+        let client = BlockingClient::retrying(timeout, &EgressPolicy::default())?;
+        block_on(self.get_collateral_with_client(&client, quote))
+    }
+}
+
+/// Get collateral and verify the quote in one call.
+///
+/// # Arguments
+///
+/// * `quote` - The raw quote to verify. Supported SGX and TDX quotes.
+/// * `options` - Where to fetch the collateral from and when to verify at.
+///
+/// # Returns
+///
+/// * `Ok(OnlineReport)` - The verified report and the collateral used
+/// * `Err(Error)` - The error
+#[cfg(feature = "report")]
+pub async fn verify_online(quote: &[u8], options: &OnlineOptions) -> Result<OnlineReport> {
+    options.egress.check(options.pccs_url(quote)?)?;
+    let client = ReqwestClient::retrying_until(
+        #[cfg(not(feature = "js"))]
+        options.timeout()?,
+        &options.egress,
+        #[cfg(not(feature = "js"))]
+        options.fetch_deadline()?,
+        options.retry,
+    )?;
+    verify_online_with_client(&client, quote, options).await
+}
+
+/// Get collateral over the given client and verify the quote in one call.
+///
+/// The client is expected to enforce the timeout and egress policy of `options`. See
+/// [`verify_online`].
+#[cfg(feature = "report")]
+pub async fn verify_online_with_client<C: HttpClient>(
+    client: &C,
+    quote: &[u8],
+    options: &OnlineOptions,
+) -> Result<OnlineReport> {
+    let now = options.now()?;
+    let cached = match &options.cache {
+        Some(cache) => cache.get(quote, now)?,
+        None => None,
+    };
+    let collateral = match cached {
+        Some(collateral) => collateral,
+        None => {
+            let collateral = get_collateral_with_client_and_options(
+                client,
+                options.pccs_url(quote)?,
+                quote,
+                &options.collateral,
+            )
+            .await?;
+            if let Some(cache) = &options.cache {
+                cache.put(quote, &collateral)?;
+            }
+            collateral
+        }
+    };
+    #[cfg(not(feature = "js"))]
+    options.deadline.check()?;
+    let report = verify_with_options(quote, &collateral, now, &options.verify)?;
+    Ok(OnlineReport { report, collateral })
+}
+
+/// Get collateral and verify the quote in one call, without an async runtime.
+///
+/// This uses a blocking HTTP client, so it must not be called from within an async runtime.
+///
+/// # Arguments
+///
+/// * `quote` - The raw quote to verify. Supported SGX and TDX quotes.
+/// * `options` - Where to fetch the collateral from and when to verify at.
+///
+/// # Returns
+///
+/// * `Ok(OnlineReport)` - The verified report and the collateral used
+/// * `Err(Error)` - The error
+#[cfg(all(feature = "report", not(feature = "js")))]
+pub fn verify_online_blocking(quote: &[u8], options: &OnlineOptions) -> Result<OnlineReport> {
+    options.egress.check(options.pccs_url(quote)?)?;
+    let client = BlockingClient::retrying_until(
+        options.timeout()?,
+        &options.egress,
+        options.fetch_deadline()?,
+        options.retry,
+    )?;
+    block_on(verify_online_with_client(&client, quote, options))
 }
@@ -0,0 +1,193 @@
+//! Merkle commitments over collateral components.
+//!
+//! Each component of a [`QuoteCollateralV3`] is hashed into a leaf and the leaves are combined
+//! into a binary hash tree. The root is included in the [`VerifiedReport`](crate::verify::VerifiedReport),
+//! so a relying party can later prove exactly which TCB info or QE identity a decision was based on
+//! by revealing only that component and its [`proof`](CollateralCommitment::proof).
+//!
+//! The CRLs are committed too, as empty leaves if absent. The PCK CRL issuer chain is not: the
+//! collateral doesn't carry it, since the PCK CRL is checked against the PCK certificate chain
+//! in the quote. Neither is the provenance, which isn't covered by any signature.
+
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "borsh")]
+use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(feature = "scale-codec")]
+use {
+    scale::{Decode, Encode},
+    scale_info::TypeInfo,
+};
+
+use crate::QuoteCollateralV3;
+
+pub type Hash = [u8; 32];
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// A committed component of the collateral, in leaf order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(Encode, Decode, TypeInfo))]
+pub enum CollateralComponent {
+    TcbInfoIssuerChain,
+    TcbInfo,
+    TcbInfoSignature,
+    QeIdentityIssuerChain,
+    QeIdentity,
+    QeIdentitySignature,
+    RootCaCrl,
+    PckCrl,
+}
+
+impl CollateralComponent {
+    pub const ALL: [CollateralComponent; 8] = [
+        CollateralComponent::TcbInfoIssuerChain,
+        CollateralComponent::TcbInfo,
+        CollateralComponent::TcbInfoSignature,
+        CollateralComponent::QeIdentityIssuerChain,
+        CollateralComponent::QeIdentity,
+        CollateralComponent::QeIdentitySignature,
+        CollateralComponent::RootCaCrl,
+        CollateralComponent::PckCrl,
+    ];
+
+    /// The position of this component among the leaves.
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    /// The bytes of this component in the given collateral.
+    pub fn data<'a>(&self, collateral: &'a QuoteCollateralV3) -> &'a [u8] {
+        match self {
+            CollateralComponent::TcbInfoIssuerChain => collateral.tcb_info_issuer_chain.as_bytes(),
+            CollateralComponent::TcbInfo => collateral.tcb_info.as_bytes(),
+            CollateralComponent::TcbInfoSignature => &collateral.tcb_info_signature,
+            CollateralComponent::QeIdentityIssuerChain => {
+                collateral.qe_identity_issuer_chain.as_bytes()
+            }
+            CollateralComponent::QeIdentity => collateral.qe_identity.as_bytes(),
+            CollateralComponent::QeIdentitySignature => &collateral.qe_identity_signature,
+            CollateralComponent::RootCaCrl => collateral.root_ca_crl.as_deref().unwrap_or_default(),
+            CollateralComponent::PckCrl => collateral.pck_crl.as_deref().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(Encode, Decode, TypeInfo))]
+pub struct CommitmentLeaf {
+    pub component: CollateralComponent,
+    #[serde(with = "serde_bytes")]
+    pub hash: Hash,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(Encode, Decode, TypeInfo))]
+pub struct CollateralCommitment {
+    #[serde(with = "serde_bytes")]
+    pub root: Hash,
+    pub leaves: Vec<CommitmentLeaf>,
+}
+
+impl CollateralCommitment {
+    /// Get the sibling hashes proving the inclusion of `component` under the root.
+    pub fn proof(&self, component: CollateralComponent) -> Vec<Hash> {
+        let mut level = self.leaves.iter().map(|leaf| leaf.hash).collect::<Vec<_>>();
+        let mut index = component.index();
+        let mut proof = Vec::new();
+        while level.len() > 1 {
+            let sibling = index ^ 1;
+            if let Some(hash) = level.get(sibling) {
+                proof.push(*hash);
+            }
+            level = next_level(&level);
+            index /= 2;
+        }
+        proof
+    }
+}
+
+/// Compute the commitment over all components of the collateral.
+pub fn commit(collateral: &QuoteCollateralV3) -> CollateralCommitment {
+    let leaves = CollateralComponent::ALL
+        .iter()
+        .map(|component| CommitmentLeaf {
+            component: *component,
+            hash: leaf_hash(*component, component.data(collateral)),
+        })
+        .collect::<Vec<_>>();
+    let mut level = leaves.iter().map(|leaf| leaf.hash).collect::<Vec<_>>();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    CollateralCommitment {
+        root: level[0],
+        leaves,
+    }
+}
+
+/// Check that `data` is the given collateral component under `root`.
+pub fn verify_proof(
+    root: &Hash,
+    component: CollateralComponent,
+    data: &[u8],
+    proof: &[Hash],
+) -> bool {
+    let mut hash = leaf_hash(component, data);
+    let mut index = component.index();
+    let mut width = CollateralComponent::ALL.len();
+    let mut proof = proof.iter();
+    while width > 1 {
+        let has_sibling = (index ^ 1) < width;
+        if has_sibling {
+            let Some(sibling) = proof.next() else {
+                return false;
+            };
+            hash = if index % 2 == 0 {
+                node_hash(&hash, sibling)
+            } else {
+                node_hash(sibling, &hash)
+            };
+        }
+        index /= 2;
+        width = width.div_ceil(2);
+    }
+    proof.next().is_none() && &hash == root
+}
+
+fn next_level(level: &[Hash]) -> Vec<Hash> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => node_hash(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+fn leaf_hash(component: CollateralComponent, data: &[u8]) -> Hash {
+    let mut ctx = ring::digest::Context::new(&ring::digest::SHA256);
+    ctx.update(&[LEAF_PREFIX, component.index() as u8]);
+    ctx.update(data);
+    to_hash(ctx.finish())
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut ctx = ring::digest::Context::new(&ring::digest::SHA256);
+    ctx.update(&[NODE_PREFIX]);
+    ctx.update(left);
+    ctx.update(right);
+    to_hash(ctx.finish())
+}
+
+fn to_hash(digest: ring::digest::Digest) -> Hash {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(digest.as_ref());
+    hash
+}
@@ -2,8 +2,7 @@
 
 pub type MrSigner = [u8; 32];
 pub type MrEnclave = [u8; 32];
-pub type Fmspc = [u8; 6];
-pub type CpuSvn = [u8; 16];
+pub use crate::types::{CpuSvn, Fmspc};
 pub type Svn = u16;
 
 pub const ATTESTATION_KEY_TYPE_ECDSA256_WITH_P256_CURVE: u16 = 2;
@@ -119,9 +118,23 @@ pub mod oids {
     pub const SGX_TYPE: OID = oid("1.2.840.113741.1.13.1.5"); // ASN1 Enumerated
     pub const PLATFORM_INSTANCE_ID: OID = oid("1.2.840.113741.1.13.1.6");
     pub const CONFIGURATION: OID = oid("1.2.840.113741.1.13.1.7");
+    pub const DYNAMIC_PLATFORM: OID = oid("1.2.840.113741.1.13.1.7.1");
+    pub const CACHED_KEYS: OID = oid("1.2.840.113741.1.13.1.7.2");
+    pub const SMT_ENABLED: OID = oid("1.2.840.113741.1.13.1.7.3");
     pub const PCESVN: OID = oid("1.2.840.113741.1.13.1.2.17");
     pub const CPUSVN: OID = oid("1.2.840.113741.1.13.1.2.18");
 
+    pub const EC_PUBLIC_KEY: OID = oid("1.2.840.10045.2.1");
+    pub const SECP256R1: OID = oid("1.2.840.10045.3.1.7");
+    pub const SECP384R1: OID = oid("1.3.132.0.34");
+
+    /// SGX quote extension of RA-TLS certificates
+    pub const RA_TLS_SGX_QUOTE: OID = oid("1.2.840.113741.1.13.1.0");
+    /// TDX quote extension of RA-TLS certificates
+    pub const RA_TLS_TDX_QUOTE: OID = oid("1.2.840.113741.1.5.5.1.6");
+    // Used by Gramine, Occlum and RATS-TLS, for SGX and TDX quotes alike
+    pub const RA_TLS_LEGACY_QUOTE: OID = oid("1.2.840.113741.1337.6");
+
     #[test]
     fn const_oid_works() {
         assert_eq!(
@@ -0,0 +1,199 @@
+//! TDX CC event log replay.
+//!
+//! The TDX firmware records what it measures into RTMR0 to RTMR3 in the CC event log (CCEL),
+//! using the crypto agile format of the TCG PC Client event log. Replaying the SHA-384 digests of
+//! the log reproduces the RTMRs, so checking them against the TD report of a verified quote
+//! proves the log is the one of the quoted TD, and its events can be trusted as measured.
+
+use alloc::vec::Vec;
+use anyhow::{bail, Context, Result};
+
+use crate::quote::Quote;
+
+const EV_NO_ACTION: u32 = 0x3;
+const TPM_ALG_SHA384: u16 = 0x000c;
+const SPEC_ID_SIGNATURE: &[u8; 16] = b"Spec ID Event03\0";
+const SHA1_DIGEST_LEN: usize = 20;
+const SHA384_DIGEST_LEN: usize = 48;
+
+/// A measured event of a CC event log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// The CC measurement register index: 0 for MRTD, 1 to 4 for RTMR0 to RTMR3
+    pub mr_index: u32,
+    pub event_type: u32,
+    /// The SHA-384 digest extended into the register
+    pub digest: [u8; SHA384_DIGEST_LEN],
+    pub data: Vec<u8>,
+}
+
+/// A parsed CC event log.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct EventLog {
+    /// The events in log order, without the `EV_NO_ACTION` ones
+    pub events: Vec<Event>,
+}
+
+impl EventLog {
+    /// Parse a CC event log in the TCG crypto agile format, as found in the CCEL ACPI table.
+    ///
+    /// Parsing stops at the padding after the last event.
+    pub fn parse(raw: &[u8]) -> Result<Self> {
+        let mut reader = Reader(raw);
+        let digest_sizes = parse_spec_id_event(&mut reader).context("Invalid Spec ID event")?;
+        let mut events = Vec::new();
+        while !reader.0.is_empty() && !is_padding(reader.0) {
+            let event = parse_event(&mut reader, &digest_sizes)
+                .with_context(|| format!("Invalid event {}", events.len() + 1))?;
+            if let Some(event) = event {
+                events.push(event);
+            }
+        }
+        Ok(Self { events })
+    }
+
+    /// Replay the events into RTMR0 to RTMR3.
+    pub fn replay(&self) -> [[u8; SHA384_DIGEST_LEN]; 4] {
+        let mut rtmrs = [[0u8; SHA384_DIGEST_LEN]; 4];
+        for event in &self.events {
+            // MRTD is measured by the TDX module, not extended by events
+            let Some(rtmr) = (event.mr_index as usize)
+                .checked_sub(1)
+                .and_then(|index| rtmrs.get_mut(index))
+            else {
+                continue;
+            };
+            let mut ctx = ring::digest::Context::new(&ring::digest::SHA384);
+            ctx.update(&rtmr[..]);
+            ctx.update(&event.digest);
+            rtmr.copy_from_slice(ctx.finish().as_ref());
+        }
+        rtmrs
+    }
+}
+
+/// Check that replaying the event log reproduces the RTMRs of a TDX quote.
+///
+/// # Arguments
+///
+/// * `quote` - The TDX quote, which should be verified already
+/// * `event_log` - The event log of the quoted TD
+///
+/// # Returns
+///
+/// * `Ok(())` - All of RTMR0 to RTMR3 match
+/// * `Err(Error)` - The quote is not a TDX quote, or the error lists the mismatched RTMRs
+pub fn verify_rtmrs(quote: &Quote, event_log: &EventLog) -> Result<()> {
+    let td_report = quote.as_tdx().context("Not a TDX quote")?;
+    let expected = [
+        &td_report.rt_mr0,
+        &td_report.rt_mr1,
+        &td_report.rt_mr2,
+        &td_report.rt_mr3,
+    ];
+    let mismatches = event_log
+        .replay()
+        .iter()
+        .zip(expected)
+        .enumerate()
+        .filter(|(_, (replayed, expected))| replayed != expected)
+        .map(|(index, (replayed, expected))| {
+            format!(
+                "RTMR{index} is {} in the quote, {} in the event log",
+                hex::encode(expected),
+                hex::encode(replayed)
+            )
+        })
+        .collect::<Vec<_>>();
+    if !mismatches.is_empty() {
+        bail!("Event log mismatch: {}", mismatches.join("; "));
+    }
+    Ok(())
+}
+
+/// Parse the leading SHA-1 format event holding the digest sizes of the log.
+fn parse_spec_id_event(reader: &mut Reader) -> Result<Vec<(u16, usize)>> {
+    let _mr_index = reader.u32()?;
+    if reader.u32()? != EV_NO_ACTION {
+        bail!("Not an EV_NO_ACTION event");
+    }
+    reader.bytes(SHA1_DIGEST_LEN)?;
+    let size = reader.u32()? as usize;
+    let mut data = Reader(reader.bytes(size)?);
+    if data.bytes(SPEC_ID_SIGNATURE.len())? != SPEC_ID_SIGNATURE {
+        bail!("Not a crypto agile event log");
+    }
+    // platformClass, specVersionMinor, specVersionMajor, specErrata and uintnSize
+    data.bytes(8)?;
+    let count = data.u32()?;
+    let mut digest_sizes = Vec::new();
+    for _ in 0..count {
+        let alg = data.u16()?;
+        let size = data.u16()? as usize;
+        digest_sizes.push((alg, size));
+    }
+    Ok(digest_sizes)
+}
+
+/// Parse a crypto agile event, `None` for `EV_NO_ACTION` ones.
+fn parse_event(reader: &mut Reader, digest_sizes: &[(u16, usize)]) -> Result<Option<Event>> {
+    let mr_index = reader.u32()?;
+    let event_type = reader.u32()?;
+    let count = reader.u32()?;
+    let mut digest = None;
+    for _ in 0..count {
+        let alg = reader.u16()?;
+        let &(_, size) = digest_sizes
+            .iter()
+            .find(|(id, _)| *id == alg)
+            .with_context(|| format!("Digest algorithm {alg:#06x} not in the Spec ID event"))?;
+        let value = reader.bytes(size)?;
+        if alg == TPM_ALG_SHA384 {
+            let value: [u8; SHA384_DIGEST_LEN] = value
+                .try_into()
+                .ok()
+                .context("Invalid SHA-384 digest size")?;
+            digest = Some(value);
+        }
+    }
+    let size = reader.u32()? as usize;
+    let data = reader.bytes(size)?.to_vec();
+    if event_type == EV_NO_ACTION {
+        return Ok(None);
+    }
+    Ok(Some(Event {
+        mr_index,
+        event_type,
+        digest: digest.context("Missing SHA-384 digest")?,
+        data,
+    }))
+}
+
+/// Whether the rest of the log is the padding of the CCEL area.
+fn is_padding(rest: &[u8]) -> bool {
+    rest.iter().all(|b| *b == 0xff) || rest.iter().all(|b| *b == 0)
+}
+
+/// Reads little endian fields off the log.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.0.len() < len {
+            bail!("Event log is truncated");
+        }
+        let (head, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
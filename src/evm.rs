@@ -0,0 +1,168 @@
+//! ABI encoded proofs for EVM chains.
+//!
+//! The quote is fully verified by this crate first. The pieces an EVM contract needs to re-check
+//! its signatures are then ABI encoded, matching the `verify` function of the reference verifier
+//! in `contracts/DcapProofVerifier.sol`:
+//!
+//! ```solidity
+//! abi.encode(
+//!     bytes signedQuote,
+//!     bytes32 quoteSigR, bytes32 quoteSigS,
+//!     bytes32 attestationKeyX, bytes32 attestationKeyY,
+//!     bytes qeReport,
+//!     bytes32 qeReportSigR, bytes32 qeReportSigS,
+//!     bytes qeAuthData,
+//!     bytes32 pckKeyX, bytes32 pckKeyY,
+//!     bytes32 tcbAssertion
+//! )
+//! ```
+//!
+//! `tcbAssertion` is
+//! `sha256(abi.encode(bytes32 pckKeyX, bytes32 pckKeyY, bytes32 collateralRoot, string tcbStatus))`,
+//! so the contract can allowlist, per PCK key, the collateral and TCB status that were accepted
+//! off-chain. An assertion issued for one platform is of no use with the PCK key of another.
+
+use alloc::vec::Vec;
+use anyhow::{anyhow, Context, Result};
+use scale::Decode;
+
+use crate::constants::*;
+use crate::quote::Quote;
+use crate::utils::{extract_certs, get_public_key, MsgContext};
+use crate::verify::{verify, VerifiedReport};
+use crate::QuoteCollateralV3;
+
+/// The verification relevant pieces of a quote, ready to be ABI encoded.
+#[derive(Debug, Clone)]
+pub struct EvmProof {
+    pub signed_quote: Vec<u8>,
+    pub quote_signature: [u8; ECDSA_SIGNATURE_BYTE_LEN],
+    pub attestation_key: [u8; ECDSA_PUBKEY_BYTE_LEN],
+    pub qe_report: [u8; ENCLAVE_REPORT_BYTE_LEN],
+    pub qe_report_signature: [u8; QE_REPORT_SIG_BYTE_LEN],
+    pub qe_auth_data: Vec<u8>,
+    pub pck_key: [u8; ECDSA_PUBKEY_BYTE_LEN],
+    pub tcb_assertion: [u8; 32],
+}
+
+/// Verify a quote and build the proof for it.
+///
+/// # Arguments
+///
+/// * `raw_quote` - The raw quote to verify. Supported SGX and TDX quotes.
+/// * `quote_collateral` - The quote collateral to verify against.
+/// * `now` - The current time in seconds since the Unix epoch
+///
+/// # Returns
+///
+/// * `Ok((VerifiedReport, EvmProof))` - The verified report and the proof
+/// * `Err(Error)` - The error
+pub fn prove(
+    raw_quote: &[u8],
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+) -> Result<(VerifiedReport, EvmProof)> {
+    let report = verify(raw_quote, quote_collateral, now)?;
+
+    let quote = Quote::decode(&mut &raw_quote[..]).msg_context("Failed to decode quote")?;
+    let signed_quote = raw_quote
+        .get(..quote.signed_length())
+        .ok_or(anyhow!("Failed to get signed quote"))?
+        .to_vec();
+    let auth_data = quote.auth_data.into_v3();
+
+    let certs = extract_certs(&auth_data.certification_data.body.data)?;
+    let pck_cert = certs.first().ok_or(anyhow!("Missing PCK certificate"))?;
+    let pck_key = get_public_key(pck_cert).context("Failed to get PCK public key")?;
+
+    let proof = EvmProof {
+        signed_quote,
+        quote_signature: auth_data.ecdsa_signature,
+        attestation_key: auth_data.ecdsa_attestation_key,
+        qe_report: auth_data.qe_report,
+        qe_report_signature: auth_data.qe_report_signature,
+        qe_auth_data: auth_data.qe_auth_data.data,
+        pck_key,
+        tcb_assertion: tcb_assertion(
+            &pck_key,
+            &report.collateral_commitment.root,
+            report.status.as_str(),
+        ),
+    };
+    Ok((report, proof))
+}
+
+impl EvmProof {
+    /// ABI encode the proof as the arguments of the reference verifier.
+    pub fn abi_encode(&self) -> Vec<u8> {
+        encode(&[
+            Token::Bytes(&self.signed_quote),
+            Token::Word(&self.quote_signature[..32]),
+            Token::Word(&self.quote_signature[32..]),
+            Token::Word(&self.attestation_key[..32]),
+            Token::Word(&self.attestation_key[32..]),
+            Token::Bytes(&self.qe_report),
+            Token::Word(&self.qe_report_signature[..32]),
+            Token::Word(&self.qe_report_signature[32..]),
+            Token::Bytes(&self.qe_auth_data),
+            Token::Word(&self.pck_key[..32]),
+            Token::Word(&self.pck_key[32..]),
+            Token::Word(&self.tcb_assertion),
+        ])
+    }
+}
+
+/// Compute
+/// `sha256(abi.encode(bytes32 pckKeyX, bytes32 pckKeyY, bytes32 collateralRoot, string tcbStatus))`.
+pub fn tcb_assertion(
+    pck_key: &[u8; ECDSA_PUBKEY_BYTE_LEN],
+    collateral_root: &[u8; 32],
+    tcb_status: &str,
+) -> [u8; 32] {
+    let encoded = encode(&[
+        Token::Word(&pck_key[..32]),
+        Token::Word(&pck_key[32..]),
+        Token::Word(collateral_root),
+        Token::Bytes(tcb_status.as_bytes()),
+    ]);
+    let digest = ring::digest::digest(&ring::digest::SHA256, &encoded);
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(digest.as_ref());
+    hash
+}
+
+enum Token<'a> {
+    /// A 32-byte static value
+    Word(&'a [u8]),
+    /// A dynamic `bytes` or `string` value
+    Bytes(&'a [u8]),
+}
+
+fn encode(tokens: &[Token]) -> Vec<u8> {
+    let head_len = 32 * tokens.len();
+    let mut head = Vec::with_capacity(head_len);
+    let mut tail = Vec::new();
+    for token in tokens {
+        match token {
+            Token::Word(word) => {
+                let mut padded = [0u8; 32];
+                padded[..word.len()].copy_from_slice(word);
+                head.extend_from_slice(&padded);
+            }
+            Token::Bytes(bytes) => {
+                head.extend_from_slice(&uint(head_len + tail.len()));
+                tail.extend_from_slice(&uint(bytes.len()));
+                tail.extend_from_slice(bytes);
+                tail.resize(tail.len().next_multiple_of(32), 0);
+            }
+        }
+    }
+    head.extend_from_slice(&tail);
+    head
+}
+
+fn uint(value: usize) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&(value as u64).to_be_bytes());
+    word
+}
@@ -0,0 +1,317 @@
+//! Collateral and evidence file formats.
+//!
+//! Besides the SCALE and JSON encodings of [`QuoteCollateralV3`], collateral shows up in the
+//! formats of Intel's tooling and bundled with quotes by TEE runtimes. This module converts them.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use anyhow::{bail, Context, Result};
+use base64::Engine as _;
+use serde_json::Value;
+
+use crate::verify::{verify, VerifiedReport};
+use crate::QuoteCollateralV3;
+
+/// A quote bundled with its collateral.
+#[derive(Debug, Clone)]
+pub struct Envelope {
+    pub quote: Vec<u8>,
+    pub collateral: QuoteCollateralV3,
+}
+
+impl Envelope {
+    /// Verify the quote against the bundled collateral, see [`verify`].
+    pub fn verify(&self, now: u64) -> Result<VerifiedReport> {
+        verify(&self.quote, &self.collateral, now)
+    }
+}
+
+/// Parse an "evidence + endorsements" JSON envelope as emitted by runtimes like Occlum or Gramine.
+///
+/// The quote is read from `quote` or `evidence`, and the collateral from `collateral`,
+/// `endorsements` or `quote_collateral`. Binary values may be hex strings, base64 strings or byte
+/// arrays. The collateral fields may be snake_case or camelCase, and the TCB info and QE identity
+/// may be either bare with separate signatures or the signed JSON documents served by PCS, as in
+/// `sgx_ql_qve_collateral_t`.
+pub fn parse_envelope(json: &[u8]) -> Result<Envelope> {
+    let envelope: Value = serde_json::from_slice(json).context("Envelope should be valid JSON")?;
+    let quote = field(&envelope, &["quote", "evidence"]).context("Envelope missing quote")?;
+    let collateral = field(
+        &envelope,
+        &["collateral", "endorsements", "quote_collateral"],
+    )
+    .context("Envelope missing collateral")?;
+    Ok(Envelope {
+        quote: decode_bytes(quote).context("Failed to decode quote")?,
+        collateral: parse_collateral_value(collateral)?,
+    })
+}
+
+fn parse_collateral_value(value: &Value) -> Result<QuoteCollateralV3> {
+    let string = |names: &[&str]| -> Result<String> {
+        let value = field(value, names).with_context(|| format!("Missing {}", names[0]))?;
+        Ok(value
+            .as_str()
+            .with_context(|| format!("{} must be a string", names[0]))?
+            .to_string())
+    };
+    let tcb_info_issuer_chain = string(&["tcb_info_issuer_chain", "tcbInfoIssuerChain"])?;
+    let tcb_info = string(&["tcb_info", "tcbInfo"])?;
+    let qe_identity_issuer_chain = string(&["qe_identity_issuer_chain", "qeIdentityIssuerChain"])?;
+    let qe_identity = string(&["qe_identity", "qeIdentity"])?;
+    let Some(tcb_info_signature) = field(value, &["tcb_info_signature", "tcbInfoSignature"]) else {
+        // Signed documents as served by PCS
+        return parse_collateral(
+            tcb_info_issuer_chain,
+            &tcb_info,
+            qe_identity_issuer_chain,
+            &qe_identity,
+        );
+    };
+    let qe_identity_signature = field(value, &["qe_identity_signature", "qeIdentitySignature"])
+        .context("Missing qe_identity_signature")?;
+    Ok(QuoteCollateralV3 {
+        tcb_info_issuer_chain,
+        tcb_info,
+        tcb_info_signature: decode_bytes(tcb_info_signature)
+            .context("Failed to decode tcb_info_signature")?,
+        qe_identity_issuer_chain,
+        qe_identity,
+        qe_identity_signature: decode_bytes(qe_identity_signature)
+            .context("Failed to decode qe_identity_signature")?,
+        root_ca_crl: field(value, &["root_ca_crl", "rootCaCrl"])
+            .map(decode_bytes)
+            .transpose()
+            .context("Failed to decode root_ca_crl")?,
+        pck_crl: field(value, &["pck_crl", "pckCrl"])
+            .map(decode_bytes)
+            .transpose()
+            .context("Failed to decode pck_crl")?,
+        provenance: Vec::new(),
+    })
+}
+
+fn field<'a>(value: &'a Value, names: &[&str]) -> Option<&'a Value> {
+    names.iter().find_map(|name| value.get(*name))
+}
+
+/// Decode a hex string, base64 string or byte array.
+fn decode_bytes(value: &Value) -> Result<Vec<u8>> {
+    match value {
+        Value::String(s) => {
+            let s = s.trim();
+            let hex_str = s.strip_prefix("0x").unwrap_or(s);
+            if let Ok(bytes) = hex::decode(hex_str) {
+                return Ok(bytes);
+            }
+            base64::engine::general_purpose::STANDARD
+                .decode(s)
+                .ok()
+                .context("Value must be hex or base64")
+        }
+        Value::Array(items) => items
+            .iter()
+            .map(|item| {
+                item.as_u64()
+                    .and_then(|b| u8::try_from(b).ok())
+                    .context("Byte array must contain bytes")
+            })
+            .collect(),
+        _ => bail!("Value must be a string or byte array"),
+    }
+}
+
+/// Read collateral for a platform from the platform collaterals JSON written by
+/// `pccsadmin fetch`, e.g. for importing into an offline PCCS.
+///
+/// # Arguments
+///
+/// * `json` - The platform collaterals file.
+/// * `fmspc` - The hex encoded FMSPC of the platform.
+/// * `is_tdx` - Whether to read the TDX TCB info and QE identity instead of the SGX ones.
+pub fn read_pccs_admin_collateral(
+    json: &[u8],
+    fmspc: &str,
+    is_tdx: bool,
+) -> Result<QuoteCollateralV3> {
+    let file: Value =
+        serde_json::from_slice(json).context("Platform collaterals should be valid JSON")?;
+    let collaterals = file.get("collaterals").unwrap_or(&file);
+    let tcb_info = collaterals["tcbinfos"]
+        .as_array()
+        .context("Missing tcbinfos")?
+        .iter()
+        .find(|entry| {
+            entry["fmspc"]
+                .as_str()
+                .is_some_and(|f| f.eq_ignore_ascii_case(fmspc))
+        })
+        .context("No TCB info for the FMSPC")?;
+    let (tcb_info, qe_identity) = if is_tdx {
+        (&tcb_info["tdx_tcbinfo"], &collaterals["tdqeidentity"])
+    } else {
+        (&tcb_info["tcbinfo"], &collaterals["qeidentity"])
+    };
+    let certificates = &collaterals["certificates"];
+    let chain = |name: &str| -> Result<String> {
+        let chain = certificates[name]
+            .as_str()
+            .with_context(|| format!("Missing {name}"))?;
+        Ok(urlencoding::decode(chain)?.into_owned())
+    };
+    parse_collateral(
+        chain("SGX-TCB-Info-Issuer-Chain")?,
+        &document(tcb_info).context("Missing TCB info")?,
+        chain("SGX-Enclave-Identity-Issuer-Chain")?,
+        &document(qe_identity).context("Missing QE identity")?,
+    )
+}
+
+/// Read collateral from a flattened `sgx_ql_qve_collateral_t`, as dumped by the Intel QVL samples.
+///
+/// The layout is the `version` and `tee_type` as little endian `u32`s, followed by the
+/// `pck_crl_issuer_chain`, `root_ca_crl`, `pck_crl`, `tcb_info_issuer_chain`, `tcb_info`,
+/// `qe_identity_issuer_chain` and `qe_identity` fields, each as a little endian `u32` size followed
+/// by that many bytes. Text fields may be NUL terminated.
+pub fn read_qve_collateral(data: &[u8]) -> Result<QuoteCollateralV3> {
+    let mut input = data.get(8..).context("Collateral file is too short")?;
+    let mut fields = Vec::with_capacity(7);
+    for _ in 0..7 {
+        let (size, rest) = input
+            .split_at_checked(4)
+            .context("Collateral file is truncated")?;
+        let size = u32::from_le_bytes(size.try_into()?) as usize;
+        let (field, rest) = rest
+            .split_at_checked(size)
+            .context("Collateral file is truncated")?;
+        fields.push(field);
+        input = rest;
+    }
+    let text = |field: &[u8]| -> Result<String> {
+        let field = field.strip_suffix(&[0]).unwrap_or(field);
+        Ok(core::str::from_utf8(field)
+            .context("Collateral field must be UTF-8")?
+            .to_string())
+    };
+    let mut collateral = parse_collateral(
+        text(fields[3])?,
+        &text(fields[4])?,
+        text(fields[5])?,
+        &text(fields[6])?,
+    )?;
+    collateral.root_ca_crl = read_crl(fields[1]).context("Failed to decode root_ca_crl")?;
+    collateral.pck_crl = read_crl(fields[2]).context("Failed to decode pck_crl")?;
+    Ok(collateral)
+}
+
+/// Decode a CRL field, which the QVL accepts as PEM, hex encoded DER or raw DER.
+fn read_crl(field: &[u8]) -> Result<Option<Vec<u8>>> {
+    let field = field.strip_suffix(&[0]).unwrap_or(field);
+    if field.is_empty() {
+        return Ok(None);
+    }
+    if field.starts_with(b"-----BEGIN") {
+        return Ok(Some(pem::parse(field)?.into_contents()));
+    }
+    if field.iter().all(u8::is_ascii_hexdigit) {
+        return Ok(Some(hex::decode(field)?));
+    }
+    Ok(Some(field.to_vec()))
+}
+
+/// Write collateral as a flattened `sgx_ql_qve_collateral_t`, see [`read_qve_collateral`].
+///
+/// The CRLs are written hex encoded, and left empty when the collateral does not carry them.
+pub fn write_qve_collateral(collateral: &QuoteCollateralV3, is_tdx: bool) -> Vec<u8> {
+    let tcb_info = format!(
+        r#"{{"tcbInfo":{},"signature":"{}"}}"#,
+        collateral.tcb_info,
+        hex::encode(&collateral.tcb_info_signature)
+    );
+    let qe_identity = format!(
+        r#"{{"enclaveIdentity":{},"signature":"{}"}}"#,
+        collateral.qe_identity,
+        hex::encode(&collateral.qe_identity_signature)
+    );
+    // Version 3.1 for TDX collateral, 3.0 for SGX
+    let (version, tee_type): (u32, u32) = if is_tdx {
+        (0x0001_0003, 0x81)
+    } else {
+        (0x0000_0003, 0)
+    };
+    let root_ca_crl = collateral.root_ca_crl.as_deref().map(hex::encode);
+    let pck_crl = collateral.pck_crl.as_deref().map(hex::encode);
+    let mut out = Vec::new();
+    out.extend_from_slice(&version.to_le_bytes());
+    out.extend_from_slice(&tee_type.to_le_bytes());
+    let fields: [&[u8]; 7] = [
+        b"",
+        root_ca_crl.as_deref().unwrap_or_default().as_bytes(),
+        pck_crl.as_deref().unwrap_or_default().as_bytes(),
+        collateral.tcb_info_issuer_chain.as_bytes(),
+        tcb_info.as_bytes(),
+        collateral.qe_identity_issuer_chain.as_bytes(),
+        qe_identity.as_bytes(),
+    ];
+    for field in fields {
+        out.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        out.extend_from_slice(field);
+    }
+    out
+}
+
+/// Get a signed JSON document, stored either as a string or inline.
+fn document(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Object(_) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Build collateral from the signed TCB info and QE identity documents served by PCS.
+pub(crate) fn parse_collateral(
+    tcb_info_issuer_chain: String,
+    raw_tcb_info: &str,
+    qe_identity_issuer_chain: String,
+    raw_qe_identity: &str,
+) -> Result<QuoteCollateralV3> {
+    let tcb_info_json: serde_json::Value =
+        serde_json::from_str(raw_tcb_info).context("TCB Info should be valid JSON")?;
+    let tcb_info = tcb_info_json["tcbInfo"].to_string();
+    let tcb_info_signature = tcb_info_json
+        .get("signature")
+        .context("TCB Info missing 'signature' field")?
+        .as_str()
+        .context("TCB Info signature must be a string")?;
+    let tcb_info_signature = hex::decode(tcb_info_signature)
+        .ok()
+        .context("TCB Info signature must be valid hex")?;
+
+    let qe_identity_json: serde_json::Value =
+        serde_json::from_str(raw_qe_identity).context("QE Identity should be valid JSON")?;
+    let qe_identity = qe_identity_json
+        .get("enclaveIdentity")
+        .context("QE Identity missing 'enclaveIdentity' field")?
+        .to_string();
+    let qe_identity_signature = qe_identity_json
+        .get("signature")
+        .context("QE Identity missing 'signature' field")?
+        .as_str()
+        .context("QE Identity signature must be a string")?;
+    let qe_identity_signature = hex::decode(qe_identity_signature)
+        .ok()
+        .context("QE Identity signature must be valid hex")?;
+
+    Ok(QuoteCollateralV3 {
+        tcb_info_issuer_chain,
+        tcb_info,
+        tcb_info_signature,
+        qe_identity_issuer_chain,
+        qe_identity,
+        qe_identity_signature,
+        root_ca_crl: None,
+        pck_crl: None,
+        provenance: Vec::new(),
+    })
+}
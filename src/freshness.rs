@@ -0,0 +1,133 @@
+//! Quote age policy.
+//!
+//! Quotes carry no timestamp, so their age has to be proven through `report_data`. The verifier
+//! issues a challenge holding the issue time and a nonce, authenticated with a key only the
+//! verifier knows. The attester puts it in the first half of `report_data`, keeping the second
+//! half for its own data, e.g. the hash of a public key:
+//!
+//! ```text
+//! report_data = timestamp (8, big endian) || nonce (8) || tag (16) || user_data (32)
+//! tag         = HMAC-SHA256(key, timestamp || nonce)[..16]
+//! ```
+//!
+//! The verifier then enforces a maximum age with [`check_quote_age`]. Since the tag cannot be forged
+//! without the key, an attester can not claim a fresher challenge than it was given.
+//!
+//! Nonces come from a [`NonceSource`], so tests can inject a deterministic one and enclaves their
+//! approved DRBG.
+
+use anyhow::{bail, Context, Result};
+
+use crate::quote::Report;
+
+/// The length of a challenge, the first half of `report_data`.
+pub const CHALLENGE_LEN: usize = 32;
+
+const TAG_LEN: usize = 16;
+
+/// Issue a challenge on the verifier side.
+///
+/// # Arguments
+///
+/// * `key` - The verifier's secret HMAC key.
+/// * `now` - The current time in seconds since the Unix epoch
+/// * `nonce` - A random nonce, making challenges issued at the same second distinct.
+pub fn issue_challenge(key: &[u8], now: u64, nonce: [u8; 8]) -> [u8; CHALLENGE_LEN] {
+    let mut challenge = [0u8; CHALLENGE_LEN];
+    challenge[..8].copy_from_slice(&now.to_be_bytes());
+    challenge[8..16].copy_from_slice(&nonce);
+    let tag = tag(key, &challenge[..16]);
+    challenge[16..].copy_from_slice(&tag);
+    challenge
+}
+
+/// Issue a challenge with a nonce drawn from `nonce_source`, see [`issue_challenge`].
+pub fn issue_challenge_with(
+    key: &[u8],
+    now: u64,
+    nonce_source: &dyn NonceSource,
+) -> Result<[u8; CHALLENGE_LEN]> {
+    let mut nonce = [0u8; 8];
+    nonce_source.fill(&mut nonce)?;
+    Ok(issue_challenge(key, now, nonce))
+}
+
+/// A source of random nonces.
+pub trait NonceSource {
+    /// Fill `dest` with random bytes.
+    fn fill(&self, dest: &mut [u8]) -> Result<()>;
+}
+
+/// The operating system's random number generator.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemNonceSource;
+
+#[cfg(feature = "std")]
+impl NonceSource for SystemNonceSource {
+    fn fill(&self, dest: &mut [u8]) -> Result<()> {
+        ring::rand::SecureRandom::fill(&ring::rand::SystemRandom::new(), dest)
+            .map_err(|_| anyhow::anyhow!("Failed to generate nonce"))
+    }
+}
+
+/// Build the `report_data` for a quote on the attester side.
+pub fn report_data(challenge: &[u8; CHALLENGE_LEN], user_data: &[u8; 32]) -> [u8; 64] {
+    let mut report_data = [0u8; 64];
+    report_data[..CHALLENGE_LEN].copy_from_slice(challenge);
+    report_data[CHALLENGE_LEN..].copy_from_slice(user_data);
+    report_data
+}
+
+/// Check that a verified report was produced for a challenge issued at most `max_age` seconds ago.
+///
+/// # Returns
+///
+/// * `Ok(u64)` - The time the challenge was issued at
+/// * `Err(Error)` - The challenge is forged, too old or issued in the future
+pub fn check_quote_age(report: &Report, key: &[u8], now: u64, max_age: u64) -> Result<u64> {
+    let report_data = match report {
+        Report::SgxEnclave(report) => &report.report_data,
+        Report::TD10(report) => &report.report_data,
+        Report::TD15(report) => &report.base.report_data,
+    };
+    let challenge: &[u8; CHALLENGE_LEN] = report_data[..CHALLENGE_LEN]
+        .try_into()
+        .context("Invalid report data length")?;
+    check_challenge(challenge, key, now, max_age)
+}
+
+/// Check a challenge taken from `report_data`, see [`check_quote_age`].
+pub fn check_challenge(
+    challenge: &[u8; CHALLENGE_LEN],
+    key: &[u8],
+    now: u64,
+    max_age: u64,
+) -> Result<u64> {
+    let expected = tag(key, &challenge[..16]);
+    let diff = expected
+        .iter()
+        .zip(&challenge[16..])
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    if diff != 0 {
+        bail!("Invalid challenge tag");
+    }
+    let mut timestamp = [0u8; 8];
+    timestamp.copy_from_slice(&challenge[..8]);
+    let issued_at = u64::from_be_bytes(timestamp);
+    if issued_at > now {
+        bail!("Challenge issued in the future");
+    }
+    if now - issued_at > max_age {
+        bail!("Quote is too old");
+    }
+    Ok(issued_at)
+}
+
+fn tag(key: &[u8], data: &[u8]) -> [u8; TAG_LEN] {
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, key);
+    let mac = ring::hmac::sign(&key, data);
+    let mut tag = [0u8; TAG_LEN];
+    tag.copy_from_slice(&mac.as_ref()[..TAG_LEN]);
+    tag
+}
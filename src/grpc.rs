@@ -0,0 +1,189 @@
+//! Attested gRPC channels over tonic.
+//!
+//! The client attaches its quote to every request with a [`QuoteInterceptor`], in the
+//! `x-dcap-quote-bin` metadata. The server verifies it with an [`AttestationInterceptor`]
+//! against the collateral in a [`CollateralCache`] and hands the verified report to the service
+//! as an [`AttestedPeer`] request extension.
+//!
+//! The quote is bound to the channel the RA-TLS way: the first 32 bytes of its `report_data` are
+//! the SHA-256 of the DER encoded `SubjectPublicKeyInfo` of the client's TLS certificate, see
+//! [`ra_tls::check_key_binding`]. A quote replayed over another channel thus fails, since its
+//! sender does not hold the key. The server must be set up for mutual TLS for this, or opt out
+//! with [`AttestationInterceptor::without_key_binding`] when the transport is bound otherwise.
+//!
+//! Interceptors run synchronously, so the server never fetches collateral itself. Requests from
+//! platforms whose collateral is not cached fail with `UNAVAILABLE`; fill the cache ahead, e.g.
+//! with [`get_collateral_cached`](crate::collateral::get_collateral_cached).
+//!
+//! Quotes and cached collateral over the interceptor's [`SizeLimits`] fail with
+//! `RESOURCE_EXHAUSTED` before they are decoded.
+//!
+//! Rejected requests get a fixed message naming the check that failed, e.g. `Quote failed to
+//! verify`, so clients can't probe the verifier through its errors. The error behind it is
+//! logged on the server.
+
+use std::time::SystemTime;
+
+use tonic::metadata::{BinaryMetadataValue, MetadataValue};
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+use crate::collateral::CollateralCache;
+use crate::limits::{SizeLimits, TooLarge};
+use crate::ra_tls;
+use crate::verify::{verify_with_options, VerifiedReport, VerifyOptions};
+
+/// The binary metadata key the quote is carried in.
+pub const QUOTE_METADATA_KEY: &str = "x-dcap-quote-bin";
+
+/// Attaches a quote to every request of a client.
+#[derive(Debug, Clone)]
+pub struct QuoteInterceptor {
+    quote: BinaryMetadataValue,
+}
+
+impl QuoteInterceptor {
+    /// Attach `quote`, whose report data binds the key of the client's TLS certificate.
+    pub fn new(quote: &[u8]) -> Self {
+        Self {
+            quote: MetadataValue::from_bytes(quote),
+        }
+    }
+}
+
+impl Interceptor for QuoteInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        request
+            .metadata_mut()
+            .insert_bin(QUOTE_METADATA_KEY, self.quote.clone());
+        Ok(request)
+    }
+}
+
+/// The verified report of the peer of a request, inserted by [`AttestationInterceptor`].
+#[derive(Debug, Clone)]
+pub struct AttestedPeer {
+    pub report: VerifiedReport,
+}
+
+/// Verifies the quote of every request to a server, rejecting requests without a valid one.
+#[derive(Clone)]
+pub struct AttestationInterceptor {
+    cache: CollateralCache,
+    options: VerifyOptions,
+    key_binding: bool,
+    now: Option<u64>,
+    limits: SizeLimits,
+}
+
+impl AttestationInterceptor {
+    /// Verify quotes against the collateral in `cache`, with the default options.
+    pub fn new(cache: CollateralCache) -> Self {
+        Self {
+            cache,
+            options: VerifyOptions::default(),
+            key_binding: true,
+            now: None,
+            limits: SizeLimits::default(),
+        }
+    }
+
+    /// Verify quotes with these options.
+    pub fn with_options(mut self, options: VerifyOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Don't require the quote to bind the key of the client's TLS certificate.
+    ///
+    /// Only for transports bound to the peer otherwise, as anyone who saw a quote can replay it.
+    pub fn without_key_binding(mut self) -> Self {
+        self.key_binding = false;
+        self
+    }
+
+    /// Verify at this time in seconds since the Unix epoch instead of the system time.
+    pub fn with_now(mut self, now: u64) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Reject quotes and collateral over these limits instead of the default ones.
+    pub fn with_limits(mut self, limits: SizeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    fn attest(&self, request: &Request<()>) -> Result<AttestedPeer, Status> {
+        let value = request
+            .metadata()
+            .get_bin(QUOTE_METADATA_KEY)
+            .ok_or_else(|| Status::unauthenticated("Missing quote"))?;
+        // Base64 takes 4 bytes per 3, reject what can't decode within the limit before decoding
+        let max_encoded_len = self.limits.max_quote_size.div_ceil(3).saturating_mul(4);
+        let encoded_len = value.as_encoded_bytes().len();
+        if encoded_len > max_encoded_len {
+            return Err(Status::resource_exhausted(format!(
+                "Quote metadata of {encoded_len} bytes exceeds the limit of {max_encoded_len} bytes"
+            )));
+        }
+        let quote = value
+            .to_bytes()
+            .map_err(|_| Status::unauthenticated("Invalid quote metadata"))?;
+        self.limits.check_quote(&quote).map_err(too_large)?;
+        let now = match self.now {
+            Some(now) => now,
+            None => SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map_err(|_| Status::internal("Failed to get current time"))?
+                .as_secs(),
+        };
+        let collateral = self
+            .cache
+            .get(&quote, now)
+            .map_err(|err| unauthenticated("Invalid quote", err))?
+            .ok_or_else(|| Status::unavailable("No collateral cached for the quote's platform"))?;
+        self.limits
+            .check_collateral(&collateral)
+            .map_err(too_large)?;
+        let report = verify_with_options(&quote, &collateral, now, &self.options)
+            .map_err(|err| unauthenticated("Quote failed to verify", err))?;
+        if self.key_binding {
+            let certs = request
+                .peer_certs()
+                .ok_or_else(|| Status::unauthenticated("Missing TLS client certificate"))?;
+            let cert = certs
+                .first()
+                .ok_or_else(|| Status::unauthenticated("Missing TLS client certificate"))?;
+            ra_tls::check_key_binding(cert.as_ref(), &report.report)
+                .map_err(|err| unauthenticated("Quote is not bound to the channel", err))?;
+        }
+        Ok(AttestedPeer { report })
+    }
+}
+
+impl Interceptor for AttestationInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let peer = self.attest(&request)?;
+        request.extensions_mut().insert(peer);
+        Ok(request)
+    }
+}
+
+fn unauthenticated(message: &str, err: anyhow::Error) -> Status {
+    if err.root_cause().downcast_ref::<TooLarge>().is_some() {
+        return too_large(err);
+    }
+    log::warn!("Rejected attestation, {message}: {err:?}");
+    Status::unauthenticated(message)
+}
+
+fn too_large(err: anyhow::Error) -> Status {
+    match err.root_cause().downcast_ref::<TooLarge>() {
+        Some(too_large) => Status::resource_exhausted(too_large.to_string()),
+        None => {
+            log::warn!("Rejected attestation: {err:?}");
+            Status::resource_exhausted("Quote or collateral too large")
+        }
+    }
+}
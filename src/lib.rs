@@ -14,6 +14,16 @@
 //! dcap-qvl = "0.1.0"
 //! ```
 //!
+//! # no_std
+//! With `default-features = false` the crate builds for `no_std` + `alloc` targets, e.g.
+//! wasm32-unknown-unknown contracts, with quote parsing and [`verify::verify`]. Fetching
+//! collateral, reading the system clock and the JSON interop need the `std` feature; the
+//! verification time is always passed in.
+//!
+//! # Minimum Supported Rust Version
+//! The library builds with Rust 1.81 or later, checked in CI. Raising it is done in minor
+//! releases.
+//!
 //! # Example: Get Collateral from PCCS_URL and Verify Quote
 //!
 //! To get collateral from a PCCS_URL and verify a quote, you can use the following example code:
@@ -38,23 +48,263 @@
 #[macro_use]
 extern crate alloc;
 
+use alloc::string::String;
+use alloc::vec::Vec;
 use scale::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "borsh")]
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// The collateral a quote is verified against.
+///
+/// The JSON encoding is stable, so collateral exported once can be used for offline
+/// verification later: an object with the fields below by their snake_case names, where the
+/// issuer chains are PEM strings, the TCB info and QE identity are the JSON strings as signed by
+/// Intel, and the signatures and CRLs are hex strings. `root_ca_crl` and `pck_crl` may be absent
+/// or `null`. See [`QuoteCollateralV3::from_json`] and [`QuoteCollateralV3::to_json`].
+///
+/// The SCALE encoding is frozen to the six fields collateral had before CRLs were added, so
+/// encodings made by older versions still decode: `root_ca_crl`, `pck_crl` and `provenance` are
+/// left out of it. Use [`QuoteCollateralV4`] to SCALE encode collateral with its CRLs.
+/// `provenance` is for audit only and isn't covered by any signature.
 #[derive(Encode, Decode, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "scale-codec", derive(scale_info::TypeInfo))]
 pub struct QuoteCollateralV3 {
     pub tcb_info_issuer_chain: String,
     pub tcb_info: String,
+    #[serde(with = "serde_bytes")]
     pub tcb_info_signature: Vec<u8>,
     pub qe_identity_issuer_chain: String,
     pub qe_identity: String,
+    #[serde(with = "serde_bytes")]
     pub qe_identity_signature: Vec<u8>,
+    /// The DER encoded Intel SGX Root CA CRL
+    #[serde(default, with = "optional_bytes")]
+    #[codec(skip)]
+    pub root_ca_crl: Option<Vec<u8>>,
+    /// The DER encoded CRL of the CA that issued the PCK certificate
+    #[serde(default, with = "optional_bytes")]
+    #[codec(skip)]
+    pub pck_crl: Option<Vec<u8>>,
+    /// Where the elements were fetched from, empty for collateral not fetched by this crate
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[codec(skip)]
+    pub provenance: Vec<CollateralSource>,
 }
 
-#[cfg(feature = "report")]
+/// The SCALE encoding of [`QuoteCollateralV3`] with its CRLs: the six fields of the V3 encoding
+/// followed by `root_ca_crl` and `pck_crl`.
+///
+/// Convert from and into [`QuoteCollateralV3`] to encode and verify it. The provenance is not
+/// encoded either.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "scale-codec", derive(scale_info::TypeInfo))]
+pub struct QuoteCollateralV4 {
+    pub tcb_info_issuer_chain: String,
+    pub tcb_info: String,
+    pub tcb_info_signature: Vec<u8>,
+    pub qe_identity_issuer_chain: String,
+    pub qe_identity: String,
+    pub qe_identity_signature: Vec<u8>,
+    pub root_ca_crl: Option<Vec<u8>>,
+    pub pck_crl: Option<Vec<u8>>,
+}
+
+impl From<QuoteCollateralV3> for QuoteCollateralV4 {
+    fn from(collateral: QuoteCollateralV3) -> Self {
+        Self {
+            tcb_info_issuer_chain: collateral.tcb_info_issuer_chain,
+            tcb_info: collateral.tcb_info,
+            tcb_info_signature: collateral.tcb_info_signature,
+            qe_identity_issuer_chain: collateral.qe_identity_issuer_chain,
+            qe_identity: collateral.qe_identity,
+            qe_identity_signature: collateral.qe_identity_signature,
+            root_ca_crl: collateral.root_ca_crl,
+            pck_crl: collateral.pck_crl,
+        }
+    }
+}
+
+impl From<QuoteCollateralV4> for QuoteCollateralV3 {
+    fn from(collateral: QuoteCollateralV4) -> Self {
+        Self {
+            tcb_info_issuer_chain: collateral.tcb_info_issuer_chain,
+            tcb_info: collateral.tcb_info,
+            tcb_info_signature: collateral.tcb_info_signature,
+            qe_identity_issuer_chain: collateral.qe_identity_issuer_chain,
+            qe_identity: collateral.qe_identity,
+            qe_identity_signature: collateral.qe_identity_signature,
+            root_ca_crl: collateral.root_ca_crl,
+            pck_crl: collateral.pck_crl,
+            provenance: Vec::new(),
+        }
+    }
+}
+
+/// An element of [`QuoteCollateralV3`].
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(scale_info::TypeInfo))]
+pub enum CollateralKind {
+    /// The TCB info and its issuer chain
+    TcbInfo,
+    /// The QE identity and its issuer chain
+    QeIdentity,
+    RootCaCrl,
+    PckCrl,
+}
+
+/// Where and when an element of the collateral was fetched.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(scale_info::TypeInfo))]
+pub struct CollateralSource {
+    pub kind: CollateralKind,
+    pub url: String,
+    /// The fetch time in seconds since the Unix epoch, if the platform has a clock
+    pub fetched_at: Option<u64>,
+    /// The response headers identifying the response, like `Date` and `Request-ID`
+    pub headers: Vec<(String, String)>,
+}
+
+#[cfg(feature = "std")]
+impl QuoteCollateralV3 {
+    /// Parse collateral from its JSON encoding.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        use anyhow::Context;
+        serde_json::from_str(json).context("Failed to parse collateral JSON")
+    }
+
+    /// Encode the collateral as pretty printed JSON.
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        use anyhow::Context;
+        serde_json::to_string_pretty(self).context("Failed to encode collateral JSON")
+    }
+}
+
+/// Serde of optional byte fields, as hex strings in human readable formats.
+mod optional_bytes {
+    use alloc::vec::Vec;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    struct Bytes<'a>(&'a [u8]);
+
+    impl Serialize for Bytes<'_> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serde_bytes::serialize(self.0, serializer)
+        }
+    }
+
+    struct BytesBuf(Vec<u8>);
+
+    impl<'de> Deserialize<'de> for BytesBuf {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            serde_bytes::deserialize(deserializer).map(BytesBuf)
+        }
+    }
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.as_deref().map(Bytes).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        Ok(Option::<BytesBuf>::deserialize(deserializer)?.map(|bytes| bytes.0))
+    }
+}
+
+/// The version of this crate.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Implement accessors for fields of a `#[non_exhaustive]` struct.
+///
+/// Fields listed under `copy` are returned by value, those under `ref` by reference.
+macro_rules! accessors {
+    ($name:ident { $(copy { $($copy:ident: $copy_ty:ty),* $(,)? })? $(ref { $($field:ident: $ty:ty),* $(,)? })? }) => {
+        impl $name {
+            $($(
+                pub fn $copy(&self) -> $copy_ty {
+                    self.$copy
+                }
+            )*)?
+            $($(
+                pub fn $field(&self) -> &$ty {
+                    &self.$field
+                }
+            )*)?
+        }
+    };
+}
+
+#[cfg(feature = "interop")]
+pub mod appraisal;
+#[cfg(all(feature = "report", not(feature = "js")))]
+pub mod archive;
+#[cfg(feature = "std")]
 pub mod collateral;
 
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "interop")]
+pub mod canonical;
+pub mod commitment;
+#[cfg(any(feature = "tdx", not(feature = "sgx")))]
+pub mod eventlog;
+pub mod evm;
+#[cfg(feature = "std")]
+pub mod formats;
+pub mod freshness;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod limits;
+#[cfg(feature = "interop")]
+pub mod maa;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod pck;
+pub mod pcs;
+#[cfg(feature = "platform-families")]
+pub mod platform_family;
+pub mod policy;
+#[cfg(feature = "interop")]
+pub mod qal_policy;
+#[cfg(feature = "interop")]
+pub mod qe_auth;
+pub mod ra_tls;
+pub mod record;
+#[cfg(feature = "std")]
+pub mod registry;
+
+#[cfg(feature = "near")]
+pub mod near;
+
+#[cfg(feature = "shared")]
+pub mod shared;
+pub mod signature;
+#[cfg(feature = "std")]
+pub mod signed_report;
+#[cfg(feature = "interop")]
+pub mod sink;
+#[cfg(feature = "std")]
+pub mod snapshot;
+#[cfg(feature = "std")]
+pub mod store;
+#[cfg(feature = "stream")]
+pub mod stream;
+pub mod trace;
+pub mod types;
+pub mod uniform;
+#[cfg(feature = "interop")]
+pub mod veraison;
+pub mod zk;
+
 mod constants;
+mod qe_identity;
 mod tcb_info;
 mod utils;
 
@@ -0,0 +1,108 @@
+//! Size caps on untrusted quotes and collateral, for verifiers exposed as services.
+//!
+//! A quote is a few kilobytes and each element of the collateral at most a few hundred, but both
+//! come from peers or caches that may be hostile. [`SizeLimits`] rejects anything larger before
+//! it is decoded or cached, so oversized certification data can't exhaust memory. The
+//! [`CollateralCache`](crate::collateral::CollateralCache), `SharedVerifier` and the gRPC
+//! `AttestationInterceptor` enforce them.
+
+use anyhow::Result;
+
+use crate::QuoteCollateralV3;
+
+/// The largest quote and collateral element accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeLimits {
+    /// The largest raw quote in bytes
+    pub max_quote_size: usize,
+    /// The largest element of the collateral in bytes, e.g. the TCB info JSON or the PCK CRL
+    pub max_collateral_field_size: usize,
+}
+
+impl Default for SizeLimits {
+    fn default() -> Self {
+        Self {
+            max_quote_size: 64 * 1024,
+            max_collateral_field_size: 1024 * 1024,
+        }
+    }
+}
+
+impl SizeLimits {
+    /// Fail with [`TooLarge`] if the quote exceeds [`max_quote_size`](Self::max_quote_size).
+    pub fn check_quote(&self, quote: &[u8]) -> Result<()> {
+        check("quote", quote.len(), self.max_quote_size)
+    }
+
+    /// Fail with [`TooLarge`] if an element of the collateral exceeds
+    /// [`max_collateral_field_size`](Self::max_collateral_field_size).
+    pub fn check_collateral(&self, collateral: &QuoteCollateralV3) -> Result<()> {
+        for (field, size) in [
+            (
+                "tcb_info_issuer_chain",
+                collateral.tcb_info_issuer_chain.len(),
+            ),
+            ("tcb_info", collateral.tcb_info.len()),
+            ("tcb_info_signature", collateral.tcb_info_signature.len()),
+            (
+                "qe_identity_issuer_chain",
+                collateral.qe_identity_issuer_chain.len(),
+            ),
+            ("qe_identity", collateral.qe_identity.len()),
+            (
+                "qe_identity_signature",
+                collateral.qe_identity_signature.len(),
+            ),
+            (
+                "root_ca_crl",
+                collateral.root_ca_crl.as_deref().map_or(0, <[u8]>::len),
+            ),
+            (
+                "pck_crl",
+                collateral.pck_crl.as_deref().map_or(0, <[u8]>::len),
+            ),
+        ] {
+            check(field, size, self.max_collateral_field_size)?;
+        }
+        Ok(())
+    }
+
+    /// The largest encoded cache entry worth decoding: every element of the collateral at its
+    /// cap, as JSON arrays of up to four bytes per byte, and some room for the rest.
+    pub(crate) fn max_cache_entry_size(&self) -> usize {
+        self.max_collateral_field_size
+            .saturating_mul(8 * 4)
+            .saturating_add(64 * 1024)
+    }
+}
+
+fn check(field: &'static str, size: usize, limit: usize) -> Result<()> {
+    if size > limit {
+        return Err(TooLarge { field, size, limit }.into());
+    }
+    Ok(())
+}
+
+/// The error of a quote or collateral element over its [`SizeLimits`].
+///
+/// Services can tell it apart from other failures, e.g. to answer HTTP 413, with
+/// `err.root_cause().downcast_ref::<TooLarge>()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooLarge {
+    /// The quote or the collateral element, e.g. `pck_crl`
+    pub field: &'static str,
+    pub size: usize,
+    pub limit: usize,
+}
+
+impl core::fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} of {} bytes exceeds the limit of {} bytes",
+            self.field, self.size, self.limit
+        )
+    }
+}
+
+impl core::error::Error for TooLarge {}
@@ -0,0 +1,91 @@
+//! Microsoft Azure Attestation (MAA) style claims.
+//!
+//! [`claims`] maps a [`VerifiedReport`] to the `x-ms-*` claims an MAA token carries, so that
+//! authorization rules written against MAA tokens keep working on reports verified by this crate.
+//! Binary values are lower case hex, as in MAA tokens.
+
+use alloc::string::String;
+use serde_json::{Map, Value};
+
+use crate::quote::{EnclaveReport, Report, TDReport10};
+use crate::verify::VerifiedReport;
+
+/// Map a verified report to MAA style claims.
+///
+/// SGX reports map to `x-ms-sgx-*` claims and TDX reports to `x-ms-tdx-*` claims. Both carry
+/// `x-ms-attestation-type` and `x-ms-tcb-status`.
+pub fn claims(report: &VerifiedReport) -> Map<String, Value> {
+    let mut claims = Map::new();
+    match &report.report {
+        Report::SgxEnclave(enclave) => {
+            claims.insert("x-ms-attestation-type".into(), "sgx".into());
+            sgx_claims(enclave, &mut claims);
+        }
+        Report::TD10(td) => {
+            claims.insert("x-ms-attestation-type".into(), "tdx".into());
+            tdx_claims(td, &mut claims);
+        }
+        Report::TD15(td) => {
+            claims.insert("x-ms-attestation-type".into(), "tdx".into());
+            tdx_claims(&td.base, &mut claims);
+            claims.insert(
+                "x-ms-tdx-mrservicetd".into(),
+                hex::encode(td.mr_service_td).into(),
+            );
+        }
+    }
+    claims.insert("x-ms-tcb-status".into(), report.status.as_str().into());
+    claims.insert(
+        "x-ms-tcb-advisory-ids".into(),
+        report.advisory_ids.clone().into(),
+    );
+    claims
+}
+
+fn sgx_claims(report: &EnclaveReport, claims: &mut Map<String, Value>) {
+    // Bit 1 of ATTRIBUTES.FLAGS is DEBUG
+    let is_debuggable = report.attributes[0] & 0x02 != 0;
+    claims.insert("x-ms-sgx-is-debuggable".into(), is_debuggable.into());
+    claims.insert(
+        "x-ms-sgx-mrenclave".into(),
+        hex::encode(report.mr_enclave).into(),
+    );
+    claims.insert(
+        "x-ms-sgx-mrsigner".into(),
+        hex::encode(report.mr_signer).into(),
+    );
+    claims.insert("x-ms-sgx-product-id".into(), report.isv_prod_id.into());
+    claims.insert("x-ms-sgx-svn".into(), report.isv_svn.into());
+    claims.insert(
+        "x-ms-sgx-report-data".into(),
+        hex::encode(report.report_data).into(),
+    );
+}
+
+fn tdx_claims(report: &TDReport10, claims: &mut Map<String, Value>) {
+    // Bit 0 of TDATTRIBUTES is DEBUG
+    let is_debuggable = report.td_attributes[0] & 0x01 != 0;
+    claims.insert("x-ms-tdx-is-debuggable".into(), is_debuggable.into());
+    let fields: [(&str, &[u8]); 13] = [
+        ("x-ms-tdx-tcbsvn", &report.tee_tcb_svn),
+        ("x-ms-tdx-mrseam", &report.mr_seam),
+        ("x-ms-tdx-mrsignerseam", &report.mr_signer_seam),
+        ("x-ms-tdx-td-attributes", &report.td_attributes),
+        ("x-ms-tdx-xfam", &report.xfam),
+        ("x-ms-tdx-mrtd", &report.mr_td),
+        ("x-ms-tdx-mrconfigid", &report.mr_config_id),
+        ("x-ms-tdx-mrowner", &report.mr_owner),
+        ("x-ms-tdx-mrownerconfig", &report.mr_owner_config),
+        ("x-ms-tdx-rtmr0", &report.rt_mr0),
+        ("x-ms-tdx-rtmr1", &report.rt_mr1),
+        ("x-ms-tdx-rtmr2", &report.rt_mr2),
+        ("x-ms-tdx-rtmr3", &report.rt_mr3),
+    ];
+    for (name, value) in fields {
+        claims.insert(name.into(), hex::encode(value).into());
+    }
+    claims.insert(
+        "x-ms-tdx-report-data".into(),
+        hex::encode(report.report_data).into(),
+    );
+}
@@ -0,0 +1,712 @@
+//! Synthetic quotes and collateral, for testing without TEE hardware.
+//!
+//! [`MockQuoteBuilder`] stands up a throwaway PKI in place of Intel's: a root CA, a PCK
+//! certificate carrying the SGX extension and a TCB signing certificate, all with fresh P-256
+//! keys. It signs a quote with the chosen measurements, and collateral whose TCB info and QE
+//! identity the quote matches with the chosen TCB status.
+//!
+//! Mock quotes only verify with their root CA trusted through [`VerifyOptions::root_ca`], so they
+//! can't pass for quotes of real hardware:
+//!
+//! ```ignore
+//! let mock = MockQuoteBuilder::new(TeeType::Tdx).mr_td(mr_td).build(now)?;
+//! let report = verify_with_options(&mock.quote, &mock.collateral, now, &mock.verify_options())?;
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{Datelike, SecondsFormat, Timelike};
+use const_oid::ObjectIdentifier;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+
+use crate::constants::*;
+use crate::qe_identity::{QeIdentity, QeTcb, QeTcbLevel};
+use crate::quote::TeeType;
+use crate::tcb_info::{Tcb, TcbComponents, TcbInfo, TcbLevel};
+use crate::types::Fmspc;
+use crate::verify::{TcbStatus, VerifyOptions};
+use crate::QuoteCollateralV3;
+
+const ECDSA_WITH_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+const COMMON_NAME: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.4.3");
+const KEY_USAGE: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.15");
+const BASIC_CONSTRAINTS: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.19");
+const CRL_NUMBER: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.5.29.20");
+
+/// The MRSIGNER of the mock QE.
+pub const MOCK_QE_MR_SIGNER: [u8; 32] = [0x4d; 32];
+
+const CPU_SVN: [u8; 16] = [2, 2, 2, 2, 3, 1, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0];
+const PCE_SVN: u16 = 13;
+const QE_SVN: u16 = 8;
+const TCB_EVALUATION_DATA_NUMBER: u32 = 17;
+/// How long the mock certificates are valid for
+const CERT_VALIDITY: u64 = 10 * 365 * 24 * 60 * 60;
+/// How long until the mock collateral's `nextUpdate`
+const COLLATERAL_VALIDITY: u64 = 30 * 24 * 60 * 60;
+/// The serial number of the mock PCK certificate
+const PCK_CERT_SERIAL: u8 = 2;
+
+/// A synthetic quote with its collateral.
+#[derive(Debug, Clone)]
+pub struct MockQuote {
+    /// The raw quote
+    pub quote: Vec<u8>,
+    /// The collateral to verify the quote with, without CRLs unless the PCK certificate is
+    /// revoked
+    pub collateral: QuoteCollateralV3,
+    /// The DER encoded mock root CA the quote and collateral chain up to
+    pub root_ca: Vec<u8>,
+}
+
+impl MockQuote {
+    /// Options trusting the mock root CA, to verify the quote with.
+    pub fn verify_options(&self) -> VerifyOptions {
+        VerifyOptions {
+            root_ca: Some(self.root_ca.clone()),
+            ..Default::default()
+        }
+    }
+}
+
+/// A synthetic RA-TLS certificate, embedding a quote bound to its key.
+#[derive(Debug, Clone)]
+pub struct MockRaTlsCert {
+    /// The DER encoded self-signed certificate
+    pub cert: Vec<u8>,
+    /// The PKCS #8 DER encoded P-256 key of the certificate, e.g. to serve it over TLS
+    pub key: Vec<u8>,
+    /// The embedded quote with its collateral
+    pub quote: MockQuote,
+}
+
+/// Builds [`MockQuote`]s of an SGX enclave or TD.
+///
+/// Measurements not set are all zeros.
+#[derive(Debug, Clone)]
+pub struct MockQuoteBuilder {
+    tee: TeeType,
+    mr_enclave: [u8; 32],
+    mr_signer: [u8; 32],
+    mr_td: [u8; 48],
+    rtmrs: [[u8; 48]; 4],
+    report_data: [u8; 64],
+    fmspc: Fmspc,
+    tcb_status: TcbStatus,
+    qe_tcb_status: TcbStatus,
+    revoke_pck_certificate: bool,
+}
+
+impl MockQuoteBuilder {
+    /// A builder of quotes of `tee` with an up to date TCB.
+    pub fn new(tee: TeeType) -> Self {
+        Self {
+            tee,
+            mr_enclave: [0; 32],
+            mr_signer: [0; 32],
+            mr_td: [0; 48],
+            rtmrs: [[0; 48]; 4],
+            report_data: [0; 64],
+            fmspc: Fmspc::from([0x00, 0x90, 0x6e, 0xd5, 0x00, 0x00]),
+            tcb_status: TcbStatus::UpToDate,
+            qe_tcb_status: TcbStatus::UpToDate,
+            revoke_pck_certificate: false,
+        }
+    }
+
+    /// Set the MRENCLAVE of an SGX quote.
+    pub fn mr_enclave(mut self, mr_enclave: [u8; 32]) -> Self {
+        self.mr_enclave = mr_enclave;
+        self
+    }
+
+    /// Set the MRSIGNER of an SGX quote.
+    pub fn mr_signer(mut self, mr_signer: [u8; 32]) -> Self {
+        self.mr_signer = mr_signer;
+        self
+    }
+
+    /// Set the MRTD of a TDX quote.
+    pub fn mr_td(mut self, mr_td: [u8; 48]) -> Self {
+        self.mr_td = mr_td;
+        self
+    }
+
+    /// Set RTMR `index` (0 to 3) of a TDX quote.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is larger than 3.
+    pub fn rtmr(mut self, index: usize, rtmr: [u8; 48]) -> Self {
+        self.rtmrs[index] = rtmr;
+        self
+    }
+
+    pub fn report_data(mut self, report_data: [u8; 64]) -> Self {
+        self.report_data = report_data;
+        self
+    }
+
+    /// Set the FMSPC of the PCK certificate and TCB info.
+    pub fn fmspc(mut self, fmspc: Fmspc) -> Self {
+        self.fmspc = fmspc;
+        self
+    }
+
+    /// Set the status of the TCB level the platform matches.
+    pub fn tcb_status(mut self, tcb_status: TcbStatus) -> Self {
+        self.tcb_status = tcb_status;
+        self
+    }
+
+    /// Set the status of the QE identity TCB level the QE matches.
+    pub fn qe_tcb_status(mut self, qe_tcb_status: TcbStatus) -> Self {
+        self.qe_tcb_status = qe_tcb_status;
+        self
+    }
+
+    /// Revoke the PCK certificate, in a root CA CRL added to the collateral.
+    pub fn revoke_pck_certificate(mut self) -> Self {
+        self.revoke_pck_certificate = true;
+        self
+    }
+
+    /// Generate the keys and certificates, and sign the quote and collateral.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The issue time of the certificates and collateral, in seconds since the Unix
+    ///   epoch
+    pub fn build(&self, now: u64) -> Result<MockQuote> {
+        let rng = SystemRandom::new();
+        let root_key = MockKey::generate(&rng)?;
+        let pck_key = MockKey::generate(&rng)?;
+        let tcb_signing_key = MockKey::generate(&rng)?;
+        let attestation_key = MockKey::generate(&rng)?;
+
+        let validity = (
+            now.saturating_sub(60 * 60),
+            now.saturating_add(CERT_VALIDITY),
+        );
+        let root_name = "Mock SGX Root CA";
+        let ca_extensions = [
+            extension(
+                BASIC_CONSTRAINTS,
+                true,
+                &sequence(&[vec![0x01, 0x01, 0xff]]),
+            ),
+            // keyCertSign and cRLSign
+            extension(KEY_USAGE, true, &[0x03, 0x02, 0x01, 0x06]),
+        ];
+        let root_ca = certificate(
+            &rng,
+            1,
+            (root_name, &root_key),
+            (root_name, &root_key),
+            validity,
+            &ca_extensions,
+        )?;
+        let mut ppid = [0u8; 16];
+        rng.fill(&mut ppid)
+            .map_err(|_| anyhow!("Failed to generate PPID"))?;
+        let leaf_extensions = |extra: Option<Vec<u8>>| {
+            let mut extensions = vec![
+                extension(BASIC_CONSTRAINTS, true, &sequence(&[])),
+                // digitalSignature
+                extension(KEY_USAGE, true, &[0x03, 0x02, 0x07, 0x80]),
+            ];
+            extensions.extend(extra);
+            extensions
+        };
+        let pck_cert = certificate(
+            &rng,
+            PCK_CERT_SERIAL,
+            ("Mock SGX PCK Certificate", &pck_key),
+            (root_name, &root_key),
+            validity,
+            &leaf_extensions(Some(extension(
+                oids::SGX_EXTENSION,
+                false,
+                &self.sgx_extension(&ppid),
+            ))),
+        )?;
+        let tcb_signing_cert = certificate(
+            &rng,
+            3,
+            ("Mock SGX TCB Signing", &tcb_signing_key),
+            (root_name, &root_key),
+            validity,
+            &leaf_extensions(None),
+        )?;
+
+        let quote = self.quote(&rng, &pck_key, &attestation_key, &[&pck_cert, &root_ca])?;
+        let tcb_info = serde_json::to_string(&self.tcb_info(now)?)?;
+        let qe_identity = serde_json::to_string(&self.qe_identity(now)?)?;
+        let issuer_chain = pem_chain(&[&tcb_signing_cert, &root_ca]);
+        let root_ca_crl = self
+            .revoke_pck_certificate
+            .then(|| crl(&rng, (root_name, &root_key), now, &[PCK_CERT_SERIAL]))
+            .transpose()?;
+        let collateral = QuoteCollateralV3 {
+            tcb_info_issuer_chain: issuer_chain.clone(),
+            tcb_info_signature: tcb_signing_key.sign(&rng, tcb_info.as_bytes())?.to_vec(),
+            tcb_info,
+            qe_identity_issuer_chain: issuer_chain,
+            qe_identity_signature: tcb_signing_key.sign(&rng, qe_identity.as_bytes())?.to_vec(),
+            qe_identity,
+            root_ca_crl,
+            pck_crl: None,
+            provenance: Vec::new(),
+        };
+        Ok(MockQuote {
+            quote,
+            collateral,
+            root_ca,
+        })
+    }
+
+    /// Build a quote like [`build`](Self::build) and an RA-TLS certificate embedding it.
+    ///
+    /// The first 32 bytes of the report data are replaced by the SHA-256 of the certificate's
+    /// `SubjectPublicKeyInfo`, binding its fresh key.
+    pub fn build_ra_tls_cert(&self, now: u64) -> Result<MockRaTlsCert> {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+            .map_err(|_| anyhow!("Failed to generate key"))?;
+        let key = MockKey::from_pkcs8(&rng, pkcs8.as_ref())?;
+        let mut report_data = self.report_data;
+        report_data[..32].copy_from_slice(
+            ring::digest::digest(&ring::digest::SHA256, &key.subject_public_key_info()).as_ref(),
+        );
+        let quote = self.clone().report_data(report_data).build(now)?;
+
+        let quote_oid = if self.is_sgx() {
+            oids::RA_TLS_SGX_QUOTE
+        } else {
+            oids::RA_TLS_TDX_QUOTE
+        };
+        let name = "Mock RA-TLS Certificate";
+        let cert = certificate(
+            &rng,
+            1,
+            (name, &key),
+            (name, &key),
+            (
+                now.saturating_sub(60 * 60),
+                now.saturating_add(CERT_VALIDITY),
+            ),
+            &[
+                extension(BASIC_CONSTRAINTS, true, &sequence(&[])),
+                // digitalSignature
+                extension(KEY_USAGE, true, &[0x03, 0x02, 0x07, 0x80]),
+                extension(quote_oid, false, &quote.quote),
+            ],
+        )?;
+        Ok(MockRaTlsCert {
+            cert,
+            key: pkcs8.as_ref().to_vec(),
+            quote,
+        })
+    }
+
+    fn is_sgx(&self) -> bool {
+        self.tee == TeeType::Sgx
+    }
+
+    /// The content of the Intel SGX extension of the PCK certificate.
+    fn sgx_extension(&self, ppid: &[u8; 16]) -> Vec<u8> {
+        let entry = |id: ObjectIdentifier, value: Vec<u8>| sequence(&[oid(id), value]);
+        let mut tcb = Vec::new();
+        for (index, svn) in (1u8..).zip(CPU_SVN) {
+            let mut id = oids::TCB.as_bytes().to_vec();
+            id.push(index);
+            tcb.push(sequence(&[tlv(0x06, &id), integer(&[svn])]));
+        }
+        tcb.push(entry(oids::PCESVN, integer(&PCE_SVN.to_be_bytes())));
+        tcb.push(entry(oids::CPUSVN, tlv(0x04, &CPU_SVN)));
+        sequence(&[
+            entry(oids::PPID, tlv(0x04, ppid)),
+            entry(oids::TCB, sequence(&tcb)),
+            entry(oids::PCEID, tlv(0x04, &[0, 0])),
+            entry(oids::FMSPC, tlv(0x04, self.fmspc.as_bytes())),
+            // Standard
+            entry(oids::SGX_TYPE, tlv(0x0a, &[0])),
+        ])
+    }
+
+    /// A version 4 quote signed by the attestation key, whose QE report is signed by the PCK key.
+    fn quote(
+        &self,
+        rng: &SystemRandom,
+        pck_key: &MockKey,
+        attestation_key: &MockKey,
+        pck_chain: &[&[u8]],
+    ) -> Result<Vec<u8>> {
+        let mut quote = Vec::new();
+        quote.extend_from_slice(&4u16.to_le_bytes());
+        quote.extend_from_slice(&ATTESTATION_KEY_TYPE_ECDSA256_WITH_P256_CURVE.to_le_bytes());
+        let tee_type = if self.is_sgx() {
+            TEE_TYPE_SGX
+        } else {
+            TEE_TYPE_TDX
+        };
+        quote.extend_from_slice(&tee_type.to_le_bytes());
+        quote.extend_from_slice(&QE_SVN.to_le_bytes());
+        quote.extend_from_slice(&PCE_SVN.to_le_bytes());
+        quote.extend_from_slice(&INTEL_QE_VENDOR_ID);
+        quote.extend_from_slice(&[0; 20]);
+        if self.is_sgx() {
+            // INIT and MODE64BIT
+            quote.extend(enclave_report(
+                0x05,
+                self.mr_enclave,
+                self.mr_signer,
+                0,
+                0,
+                &self.report_data,
+            ));
+        } else {
+            quote.extend(self.td_report());
+        }
+
+        let attestation_public_key = attestation_key.public_key();
+        let qe_auth_data = [0u8; AUTHENTICATION_DATA_LEN];
+        let mut qe_report_data = [0u8; 64];
+        qe_report_data[..32].copy_from_slice(
+            ring::digest::digest(
+                &ring::digest::SHA256,
+                &[&attestation_public_key[..], &qe_auth_data].concat(),
+            )
+            .as_ref(),
+        );
+        let qe_isv_prod_id = if self.is_sgx() { 1 } else { 2 };
+        // INIT and PROVISIONKEY, as of Intel's QE
+        let qe_report = enclave_report(
+            0x11,
+            [0; 32],
+            MOCK_QE_MR_SIGNER,
+            qe_isv_prod_id,
+            QE_SVN,
+            &qe_report_data,
+        );
+
+        let chain = pem_chain(pck_chain);
+        let mut qe_report_certification_data = qe_report.clone();
+        qe_report_certification_data.extend(pck_key.sign(rng, &qe_report)?);
+        qe_report_certification_data.extend_from_slice(&(qe_auth_data.len() as u16).to_le_bytes());
+        qe_report_certification_data.extend_from_slice(&qe_auth_data);
+        qe_report_certification_data.extend_from_slice(&PCK_CERT_CHAIN.to_le_bytes());
+        qe_report_certification_data.extend_from_slice(&(chain.len() as u32).to_le_bytes());
+        qe_report_certification_data.extend_from_slice(chain.as_bytes());
+
+        let mut auth_data = attestation_key.sign(rng, &quote)?.to_vec();
+        auth_data.extend_from_slice(&attestation_public_key);
+        auth_data.extend_from_slice(&QE_REPORT_CERT.to_le_bytes());
+        auth_data.extend_from_slice(&(qe_report_certification_data.len() as u32).to_le_bytes());
+        auth_data.extend(qe_report_certification_data);
+        quote.extend_from_slice(&(auth_data.len() as u32).to_le_bytes());
+        quote.extend(auth_data);
+        Ok(quote)
+    }
+
+    fn td_report(&self) -> Vec<u8> {
+        let mut report = Vec::with_capacity(TD_REPORT10_BYTE_LEN);
+        // TEE TCB SVN, MRSEAM, MRSIGNERSEAM, SEAM attributes, TD attributes and XFAM. A module of
+        // major version 0 is only matched against a `tdxModule` the TCB info doesn't have.
+        report.resize(16 + 48 + 48 + 8 + 8 + 8, 0);
+        report.extend_from_slice(&self.mr_td);
+        // MRCONFIGID, MROWNER and MROWNERCONFIG
+        report.resize(report.len() + 3 * 48, 0);
+        for rtmr in &self.rtmrs {
+            report.extend_from_slice(rtmr);
+        }
+        report.extend_from_slice(&self.report_data);
+        report
+    }
+
+    fn tcb_info(&self, now: u64) -> Result<TcbInfo> {
+        let components = |svns: &[u8]| {
+            svns.iter()
+                .map(|&svn| TcbComponents { svn })
+                .collect::<Vec<_>>()
+        };
+        Ok(TcbInfo {
+            id: if self.is_sgx() { "SGX" } else { "TDX" }.into(),
+            version: 3,
+            issue_date: rfc3339(now)?,
+            next_update: rfc3339(now.saturating_add(COLLATERAL_VALIDITY))?,
+            fmspc: hex::encode_upper(self.fmspc.as_bytes()),
+            pce_id: "0000".into(),
+            tcb_type: 0,
+            tcb_evaluation_data_number: TCB_EVALUATION_DATA_NUMBER,
+            tdx_module: None,
+            tdx_module_identities: Vec::new(),
+            tcb_levels: vec![TcbLevel {
+                tcb: Tcb {
+                    sgx_components: components(&CPU_SVN),
+                    tdx_components: if self.is_sgx() {
+                        Vec::new()
+                    } else {
+                        components(&[0; 16])
+                    },
+                    pce_svn: PCE_SVN,
+                },
+                tcb_date: rfc3339(now)?,
+                tcb_status: self.tcb_status.as_str().into(),
+                advisory_ids: Vec::new(),
+            }],
+        })
+    }
+
+    fn qe_identity(&self, now: u64) -> Result<QeIdentity> {
+        Ok(QeIdentity {
+            id: if self.is_sgx() { "QE" } else { "TD_QE" }.into(),
+            version: 2,
+            issue_date: rfc3339(now)?,
+            next_update: rfc3339(now.saturating_add(COLLATERAL_VALIDITY))?,
+            tcb_evaluation_data_number: TCB_EVALUATION_DATA_NUMBER,
+            miscselect: "00000000".into(),
+            miscselect_mask: "FFFFFFFF".into(),
+            attributes: "11000000000000000000000000000000".into(),
+            attributes_mask: "FBFFFFFFFFFFFFFF0000000000000000".into(),
+            mrsigner: hex::encode_upper(MOCK_QE_MR_SIGNER),
+            isvprodid: if self.is_sgx() { 1 } else { 2 },
+            tcb_levels: vec![QeTcbLevel {
+                tcb: QeTcb { isvsvn: QE_SVN },
+                tcb_date: rfc3339(now)?,
+                tcb_status: self.qe_tcb_status.as_str().into(),
+                advisory_ids: Vec::new(),
+            }],
+        })
+    }
+}
+
+/// An enclave report with the given first byte of the attributes.
+fn enclave_report(
+    attributes: u8,
+    mr_enclave: [u8; 32],
+    mr_signer: [u8; 32],
+    isv_prod_id: u16,
+    isv_svn: u16,
+    report_data: &[u8; 64],
+) -> Vec<u8> {
+    let mut report = Vec::with_capacity(ENCLAVE_REPORT_BYTE_LEN);
+    report.extend_from_slice(&CPU_SVN);
+    // MISCSELECT and reserved
+    report.resize(16 + 4 + 28, 0);
+    report.push(attributes);
+    report.resize(report.len() + 15, 0);
+    report.extend_from_slice(&mr_enclave);
+    report.resize(report.len() + 32, 0);
+    report.extend_from_slice(&mr_signer);
+    report.resize(report.len() + 96, 0);
+    report.extend_from_slice(&isv_prod_id.to_le_bytes());
+    report.extend_from_slice(&isv_svn.to_le_bytes());
+    report.resize(report.len() + 60, 0);
+    report.extend_from_slice(report_data);
+    report
+}
+
+/// A P-256 key pair.
+struct MockKey(EcdsaKeyPair);
+
+impl MockKey {
+    fn generate(rng: &SystemRandom) -> Result<Self> {
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, rng)
+            .map_err(|_| anyhow!("Failed to generate key"))?;
+        Self::from_pkcs8(rng, pkcs8.as_ref())
+    }
+
+    fn from_pkcs8(rng: &SystemRandom, pkcs8: &[u8]) -> Result<Self> {
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8, rng)
+            .map_err(|_| anyhow!("Failed to load key"))?;
+        Ok(Self(key))
+    }
+
+    /// The uncompressed public key (X || Y).
+    fn public_key(&self) -> [u8; ECDSA_PUBKEY_BYTE_LEN] {
+        let mut key = [0u8; ECDSA_PUBKEY_BYTE_LEN];
+        // Skip the 0x04 of the SEC1 encoding
+        key.copy_from_slice(&self.0.public_key().as_ref()[1..]);
+        key
+    }
+
+    /// The raw (r || s) ECDSA signature of `message` with SHA-256.
+    fn sign(&self, rng: &SystemRandom, message: &[u8]) -> Result<[u8; ECDSA_SIGNATURE_BYTE_LEN]> {
+        let signature = self
+            .0
+            .sign(rng, message)
+            .map_err(|_| anyhow!("Failed to sign"))?;
+        signature
+            .as_ref()
+            .try_into()
+            .ok()
+            .context("Signature length mismatch")
+    }
+
+    fn subject_public_key_info(&self) -> Vec<u8> {
+        sequence(&[
+            sequence(&[oid(oids::EC_PUBLIC_KEY), oid(oids::SECP256R1)]),
+            tlv(0x03, &[&[0][..], self.0.public_key().as_ref()].concat()),
+        ])
+    }
+}
+
+/// A DER encoded X.509 v3 certificate, signed with ECDSA and SHA-256.
+fn certificate(
+    rng: &SystemRandom,
+    serial: u8,
+    (subject, subject_key): (&str, &MockKey),
+    (issuer, issuer_key): (&str, &MockKey),
+    (not_before, not_after): (u64, u64),
+    extensions: &[Vec<u8>],
+) -> Result<Vec<u8>> {
+    let tbs = sequence(&[
+        tlv(0xa0, &integer(&[2])),
+        integer(&[serial]),
+        ecdsa_with_sha256(),
+        name(issuer),
+        sequence(&[time(not_before)?, time(not_after)?]),
+        name(subject),
+        subject_key.subject_public_key_info(),
+        tlv(0xa3, &sequence(extensions)),
+    ]);
+    signed(rng, tbs, issuer_key)
+}
+
+/// A DER encoded v2 CRL revoking the certificates of the given serial numbers, signed with ECDSA
+/// and SHA-256.
+fn crl(
+    rng: &SystemRandom,
+    (issuer, issuer_key): (&str, &MockKey),
+    now: u64,
+    revoked: &[u8],
+) -> Result<Vec<u8>> {
+    let revoked = revoked
+        .iter()
+        .map(|serial| Ok(sequence(&[integer(&[*serial]), time(now)?])))
+        .collect::<Result<Vec<_>>>()?;
+    let tbs = sequence(&[
+        integer(&[1]),
+        ecdsa_with_sha256(),
+        name(issuer),
+        time(now)?,
+        time(now.saturating_add(COLLATERAL_VALIDITY))?,
+        sequence(&revoked),
+        tlv(
+            0xa0,
+            &sequence(&[extension(CRL_NUMBER, false, &integer(&[1]))]),
+        ),
+    ]);
+    signed(rng, tbs, issuer_key)
+}
+
+/// A signed DER structure: the `tbs` content, the ECDSA with SHA-256 algorithm and the signature.
+fn signed(rng: &SystemRandom, tbs: Vec<u8>, key: &MockKey) -> Result<Vec<u8>> {
+    let signature = key.sign(rng, &tbs)?;
+    let (r, s) = signature.split_at(32);
+    let signature = sequence(&[integer(r), integer(s)]);
+    Ok(sequence(&[
+        tbs,
+        ecdsa_with_sha256(),
+        tlv(0x03, &[&[0][..], &signature].concat()),
+    ]))
+}
+
+fn ecdsa_with_sha256() -> Vec<u8> {
+    sequence(&[oid(ECDSA_WITH_SHA256)])
+}
+
+fn extension(id: ObjectIdentifier, critical: bool, value: &[u8]) -> Vec<u8> {
+    let mut fields = vec![oid(id)];
+    if critical {
+        fields.push(vec![0x01, 0x01, 0xff]);
+    }
+    fields.push(tlv(0x04, value));
+    sequence(&fields)
+}
+
+fn name(common_name: &str) -> Vec<u8> {
+    let attribute = sequence(&[oid(COMMON_NAME), tlv(0x0c, common_name.as_bytes())]);
+    sequence(&[tlv(0x31, &attribute)])
+}
+
+/// A UTCTime, or a GeneralizedTime from 2050 on.
+fn time(secs: u64) -> Result<Vec<u8>> {
+    let time = date_time(secs)?;
+    let rest = format!(
+        "{:02}{:02}{:02}{:02}{:02}Z",
+        time.month(),
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second()
+    );
+    Ok(match time.year() {
+        year @ 1950..=2049 => tlv(0x17, format!("{:02}{rest}", year % 100).as_bytes()),
+        year => tlv(0x18, format!("{year:04}{rest}").as_bytes()),
+    })
+}
+
+fn rfc3339(secs: u64) -> Result<String> {
+    Ok(date_time(secs)?.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+fn date_time(secs: u64) -> Result<chrono::DateTime<chrono::Utc>> {
+    i64::try_from(secs)
+        .ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+        .context("Time out of range")
+}
+
+fn pem_chain(certs: &[&[u8]]) -> String {
+    let certs = certs
+        .iter()
+        .map(|der| pem::Pem::new("CERTIFICATE", der.to_vec()))
+        .collect::<Vec<_>>();
+    let config = pem::EncodeConfig::new().set_line_ending(pem::LineEnding::LF);
+    pem::encode_many_config(&certs, config)
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let start = bytes
+            .iter()
+            .position(|&b| b != 0)
+            .unwrap_or(bytes.len() - 1);
+        out.push(0x80 | (bytes.len() - start) as u8);
+        out.extend_from_slice(&bytes[start..]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+fn sequence(fields: &[Vec<u8>]) -> Vec<u8> {
+    tlv(0x30, &fields.concat())
+}
+
+fn oid(id: ObjectIdentifier) -> Vec<u8> {
+    tlv(0x06, id.as_bytes())
+}
+
+/// A DER INTEGER of the unsigned big endian `value`.
+fn integer(value: &[u8]) -> Vec<u8> {
+    let start = value
+        .iter()
+        .position(|&b| b != 0)
+        .unwrap_or(value.len().saturating_sub(1));
+    let value = &value[start..];
+    if value.first().is_some_and(|&b| b & 0x80 != 0) {
+        tlv(0x02, &[&[0][..], value].concat())
+    } else {
+        tlv(0x02, value)
+    }
+}
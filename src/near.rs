@@ -0,0 +1,143 @@
+//! Helpers for gating NEAR contract actions on TEE attestation.
+//!
+//! Full verification is too expensive for NEAR's gas limits, so it is split in two:
+//!
+//! 1. Off-chain, [`prevalidate_collateral`] checks the TCB info and QE identity certificate chains
+//!    and signatures once and produces a Borsh encodable [`PrevalidatedCollateral`].
+//! 2. On-chain, [`verify`] only checks the quote itself (PCK chain, QE report and attestation key
+//!    signatures) and matches its TCB against the prevalidated collateral.
+//!
+//! The CRLs in the collateral are kept, so the PCK chain is still checked against them on-chain.
+//!
+//! The contract must only accept [`PrevalidatedCollateral`] from a trusted account, since its
+//! TCB info and QE identity are not re-checked on-chain.
+
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail, Result};
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::commitment::{self, CollateralCommitment};
+use crate::qe_identity::QeIdentity;
+use crate::quote::Report;
+use crate::signature::{RingVerifier, SignatureVerifier};
+use crate::tcb_info::TcbInfo;
+use crate::verify::{self, Revocation, VerifiedReport, VerifyOptions};
+use crate::QuoteCollateralV3;
+
+/// Collateral whose TCB info and QE identity chains and signatures have been checked off-chain.
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize)]
+pub struct PrevalidatedCollateral {
+    tcb_info: TcbInfo,
+    qe_identity: QeIdentity,
+    root_ca_crl: Option<Vec<u8>>,
+    pck_crl: Option<Vec<u8>>,
+    /// Commitment over the collateral that was validated
+    pub collateral_commitment: CollateralCommitment,
+    /// The time the collateral was validated at, in seconds since the Unix epoch
+    pub validated_at: u64,
+}
+
+/// Validate the collateral off-chain.
+///
+/// # Arguments
+///
+/// * `quote_collateral` - The quote collateral to validate.
+/// * `now` - The current time in seconds since the Unix epoch
+pub fn prevalidate_collateral(
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+) -> Result<PrevalidatedCollateral> {
+    let revocation = Revocation::new(quote_collateral, &VerifyOptions::default());
+    // Without accepted expired certificates the chain checks have no warnings
+    let (tcb_info, _) = verify::verify_tcb_info(quote_collateral, now, &revocation, &RingVerifier)?;
+    let (qe_identity, _) =
+        verify::verify_qe_identity(quote_collateral, now, &revocation, &RingVerifier)?;
+    Ok(PrevalidatedCollateral {
+        tcb_info,
+        qe_identity,
+        root_ca_crl: quote_collateral.root_ca_crl.clone(),
+        pck_crl: quote_collateral.pck_crl.clone(),
+        collateral_commitment: commitment::commit(quote_collateral),
+        validated_at: now,
+    })
+}
+
+/// Verify a quote on-chain against prevalidated collateral.
+///
+/// # Arguments
+///
+/// * `raw_quote` - The raw quote to verify. Supported SGX and TDX quotes.
+/// * `collateral` - The collateral validated off-chain by [`prevalidate_collateral`].
+/// * `now` - The current block time in seconds since the Unix epoch
+pub fn verify(
+    raw_quote: &[u8],
+    collateral: &PrevalidatedCollateral,
+    now: u64,
+) -> Result<VerifiedReport> {
+    verify_with_signature_verifier(raw_quote, collateral, now, &RingVerifier)
+}
+
+/// Verify a quote on-chain, checking its ECDSA signatures with the given verifier, e.g. one
+/// backed by a host function.
+pub fn verify_with_signature_verifier(
+    raw_quote: &[u8],
+    collateral: &PrevalidatedCollateral,
+    now: u64,
+    signature_verifier: &dyn SignatureVerifier,
+) -> Result<VerifiedReport> {
+    let verdict = verify::verify_quote(
+        raw_quote,
+        &collateral.tcb_info,
+        &collateral.qe_identity,
+        now,
+        &Revocation {
+            root_ca_crl: collateral.root_ca_crl.as_deref(),
+            pck_crl: collateral.pck_crl.as_deref(),
+            require: false,
+            accept_expired: None,
+            root_ca: None,
+        },
+        signature_verifier,
+    )?;
+    Ok(VerifiedReport {
+        status: verdict.status,
+        advisory_ids: verdict.advisory_ids,
+        report: verdict.report,
+        collateral_commitment: collateral.collateral_commitment.clone(),
+        warnings: verdict.warnings,
+        platform_id: Some(verdict.platform_id),
+        supplemental: Some(verdict.supplemental),
+        provenance: Vec::new(),
+    })
+}
+
+/// Verify a quote on-chain and require its measurement to be one of `allowed_measurements`.
+///
+/// The measurement is MRENCLAVE for SGX quotes and MRTD for TDX quotes.
+pub fn verify_with_measurements(
+    raw_quote: &[u8],
+    collateral: &PrevalidatedCollateral,
+    now: u64,
+    allowed_measurements: &[&[u8]],
+) -> Result<VerifiedReport> {
+    let report = verify(raw_quote, collateral, now)?;
+    let actual = measurement(&report.report);
+    if !allowed_measurements.iter().any(|m| *m == actual) {
+        bail!("Measurement is not allowed");
+    }
+    Ok(report)
+}
+
+/// Get the measurement of a report: MRENCLAVE for SGX and MRTD for TDX.
+pub fn measurement(report: &Report) -> &[u8] {
+    match report {
+        Report::SgxEnclave(report) => &report.mr_enclave,
+        Report::TD10(report) => &report.mr_td,
+        Report::TD15(report) => &report.base.mr_td,
+    }
+}
+
+/// Borsh encode a verified report, e.g. to store it in contract state.
+pub fn encode_report(report: &VerifiedReport) -> Result<Vec<u8>> {
+    borsh::to_vec(report).map_err(|_| anyhow!("Failed to encode report"))
+}
@@ -0,0 +1,167 @@
+//! The SGX extension of PCK certificates.
+//!
+//! The PCK leaf certificate of a quote identifies the platform: its FMSPC, which selects the TCB
+//! info to evaluate it against, and the TCB components and PCE SVN the certificate was issued
+//! for. [`parse_pck_extension`] reads them from the certificate, so they can be used without
+//! parsing the ASN.1 again, for example to fetch the TCB info of the platform.
+//!
+//! Certificates of multi-package platforms, issued by the Platform CA, also carry the platform
+//! instance ID and the [configuration](PckConfiguration) the platform was registered with.
+
+use alloc::vec::Vec;
+use anyhow::{bail, Context, Result};
+use const_oid::ObjectIdentifier;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "borsh")]
+use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(feature = "scale-codec")]
+use {
+    scale::{Decode, Encode},
+    scale_info::TypeInfo,
+};
+
+use crate::constants::{oids, Svn};
+use crate::types::{CpuSvn, Fmspc};
+use crate::utils;
+
+/// The kind of SGX the platform supports, from the SGX Type of the PCK certificate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(Encode, Decode, TypeInfo))]
+#[non_exhaustive]
+pub enum SgxType {
+    Standard,
+    Scalable,
+    ScalableWithIntegrity,
+}
+
+/// The configuration of a multi-package platform, from the Configuration of its PCK certificate.
+///
+/// Each setting is `None` if the certificate leaves it out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(Encode, Decode, TypeInfo))]
+pub struct PckConfiguration {
+    /// Whether packages can be added to or replaced in the platform after its registration
+    pub dynamic_platform: Option<bool>,
+    /// Whether the registration service caches the platform root keys
+    pub cached_keys: Option<bool>,
+    /// Whether simultaneous multithreading is enabled
+    pub smt_enabled: Option<bool>,
+}
+
+/// The fields of the SGX extension of a PCK certificate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct PckCertData {
+    #[serde(with = "serde_bytes")]
+    pub ppid: Vec<u8>,
+    /// The SVNs of the 16 SGX TCB components
+    #[serde(with = "serde_bytes")]
+    pub tcb_components: [u8; 16],
+    pub pce_svn: Svn,
+    pub cpu_svn: CpuSvn,
+    #[serde(with = "serde_bytes")]
+    pub pce_id: [u8; 2],
+    pub fmspc: Fmspc,
+    pub sgx_type: SgxType,
+    /// Only in certificates of multi-package platforms
+    pub platform_instance_id: Option<[u8; 16]>,
+    /// Only in certificates of multi-package platforms
+    pub configuration: Option<PckConfiguration>,
+}
+
+impl PckCertData {
+    /// Whether the platform has several packages (sockets), going by the platform instance ID
+    /// only the Platform CA issues.
+    pub fn is_multi_package(&self) -> bool {
+        self.platform_instance_id.is_some()
+    }
+}
+
+/// Parse the SGX extension of a PCK certificate.
+///
+/// # Arguments
+///
+/// * `der` - The DER encoded PCK certificate
+///
+/// # Returns
+///
+/// * `Ok(PckCertData)` - The fields of the extension
+/// * `Err(Error)` - The certificate has no SGX extension, or it is malformed
+pub fn parse_pck_extension(der: &[u8]) -> Result<PckCertData> {
+    let extension = utils::get_intel_extension(der).context("Failed to get Intel extension")?;
+    let mut tcb_components = [0u8; 16];
+    for (index, svn) in (1u8..).zip(tcb_components.iter_mut()) {
+        // The component OIDs are those of the TCB with the component number appended
+        let mut oid = oids::TCB.as_bytes().to_vec();
+        oid.push(index);
+        let data = utils::find_extension(&[oids::TCB.as_bytes(), &oid], &extension)
+            .with_context(|| format!("Failed to find TCB component {index}"))?;
+        *svn = small_integer(&data).with_context(|| format!("Invalid TCB component {index}"))?;
+    }
+    let pce_id: [u8; 2] = utils::find_extension(&[oids::PCEID.as_bytes()], &extension)
+        .context("Failed to find PCE ID")?
+        .try_into()
+        .ok()
+        .context("PCE ID length mismatch")?;
+    Ok(PckCertData {
+        ppid: utils::get_ppid(&extension)?,
+        tcb_components,
+        pce_svn: utils::get_pce_svn(&extension)?,
+        cpu_svn: utils::get_cpu_svn(&extension)?,
+        pce_id,
+        fmspc: utils::get_fmspc(&extension)?,
+        sgx_type: sgx_type(&extension)?,
+        platform_instance_id: utils::get_platform_instance_id(&extension)?,
+        configuration: configuration(&extension)?,
+    })
+}
+
+/// Read the SGX Type of the Intel extension of a PCK certificate.
+pub(crate) fn sgx_type(extension: &[u8]) -> Result<SgxType> {
+    let sgx_type = utils::find_extension(&[oids::SGX_TYPE.as_bytes()], extension)
+        .context("Failed to find SGX Type")?;
+    let sgx_type = match small_integer(&sgx_type).context("Invalid SGX Type")? {
+        0 => SgxType::Standard,
+        1 => SgxType::Scalable,
+        2 => SgxType::ScalableWithIntegrity,
+        other => bail!("Unknown SGX Type {other}"),
+    };
+    Ok(sgx_type)
+}
+
+/// Read the Configuration of the Intel extension of a PCK certificate, `None` if it has none.
+pub(crate) fn configuration(extension: &[u8]) -> Result<Option<PckConfiguration>> {
+    if utils::find_extension(&[oids::CONFIGURATION.as_bytes()], extension).is_err() {
+        return Ok(None);
+    }
+    let setting = |oid: ObjectIdentifier, name: &str| -> Result<Option<bool>> {
+        let Ok(data) =
+            utils::find_extension(&[oids::CONFIGURATION.as_bytes(), oid.as_bytes()], extension)
+        else {
+            return Ok(None);
+        };
+        match data[..] {
+            [0] => Ok(Some(false)),
+            [0xff] => Ok(Some(true)),
+            _ => bail!("Invalid {name}"),
+        }
+    };
+    Ok(Some(PckConfiguration {
+        dynamic_platform: setting(oids::DYNAMIC_PLATFORM, "dynamicPlatform")?,
+        cached_keys: setting(oids::CACHED_KEYS, "cachedKeys")?,
+        smt_enabled: setting(oids::SMT_ENABLED, "SMTEnabled")?,
+    }))
+}
+
+/// Decode the content of a DER INTEGER or ENUMERATED that fits a byte.
+fn small_integer(data: &[u8]) -> Result<u8> {
+    match data {
+        [value] if *value < 0x80 => Ok(*value),
+        // Values from 128 are prefixed with a zero byte to keep them positive
+        [0, value] if *value >= 0x80 => Ok(*value),
+        _ => bail!("Integer out of range"),
+    }
+}
@@ -0,0 +1,177 @@
+//! The request URLs of the Intel PCS and PCCS collateral endpoints.
+//!
+//! External fetchers and mirrors can use these to request exactly what this crate fetches. Each
+//! function takes the base URL of a PCS or PCCS server, e.g. [`SGX_BASE_URL`], with or without a
+//! trailing `/`.
+//!
+//! The `pckcrl` and `pckcert` endpoints only exist under the SGX base URL, also for TDX
+//! platforms, see [`sgx_base_url`].
+
+use alloc::string::String;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{CpuSvn, Fmspc};
+
+/// The base URL of Intel PCS for SGX collateral.
+pub const SGX_BASE_URL: &str = "https://api.trustedservices.intel.com/sgx/certification/v4";
+/// The base URL of Intel PCS for TDX collateral.
+pub const TDX_BASE_URL: &str = "https://api.trustedservices.intel.com/tdx/certification/v4";
+
+/// Where Intel publishes the DER encoded Intel SGX Root CA CRL.
+pub const ROOT_CA_CRL_URL: &str =
+    "https://certificates.trustedservices.intel.com/IntelSGXRootCA.der";
+
+/// The base URL of Intel PCS for SGX or TDX collateral.
+pub fn base_url(is_sgx: bool) -> &'static str {
+    if is_sgx {
+        SGX_BASE_URL
+    } else {
+        TDX_BASE_URL
+    }
+}
+
+/// The SGX counterpart of a TDX base URL, under which the PCK endpoints live.
+pub fn sgx_base_url(base_url: &str) -> String {
+    trim(base_url).replace("/tdx/certification/", "/sgx/certification/")
+}
+
+/// The CA issuing a PCK certificate, and so its CRL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PckCa {
+    Processor,
+    Platform,
+}
+
+impl PckCa {
+    /// The CA name, as in PCS URLs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PckCa::Processor => "processor",
+            PckCa::Platform => "platform",
+        }
+    }
+}
+
+/// Which TCB evaluation PCS serves TCB info and QE identities of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TcbUpdate {
+    /// The evaluation in effect
+    #[default]
+    Standard,
+    /// The evaluation of an upcoming TCB recovery, served ahead of its effective date
+    Early,
+}
+
+impl TcbUpdate {
+    /// The update type, as in PCS URLs.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TcbUpdate::Standard => "standard",
+            TcbUpdate::Early => "early",
+        }
+    }
+}
+
+/// Which TCB info and QE identity to request.
+///
+/// PCS serves either an update type or a specific evaluation, so
+/// [`tcb_evaluation_data_number`](Self::tcb_evaluation_data_number) takes precedence over
+/// [`update`](Self::update) when both are set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CollateralOptions {
+    /// The update type to request
+    pub update: TcbUpdate,
+    /// Request the collateral of this `tcbEvaluationDataNumber`, e.g. to reproduce a past
+    /// verification
+    pub tcb_evaluation_data_number: Option<u32>,
+}
+
+impl CollateralOptions {
+    /// The query parameter selecting the evaluation, `None` for the PCS default.
+    fn query(&self) -> Option<String> {
+        match (self.tcb_evaluation_data_number, self.update) {
+            (Some(number), _) => Some(format!("tcbEvaluationDataNumber={number}")),
+            (None, TcbUpdate::Early) => Some(format!("update={}", TcbUpdate::Early.as_str())),
+            (None, TcbUpdate::Standard) => None,
+        }
+    }
+}
+
+/// The platform identity a PCK certificate is requested for.
+#[derive(Debug, Clone, Copy)]
+pub struct PckCertQuery<'a> {
+    /// The PPID encrypted with the PPID encryption key, required by PCS
+    pub encrypted_ppid: Option<&'a [u8]>,
+    /// The QE ID, required by PCCS
+    pub qe_id: Option<&'a [u8]>,
+    pub cpu_svn: CpuSvn,
+    pub pce_svn: u16,
+    pub pce_id: u16,
+}
+
+/// The URL of the TCB info of a platform.
+pub fn tcb_info(base_url: &str, fmspc: &Fmspc) -> String {
+    tcb_info_with_options(base_url, fmspc, &CollateralOptions::default())
+}
+
+/// The URL of the TCB info of a platform, at the evaluation selected by `options`.
+pub fn tcb_info_with_options(base_url: &str, fmspc: &Fmspc, options: &CollateralOptions) -> String {
+    let mut url = format!("{}/tcb?fmspc={fmspc}", trim(base_url));
+    if let Some(query) = options.query() {
+        url.push_str(&format!("&{query}"));
+    }
+    url
+}
+
+/// The URL of the QE identity.
+pub fn qe_identity(base_url: &str) -> String {
+    qe_identity_with_options(base_url, &CollateralOptions::default())
+}
+
+/// The URL of the QE identity, at the evaluation selected by `options`.
+pub fn qe_identity_with_options(base_url: &str, options: &CollateralOptions) -> String {
+    let mut url = format!("{}/qe/identity", trim(base_url));
+    if let Some(query) = options.query() {
+        url.push_str(&format!("?{query}"));
+    }
+    url
+}
+
+/// The URL of the Intel SGX Root CA CRL: Intel's own for PCS, the `rootcacrl` endpoint of PCCS.
+pub fn root_ca_crl(base_url: &str) -> String {
+    if base_url.starts_with(SGX_BASE_URL) || base_url.starts_with(TDX_BASE_URL) {
+        ROOT_CA_CRL_URL.into()
+    } else {
+        format!("{}/rootcacrl", sgx_base_url(base_url))
+    }
+}
+
+/// The URL of the DER encoded CRL of a PCK CA.
+pub fn pck_crl(base_url: &str, ca: PckCa) -> String {
+    format!("{}/pckcrl?ca={}&encoding=der", trim(base_url), ca.as_str())
+}
+
+/// The URL of the PCK certificate of a platform at a TCB level.
+///
+/// The SVNs and PCE ID are encoded little endian, as PCS expects.
+pub fn pck_cert(base_url: &str, query: &PckCertQuery) -> String {
+    let mut url = format!(
+        "{}/pckcert?cpusvn={}&pcesvn={}&pceid={}",
+        trim(base_url),
+        query.cpu_svn,
+        hex::encode(query.pce_svn.to_le_bytes()),
+        hex::encode(query.pce_id.to_le_bytes()),
+    );
+    if let Some(encrypted_ppid) = query.encrypted_ppid {
+        url.push_str(&format!("&encrypted_ppid={}", hex::encode(encrypted_ppid)));
+    }
+    if let Some(qe_id) = query.qe_id {
+        url.push_str(&format!("&qeid={}", hex::encode(qe_id)));
+    }
+    url
+}
+
+fn trim(base_url: &str) -> &str {
+    base_url.trim_end_matches('/')
+}
@@ -0,0 +1,94 @@
+//! Human readable platform families of FMSPCs, for fleet dashboards.
+//!
+//! The second and third bytes of an FMSPC are the CPUID family and model signature of the
+//! platform, without the stepping, so they identify its processor generation. The built-in
+//! table covers the SGX and TDX capable generations known when this crate was released; newer
+//! ones can be added at runtime with [`PlatformFamilies::insert`] or
+//! [`PlatformFamilies::extend_from_json`].
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use anyhow::{Context, Result};
+
+use crate::types::Fmspc;
+
+/// CPUID signatures without stepping and the platform families they belong to.
+const BUILTIN_FAMILIES: [(u16, &str); 13] = [
+    (0x506E, "Skylake"),
+    (0x706A, "Gemini Lake"),
+    (0x706E, "Ice Lake"),
+    (0x806E, "Kaby Lake / Whiskey Lake / Amber Lake"),
+    (0x906E, "Kaby Lake / Coffee Lake"),
+    (0xA065, "Comet Lake"),
+    (0xA066, "Comet Lake"),
+    (0xA067, "Rocket Lake"),
+    (0x606A, "Ice Lake Xeon SP"),
+    (0x606C, "Ice Lake Xeon D"),
+    (0x806F, "Sapphire Rapids Xeon SP"),
+    (0xC06F, "Emerald Rapids Xeon SP"),
+    (0xA06D, "Granite Rapids Xeon"),
+];
+
+/// A mapping from FMSPCs to platform families.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlatformFamilies {
+    families: BTreeMap<u16, String>,
+}
+
+impl Default for PlatformFamilies {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl PlatformFamilies {
+    /// The built-in mapping.
+    pub fn builtin() -> Self {
+        Self {
+            families: BUILTIN_FAMILIES
+                .iter()
+                .map(|(signature, family)| (*signature, family.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Map the CPUID signature without stepping, e.g. `0x606A`, to a family.
+    pub fn insert(&mut self, signature: u16, family: impl Into<String>) {
+        self.families.insert(signature, family.into());
+    }
+
+    /// Add the mappings of a JSON object from hex CPUID signatures to families, e.g.
+    /// `{"606A": "Ice Lake Xeon SP"}`, replacing existing ones.
+    pub fn extend_from_json(&mut self, json: &str) -> Result<()> {
+        let families: BTreeMap<String, String> =
+            serde_json::from_str(json).context("Failed to parse platform families")?;
+        for (signature, family) in families {
+            let signature = u16::from_str_radix(&signature, 16)
+                .ok()
+                .with_context(|| format!("Invalid CPUID signature {signature:?}"))?;
+            self.insert(signature, family);
+        }
+        Ok(())
+    }
+
+    /// The platform family of an FMSPC.
+    pub fn family(&self, fmspc: &Fmspc) -> Option<&str> {
+        self.families
+            .get(&cpuid_signature(fmspc))
+            .map(String::as_str)
+    }
+}
+
+/// The platform family of an FMSPC according to the built-in mapping.
+pub fn platform_family(fmspc: &Fmspc) -> Option<&'static str> {
+    let signature = cpuid_signature(fmspc);
+    BUILTIN_FAMILIES
+        .iter()
+        .find(|(known, _)| *known == signature)
+        .map(|(_, family)| *family)
+}
+
+fn cpuid_signature(fmspc: &Fmspc) -> u16 {
+    let bytes = fmspc.as_bytes();
+    u16::from_be_bytes([bytes[1], bytes[2]])
+}
@@ -0,0 +1,420 @@
+//! Relying-party checks on verified quotes.
+//!
+//! [`verify`] establishes that a quote comes from a genuine, sufficiently patched platform; which
+//! enclaves and TDs to trust is up to the relying party. A [`QuotePolicy`] collects those
+//! checks: allowlists of measurements, a minimum ISV SVN, the accepted TCB statuses, the
+//! tolerated advisories, whether debug and privileged enclaves are denied and the accepted
+//! platform kinds. [`verify_with_policy`] verifies a quote and evaluates every rule of the
+//! policy, reporting each outcome.
+//!
+//! Rules that are not configured are not checked, so an empty policy accepts every verified
+//! quote.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::pck::SgxType;
+use crate::quote::Report;
+use crate::verify::{verify, PlatformId, TcbStatus, VerifiedReport};
+use crate::QuoteCollateralV3;
+
+/// Checks of the relying party on verified quotes, built with chained calls.
+///
+/// ```
+/// use dcap_qvl::policy::QuotePolicy;
+/// use dcap_qvl::verify::TcbStatus;
+///
+/// let policy = QuotePolicy::new()
+///     .allow_mr_signer([0u8; 32])
+///     .min_isv_svn(2)
+///     .accept_tcb_status(TcbStatus::UpToDate)
+///     .deny_debug();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuotePolicy {
+    mr_enclaves: Vec<[u8; 32]>,
+    mr_signers: Vec<[u8; 32]>,
+    mr_tds: Vec<[u8; 48]>,
+    rtmrs: [Vec<[u8; 48]>; 4],
+    min_isv_svn: Option<u16>,
+    tcb_statuses: Vec<TcbStatus>,
+    tolerated_advisories: Option<Vec<String>>,
+    deny_debug: bool,
+    deny_privileged_attributes: bool,
+    sgx_types: Vec<SgxType>,
+    platform_settings: Vec<(PlatformSetting, bool)>,
+}
+
+/// A rule of a [`QuotePolicy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyRule {
+    MrEnclave,
+    MrSigner,
+    MrTd,
+    /// The runtime measurement register with this index
+    Rtmr(u8),
+    MinIsvSvn,
+    TcbStatus,
+    AdvisoryIds,
+    Debug,
+    /// The PROVISIONKEY and EINITTOKEN_KEY attributes
+    PrivilegedAttributes,
+    SgxType,
+    PlatformSetting(PlatformSetting),
+}
+
+/// A property of the platform, from its PCK certificate, that a policy can require or forbid.
+///
+/// Multi-package platforms have different threat models: their packages share keys, and may be
+/// added or replaced after registration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlatformSetting {
+    /// The platform has several packages (sockets)
+    MultiPackage,
+    /// Packages can be added to or replaced in the platform after its registration
+    DynamicPlatform,
+    /// The registration service caches the platform root keys
+    CachedKeys,
+    /// Simultaneous multithreading is enabled
+    SmtEnabled,
+}
+
+impl PlatformSetting {
+    fn name(&self) -> &'static str {
+        match self {
+            PlatformSetting::MultiPackage => "Multi-package",
+            PlatformSetting::DynamicPlatform => "Dynamic platform",
+            PlatformSetting::CachedKeys => "Cached keys",
+            PlatformSetting::SmtEnabled => "SMT",
+        }
+    }
+
+    /// Whether the platform of a report has this setting, `None` if its PCK certificate doesn't
+    /// say.
+    fn of(&self, report: &VerifiedReport) -> Option<bool> {
+        let configuration = report
+            .supplemental
+            .as_ref()
+            .and_then(|supplemental| supplemental.pck_configuration);
+        match self {
+            PlatformSetting::MultiPackage => report
+                .platform_id
+                .as_ref()
+                .map(|id| matches!(id, PlatformId::PlatformInstance(_))),
+            PlatformSetting::DynamicPlatform => configuration?.dynamic_platform,
+            PlatformSetting::CachedKeys => configuration?.cached_keys,
+            PlatformSetting::SmtEnabled => configuration?.smt_enabled,
+        }
+    }
+}
+
+/// The outcome of one rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleOutcome {
+    pub rule: PolicyRule,
+    /// Why the rule failed, or `None` if it passed
+    pub failure: Option<String>,
+}
+
+/// A verified report with the outcome of every configured rule of a policy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyReport {
+    pub report: VerifiedReport,
+    pub outcomes: Vec<RuleOutcome>,
+}
+
+impl PolicyReport {
+    /// Whether every rule passed.
+    pub fn passed(&self) -> bool {
+        self.outcomes
+            .iter()
+            .all(|outcome| outcome.failure.is_none())
+    }
+
+    /// The rules that failed.
+    pub fn failures(&self) -> impl Iterator<Item = &RuleOutcome> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.failure.is_some())
+    }
+
+    /// The verified report if every rule passed, or an error listing the failures.
+    pub fn into_result(self) -> Result<VerifiedReport> {
+        if self.passed() {
+            return Ok(self.report);
+        }
+        let failures = self
+            .failures()
+            .filter_map(|outcome| outcome.failure.as_deref())
+            .collect::<Vec<_>>()
+            .join("; ");
+        bail!("Quote rejected by policy: {failures}")
+    }
+}
+
+impl QuotePolicy {
+    /// A policy without rules.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept SGX enclaves with this MRENCLAVE.
+    pub fn allow_mr_enclave(mut self, mr_enclave: [u8; 32]) -> Self {
+        self.mr_enclaves.push(mr_enclave);
+        self
+    }
+
+    /// Accept SGX enclaves signed by this MRSIGNER.
+    pub fn allow_mr_signer(mut self, mr_signer: [u8; 32]) -> Self {
+        self.mr_signers.push(mr_signer);
+        self
+    }
+
+    /// Accept TDs with this MRTD.
+    pub fn allow_mr_td(mut self, mr_td: [u8; 48]) -> Self {
+        self.mr_tds.push(mr_td);
+        self
+    }
+
+    /// Accept TDs whose RTMR `index` (0 to 3) has this value.
+    ///
+    /// # Panics
+    ///
+    /// If `index` is larger than 3.
+    pub fn allow_rtmr(mut self, index: usize, rtmr: [u8; 48]) -> Self {
+        self.rtmrs[index].push(rtmr);
+        self
+    }
+
+    /// Require SGX enclaves to have at least this ISV SVN.
+    pub fn min_isv_svn(mut self, svn: u16) -> Self {
+        self.min_isv_svn = Some(svn);
+        self
+    }
+
+    /// Accept platforms with this TCB status.
+    pub fn accept_tcb_status(mut self, status: TcbStatus) -> Self {
+        self.tcb_statuses.push(status);
+        self
+    }
+
+    /// Reject platforms affected by advisories other than these. Without this call advisories
+    /// aren't checked, with an empty list every advisory is rejected.
+    pub fn tolerate_advisories<I, S>(mut self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tolerated_advisories
+            .get_or_insert_with(Vec::new)
+            .extend(ids.into_iter().map(Into::into));
+        self
+    }
+
+    /// Reject enclaves and TDs with the DEBUG attribute set.
+    pub fn deny_debug(mut self) -> Self {
+        self.deny_debug = true;
+        self
+    }
+
+    /// Reject SGX enclaves with the PROVISIONKEY or EINITTOKEN_KEY attribute set, which only
+    /// Intel's architectural enclaves need.
+    pub fn deny_privileged_attributes(mut self) -> Self {
+        self.deny_privileged_attributes = true;
+        self
+    }
+
+    /// Accept platforms of this SGX type.
+    pub fn allow_sgx_type(mut self, sgx_type: SgxType) -> Self {
+        self.sgx_types.push(sgx_type);
+        self
+    }
+
+    /// Require a platform setting to be enabled, or to be disabled.
+    ///
+    /// Only multi-package platforms have a configuration in their PCK certificate, so the rules
+    /// on the other settings fail for single-package platforms either way.
+    pub fn require_platform_setting(mut self, setting: PlatformSetting, enabled: bool) -> Self {
+        self.platform_settings.push((setting, enabled));
+        self
+    }
+
+    /// Evaluate the configured rules against a verified report.
+    pub fn evaluate(&self, report: &VerifiedReport) -> Vec<RuleOutcome> {
+        let mut outcomes = Vec::new();
+        let mut check = |rule, failure: Option<String>| {
+            outcomes.push(RuleOutcome { rule, failure });
+        };
+        let sgx = report.report.as_sgx();
+        let td = report.report.as_td10();
+
+        if !self.mr_enclaves.is_empty() {
+            check(
+                PolicyRule::MrEnclave,
+                match sgx {
+                    Some(sgx) if self.mr_enclaves.contains(&sgx.mr_enclave) => None,
+                    Some(sgx) => Some(format!(
+                        "MRENCLAVE {} is not allowed",
+                        hex::encode(sgx.mr_enclave)
+                    )),
+                    None => Some(not_a(&report.report, "SGX")),
+                },
+            );
+        }
+        if !self.mr_signers.is_empty() {
+            check(
+                PolicyRule::MrSigner,
+                match sgx {
+                    Some(sgx) if self.mr_signers.contains(&sgx.mr_signer) => None,
+                    Some(sgx) => Some(format!(
+                        "MRSIGNER {} is not allowed",
+                        hex::encode(sgx.mr_signer)
+                    )),
+                    None => Some(not_a(&report.report, "SGX")),
+                },
+            );
+        }
+        if let Some(min) = self.min_isv_svn {
+            check(
+                PolicyRule::MinIsvSvn,
+                match sgx {
+                    Some(sgx) if sgx.isv_svn >= min => None,
+                    Some(sgx) => Some(format!("ISV SVN {} is below {min}", sgx.isv_svn)),
+                    None => Some(not_a(&report.report, "SGX")),
+                },
+            );
+        }
+        if !self.mr_tds.is_empty() {
+            check(
+                PolicyRule::MrTd,
+                match td {
+                    Some(td) if self.mr_tds.contains(&td.mr_td) => None,
+                    Some(td) => Some(format!("MRTD {} is not allowed", hex::encode(td.mr_td))),
+                    None => Some(not_a(&report.report, "TD")),
+                },
+            );
+        }
+        for (index, allowed) in self.rtmrs.iter().enumerate() {
+            if allowed.is_empty() {
+                continue;
+            }
+            check(
+                PolicyRule::Rtmr(index as u8),
+                match td {
+                    Some(td) => {
+                        let rtmr = [&td.rt_mr0, &td.rt_mr1, &td.rt_mr2, &td.rt_mr3][index];
+                        (!allowed.contains(rtmr))
+                            .then(|| format!("RTMR{index} {} is not allowed", hex::encode(rtmr)))
+                    }
+                    None => Some(not_a(&report.report, "TD")),
+                },
+            );
+        }
+        if !self.tcb_statuses.is_empty() {
+            check(
+                PolicyRule::TcbStatus,
+                (!self.tcb_statuses.contains(&report.status))
+                    .then(|| format!("TCB status {} is not accepted", report.status)),
+            );
+        }
+        if let Some(tolerated) = &self.tolerated_advisories {
+            let untolerated = report
+                .advisory_ids
+                .iter()
+                .filter(|id| !tolerated.contains(id))
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            check(
+                PolicyRule::AdvisoryIds,
+                (!untolerated.is_empty())
+                    .then(|| format!("Advisories {} are not tolerated", untolerated.join(", "))),
+            );
+        }
+        if self.deny_debug {
+            check(
+                PolicyRule::Debug,
+                report
+                    .report
+                    .is_debug()
+                    .then(|| String::from("DEBUG attribute is set")),
+            );
+        }
+        if self.deny_privileged_attributes {
+            let set = [
+                (report.report.has_provision_key(), "PROVISIONKEY"),
+                (report.report.has_einittoken_key(), "EINITTOKEN_KEY"),
+            ]
+            .into_iter()
+            .filter_map(|(set, name)| set.then_some(name))
+            .collect::<Vec<_>>();
+            check(
+                PolicyRule::PrivilegedAttributes,
+                match set[..] {
+                    [] => None,
+                    [name] => Some(format!("{name} attribute is set")),
+                    _ => Some(format!("{} attributes are set", set.join(" and "))),
+                },
+            );
+        }
+        if !self.sgx_types.is_empty() {
+            let sgx_type = report
+                .supplemental
+                .as_ref()
+                .and_then(|supplemental| supplemental.sgx_type);
+            check(
+                PolicyRule::SgxType,
+                match sgx_type {
+                    Some(sgx_type) if self.sgx_types.contains(&sgx_type) => None,
+                    Some(sgx_type) => Some(format!("SGX type {sgx_type:?} is not allowed")),
+                    None => Some(String::from("SGX type is unknown")),
+                },
+            );
+        }
+        for (setting, enabled) in &self.platform_settings {
+            check(
+                PolicyRule::PlatformSetting(*setting),
+                match setting.of(report) {
+                    Some(actual) if actual == *enabled => None,
+                    Some(true) => Some(format!("{} is enabled", setting.name())),
+                    Some(false) => Some(format!("{} is disabled", setting.name())),
+                    None => Some(format!("{} is unknown", setting.name())),
+                },
+            );
+        }
+        outcomes
+    }
+}
+
+/// Verify a quote and evaluate a policy against the verified report.
+///
+/// # Arguments
+///
+/// * `raw_quote` - The raw quote to verify
+/// * `quote_collateral` - The collateral to verify the quote against
+/// * `now` - The current time in seconds since the Unix epoch
+/// * `policy` - The policy to evaluate
+///
+/// # Returns
+///
+/// * `Ok(PolicyReport)` - The report with the outcome of every rule; the quote is acceptable
+///   only if [`PolicyReport::passed`]
+/// * `Err(Error)` - The quote failed to verify
+pub fn verify_with_policy(
+    raw_quote: &[u8],
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+    policy: &QuotePolicy,
+) -> Result<PolicyReport> {
+    let report = verify(raw_quote, quote_collateral, now)?;
+    let outcomes = policy.evaluate(&report);
+    Ok(PolicyReport { report, outcomes })
+}
+
+fn not_a(report: &Report, tee: &str) -> String {
+    let actual = if report.is_sgx() { "SGX" } else { "TD" };
+    format!("Rule applies to {tee} reports, got a {actual} report")
+}
@@ -0,0 +1,404 @@
+//! Appraisal policies in the format of Intel's Quote Appraisal Library (QAL).
+//!
+//! QAL consumes policies as JWTs whose payload holds a `policy_payload` with a list of rules,
+//! each an `environment` naming the class of evidence it is for and a `reference` of expected
+//! values. Parsing these lets the same policy files drive QAL-based verifiers and this crate.
+//!
+//! Rules whose reference only has platform constraints (`accepted_tcb_status`,
+//! `collateral_grace_period`) apply to every report. Rules with `sgx_*` or `tdx_*` references
+//! apply to reports of that TEE, and a policy that has such rules rejects reports of a TEE none
+//! of them is for. Reference keys this crate can't evaluate are rejected when parsing, so a
+//! policy never passes with a constraint silently ignored.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+
+use crate::appraisal::{decode_jws, jws_algorithm, Jws};
+use crate::quote::Report;
+use crate::signature::{self, RingVerifier, SignatureAlgorithm};
+use crate::verify::{TcbStatus, VerifiedReport, Warning};
+
+/// Keys of byte fields that must equal the reference value.
+const EXACT_FIELDS: [&str; 13] = [
+    "sgx_mrenclave",
+    "sgx_mrsigner",
+    "tdx_mrtd",
+    "tdx_rtmr0",
+    "tdx_rtmr1",
+    "tdx_rtmr2",
+    "tdx_rtmr3",
+    "tdx_mrconfigid",
+    "tdx_mrowner",
+    "tdx_mrownerconfig",
+    "tdx_mrseam",
+    "tdx_mrsignerseam",
+    "tdx_mrservicetd",
+];
+/// Keys of byte fields compared under an optional `<key>_mask`.
+const MASKED_FIELDS: [&str; 5] = [
+    "sgx_attributes",
+    "sgx_miscselect",
+    "tdx_td_attributes",
+    "tdx_xfam",
+    "tdx_seam_attributes",
+];
+/// Platform options that only restrict evidence when `false`.
+const PERMISSIVE_OPTIONS: [&str; 3] = [
+    "allow_dynamic_platform",
+    "allow_cached_keys",
+    "allow_smt_enabled",
+];
+
+/// A parsed QAL appraisal policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QalPolicy {
+    pub id: Option<String>,
+    pub version: Option<String>,
+    pub rules: Vec<PolicyRule>,
+}
+
+/// One rule of a policy, from an `environment` and `reference` pair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyRule {
+    /// The `class_id` of the environment the rule is for
+    pub class_id: Option<String>,
+    /// The TEE the rule is for, or `None` for a platform rule
+    pub tee: Option<PolicyTee>,
+    pub constraints: Vec<Constraint>,
+}
+
+/// The TEE a policy rule is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyTee {
+    Sgx,
+    Tdx,
+}
+
+/// A single constraint of a policy rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Constraint {
+    /// The TCB status must be one of these
+    AcceptedTcbStatus(Vec<TcbStatus>),
+    /// The collateral may be used at most this many seconds past its `nextUpdate`
+    CollateralGracePeriod(u64),
+    /// A report field must equal the value
+    Equals { field: String, value: Vec<u8> },
+    /// A report field under the mask must equal the value under the mask
+    Masked {
+        field: String,
+        value: Vec<u8>,
+        mask: Vec<u8>,
+    },
+    /// The ISV product ID must equal the value
+    IsvProdId(u16),
+    /// The ISV SVN must be at least the value
+    MinIsvSvn(u16),
+}
+
+/// Parse a policy JWT after checking its signature.
+///
+/// # Arguments
+///
+/// * `token` - The policy in JWS compact serialization, signed with ES256 or ES384
+/// * `public_key` - The uncompressed public key (X || Y) of the policy signer
+///
+/// # Returns
+///
+/// * `Ok(QalPolicy)` - The policy
+/// * `Err(Error)` - The error
+pub fn parse_policy(token: &str, public_key: &[u8]) -> Result<QalPolicy> {
+    let Jws {
+        signing_input,
+        header,
+        claims,
+        signature,
+    } = decode_jws(token)?;
+    let alg = header.get("alg").and_then(Value::as_str);
+    let algorithm = [
+        SignatureAlgorithm::EcdsaP256Sha256,
+        SignatureAlgorithm::EcdsaP384Sha384,
+    ]
+    .into_iter()
+    .find(|algorithm| alg == Some(jws_algorithm(*algorithm)))
+    .context("Unsupported policy signature algorithm")?;
+    signature::verify_with_algorithm(
+        &RingVerifier,
+        algorithm,
+        public_key,
+        signing_input.as_bytes(),
+        &signature,
+    )
+    .context("Policy signature is invalid")?;
+    policy_from_claims(&claims)
+}
+
+/// Parse a policy without checking any signature, for policies whose integrity is established
+/// otherwise, e.g. compiled into the verifier.
+///
+/// Accepts either a JWT, whose signature is ignored, or the JSON of its payload.
+pub fn parse_policy_unverified(policy: &str) -> Result<QalPolicy> {
+    let claims = match serde_json::from_str(policy) {
+        Ok(claims) => claims,
+        Err(_) => decode_jws(policy)?.claims,
+    };
+    policy_from_claims(&claims)
+}
+
+impl QalPolicy {
+    /// Check a verified report against every rule of the policy.
+    pub fn evaluate(&self, report: &VerifiedReport) -> Result<()> {
+        let tee = if report.report.is_sgx() {
+            PolicyTee::Sgx
+        } else {
+            PolicyTee::Tdx
+        };
+        let mut has_tee_rules = false;
+        let mut matched_tee = false;
+        for rule in &self.rules {
+            if let Some(rule_tee) = rule.tee {
+                has_tee_rules = true;
+                if rule_tee != tee {
+                    continue;
+                }
+                matched_tee = true;
+            }
+            for constraint in &rule.constraints {
+                constraint.check(report)?;
+            }
+        }
+        if has_tee_rules && !matched_tee {
+            bail!("Policy has no rule for {tee:?} reports");
+        }
+        Ok(())
+    }
+}
+
+impl Constraint {
+    /// Check the constraint against a verified report.
+    pub fn check(&self, report: &VerifiedReport) -> Result<()> {
+        match self {
+            Constraint::AcceptedTcbStatus(accepted) => {
+                if !accepted.contains(&report.status) {
+                    bail!("TCB status {} is not accepted by the policy", report.status);
+                }
+            }
+            Constraint::CollateralGracePeriod(grace_period) => {
+                for warning in &report.warnings {
+                    if let Warning::StaleCollateral { expired_for } = warning {
+                        if expired_for > grace_period {
+                            bail!("Collateral expired {expired_for}s ago, past the grace period");
+                        }
+                    }
+                }
+            }
+            Constraint::Equals { field, value } => {
+                let actual = report_field(&report.report, field)?;
+                if actual != *value {
+                    bail!("{field} does not match the policy");
+                }
+            }
+            Constraint::Masked { field, value, mask } => {
+                let actual = report_field(&report.report, field)?;
+                let matches = actual.len() == value.len()
+                    && actual
+                        .iter()
+                        .zip(value)
+                        .zip(mask)
+                        .all(|((actual, value), mask)| actual & mask == value & mask);
+                if !matches {
+                    bail!("{field} does not match the policy");
+                }
+            }
+            Constraint::IsvProdId(expected) => {
+                let report = report.report.as_sgx().context("sgx_isvprodid needs SGX")?;
+                if report.isv_prod_id != *expected {
+                    bail!("sgx_isvprodid does not match the policy");
+                }
+            }
+            Constraint::MinIsvSvn(min) => {
+                let report = report.report.as_sgx().context("sgx_isvsvn_min needs SGX")?;
+                if report.isv_svn < *min {
+                    bail!("ISV SVN {} is below the policy minimum", report.isv_svn);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn policy_from_claims(claims: &Value) -> Result<QalPolicy> {
+    let payload = claims.get("policy_payload").unwrap_or(claims);
+    let string = |key| payload.get(key).and_then(Value::as_str).map(String::from);
+    let rules = payload
+        .get("policy")
+        .and_then(Value::as_array)
+        .context("Policy has no rules")?
+        .iter()
+        .map(parse_rule)
+        .collect::<Result<Vec<_>>>()?;
+    Ok(QalPolicy {
+        id: string("id"),
+        version: string("version"),
+        rules,
+    })
+}
+
+fn parse_rule(rule: &Value) -> Result<PolicyRule> {
+    let class_id = rule
+        .get("environment")
+        .and_then(|environment| environment.get("class_id"))
+        .and_then(Value::as_str)
+        .map(String::from);
+    let reference = rule
+        .get("reference")
+        .and_then(Value::as_object)
+        .context("Policy rule has no reference")?;
+
+    let mut tee = None;
+    let mut constraints = Vec::new();
+    for (key, value) in reference {
+        let rule_tee = if key.starts_with("sgx_") {
+            Some(PolicyTee::Sgx)
+        } else if key.starts_with("tdx_") {
+            Some(PolicyTee::Tdx)
+        } else {
+            None
+        };
+        if let Some(rule_tee) = rule_tee {
+            if tee.is_some_and(|tee| tee != rule_tee) {
+                bail!("Policy rule mixes SGX and TDX references");
+            }
+            tee = Some(rule_tee);
+        }
+        if let Some(constraint) = parse_constraint(key, value, reference)? {
+            constraints.push(constraint);
+        }
+    }
+    Ok(PolicyRule {
+        class_id,
+        tee,
+        constraints,
+    })
+}
+
+/// Parse a reference entry, or `None` for entries that don't constrain anything.
+fn parse_constraint(
+    key: &str,
+    value: &Value,
+    reference: &Map<String, Value>,
+) -> Result<Option<Constraint>> {
+    let hex_value = |value: &Value| {
+        let value = value
+            .as_str()
+            .with_context(|| format!("{key} must be a hex string"))?;
+        hex::decode(value).with_context(|| format!("{key} must be a hex string"))
+    };
+    let int_value = || {
+        value
+            .as_u64()
+            .with_context(|| format!("{key} must be an integer"))
+    };
+    let constraint = match key {
+        "accepted_tcb_status" => {
+            let accepted = value
+                .as_array()
+                .context("accepted_tcb_status must be a list")?
+                .iter()
+                .map(|status| {
+                    status
+                        .as_str()
+                        .context("Invalid TCB status")?
+                        .parse::<TcbStatus>()
+                })
+                .collect::<Result<_>>()?;
+            Constraint::AcceptedTcbStatus(accepted)
+        }
+        "collateral_grace_period" => Constraint::CollateralGracePeriod(int_value()?),
+        "sgx_isvprodid" => {
+            Constraint::IsvProdId(int_value()?.try_into().context("sgx_isvprodid too large")?)
+        }
+        "sgx_isvsvn_min" => Constraint::MinIsvSvn(
+            int_value()?
+                .try_into()
+                .context("sgx_isvsvn_min too large")?,
+        ),
+        key if EXACT_FIELDS.contains(&key) => Constraint::Equals {
+            field: key.to_string(),
+            value: hex_value(value)?,
+        },
+        key if MASKED_FIELDS.contains(&key) => {
+            let value = hex_value(value)?;
+            let mask = match reference.get(&format!("{key}_mask")) {
+                Some(mask) => hex_value(mask)?,
+                None => vec![0xff; value.len()],
+            };
+            if mask.len() != value.len() {
+                bail!("{key}_mask must be as long as {key}");
+            }
+            Constraint::Masked {
+                field: key.to_string(),
+                value,
+                mask,
+            }
+        }
+        key if key
+            .strip_suffix("_mask")
+            .is_some_and(|field| MASKED_FIELDS.contains(&field)) =>
+        {
+            if !reference.contains_key(key.trim_end_matches("_mask")) {
+                bail!("{key} has no value to mask");
+            }
+            return Ok(None);
+        }
+        key if PERMISSIVE_OPTIONS.contains(&key) => {
+            if value.as_bool() != Some(true) {
+                bail!("Policy option {key} is not supported");
+            }
+            return Ok(None);
+        }
+        _ => bail!("Unsupported policy reference {key}"),
+    };
+    Ok(Some(constraint))
+}
+
+/// The bytes of a report field by its policy reference key.
+///
+/// MISCSELECT is compared in the little endian byte order it has in the report.
+fn report_field(report: &Report, field: &str) -> Result<Vec<u8>> {
+    if let Some(report) = report.as_sgx() {
+        let value = match field {
+            "sgx_mrenclave" => report.mr_enclave.to_vec(),
+            "sgx_mrsigner" => report.mr_signer.to_vec(),
+            "sgx_attributes" => report.attributes.to_vec(),
+            "sgx_miscselect" => report.misc_select.to_le_bytes().to_vec(),
+            _ => bail!("{field} is not a field of SGX reports"),
+        };
+        return Ok(value);
+    }
+    let td = report.as_td10().context("Unsupported report type")?;
+    let value: &[u8] = match field {
+        "tdx_mrtd" => &td.mr_td,
+        "tdx_rtmr0" => &td.rt_mr0,
+        "tdx_rtmr1" => &td.rt_mr1,
+        "tdx_rtmr2" => &td.rt_mr2,
+        "tdx_rtmr3" => &td.rt_mr3,
+        "tdx_mrconfigid" => &td.mr_config_id,
+        "tdx_mrowner" => &td.mr_owner,
+        "tdx_mrownerconfig" => &td.mr_owner_config,
+        "tdx_mrseam" => &td.mr_seam,
+        "tdx_mrsignerseam" => &td.mr_signer_seam,
+        "tdx_td_attributes" => &td.td_attributes,
+        "tdx_xfam" => &td.xfam,
+        "tdx_seam_attributes" => &td.seam_attributes,
+        "tdx_mrservicetd" => {
+            &report
+                .as_td15()
+                .context("tdx_mrservicetd needs a TD 1.5 report")?
+                .mr_service_td
+        }
+        _ => bail!("{field} is not a field of TD reports"),
+    };
+    Ok(value.to_vec())
+}
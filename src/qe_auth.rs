@@ -0,0 +1,84 @@
+//! Interpreting the QE authentication data of quotes.
+//!
+//! The QE authentication data is opaque to the verifier: it is bound to the attestation key by
+//! the QE report, but what it carries is up to the QE vendor and deployment. A
+//! [`QeAuthDataRegistry`] maps QE vendor IDs to [`QeAuthDataInterpreter`]s that decode it into
+//! JSON, so decoders can show deployment-specific metadata next to the quote.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use anyhow::Result;
+use serde_json::Value;
+
+use crate::quote::Quote;
+
+pub use crate::constants::INTEL_QE_VENDOR_ID;
+
+/// Decodes the QE authentication data of one QE vendor.
+pub trait QeAuthDataInterpreter: Send + Sync {
+    /// Decode the raw QE authentication data of a quote.
+    fn interpret(&self, qe_auth_data: &[u8]) -> Result<Value>;
+}
+
+impl<F: Fn(&[u8]) -> Result<Value> + Send + Sync> QeAuthDataInterpreter for F {
+    fn interpret(&self, qe_auth_data: &[u8]) -> Result<Value> {
+        self(qe_auth_data)
+    }
+}
+
+/// QE authentication data interpreters keyed by QE vendor ID.
+#[derive(Default)]
+pub struct QeAuthDataRegistry {
+    interpreters: BTreeMap<[u8; 16], Box<dyn QeAuthDataInterpreter>>,
+}
+
+impl QeAuthDataRegistry {
+    /// Create a registry without interpreters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a registry with the interpreters of this crate, i.e. [`interpret_intel`] for
+    /// Intel's QE.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(INTEL_QE_VENDOR_ID, interpret_intel);
+        registry
+    }
+
+    /// Register the interpreter for a QE vendor, replacing any previous one.
+    pub fn register(
+        &mut self,
+        qe_vendor_id: [u8; 16],
+        interpreter: impl QeAuthDataInterpreter + 'static,
+    ) -> &mut Self {
+        self.interpreters
+            .insert(qe_vendor_id, Box::new(interpreter));
+        self
+    }
+
+    /// Interpret the QE authentication data of a quote.
+    ///
+    /// # Returns
+    ///
+    /// * `None` - No interpreter is registered for the quote's QE vendor
+    /// * `Some(Ok(Value))` - The interpreted data
+    /// * `Some(Err(Error))` - The interpreter failed to decode the data
+    pub fn interpret(&self, quote: &Quote) -> Option<Result<Value>> {
+        let interpreter = self.interpreters.get(&quote.header.qe_vendor_id)?;
+        Some(interpreter.interpret(quote.qe_auth_data()))
+    }
+}
+
+/// Interpret the QE authentication data of Intel's QE.
+///
+/// Unless the quote requester supplied its own, this is the placeholder `00 01 .. 1f`, which is
+/// reported with `"default": true`.
+pub fn interpret_intel(qe_auth_data: &[u8]) -> Result<Value> {
+    let is_default =
+        qe_auth_data.len() == 32 && qe_auth_data.iter().zip(0u8..).all(|(a, b)| *a == b);
+    Ok(serde_json::json!({
+        "default": is_default,
+        "data": hex::encode(qe_auth_data),
+    }))
+}
@@ -5,7 +5,15 @@ use anyhow::{anyhow, bail, Context, Result};
 use scale::{Decode, Input};
 use serde::{Deserialize, Serialize};
 
-use crate::{constants::*, utils};
+#[cfg(feature = "borsh")]
+use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(feature = "scale-codec")]
+use {scale::Encode, scale_info::TypeInfo};
+
+use crate::constants::*;
+use crate::pck::{parse_pck_extension, PckCertData};
+use crate::types::{Measurement, ReportData};
+use crate::utils::{self, MsgContext};
 
 #[derive(Debug, Clone)]
 pub struct Data<T> {
@@ -29,6 +37,24 @@ impl<'de, T> Deserialize<'de> for Data<T> {
     }
 }
 
+#[cfg(feature = "borsh")]
+impl<T> BorshSerialize for Data<T> {
+    fn serialize<W: borsh::io::Write>(&self, writer: &mut W) -> borsh::io::Result<()> {
+        BorshSerialize::serialize(&self.data, writer)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl<T> BorshDeserialize for Data<T> {
+    fn deserialize_reader<R: borsh::io::Read>(reader: &mut R) -> borsh::io::Result<Self> {
+        let data = Vec::<u8>::deserialize_reader(reader)?;
+        Ok(Data {
+            data,
+            _marker: core::marker::PhantomData,
+        })
+    }
+}
+
 impl<T: Decode + Into<u64>> Decode for Data<T> {
     fn decode<I: Input>(input: &mut I) -> Result<Self, scale::Error> {
         let len = T::decode(input)?;
@@ -42,6 +68,8 @@ impl<T: Decode + Into<u64>> Decode for Data<T> {
 }
 
 #[derive(Decode, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[non_exhaustive]
 pub struct Header {
     pub version: u16,
     pub attestation_key_type: u16,
@@ -54,6 +82,20 @@ pub struct Header {
     pub user_data: [u8; 20],
 }
 
+accessors!(Header {
+    copy {
+        version: u16,
+        attestation_key_type: u16,
+        tee_type: u32,
+        qe_svn: u16,
+        pce_svn: u16,
+    }
+    ref {
+        qe_vendor_id: [u8; 16],
+        user_data: [u8; 20],
+    }
+});
+
 impl Header {
     pub fn is_sgx(&self) -> bool {
         self.tee_type == TEE_TYPE_SGX
@@ -67,6 +109,9 @@ pub struct Body {
 }
 
 #[derive(Serialize, Deserialize, Decode, Debug, Clone)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(Encode, TypeInfo))]
+#[non_exhaustive]
 pub struct EnclaveReport {
     #[serde(with = "serde_bytes")]
     pub cpu_svn: [u8; 16],
@@ -91,7 +136,25 @@ pub struct EnclaveReport {
     pub report_data: [u8; 64],
 }
 
+accessors!(EnclaveReport {
+    copy {
+        misc_select: u32,
+        isv_prod_id: u16,
+        isv_svn: u16,
+    }
+    ref {
+        cpu_svn: [u8; 16],
+        attributes: [u8; 16],
+        mr_enclave: [u8; 32],
+        mr_signer: [u8; 32],
+        report_data: [u8; 64],
+    }
+});
+
 #[derive(Decode, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(Encode, TypeInfo))]
+#[non_exhaustive]
 pub struct TDReport10 {
     #[serde(with = "serde_bytes")]
     pub tee_tcb_svn: [u8; 16],
@@ -125,7 +188,30 @@ pub struct TDReport10 {
     pub report_data: [u8; 64],
 }
 
+accessors!(TDReport10 {
+    ref {
+        tee_tcb_svn: [u8; 16],
+        mr_seam: [u8; 48],
+        mr_signer_seam: [u8; 48],
+        seam_attributes: [u8; 8],
+        td_attributes: [u8; 8],
+        xfam: [u8; 8],
+        mr_td: [u8; 48],
+        mr_config_id: [u8; 48],
+        mr_owner: [u8; 48],
+        mr_owner_config: [u8; 48],
+        rt_mr0: [u8; 48],
+        rt_mr1: [u8; 48],
+        rt_mr2: [u8; 48],
+        rt_mr3: [u8; 48],
+        report_data: [u8; 64],
+    }
+});
+
 #[derive(Decode, Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(Encode, TypeInfo))]
+#[non_exhaustive]
 pub struct TDReport15 {
     pub base: TDReport10,
     #[serde(with = "serde_bytes")]
@@ -134,7 +220,16 @@ pub struct TDReport15 {
     pub mr_service_td: [u8; 48],
 }
 
+accessors!(TDReport15 {
+    ref {
+        base: TDReport10,
+        tee_tcb_svn2: [u8; 16],
+        mr_service_td: [u8; 48],
+    }
+});
+
 #[derive(Decode, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 pub struct CertificationData {
     pub cert_type: u16,
     pub body: Data<u32>,
@@ -151,6 +246,7 @@ impl core::fmt::Debug for CertificationData {
 }
 
 #[derive(Decode, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 pub struct QEReportCertificationData {
     #[serde(with = "serde_bytes")]
     pub qe_report: [u8; ENCLAVE_REPORT_BYTE_LEN],
@@ -161,6 +257,7 @@ pub struct QEReportCertificationData {
 }
 
 #[derive(Decode, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 pub struct AuthDataV3 {
     #[serde(with = "serde_bytes")]
     pub ecdsa_signature: [u8; ECDSA_SIGNATURE_BYTE_LEN],
@@ -175,6 +272,7 @@ pub struct AuthDataV3 {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 pub struct AuthDataV4 {
     #[serde(with = "serde_bytes")]
     pub ecdsa_signature: [u8; ECDSA_SIGNATURE_BYTE_LEN],
@@ -214,6 +312,7 @@ impl Decode for AuthDataV4 {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 pub enum AuthData {
     V3(AuthDataV3),
     V4(AuthDataV4),
@@ -243,6 +342,9 @@ fn decode_auth_data(ver: u16, input: &mut &[u8]) -> Result<AuthData, scale::Erro
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(Encode, Decode, TypeInfo))]
+#[non_exhaustive]
 pub enum Report {
     SgxEnclave(EnclaveReport),
     TD10(TDReport10),
@@ -275,15 +377,80 @@ impl Report {
             _ => None,
         }
     }
+
+    /// Whether the enclave or TD has its DEBUG attribute set.
+    pub fn is_debug(&self) -> bool {
+        match self {
+            // ATTRIBUTES.DEBUG is bit 1
+            Report::SgxEnclave(report) => report.attributes[0] & 0x02 != 0,
+            // TDATTRIBUTES.DEBUG is bit 0
+            Report::TD10(report) => report.td_attributes[0] & 0x01 != 0,
+            Report::TD15(report) => report.base.td_attributes[0] & 0x01 != 0,
+        }
+    }
+
+    /// Whether the SGX enclave has access to the provisioning key, which only Intel's
+    /// provisioning enclaves need. Always false for TDs.
+    pub fn has_provision_key(&self) -> bool {
+        // ATTRIBUTES.PROVISIONKEY is bit 4
+        self.as_sgx()
+            .is_some_and(|report| report.attributes[0] & 0x10 != 0)
+    }
+
+    /// Whether the SGX enclave has access to the launch (EINITTOKEN) key, which only launch
+    /// enclaves need. Always false for TDs.
+    pub fn has_einittoken_key(&self) -> bool {
+        // ATTRIBUTES.EINITTOKEN_KEY is bit 5
+        self.as_sgx()
+            .is_some_and(|report| report.attributes[0] & 0x20 != 0)
+    }
+
+    /// The report data of the enclave or TD.
+    pub fn report_data(&self) -> ReportData {
+        match self {
+            Report::SgxEnclave(report) => report.report_data.into(),
+            Report::TD10(report) => report.report_data.into(),
+            Report::TD15(report) => report.base.report_data.into(),
+        }
+    }
+
+    /// The measurement of the enclave or TD: MRENCLAVE for SGX and MRTD for TDX.
+    pub fn measurement(&self) -> Measurement {
+        match self {
+            Report::SgxEnclave(report) => Measurement(report.mr_enclave.to_vec()),
+            Report::TD10(report) => Measurement(report.mr_td.to_vec()),
+            Report::TD15(report) => Measurement(report.base.mr_td.to_vec()),
+        }
+    }
+}
+
+/// The TEE a quote was produced by.
+///
+/// The report body of a parsed [`Quote`] always matches its TEE type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum TeeType {
+    Sgx,
+    Tdx,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[non_exhaustive]
 pub struct Quote {
     pub header: Header,
     pub report: Report,
     pub auth_data: AuthData,
 }
 
+accessors!(Quote {
+    ref {
+        header: Header,
+        report: Report,
+        auth_data: AuthData,
+    }
+});
+
 impl Decode for Quote {
     fn decode<I: Input>(input: &mut I) -> Result<Self, scale::Error> {
         let header = Header::decode(input)?;
@@ -318,6 +485,14 @@ impl Decode for Quote {
                     }
                     _ => return Err(scale::Error::from("Unsupported body type")),
                 }
+                let tee_type = if report.is_sgx() {
+                    TEE_TYPE_SGX
+                } else {
+                    TEE_TYPE_TDX
+                };
+                if header.tee_type != tee_type {
+                    return Err(scale::Error::from("Body type does not match TEE type"));
+                }
             }
             _ => return Err(scale::Error::from("Unsupported quote version")),
         }
@@ -335,10 +510,27 @@ impl Quote {
     /// Parse a TEE quote from a byte slice.
     pub fn parse(quote: &[u8]) -> Result<Self> {
         let mut input = &quote[..];
-        let quote = Quote::decode(&mut input)?;
+        let quote = Quote::decode(&mut input).map_err(anyhow::Error::msg)?;
         Ok(quote)
     }
 
+    /// Parse a quote, classifying quotes of TEE types this crate doesn't verify as
+    /// [`ClassifiedQuote::UnknownTee`] instead of failing.
+    ///
+    /// Lets gateways log and forward evidence of newer TEEs. Quotes of known TEE types still
+    /// fail to parse if they are malformed.
+    pub fn classify(raw_quote: &[u8]) -> Result<ClassifiedQuote> {
+        let header = Header::decode(&mut &raw_quote[..]).msg_context("Failed to decode header")?;
+        if ALLOWED_TEE_TYPES.contains(&header.tee_type) {
+            return Ok(ClassifiedQuote::Known(Quote::parse(raw_quote)?));
+        }
+        Ok(ClassifiedQuote::UnknownTee(UnknownTeeQuote {
+            tee_type: header.tee_type,
+            header: raw_quote[..HEADER_BYTE_LEN].to_vec(),
+            body: raw_quote[HEADER_BYTE_LEN..].to_vec(),
+        }))
+    }
+
     /// Get the raw certificate chain from the quote.
     pub fn raw_cert_chain(&self) -> Result<&[u8]> {
         let cert_data = match &self.auth_data {
@@ -351,6 +543,44 @@ impl Quote {
         Ok(&cert_data.body.data)
     }
 
+    /// Get the raw QE authentication data, which the QE report binds to the attestation key.
+    ///
+    /// Its content is up to the QE vendor, see [`crate::qe_auth`] for interpreting it.
+    pub fn qe_auth_data(&self) -> &[u8] {
+        match &self.auth_data {
+            AuthData::V3(data) => &data.qe_auth_data.data,
+            AuthData::V4(data) => &data.qe_report_data.qe_auth_data.data,
+        }
+    }
+
+    /// The TEE the quote was produced by.
+    pub fn tee_type(&self) -> TeeType {
+        if self.report.is_sgx() {
+            TeeType::Sgx
+        } else {
+            TeeType::Tdx
+        }
+    }
+
+    pub fn is_sgx(&self) -> bool {
+        self.tee_type() == TeeType::Sgx
+    }
+
+    pub fn is_tdx(&self) -> bool {
+        self.tee_type() == TeeType::Tdx
+    }
+
+    /// The enclave report of an SGX quote.
+    pub fn as_sgx(&self) -> Option<&EnclaveReport> {
+        self.report.as_sgx()
+    }
+
+    /// The TD report of a TDX quote. For TDX 1.5 quotes this is the part shared with TDX 1.0,
+    /// the rest is in [`Report::as_td15`].
+    pub fn as_tdx(&self) -> Option<&TDReport10> {
+        self.report.as_td10()
+    }
+
     /// Get the FMSPC from the quote.
     pub fn fmspc(&self) -> Result<Fmspc> {
         let raw_cert_chain = self
@@ -363,6 +593,16 @@ impl Quote {
         utils::get_fmspc(&extension_section)
     }
 
+    /// Parse the SGX extension of the PCK certificate of the quote.
+    pub fn pck_extension(&self) -> Result<PckCertData> {
+        let raw_cert_chain = self
+            .raw_cert_chain()
+            .context("Failed to get raw cert chain")?;
+        let certs = utils::extract_certs(raw_cert_chain).context("Failed to extract certs")?;
+        let cert = certs.first().context("Missing PCK certificate")?;
+        parse_pck_extension(cert)
+    }
+
     /// Get the the length of signed data in the quote.
     pub fn signed_length(&self) -> usize {
         let mut len = match self.report {
@@ -375,4 +615,251 @@ impl Quote {
         }
         len
     }
+
+    /// Locate the fields of a raw quote, for extracting, redacting or signing parts of it
+    /// without re-implementing the quote format.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(QuoteLayout)` - The ranges of the fields within `raw_quote`
+    /// * `Err(Error)` - The quote failed to parse
+    pub fn layout(raw_quote: &[u8]) -> Result<QuoteLayout> {
+        let quote = Quote::parse(raw_quote).context("Failed to parse quote")?;
+
+        let header = ByteRange::new(0, HEADER_BYTE_LEN);
+        let body =
+            (quote.header.version == 5).then(|| ByteRange::new(header.end(), BODY_BYTE_SIZE));
+        let report_offset = body.unwrap_or(header).end();
+        let report = match quote.report {
+            Report::SgxEnclave(_) => ByteRange::new(report_offset, ENCLAVE_REPORT_BYTE_LEN),
+            Report::TD10(_) => ByteRange::new(report_offset, TD_REPORT10_BYTE_LEN),
+            Report::TD15(_) => ByteRange::new(report_offset, TD_REPORT15_BYTE_LEN),
+        };
+        // The report data ends the enclave report and the part of TD reports shared with TDX 1.0
+        let report_data_end = match quote.report {
+            Report::SgxEnclave(_) => report.end(),
+            Report::TD10(_) | Report::TD15(_) => report_offset + TD_REPORT10_BYTE_LEN,
+        };
+        let report_data = ByteRange::new(report_data_end - 64, 64);
+
+        let (auth_data_len, qe_auth_data_len, cert_data_len) = match &quote.auth_data {
+            AuthData::V3(data) => (
+                ECDSA_SIGNATURE_BYTE_LEN
+                    + ECDSA_PUBKEY_BYTE_LEN
+                    + QE_REPORT_BYTE_LEN
+                    + QE_REPORT_SIG_BYTE_LEN
+                    + QE_AUTH_DATA_SIZE_BYTE_LEN
+                    + data.qe_auth_data.data.len()
+                    + QE_CERT_DATA_TYPE_BYTE_LEN
+                    + QE_CERT_DATA_SIZE_BYTE_LEN
+                    + data.certification_data.body.data.len(),
+                data.qe_auth_data.data.len(),
+                data.certification_data.body.data.len(),
+            ),
+            AuthData::V4(data) => (
+                ECDSA_SIGNATURE_BYTE_LEN
+                    + ECDSA_PUBKEY_BYTE_LEN
+                    + CERTIFICATION_DATA_TYPE_BYTE_LEN
+                    + CERTIFICATION_DATA_SIZE_BYTE_LEN
+                    + data.certification_data.body.data.len(),
+                data.qe_report_data.qe_auth_data.data.len(),
+                data.qe_report_data.certification_data.body.data.len(),
+            ),
+        };
+        let auth_data = ByteRange::new(report.end() + AUTH_DATA_SIZE_BYTE_LEN, auth_data_len);
+        let ecdsa_signature = ByteRange::new(auth_data.offset, ECDSA_SIGNATURE_BYTE_LEN);
+        let attestation_key = ByteRange::new(ecdsa_signature.end(), ECDSA_PUBKEY_BYTE_LEN);
+        // Version 4 wraps the QE report in certification data of its own
+        let qe_report_offset = match quote.auth_data {
+            AuthData::V3(_) => attestation_key.end(),
+            AuthData::V4(_) => {
+                attestation_key.end()
+                    + CERTIFICATION_DATA_TYPE_BYTE_LEN
+                    + CERTIFICATION_DATA_SIZE_BYTE_LEN
+            }
+        };
+        let qe_report = ByteRange::new(qe_report_offset, QE_REPORT_BYTE_LEN);
+        let qe_report_signature = ByteRange::new(qe_report.end(), QE_REPORT_SIG_BYTE_LEN);
+        let qe_auth_data = ByteRange::new(
+            qe_report_signature.end() + QE_AUTH_DATA_SIZE_BYTE_LEN,
+            qe_auth_data_len,
+        );
+        let cert_data = ByteRange::new(
+            qe_auth_data.end() + QE_CERT_DATA_TYPE_BYTE_LEN + QE_CERT_DATA_SIZE_BYTE_LEN,
+            cert_data_len,
+        );
+        Ok(QuoteLayout {
+            header,
+            body,
+            report,
+            report_data,
+            signed: ByteRange::new(0, report.end()),
+            auth_data,
+            ecdsa_signature,
+            attestation_key,
+            qe_report,
+            qe_report_signature,
+            qe_auth_data,
+            cert_data,
+        })
+    }
+
+    /// Zero the report_data and QE authentication data of a raw quote.
+    ///
+    /// These fields may carry user secrets or identifiers. The redacted quote is still
+    /// decodable, but its signatures no longer verify.
+    pub fn redact(raw_quote: &[u8]) -> Result<Vec<u8>> {
+        let layout = Quote::layout(raw_quote)?;
+        let mut redacted = raw_quote.to_vec();
+        for range in [layout.report_data, layout.qe_auth_data] {
+            zero_range(&mut redacted, range.offset, range.end())?;
+        }
+        Ok(redacted)
+    }
+
+    /// Find structural anomalies of a raw quote that do not prevent decoding.
+    ///
+    /// These are not verification failures, but a genuine Intel QE never produces them, so they
+    /// are worth monitoring as a sign of malformed or adversarial quotes.
+    pub fn anomalies(raw_quote: &[u8]) -> Result<Vec<Anomaly>> {
+        let mut input = raw_quote;
+        let quote = Quote::decode(&mut input).msg_context("Failed to decode quote")?;
+        let mut anomalies = Vec::new();
+
+        if !input.is_empty() {
+            anomalies.push(Anomaly::TrailingBytes { len: input.len() });
+        }
+        if quote.header.qe_vendor_id != INTEL_QE_VENDOR_ID {
+            anomalies.push(Anomaly::UnknownQeVendor);
+        }
+        if let Report::SgxEnclave(report) = &quote.report {
+            report.reserved_anomalies("report", &mut anomalies);
+        }
+
+        let auth_data = quote.auth_data.into_v3();
+        if let Ok(qe_report) = EnclaveReport::decode(&mut &auth_data.qe_report[..]) {
+            qe_report.reserved_anomalies("qe_report", &mut anomalies);
+        }
+        let qe_auth_data_len = auth_data.qe_auth_data.data.len();
+        if qe_auth_data_len != AUTHENTICATION_DATA_LEN {
+            anomalies.push(Anomaly::UnusualQeAuthDataSize {
+                size: qe_auth_data_len,
+            });
+        }
+        if auth_data.certification_data.cert_type != PCK_CERT_CHAIN {
+            anomalies.push(Anomaly::UnexpectedCertDataType {
+                cert_type: auth_data.certification_data.cert_type,
+            });
+        }
+        Ok(anomalies)
+    }
+}
+
+/// A quote classified by its TEE type, see [`Quote::classify`].
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClassifiedQuote {
+    Known(Quote),
+    UnknownTee(UnknownTeeQuote),
+}
+
+/// The raw parts of a quote of a TEE type this crate doesn't verify.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnknownTeeQuote {
+    pub tee_type: u32,
+    /// The raw quote header
+    #[serde(with = "serde_bytes")]
+    pub header: Vec<u8>,
+    /// Everything after the header, as its layout is not known
+    #[serde(with = "serde_bytes")]
+    pub body: Vec<u8>,
+}
+
+/// A range of bytes of a raw quote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ByteRange {
+    pub offset: usize,
+    pub len: usize,
+}
+
+impl ByteRange {
+    fn new(offset: usize, len: usize) -> Self {
+        Self { offset, len }
+    }
+
+    /// The offset just past the range.
+    pub fn end(&self) -> usize {
+        self.offset + self.len
+    }
+
+    /// The range, for indexing the raw quote.
+    pub fn range(&self) -> core::ops::Range<usize> {
+        self.offset..self.end()
+    }
+}
+
+/// Where the fields of a quote are in the raw quote, see [`Quote::layout`].
+///
+/// Length prefixes are not part of the ranges, e.g. `auth_data` starts after its size.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuoteLayout {
+    pub header: ByteRange,
+    /// The body descriptor of version 5 quotes, `None` for earlier versions
+    pub body: Option<ByteRange>,
+    /// The enclave or TD report
+    pub report: ByteRange,
+    pub report_data: ByteRange,
+    /// The header, body descriptor and report, which the attestation key signs
+    pub signed: ByteRange,
+    /// The signature data, everything after the report and its size
+    pub auth_data: ByteRange,
+    pub ecdsa_signature: ByteRange,
+    pub attestation_key: ByteRange,
+    pub qe_report: ByteRange,
+    /// The PCK signature over the QE report
+    pub qe_report_signature: ByteRange,
+    pub qe_auth_data: ByteRange,
+    /// The certification data of the QE, usually the PCK certificate chain
+    pub cert_data: ByteRange,
+}
+
+/// A structural anomaly of a quote, see [`Quote::anomalies`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum Anomaly {
+    /// A reserved field is not all zeros
+    ReservedBitsSet { field: String },
+    /// The QE authentication data is not the usual 32 bytes
+    UnusualQeAuthDataSize { size: usize },
+    /// The certification data is not a PCK certificate chain
+    UnexpectedCertDataType { cert_type: u16 },
+    /// The QE vendor is not Intel
+    UnknownQeVendor,
+    /// There are bytes after the end of the quote
+    TrailingBytes { len: usize },
+}
+
+impl EnclaveReport {
+    fn reserved_anomalies(&self, name: &str, anomalies: &mut Vec<Anomaly>) {
+        let reserved: [(&str, &[u8]); 4] = [
+            ("reserved1", &self.reserved1),
+            ("reserved2", &self.reserved2),
+            ("reserved3", &self.reserved3),
+            ("reserved4", &self.reserved4),
+        ];
+        for (field, value) in reserved {
+            if value.iter().any(|b| *b != 0) {
+                anomalies.push(Anomaly::ReservedBitsSet {
+                    field: format!("{name}.{field}"),
+                });
+            }
+        }
+    }
+}
+
+fn zero_range(data: &mut [u8], start: usize, end: usize) -> Result<()> {
+    data.get_mut(start..end)
+        .ok_or(anyhow!("Quote is too short"))?
+        .fill(0);
+    Ok(())
 }
@@ -0,0 +1,89 @@
+//! RA-TLS certificates.
+//!
+//! An RA-TLS certificate embeds a quote in an X.509 extension, using Intel's SGX
+//! (`1.2.840.113741.1.13.1.0`) or TDX (`1.2.840.113741.1.5.5.1.6`) quote OID, or the
+//! `1.2.840.113741.1337.6` OID of Gramine, Occlum and RATS-TLS. The quote binds the
+//! certificate key by carrying the SHA-256 of the certificate's DER encoded
+//! `SubjectPublicKeyInfo` in the first 32 bytes of its `report_data`.
+
+use alloc::vec::Vec;
+use anyhow::{bail, Context, Result};
+use const_oid::ObjectIdentifier;
+use x509_cert::Certificate;
+
+use crate::constants::oids;
+use crate::quote::{Quote, Report};
+use crate::utils::MsgContext;
+use crate::verify::{verify_with_options, VerifiedReport, VerifyOptions};
+use crate::QuoteCollateralV3;
+
+const QUOTE_OIDS: [ObjectIdentifier; 3] = [
+    oids::RA_TLS_SGX_QUOTE,
+    oids::RA_TLS_TDX_QUOTE,
+    oids::RA_TLS_LEGACY_QUOTE,
+];
+
+/// Extract the quote from a DER encoded RA-TLS certificate.
+pub fn extract_quote(cert_der: &[u8]) -> Result<Vec<u8>> {
+    let cert: Certificate =
+        der::Decode::from_der(cert_der).msg_context("Failed to decode certificate")?;
+    let mut quotes = cert
+        .tbs_certificate
+        .extensions
+        .as_deref()
+        .unwrap_or(&[])
+        .iter()
+        .filter(|e| QUOTE_OIDS.contains(&e.extn_id))
+        .map(|e| e.extn_value.as_bytes());
+    let quote = quotes.next().context("Quote extension not found")?;
+    if quotes.next().is_some() {
+        bail!("Quote extension ambiguity");
+    }
+    Ok(quote.to_vec())
+}
+
+/// Extract and decode the quote of a DER encoded RA-TLS certificate, without verifying it.
+pub fn extract_quote_from_cert(cert_der: &[u8]) -> Result<Quote> {
+    Quote::parse(&extract_quote(cert_der)?).context("Failed to decode quote")
+}
+
+/// Check that a report binds the key of a DER encoded certificate.
+pub fn check_key_binding(cert_der: &[u8], report: &Report) -> Result<()> {
+    let cert: Certificate =
+        der::Decode::from_der(cert_der).msg_context("Failed to decode certificate")?;
+    let spki = der::Encode::to_der(&cert.tbs_certificate.subject_public_key_info)
+        .msg_context("Failed to encode public key")?;
+    let key_hash = ring::digest::digest(&ring::digest::SHA256, &spki);
+    if key_hash.as_ref() != &report.report_data().as_bytes()[..32] {
+        bail!("Certificate key is not bound to the quote");
+    }
+    Ok(())
+}
+
+/// Verify the quote of a DER encoded RA-TLS certificate and its binding to the certificate key.
+///
+/// # Arguments
+///
+/// * `cert_der` - The DER encoded RA-TLS certificate.
+/// * `quote_collateral` - The collateral of the embedded quote.
+/// * `now` - The current time in seconds since the Unix epoch
+pub fn verify_ra_tls_cert(
+    cert_der: &[u8],
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+) -> Result<VerifiedReport> {
+    verify_ra_tls_cert_with_options(cert_der, quote_collateral, now, &VerifyOptions::default())
+}
+
+/// Verify an RA-TLS certificate like [`verify_ra_tls_cert`], verifying its quote with `options`.
+pub fn verify_ra_tls_cert_with_options(
+    cert_der: &[u8],
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+    options: &VerifyOptions,
+) -> Result<VerifiedReport> {
+    let quote = extract_quote(cert_der)?;
+    let report = verify_with_options(&quote, quote_collateral, now, options)?;
+    check_key_binding(cert_der, &report.report)?;
+    Ok(report)
+}
@@ -0,0 +1,135 @@
+//! Verification results as a concise verdict with an optional evidence appendix.
+//!
+//! A [`VerifiedReport`] holds everything the verification found, which is more than most callers
+//! of an attestation API need. A [`VerdictRecord`] splits it into a small, stable [`Verdict`],
+//! with the TCB status, when the verdict expires and the hash of the policy applied, and the
+//! [`Evidence`] behind it: the parsed report, the digests of the collateral, the warnings and
+//! policy outcomes and, if verified with [`verify_traced`](crate::verify::verify_traced), the
+//! steps the verification ran. Which to include is chosen per record with [`Detail`], so APIs
+//! can return verdicts while audit logs keep the full record, e.g. in a
+//! [`ReportSink`](crate::sink::ReportSink) through
+//! [`sink::ReportRecord::of_verdict`](crate::sink::ReportRecord::of_verdict).
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+use crate::commitment::CollateralCommitment;
+use crate::policy::{PolicyReport, RuleOutcome};
+use crate::quote::Report;
+use crate::trace::VerifyTrace;
+use crate::verify::{PlatformId, SupplementalData, TcbStatus, VerifiedReport, Warning};
+use crate::CollateralSource;
+
+/// How much of a verification result a [`VerdictRecord`] holds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Detail {
+    /// The verdict only
+    #[default]
+    Verdict,
+    /// The verdict and its evidence
+    Full,
+}
+
+/// The outcome of a verification, without the evidence behind it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Verdict {
+    pub status: TcbStatus,
+    pub advisory_ids: Vec<String>,
+    /// When the verdict is to be renewed, the earliest `nextUpdate` of the collateral in seconds
+    /// since the Unix epoch. `None` if unknown, e.g. for appraisal tokens
+    pub expires_at: Option<u64>,
+    /// Whether the policy accepted the report, `None` if none was applied
+    #[serde(default)]
+    pub accepted: Option<bool>,
+    /// The hex encoded hash of the policy applied, as given by the verifier
+    #[serde(default)]
+    pub policy_hash: Option<String>,
+}
+
+/// The record a verdict was reached on, for audits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Evidence {
+    /// The verified enclave or TD report
+    pub report: Report,
+    #[serde(default)]
+    pub platform_id: Option<PlatformId>,
+    #[serde(default)]
+    pub supplemental: Option<SupplementalData>,
+    /// The digests of the collateral components the quote was verified against
+    pub collateral_commitment: CollateralCommitment,
+    /// Where the collateral was fetched from, empty if unknown
+    #[serde(default)]
+    pub provenance: Vec<CollateralSource>,
+    /// Non-fatal issues the verification proceeded despite
+    #[serde(default)]
+    pub warnings: Vec<Warning>,
+    /// The outcome of every rule of the policy applied, empty if none was
+    #[serde(default)]
+    pub policy_outcomes: Vec<RuleOutcome>,
+    /// The steps the verification ran, `None` if it was not traced
+    #[serde(default)]
+    pub trace: Option<VerifyTrace>,
+}
+
+/// A verdict, with its evidence if recorded in [`Detail::Full`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerdictRecord {
+    pub verdict: Verdict,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evidence: Option<Evidence>,
+}
+
+impl VerdictRecord {
+    /// The record of a verified report.
+    pub fn new(report: &VerifiedReport, detail: Detail) -> Self {
+        let verdict = Verdict {
+            status: report.status,
+            advisory_ids: report.advisory_ids.clone(),
+            expires_at: report
+                .supplemental
+                .as_ref()
+                .map(|supplemental| supplemental.earliest_expiration),
+            accepted: None,
+            policy_hash: None,
+        };
+        let evidence = (detail == Detail::Full).then(|| Evidence {
+            report: report.report.clone(),
+            platform_id: report.platform_id.clone(),
+            supplemental: report.supplemental.clone(),
+            collateral_commitment: report.collateral_commitment.clone(),
+            provenance: report.provenance.clone(),
+            warnings: report.warnings.clone(),
+            policy_outcomes: Vec::new(),
+            trace: None,
+        });
+        Self { verdict, evidence }
+    }
+
+    /// The record of a report evaluated against a policy, see [`Self::with_policy_hash`] to
+    /// identify the policy.
+    pub fn of_policy_report(report: &PolicyReport, detail: Detail) -> Self {
+        let mut record = Self::new(&report.report, detail);
+        record.verdict.accepted = Some(report.passed());
+        if let Some(evidence) = &mut record.evidence {
+            evidence.policy_outcomes = report.outcomes.clone();
+        }
+        record
+    }
+
+    /// Identify the policy applied by its hash, e.g. the SHA-256 of its source.
+    pub fn with_policy_hash(mut self, hash: &[u8]) -> Self {
+        self.verdict.policy_hash = Some(hex::encode(hash));
+        self
+    }
+
+    /// Attach the trace [`verify_traced`](crate::verify::verify_traced) returned for the report to
+    /// the evidence, if recorded.
+    pub fn with_trace(mut self, trace: VerifyTrace) -> Self {
+        if let Some(evidence) = &mut self.evidence {
+            evidence.trace = Some(trace);
+        }
+        self
+    }
+}
@@ -0,0 +1,212 @@
+//! Lookups of verified measurements in a registry of known workloads.
+//!
+//! [`verify`] establishes that a quote comes from a genuine platform, not which software it
+//! runs. A [`MeasurementRegistry`] maps the MRENCLAVE of an enclave or the MRTD of a TD to the
+//! [`Workload`] it belongs to and whether that workload may still be trusted.
+//! [`StaticRegistry`] holds the entries in memory, e.g. read from a JSON file, and
+//! [`HttpRegistry`] asks a registry service over any [`HttpClient`].
+//!
+//! [`verify_with_registry`] verifies a quote and looks up its measurement, failing for unknown
+//! and revoked workloads.
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use core::future::Future;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::collateral::HttpClient;
+use crate::types::Measurement;
+use crate::verify::{verify, VerifiedReport};
+use crate::QuoteCollateralV3;
+
+/// Whether a registered workload may be trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkloadStatus {
+    Allowed,
+    /// Still accepted, but superseded by a newer release
+    Deprecated,
+    /// No longer accepted, e.g. after a vulnerability
+    Revoked,
+}
+
+/// A workload registered under a measurement.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Workload {
+    /// The name of the workload, e.g. `key-manager`
+    pub name: String,
+    /// The release the measurement is of
+    #[serde(default)]
+    pub version: Option<String>,
+    pub status: WorkloadStatus,
+    /// Any further metadata of the registry, as registered
+    #[serde(default)]
+    pub metadata: Map<String, Value>,
+}
+
+/// A registry of the measurements of known workloads.
+///
+/// Lookups are async so registries can be remote; in-memory registries can return
+/// [`core::future::ready`].
+pub trait MeasurementRegistry {
+    /// Look up the workload registered under an MRENCLAVE or MRTD, `None` if it is unknown.
+    fn lookup(&self, measurement: &Measurement) -> impl Future<Output = Result<Option<Workload>>>;
+}
+
+/// A registry entry, as in the JSON files read by [`StaticRegistry`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    measurement: Measurement,
+    #[serde(flatten)]
+    workload: Workload,
+}
+
+/// A registry held in memory.
+///
+/// Its JSON form is an array of workloads, each with the hex encoded `measurement` it is
+/// registered under:
+///
+/// ```json
+/// [{ "measurement": "c4fd...", "name": "key-manager", "version": "1.2.0", "status": "allowed" }]
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StaticRegistry {
+    entries: Vec<Entry>,
+}
+
+impl StaticRegistry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a workload under a measurement, replacing any workload registered before.
+    pub fn register(mut self, measurement: Measurement, workload: Workload) -> Self {
+        self.entries
+            .retain(|entry| entry.measurement != measurement);
+        self.entries.push(Entry {
+            measurement,
+            workload,
+        });
+        self
+    }
+
+    /// Parse a registry from its JSON form.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let entries: Vec<Entry> = serde_json::from_str(json).context("Failed to parse registry")?;
+        Ok(entries.into_iter().fold(Self::new(), |registry, entry| {
+            registry.register(entry.measurement, entry.workload)
+        }))
+    }
+
+    /// Read a registry from a JSON file.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let json = std::fs::read_to_string(path).context("Failed to read registry file")?;
+        Self::from_json(&json)
+    }
+}
+
+impl MeasurementRegistry for StaticRegistry {
+    fn lookup(&self, measurement: &Measurement) -> impl Future<Output = Result<Option<Workload>>> {
+        let workload = self
+            .entries
+            .iter()
+            .find(|entry| entry.measurement == *measurement)
+            .map(|entry| entry.workload.clone());
+        core::future::ready(Ok(workload))
+    }
+}
+
+/// A registry service over HTTP.
+///
+/// `GET {base_url}/{measurement}`, with the measurement hex encoded, returns the JSON
+/// [`Workload`] registered under it, or status 404 if it is unknown.
+#[derive(Debug, Clone)]
+pub struct HttpRegistry<C> {
+    client: C,
+    base_url: String,
+}
+
+impl<C: HttpClient> HttpRegistry<C> {
+    /// A registry at `base_url`, asked over `client`.
+    pub fn new(client: C, base_url: impl Into<String>) -> Self {
+        Self {
+            client,
+            base_url: base_url.into(),
+        }
+    }
+}
+
+impl<C: HttpClient> MeasurementRegistry for HttpRegistry<C> {
+    async fn lookup(&self, measurement: &Measurement) -> Result<Option<Workload>> {
+        let url = format!("{}/{measurement}", self.base_url.trim_end_matches('/'));
+        let response = self.client.get(&url).await?;
+        match response.status {
+            200 => serde_json::from_slice(&response.body)
+                .context("Failed to decode workload")
+                .map(Some),
+            404 => Ok(None),
+            status => bail!("Registry returned status {status} for {url}"),
+        }
+    }
+}
+
+/// A verified report with the workload its measurement is registered as.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkloadReport {
+    pub report: VerifiedReport,
+    pub workload: Workload,
+}
+
+/// Look up the workload of a verified report.
+///
+/// # Returns
+///
+/// * `Ok(Workload)` - The workload, allowed or deprecated
+/// * `Err(Error)` - The measurement is unknown or its workload is revoked, or the lookup failed
+pub async fn lookup_workload<R: MeasurementRegistry>(
+    registry: &R,
+    report: &VerifiedReport,
+) -> Result<Workload> {
+    let measurement = report.report.measurement();
+    let Some(workload) = registry
+        .lookup(&measurement)
+        .await
+        .context("Failed to look up measurement")?
+    else {
+        bail!("Measurement {measurement} is not registered");
+    };
+    if workload.status == WorkloadStatus::Revoked {
+        bail!(
+            "Measurement {measurement} is of revoked workload {}",
+            workload.name
+        );
+    }
+    Ok(workload)
+}
+
+/// Verify a quote and look up the workload of its measurement.
+///
+/// # Arguments
+///
+/// * `raw_quote` - The raw quote to verify
+/// * `quote_collateral` - The collateral to verify the quote against
+/// * `now` - The current time in seconds since the Unix epoch
+/// * `registry` - The registry to look the measurement up in
+///
+/// # Returns
+///
+/// * `Ok(WorkloadReport)` - The verified report and its allowed or deprecated workload
+/// * `Err(Error)` - The quote failed to verify, or its workload is unknown or revoked
+pub async fn verify_with_registry<R: MeasurementRegistry>(
+    raw_quote: &[u8],
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+    registry: &R,
+) -> Result<WorkloadReport> {
+    let report = verify(raw_quote, quote_collateral, now)?;
+    let workload = lookup_workload(registry, &report).await?;
+    Ok(WorkloadReport { report, workload })
+}
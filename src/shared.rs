@@ -0,0 +1,663 @@
+//! A verifier handle shared across request handlers.
+//!
+//! [`SharedVerifier`] owns a collateral cache keyed by platform, i.e. by FMSPC, TEE type and the
+//! CA issuing the PCK certificate, whose CRL is cached with it. It fetches collateral on the first
+//! quote of each platform and, once [`spawn_refresher`](SharedVerifier::spawn_refresher) is
+//! called, re-fetches it in the background before it expires. Cloning the handle is cheap and
+//! [`verify`](SharedVerifier::verify) only takes a read lock on the cache.
+//!
+//! Concurrent fetches of the same platform's collateral are coalesced into one. Each replica
+//! refreshes at a jittered time within [`refresh_jitter`](SharedVerifierOptions::refresh_jitter)
+//! before `nextUpdate - refresh_margin`, so a fleet sharing a store spreads its refreshes and the
+//! first replica to refresh spares the others.
+//!
+//! Quotes are verified with [`verify_options`](SharedVerifierOptions::verify_options). With
+//! [`result_ttl`](SharedVerifierOptions::result_ttl) set, successful verifications are also
+//! cached by quote hash and a digest of those options. Whenever a refresh brings new collateral,
+//! the cached results it makes stale are dropped, so a cached verdict never outlives a TCB update
+//! or a revocation: those of the platform for a new TCB info or QE identity, those of every
+//! platform of the PCK CA for a new PCK CRL, and all of them for a new root CA CRL. CRLs can be
+//! updated any time, so the refresher also polls the collateral every
+//! [`crl_poll_interval`](SharedVerifierOptions::crl_poll_interval). Results are stored under keys
+//! prefixed by their CA and platform, so dropping them only lists those keys. Expired results are
+//! evicted by the refresher, or when next looked up.
+//!
+//! Collateral is fetched over reqwest by default, or through the [`CollateralFetcher`] passed to
+//! [`with_fetcher`](SharedVerifier::with_fetcher), e.g. over a custom
+//! [`HttpClient`](crate::collateral::HttpClient).
+//!
+//! Both caches live in a [`CacheStore`], in memory by default. Stores doing I/O are called on
+//! tokio's blocking threads, see [`CacheStore::blocking`]. Replicas sharing a store trust each
+//! other's cached results, so a shared store must only be writable by the verifiers.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::time::Duration;
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::collateral::{get_collateral_with_policy, pck_ca, pcs_url_for, EgressPolicy};
+use crate::freshness::{NonceSource, SystemNonceSource};
+use crate::limits::SizeLimits;
+use crate::pcs::PckCa;
+use crate::quote::Quote;
+use crate::store::{CacheStore, MemoryStore};
+use crate::types::Fmspc;
+use crate::verify::{
+    check_qe_identity_update, verify_collateral, verify_with_options, VerifiedReport,
+    VerifyOptions, Warning,
+};
+use crate::QuoteCollateralV3;
+
+/// Options for [`SharedVerifier`].
+#[derive(Debug, Clone)]
+pub struct SharedVerifierOptions {
+    /// The base URL of PCCS server. Intel PCS is used if not set.
+    pub pccs_url: Option<String>,
+    /// The timeout for each collateral request.
+    pub timeout: Duration,
+    /// How long before its `nextUpdate` the collateral is refreshed.
+    pub refresh_margin: Duration,
+    /// The window before `nextUpdate - refresh_margin` over which replicas spread their
+    /// refreshes. Should be well below `refresh_margin`.
+    pub refresh_jitter: Duration,
+    /// The longest the background refresher sleeps between checks for expiring collateral.
+    pub refresh_interval: Duration,
+    /// How often the background refresher re-fetches cached collateral for new CRLs, however
+    /// long before its `nextUpdate`. `None` only refreshes collateral as it expires.
+    pub crl_poll_interval: Option<Duration>,
+    /// How long past its `nextUpdate` cached collateral may still be used when refreshing it
+    /// fails. Reports verified with such collateral carry a [`Warning::StaleCollateral`].
+    ///
+    /// Defaults to `None`, failing closed.
+    pub max_staleness: Option<Duration>,
+    /// How long successful verification results are cached, bounded by the collateral's
+    /// `nextUpdate`.
+    ///
+    /// Defaults to `None`, not caching results.
+    pub result_ttl: Option<Duration>,
+    /// Restrictions on the hosts contacted and the proxy used
+    pub egress: EgressPolicy,
+    /// Check the signatures of fetched collateral before caching it, so a corrupted PCCS mirror
+    /// fails the refresh instead of every later verification. A QE identity rolling back the
+    /// cached one fails it too, see [`check_qe_identity_update`].
+    pub validate_on_fetch: bool,
+    /// The largest quote verified and collateral element cached. Larger store entries are
+    /// treated as misses.
+    pub limits: SizeLimits,
+    /// The options quotes are verified with. Cached results are only reused by verifiers with
+    /// the same options, and those accepting expired certificates only by this verifier, as
+    /// their predicate can't be compared.
+    pub verify_options: VerifyOptions,
+}
+
+impl Default for SharedVerifierOptions {
+    fn default() -> Self {
+        Self {
+            pccs_url: None,
+            timeout: Duration::from_secs(120),
+            refresh_margin: Duration::from_secs(24 * 60 * 60),
+            refresh_jitter: Duration::from_secs(60 * 60),
+            refresh_interval: Duration::from_secs(10 * 60),
+            crl_poll_interval: Some(Duration::from_secs(60 * 60)),
+            max_staleness: None,
+            result_ttl: None,
+            egress: EgressPolicy::default(),
+            validate_on_fetch: false,
+            limits: SizeLimits::default(),
+            verify_options: VerifyOptions::default(),
+        }
+    }
+}
+
+/// Where a [`SharedVerifier`] fetches collateral from.
+///
+/// Implement it over an [`HttpClient`](crate::collateral::HttpClient) with
+/// [`get_collateral_for_platform_with_client`](crate::collateral::get_collateral_for_platform_with_client),
+/// whose future is `Send` for clients whose futures are, so the refresher can run it.
+pub trait CollateralFetcher: Send + Sync {
+    /// Fetch the collateral of a platform from PCCS.
+    ///
+    /// # Arguments
+    ///
+    /// * `pccs_url` - The base URL of PCCS server, or of Intel PCS
+    /// * `fmspc` - The hex encoded FMSPC of the platform
+    /// * `pck_ca` - The CA issuing the PCK certificates of the platform, whose CRL to fetch
+    fn fetch<'a>(
+        &'a self,
+        pccs_url: &'a str,
+        fmspc: &'a str,
+        pck_ca: PckCa,
+    ) -> BoxFuture<'a, Result<QuoteCollateralV3>>;
+}
+
+/// The default fetcher, over reqwest with the timeout and egress policy of the options.
+struct ReqwestFetcher {
+    timeout: Duration,
+    egress: EgressPolicy,
+}
+
+impl CollateralFetcher for ReqwestFetcher {
+    fn fetch<'a>(
+        &'a self,
+        pccs_url: &'a str,
+        fmspc: &'a str,
+        pck_ca: PckCa,
+    ) -> BoxFuture<'a, Result<QuoteCollateralV3>> {
+        Box::pin(get_collateral_with_policy(
+            pccs_url,
+            fmspc,
+            Some(pck_ca),
+            self.timeout,
+            &self.egress,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+struct CacheKey {
+    fmspc: Fmspc,
+    is_sgx: bool,
+    /// The issuer of the PCK certificate, whose CRL is part of the collateral
+    pck_ca: PckCa,
+}
+
+impl CacheKey {
+    fn tee(&self) -> &'static str {
+        if self.is_sgx {
+            "sgx"
+        } else {
+            "tdx"
+        }
+    }
+
+    fn store_key(&self) -> Vec<u8> {
+        let ca = self.pck_ca.as_str();
+        format!("collateral/{}/{ca}/{}", self.tee(), hex::encode(self.fmspc)).into_bytes()
+    }
+
+    /// The prefix of the keys of the results verified with the collateral of the platform.
+    fn results_prefix(&self) -> Vec<u8> {
+        let mut prefix = ca_results_prefix(self.pck_ca);
+        prefix.extend_from_slice(format!("{}/{}/", self.tee(), hex::encode(self.fmspc)).as_bytes());
+        prefix
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    platform: CacheKey,
+    collateral: QuoteCollateralV3,
+    next_update: u64,
+    /// When the collateral was fetched, zero for entries cached before it was recorded
+    #[serde(default)]
+    fetched_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ResultEntry {
+    report: VerifiedReport,
+    expires_at: u64,
+}
+
+struct Inner {
+    options: SharedVerifierOptions,
+    store: Arc<dyn CacheStore>,
+    fetcher: Arc<dyn CollateralFetcher>,
+    /// Per replica randomness for the refresh jitter
+    jitter_seed: [u8; 8],
+    /// Locks serializing the fetches of each platform's collateral
+    refreshing: Mutex<BTreeMap<CacheKey, Arc<tokio::sync::Mutex<()>>>>,
+    /// The digest of the verify options, keying the cached results
+    options_digest: String,
+}
+
+/// A cheaply cloneable verifier with a shared collateral cache.
+#[derive(Clone)]
+pub struct SharedVerifier {
+    inner: Arc<Inner>,
+}
+
+impl SharedVerifier {
+    pub fn new(options: SharedVerifierOptions) -> Self {
+        Self::with_store(options, Arc::new(MemoryStore::default()))
+    }
+
+    /// Create a verifier keeping its caches in `store`.
+    pub fn with_store(options: SharedVerifierOptions, store: Arc<dyn CacheStore>) -> Self {
+        let fetcher = Arc::new(ReqwestFetcher {
+            timeout: options.timeout,
+            egress: options.egress.clone(),
+        });
+        Self::with_fetcher(options, store, fetcher)
+    }
+
+    /// Create a verifier keeping its caches in `store` and fetching collateral with `fetcher`.
+    ///
+    /// The `timeout` and `egress` options are up to the fetcher.
+    pub fn with_fetcher(
+        options: SharedVerifierOptions,
+        store: Arc<dyn CacheStore>,
+        fetcher: Arc<dyn CollateralFetcher>,
+    ) -> Self {
+        let mut jitter_seed = [0u8; 8];
+        if let Err(err) = SystemNonceSource.fill(&mut jitter_seed) {
+            log::warn!("Failed to seed refresh jitter: {err:?}");
+        }
+        let options_digest = options_digest(&options.verify_options, &jitter_seed);
+        Self {
+            inner: Arc::new(Inner {
+                options,
+                store,
+                fetcher,
+                jitter_seed,
+                refreshing: Mutex::new(BTreeMap::new()),
+                options_digest,
+            }),
+        }
+    }
+
+    /// Verify a quote at the current time with cached collateral.
+    pub async fn verify(&self, quote: &[u8]) -> Result<VerifiedReport> {
+        self.verify_at(quote, unix_now()?).await
+    }
+
+    /// Verify a quote at `now`, in seconds since the Unix epoch, with cached collateral.
+    ///
+    /// The verification itself is CPU bound, it runs on tokio's blocking threads so it doesn't
+    /// stall the tasks sharing the caller's worker thread.
+    pub async fn verify_at(&self, quote: &[u8], now: u64) -> Result<VerifiedReport> {
+        self.inner.options.limits.check_quote(quote)?;
+        let platform = platform_of(quote)?;
+        let result_key = result_key(&platform, &self.inner.options_digest, quote);
+        if let Some(entry) = self.load::<ResultEntry>(&result_key).await {
+            if now < entry.expires_at {
+                return Ok(entry.report);
+            }
+            self.remove(&result_key).await;
+        }
+        let (collateral, warning) = self.lookup(platform, now).await?;
+        let raw_quote = quote.to_vec();
+        let checked = collateral.clone();
+        let inner = self.inner.clone();
+        let mut report = tokio::task::spawn_blocking(move || {
+            verify_with_options(&raw_quote, &checked, now, &inner.options.verify_options)
+        })
+        .await
+        .context("Verification task failed")??;
+        // Stale collateral is already flagged by the verifier itself
+        if let Some(warning) = warning.filter(|warning| !report.warnings.contains(warning)) {
+            report.warnings.push(warning);
+        }
+        if let Some(ttl) = self.inner.options.result_ttl {
+            // Results verified with stale collateral are not worth keeping
+            let stale = report
+                .warnings
+                .iter()
+                .any(|warning| matches!(warning, Warning::StaleCollateral { .. }));
+            if !stale {
+                let next_update = collateral.next_update()?;
+                let entry = ResultEntry {
+                    report: report.clone(),
+                    expires_at: next_update.min(now.saturating_add(ttl.as_secs())),
+                };
+                self.save(&result_key, &entry).await;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Get the collateral for a quote, fetching it if it is not cached or expired at `now`.
+    pub async fn collateral_for(&self, quote: &[u8], now: u64) -> Result<Arc<QuoteCollateralV3>> {
+        Ok(self.lookup(platform_of(quote)?, now).await?.0)
+    }
+
+    /// When this replica next refreshes the cached collateral of the quote's platform, in
+    /// seconds since the Unix epoch. `None` if it is not cached.
+    ///
+    /// That is [`refresh_margin`](SharedVerifierOptions::refresh_margin) and a jitter of up to
+    /// [`refresh_jitter`](SharedVerifierOptions::refresh_jitter) before its `nextUpdate`, or
+    /// [`crl_poll_interval`](SharedVerifierOptions::crl_poll_interval) after it was fetched if
+    /// that is earlier.
+    pub async fn next_refresh(&self, quote: &[u8]) -> Result<Option<u64>> {
+        let key = platform_of(quote)?;
+        let entry = self.load::<CacheEntry>(&key.store_key()).await;
+        Ok(entry.map(|entry| self.refresh_due(&entry)))
+    }
+
+    async fn lookup(
+        &self,
+        key: CacheKey,
+        now: u64,
+    ) -> Result<(Arc<QuoteCollateralV3>, Option<Warning>)> {
+        let cached = match self.load::<CacheEntry>(&key.store_key()).await {
+            Some(entry) if now < entry.next_update => {
+                return Ok((Arc::new(entry.collateral), None))
+            }
+            cached => cached,
+        };
+        let err = match self
+            .refresh_if(key, now, |entry| now >= entry.next_update)
+            .await
+        {
+            Ok(collateral) => return Ok((collateral, None)),
+            Err(err) => err,
+        };
+        let (Some(entry), Some(max_staleness)) = (cached, self.inner.options.max_staleness) else {
+            return Err(err);
+        };
+        let expired_for = now - entry.next_update;
+        if expired_for > max_staleness.as_secs() {
+            return Err(err.context("Cached collateral is too stale"));
+        }
+        log::warn!("Using collateral expired for {expired_for}s: {err:?}");
+        Ok((
+            Arc::new(entry.collateral),
+            Some(Warning::StaleCollateral { expired_for }),
+        ))
+    }
+
+    /// Spawn a task on the current tokio runtime that refreshes collateral before it expires, and
+    /// polls it for new CRLs.
+    pub fn spawn_refresher(&self) -> tokio::task::JoinHandle<()> {
+        let verifier = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let interval = verifier.inner.options.refresh_interval;
+                let next_due = match unix_now() {
+                    Ok(now) => verifier.refresh_expiring(now).await,
+                    Err(_) => None,
+                };
+                tokio::time::sleep(next_due.map_or(interval, |due| due.min(interval))).await;
+            }
+        })
+    }
+
+    /// Refresh the cached collateral that is due at `now`, in seconds since the Unix epoch,
+    /// returning how long until the next entry is. Expired results are evicted along the way.
+    ///
+    /// The background refresher calls it at the current time, call it directly to drive refreshes
+    /// from a scheduler of your own instead.
+    pub async fn refresh_expiring(&self, now: u64) -> Option<Duration> {
+        if let Err(err) = self.evict_results(now).await {
+            log::warn!("Failed to evict expired results: {err:?}");
+        }
+        let keys = match self.store_call(|store| store.keys(b"collateral/")).await {
+            Ok(keys) => keys,
+            Err(err) => {
+                log::warn!("Failed to list cached collateral: {err:?}");
+                return None;
+            }
+        };
+        let mut next_due = None::<u64>;
+        for key in &keys {
+            let Some(entry) = self.load::<CacheEntry>(key).await else {
+                continue;
+            };
+            let due = self.refresh_due(&entry);
+            if due > now {
+                next_due = Some(next_due.map_or(due, |next| next.min(due)));
+                continue;
+            }
+            // Another replica may have refreshed it meanwhile
+            let result = self
+                .refresh_if(entry.platform, now, |cached| {
+                    self.refresh_due(cached) <= now
+                })
+                .await;
+            if let Err(err) = result {
+                log::warn!("Failed to refresh collateral: {err:?}");
+            }
+        }
+        next_due.map(|due| Duration::from_secs(due - now))
+    }
+
+    /// When this replica refreshes a cache entry: `refresh_margin` and a jitter stable for the
+    /// entry before its `nextUpdate`, or `crl_poll_interval` after it was fetched if earlier.
+    fn refresh_due(&self, entry: &CacheEntry) -> u64 {
+        let options = &self.inner.options;
+        let jitter = options.refresh_jitter.as_secs();
+        let offset = if jitter == 0 {
+            0
+        } else {
+            let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+            context.update(&self.inner.jitter_seed);
+            context.update(&entry.platform.store_key());
+            context.update(&entry.next_update.to_le_bytes());
+            let digest = context.finish();
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&digest.as_ref()[..8]);
+            u64::from_le_bytes(bytes) % jitter
+        };
+        let due = entry
+            .next_update
+            .saturating_sub(options.refresh_margin.as_secs())
+            .saturating_sub(offset);
+        match options.crl_poll_interval {
+            Some(interval) => due.min(entry.fetched_at.saturating_add(interval.as_secs())),
+            None => due,
+        }
+    }
+
+    /// Fetch the collateral of a platform at `now`, unless the cached entry, loaded once
+    /// concurrent fetches of the platform are done, doesn't `need_refresh`.
+    async fn refresh_if(
+        &self,
+        key: CacheKey,
+        now: u64,
+        need_refresh: impl Fn(&CacheEntry) -> bool,
+    ) -> Result<Arc<QuoteCollateralV3>> {
+        let lock = {
+            let mut refreshing = self
+                .inner
+                .refreshing
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            refreshing.entry(key).or_default().clone()
+        };
+        let _guard = lock.lock().await;
+        if let Some(entry) = self.load::<CacheEntry>(&key.store_key()).await {
+            if !need_refresh(&entry) {
+                return Ok(Arc::new(entry.collateral));
+            }
+        }
+        self.refresh(key, now).await
+    }
+
+    /// Run a store call, on a blocking thread if the store blocks on I/O.
+    async fn store_call<T: Send + 'static>(
+        &self,
+        call: impl FnOnce(&dyn CacheStore) -> Result<T> + Send + 'static,
+    ) -> Result<T> {
+        let store = self.inner.store.clone();
+        if !store.blocking() {
+            return call(&*store);
+        }
+        tokio::task::spawn_blocking(move || call(&*store))
+            .await
+            .context("Cache store call panicked")?
+    }
+
+    /// Load a cache entry. A store or decoding failure is logged and treated as a miss.
+    async fn load<T: DeserializeOwned>(&self, key: &[u8]) -> Option<T> {
+        let key = key.to_vec();
+        let value = match self.store_call(move |store| store.get(&key)).await {
+            Ok(value) => value?,
+            Err(err) => {
+                log::warn!("Failed to read cache: {err:?}");
+                return None;
+            }
+        };
+        if value.len() > self.inner.options.limits.max_cache_entry_size() {
+            log::warn!("Ignoring cache entry of {} bytes", value.len());
+            return None;
+        }
+        serde_json::from_slice(&value)
+            .map_err(|err| log::warn!("Failed to decode cache entry: {err:?}"))
+            .ok()
+    }
+
+    /// Remove a cache entry. A store failure is logged, the entry is ignored once expired anyway.
+    async fn remove(&self, key: &[u8]) {
+        let key = key.to_vec();
+        if let Err(err) = self.store_call(move |store| store.remove(&key)).await {
+            log::warn!("Failed to remove cache entry: {err:?}");
+        }
+    }
+
+    /// Save a cache entry. A store failure is logged, caching is best effort.
+    async fn save<T: Serialize>(&self, key: &[u8], value: &T) {
+        let result = match serde_json::to_vec(value) {
+            Ok(value) => {
+                let key = key.to_vec();
+                self.store_call(move |store| store.put(&key, &value)).await
+            }
+            Err(err) => Err(err.into()),
+        };
+        if let Err(err) = result {
+            log::warn!("Failed to write cache: {err:?}");
+        }
+    }
+
+    async fn refresh(&self, key: CacheKey, now: u64) -> Result<Arc<QuoteCollateralV3>> {
+        let pccs_url = match self.inner.options.pccs_url.as_deref() {
+            Some(url) if !url.is_empty() => url,
+            _ => pcs_url_for(key.is_sgx),
+        };
+        let collateral = self
+            .inner
+            .fetcher
+            .fetch(pccs_url, &hex::encode_upper(key.fmspc), key.pck_ca)
+            .await?;
+        self.inner.options.limits.check_collateral(&collateral)?;
+        let previous = self.load::<CacheEntry>(&key.store_key()).await;
+        if self.inner.options.validate_on_fetch {
+            verify_collateral(&collateral, unix_now()?).context("Fetched invalid collateral")?;
+            if let Some(previous) = &previous {
+                check_qe_identity_update(&previous.collateral.qe_identity, &collateral.qe_identity)
+                    .context("Fetched QE identity does not update the cached one")?;
+            }
+        }
+        let entry = CacheEntry {
+            platform: key,
+            next_update: collateral.next_update()?,
+            collateral,
+            fetched_at: now,
+        };
+        self.save(&key.store_key(), &entry).await;
+        let stale = previous.and_then(|previous| {
+            stale_results_prefix(&key, &previous.collateral, &entry.collateral)
+        });
+        if let Some(prefix) = stale {
+            self.invalidate_results(prefix).await?;
+        }
+        Ok(Arc::new(entry.collateral))
+    }
+
+    /// Drop the cached results whose keys start with `prefix`, without reading them.
+    async fn invalidate_results(&self, prefix: Vec<u8>) -> Result<()> {
+        for key in self.store_call(move |store| store.keys(&prefix)).await? {
+            self.store_call(move |store| store.remove(&key)).await?;
+        }
+        Ok(())
+    }
+
+    /// Drop the cached results expired at `now`, and those that fail to decode.
+    ///
+    /// As expiry isn't part of the keys, every cached result is read. Only the refresher does it,
+    /// lookups drop the expired results they hit.
+    async fn evict_results(&self, now: u64) -> Result<()> {
+        for key in self.store_call(|store| store.keys(b"result/")).await? {
+            let entry = self.load::<ResultEntry>(&key).await;
+            if entry.map_or(true, |entry| now >= entry.expires_at) {
+                self.store_call(move |store| store.remove(&key)).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The prefix of the keys of the cached results a collateral update of the platform `key` makes
+/// stale, `None` if the update carries the same signed elements, whenever and wherever they were
+/// fetched from.
+fn stale_results_prefix(
+    key: &CacheKey,
+    previous: &QuoteCollateralV3,
+    new: &QuoteCollateralV3,
+) -> Option<Vec<u8>> {
+    if previous.root_ca_crl != new.root_ca_crl {
+        // Revokes PCK CAs and collateral signing certificates of every platform
+        return Some(b"result/".to_vec());
+    }
+    if previous.pck_crl != new.pck_crl {
+        return Some(ca_results_prefix(key.pck_ca));
+    }
+    let same_platform_content = previous.tcb_info_issuer_chain == new.tcb_info_issuer_chain
+        && previous.tcb_info == new.tcb_info
+        && previous.tcb_info_signature == new.tcb_info_signature
+        && previous.qe_identity_issuer_chain == new.qe_identity_issuer_chain
+        && previous.qe_identity == new.qe_identity
+        && previous.qe_identity_signature == new.qe_identity_signature;
+    (!same_platform_content).then(|| key.results_prefix())
+}
+
+/// The prefix of the keys of the results of the platforms whose PCK certificates `pck_ca` issues.
+fn ca_results_prefix(pck_ca: PckCa) -> Vec<u8> {
+    format!("result/{}/", pck_ca.as_str()).into_bytes()
+}
+
+/// The platform of a quote, keying its collateral.
+fn platform_of(quote: &[u8]) -> Result<CacheKey> {
+    let quote = Quote::parse(quote).context("Failed to parse quote")?;
+    Ok(CacheKey {
+        fmspc: quote.fmspc().context("Failed to get FMSPC")?,
+        is_sgx: quote.header.is_sgx(),
+        pck_ca: pck_ca(&quote)?,
+    })
+}
+
+fn result_key(platform: &CacheKey, options_digest: &str, quote: &[u8]) -> Vec<u8> {
+    let digest = ring::digest::digest(&ring::digest::SHA256, quote);
+    let mut key = platform.results_prefix();
+    key.extend_from_slice(format!("{options_digest}/{}", hex::encode(digest.as_ref())).as_bytes());
+    key
+}
+
+/// A digest of verify options, salted with `seed` if they accept expired certificates.
+fn options_digest(options: &VerifyOptions, seed: &[u8; 8]) -> String {
+    // Listing the fields makes new options fail to build until they are keyed
+    let VerifyOptions {
+        require_crls,
+        min_tcb_evaluation_data_number,
+        accept_expired_cert,
+        collateral_grace_period,
+        allowed_tcb_statuses,
+        out_of_date_as_warning,
+        root_ca,
+    } = options;
+    let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+    context.update(
+        format!(
+            "{require_crls:?}/{min_tcb_evaluation_data_number:?}/{collateral_grace_period:?}/\
+             {allowed_tcb_statuses:?}/{out_of_date_as_warning:?}/{root_ca:?}"
+        )
+        .as_bytes(),
+    );
+    if accept_expired_cert.is_some() {
+        context.update(seed);
+    }
+    hex::encode(&context.finish().as_ref()[..16])
+}
+
+fn unix_now() -> Result<u64> {
+    Ok(SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .context("Failed to get current time")?
+        .as_secs())
+}
@@ -0,0 +1,121 @@
+//! Pluggable ECDSA signature verification.
+//!
+//! The verifier checks three ECDSA-P256 signatures per quote: the TCB info signature by the TCB
+//! signing key, the QE report signature by the PCK key and the quote signature by the attestation
+//! key. Implementing [`SignatureVerifier`] lets embedders offload these checks to hardware (HSM,
+//! secure element) or to host-side precompiles in blockchain runtimes, while the crate keeps
+//! orchestrating the overall verification. [`RingVerifier`] is used by default.
+//!
+//! Certificate chain signatures are still checked by `webpki`.
+//!
+//! Collateral signatures are checked with the [`SignatureAlgorithm`] of the signing certificate's
+//! key, so collateral signed with ECDSA-P384 verifies once Intel adopts it.
+
+use anyhow::{anyhow, bail, Context, Result};
+
+/// The algorithm of a collateral signature, given by the curve of the signing key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureAlgorithm {
+    EcdsaP256Sha256,
+    EcdsaP384Sha384,
+}
+
+impl SignatureAlgorithm {
+    /// The length of an uncompressed `X || Y` public key.
+    pub fn public_key_len(&self) -> usize {
+        match self {
+            SignatureAlgorithm::EcdsaP256Sha256 => 64,
+            SignatureAlgorithm::EcdsaP384Sha384 => 96,
+        }
+    }
+
+    /// The length of a raw `r || s` signature.
+    pub fn signature_len(&self) -> usize {
+        self.public_key_len()
+    }
+}
+
+/// Verifies ECDSA signatures on behalf of the quote verifier.
+pub trait SignatureVerifier {
+    /// Verify an ECDSA-P256 signature over the SHA-256 digest of `message`.
+    ///
+    /// * `public_key` - The uncompressed public key as `X || Y`
+    /// * `signature` - The raw signature as `r || s`
+    fn verify_p256_sha256(
+        &self,
+        public_key: &[u8; 64],
+        message: &[u8],
+        signature: &[u8; 64],
+    ) -> Result<()>;
+
+    /// Verify an ECDSA-P384 signature over the SHA-384 digest of `message`.
+    ///
+    /// * `public_key` - The uncompressed public key as `X || Y`
+    /// * `signature` - The raw signature as `r || s`
+    fn verify_p384_sha384(
+        &self,
+        public_key: &[u8; 96],
+        message: &[u8],
+        signature: &[u8; 96],
+    ) -> Result<()> {
+        let _ = (public_key, message, signature);
+        bail!("ECDSA-P384 is not supported by this verifier")
+    }
+}
+
+/// Verify a signature with the given algorithm.
+pub(crate) fn verify_with_algorithm(
+    verifier: &dyn SignatureVerifier,
+    algorithm: SignatureAlgorithm,
+    public_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> Result<()> {
+    if signature.len() != algorithm.signature_len() {
+        bail!("Invalid signature length for {algorithm:?}");
+    }
+    match algorithm {
+        SignatureAlgorithm::EcdsaP256Sha256 => verifier.verify_p256_sha256(
+            public_key.try_into().context("Invalid public key length")?,
+            message,
+            signature.try_into()?,
+        ),
+        SignatureAlgorithm::EcdsaP384Sha384 => verifier.verify_p384_sha384(
+            public_key.try_into().context("Invalid public key length")?,
+            message,
+            signature.try_into()?,
+        ),
+    }
+}
+
+/// The default [`SignatureVerifier`], backed by `ring`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RingVerifier;
+
+impl SignatureVerifier for RingVerifier {
+    fn verify_p256_sha256(
+        &self,
+        public_key: &[u8; 64],
+        message: &[u8],
+        signature: &[u8; 64],
+    ) -> Result<()> {
+        let mut pub_key = [0x04u8; 65]; //Prepend 0x04 to specify uncompressed format
+        pub_key[1..].copy_from_slice(public_key);
+        ring::signature::UnparsedPublicKey::new(&ring::signature::ECDSA_P256_SHA256_FIXED, pub_key)
+            .verify(message, signature)
+            .map_err(|_| anyhow!("Invalid signature"))
+    }
+
+    fn verify_p384_sha384(
+        &self,
+        public_key: &[u8; 96],
+        message: &[u8],
+        signature: &[u8; 96],
+    ) -> Result<()> {
+        let mut pub_key = [0x04u8; 97];
+        pub_key[1..].copy_from_slice(public_key);
+        ring::signature::UnparsedPublicKey::new(&ring::signature::ECDSA_P384_SHA384_FIXED, pub_key)
+            .verify(message, signature)
+            .map_err(|_| anyhow!("Invalid signature"))
+    }
+}
@@ -0,0 +1,143 @@
+//! Signed verification reports, for verifiers handing their verdicts on.
+//!
+//! A [`SignedReport`] is a single JSON document carrying the report exactly as signed, the
+//! signature over it and the DER encoded `SubjectPublicKeyInfo` of the signing key. Relying
+//! parties check it against the key they pin with [`SignedReport::verify_with_key`]. Since the
+//! report and its signature travel together, a saved report is never left without the signature
+//! asked for.
+//!
+//! A verifier running in a TEE can attach its own quote instead of having its key pinned, the
+//! attested verifier mode. The quote binds the signing key the RA-TLS way: the first 32 bytes of
+//! its report data are the SHA-256 of the key's `SubjectPublicKeyInfo`.
+//! [`SignedReport::verify_chained`] verifies that quote as any other, and returns its report for
+//! the relying party to check the verifier's measurements against.
+//!
+//! Byte fields are hex strings, like those of [`QuoteCollateralV3`].
+
+use alloc::vec::Vec;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use x509_cert::spki::SubjectPublicKeyInfoOwned;
+
+use crate::verify::{verify_with_options, VerifiedReport, VerifyOptions};
+use crate::QuoteCollateralV3;
+
+/// The algorithm of a report signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SignatureScheme {
+    /// ECDSA over P-256 with SHA-256, the signature DER encoded
+    EcdsaNistp256Sha256,
+    /// ECDSA over P-384 with SHA-384, the signature DER encoded
+    EcdsaNistp384Sha384,
+    Ed25519,
+    /// RSASSA-PSS with SHA-256, for keys of 2048 to 8192 bits
+    RsaPssSha256,
+}
+
+impl SignatureScheme {
+    fn algorithm(&self) -> &'static dyn ring::signature::VerificationAlgorithm {
+        match self {
+            SignatureScheme::EcdsaNistp256Sha256 => &ring::signature::ECDSA_P256_SHA256_ASN1,
+            SignatureScheme::EcdsaNistp384Sha384 => &ring::signature::ECDSA_P384_SHA384_ASN1,
+            SignatureScheme::Ed25519 => &ring::signature::ED25519,
+            SignatureScheme::RsaPssSha256 => &ring::signature::RSA_PSS_2048_8192_SHA256,
+        }
+    }
+}
+
+/// A report with its signature and the key that made it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedReport {
+    /// The JSON report, the exact bytes signed
+    #[serde(with = "serde_bytes")]
+    pub report: Vec<u8>,
+    pub scheme: SignatureScheme,
+    #[serde(with = "serde_bytes")]
+    pub signature: Vec<u8>,
+    /// The DER encoded `SubjectPublicKeyInfo` of the signing key
+    #[serde(with = "serde_bytes")]
+    pub public_key: Vec<u8>,
+    /// The quote of the verifier that signed the report, binding its key
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "crate::optional_bytes"
+    )]
+    pub verifier_quote: Option<Vec<u8>>,
+}
+
+impl SignedReport {
+    /// Decode a signed report document.
+    pub fn from_json(json: &[u8]) -> Result<Self> {
+        serde_json::from_slice(json).context("Failed to decode signed report")
+    }
+
+    /// Encode the signed report as pretty printed JSON.
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self).context("Failed to encode signed report")
+    }
+
+    /// Check the signature against the embedded key, returning the report.
+    ///
+    /// This only proves the report wasn't altered since it was signed by whoever holds the key.
+    /// Check the key itself too, with [`verify_with_key`](Self::verify_with_key).
+    pub fn verify_signature(&self) -> Result<serde_json::Value> {
+        let spki = <SubjectPublicKeyInfoOwned as der::Decode>::from_der(&self.public_key)
+            .map_err(|err| anyhow!("Failed to decode report signing key: {err}"))?;
+        let key = spki
+            .subject_public_key
+            .as_bytes()
+            .context("Invalid report signing key")?;
+        ring::signature::UnparsedPublicKey::new(self.scheme.algorithm(), key)
+            .verify(&self.report, &self.signature)
+            .map_err(|_| anyhow!("Invalid report signature"))?;
+        serde_json::from_slice(&self.report).context("Failed to decode signed report JSON")
+    }
+
+    /// Check the report was signed by the pinned key, given as its DER encoded
+    /// `SubjectPublicKeyInfo`, returning the report.
+    pub fn verify_with_key(&self, public_key: &[u8]) -> Result<serde_json::Value> {
+        if self.public_key != public_key {
+            bail!("Report is not signed by the trusted key");
+        }
+        self.verify_signature()
+    }
+
+    /// Check the report was signed by an attested verifier, returning the report and the
+    /// verified report of the verifier's quote.
+    ///
+    /// # Arguments
+    ///
+    /// * `quote_collateral` - The collateral of the verifier's quote
+    /// * `now` - The current time in seconds since the Unix epoch
+    /// * `options` - The options to verify the verifier's quote with
+    pub fn verify_chained(
+        &self,
+        quote_collateral: &QuoteCollateralV3,
+        now: u64,
+        options: &VerifyOptions,
+    ) -> Result<(serde_json::Value, VerifiedReport)> {
+        let quote = self
+            .verifier_quote
+            .as_deref()
+            .context("Signed report carries no verifier quote")?;
+        let report = self.verify_signature()?;
+        let verifier = verify_with_options(quote, quote_collateral, now, options)
+            .context("Failed to verify verifier quote")?;
+        if self.key_hash() != verifier.report.report_data().as_bytes()[..32] {
+            bail!("Report signing key is not bound to the verifier quote");
+        }
+        Ok((report, verifier))
+    }
+
+    /// The SHA-256 of the signing key's `SubjectPublicKeyInfo`, which the first 32 bytes of the
+    /// verifier quote's report data must be.
+    pub fn key_hash(&self) -> [u8; 32] {
+        let digest = ring::digest::digest(&ring::digest::SHA256, &self.public_key);
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(digest.as_ref());
+        hash
+    }
+}
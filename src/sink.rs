@@ -0,0 +1,247 @@
+//! Persistence of verification decisions, for services that archive every decision.
+//!
+//! A [`ReportSink`] receives [`ReportRecord`]s, each the outcome of verifying one quote.
+//! [`DirectorySink`] writes one JSON file per record, [`NdjsonSink`] appends records to a
+//! newline delimited JSON file and [`ObjectSink`] writes batches to an S3-style object store
+//! through a minimal [`ObjectStore`] client trait. Wrapping a sink in a [`BatchingSink`] buffers
+//! records and hands them over in batches.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::record::VerdictRecord;
+use crate::verify::VerifiedReport;
+
+/// The outcome of verifying one quote.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportRecord {
+    /// The verification time in seconds since the Unix epoch
+    pub verified_at: u64,
+    /// The SHA-256 of the raw quote
+    #[serde(with = "serde_bytes")]
+    pub quote_hash: [u8; 32],
+    pub outcome: Outcome,
+}
+
+/// Whether a quote was accepted, with the report or the reason it was rejected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Verified(VerifiedReport),
+    /// Verified, recorded as a verdict with its evidence if asked for
+    Recorded(VerdictRecord),
+    Rejected {
+        error: String,
+    },
+}
+
+impl ReportRecord {
+    /// Record the result of verifying `raw_quote` at `now`.
+    pub fn new(raw_quote: &[u8], now: u64, result: &Result<VerifiedReport>) -> Self {
+        let outcome = match result {
+            Ok(report) => Outcome::Verified(report.clone()),
+            Err(err) => Outcome::Rejected {
+                error: format!("{err:#}"),
+            },
+        };
+        Self::with_outcome(raw_quote, now, outcome)
+    }
+
+    /// Record the verdict on `raw_quote` reached at `now`, e.g. of a policy report, keeping only
+    /// the detail of the [`VerdictRecord`].
+    pub fn of_verdict(raw_quote: &[u8], now: u64, record: VerdictRecord) -> Self {
+        Self::with_outcome(raw_quote, now, Outcome::Recorded(record))
+    }
+
+    fn with_outcome(raw_quote: &[u8], now: u64, outcome: Outcome) -> Self {
+        let digest = ring::digest::digest(&ring::digest::SHA256, raw_quote);
+        let mut quote_hash = [0u8; 32];
+        quote_hash.copy_from_slice(digest.as_ref());
+        Self {
+            verified_at: now,
+            quote_hash,
+            outcome,
+        }
+    }
+
+    /// A name for the record that is unique per quote and verification time.
+    pub fn name(&self) -> String {
+        format!("{}-{}", self.verified_at, hex::encode(self.quote_hash))
+    }
+}
+
+/// A destination for verification records.
+pub trait ReportSink: Send + Sync {
+    /// Persist a batch of records.
+    fn write(&self, records: &[ReportRecord]) -> Result<()>;
+
+    /// Persist any records buffered by the sink.
+    fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes each record to its own JSON file in a directory.
+#[derive(Debug, Clone)]
+pub struct DirectorySink {
+    root: PathBuf,
+}
+
+impl DirectorySink {
+    /// Open the sink at `root`, creating the directory if needed.
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(&root).context("Failed to create report directory")?;
+        Ok(Self { root })
+    }
+}
+
+impl ReportSink for DirectorySink {
+    fn write(&self, records: &[ReportRecord]) -> Result<()> {
+        for record in records {
+            let path = self.root.join(format!("{}.json", record.name()));
+            let json = serde_json::to_vec(record).context("Failed to encode report record")?;
+            std::fs::write(path, json).context("Failed to write report record")?;
+        }
+        Ok(())
+    }
+}
+
+/// Appends records to a newline delimited JSON file.
+#[derive(Debug)]
+pub struct NdjsonSink {
+    file: Mutex<File>,
+}
+
+impl NdjsonSink {
+    /// Open the file at `path` for appending, creating it if needed.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path.into())
+            .context("Failed to open report file")?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl ReportSink for NdjsonSink {
+    fn write(&self, records: &[ReportRecord]) -> Result<()> {
+        let lines = encode_ndjson(records)?;
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        // One write per batch, so concurrent writers don't interleave lines
+        file.write_all(&lines)
+            .context("Failed to write report records")
+    }
+
+    fn flush(&self) -> Result<()> {
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        file.flush().context("Failed to flush report file")
+    }
+}
+
+/// A client of an S3-style object store, implemented over the SDK of the store in use.
+pub trait ObjectStore: Send + Sync {
+    /// Store `body` under `key`.
+    fn put_object(&self, key: &str, body: &[u8]) -> Result<()>;
+}
+
+/// Writes each batch of records as one NDJSON object to an object store.
+///
+/// Objects are keyed `<prefix><name of the first record>-<number of records>.ndjson`, so they
+/// sort by time.
+pub struct ObjectSink<S> {
+    store: S,
+    prefix: String,
+}
+
+impl<S: ObjectStore> ObjectSink<S> {
+    /// Write to `store` under keys starting with `prefix`, e.g. `"reports/"`.
+    pub fn new(store: S, prefix: impl Into<String>) -> Self {
+        Self {
+            store,
+            prefix: prefix.into(),
+        }
+    }
+}
+
+impl<S: ObjectStore> ReportSink for ObjectSink<S> {
+    fn write(&self, records: &[ReportRecord]) -> Result<()> {
+        let Some(first) = records.first() else {
+            return Ok(());
+        };
+        let key = format!("{}{}-{}.ndjson", self.prefix, first.name(), records.len());
+        self.store
+            .put_object(&key, &encode_ndjson(records)?)
+            .with_context(|| format!("Failed to put {key}"))
+    }
+}
+
+/// Buffers records and writes them to the inner sink once `max_records` have accumulated, on
+/// [`ReportSink::flush`] and when dropped.
+pub struct BatchingSink<S: ReportSink> {
+    inner: S,
+    max_records: usize,
+    buffer: Mutex<Vec<ReportRecord>>,
+}
+
+impl<S: ReportSink> BatchingSink<S> {
+    pub fn new(inner: S, max_records: usize) -> Self {
+        Self {
+            inner,
+            max_records: max_records.max(1),
+            buffer: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn take_buffer(&self) -> Vec<ReportRecord> {
+        let mut buffer = self.buffer.lock().unwrap_or_else(PoisonError::into_inner);
+        core::mem::take(&mut *buffer)
+    }
+}
+
+impl<S: ReportSink> ReportSink for BatchingSink<S> {
+    fn write(&self, records: &[ReportRecord]) -> Result<()> {
+        let batch = {
+            let mut buffer = self.buffer.lock().unwrap_or_else(PoisonError::into_inner);
+            buffer.extend_from_slice(records);
+            if buffer.len() < self.max_records {
+                return Ok(());
+            }
+            core::mem::take(&mut *buffer)
+        };
+        self.inner.write(&batch)
+    }
+
+    fn flush(&self) -> Result<()> {
+        let batch = self.take_buffer();
+        if !batch.is_empty() {
+            self.inner.write(&batch)?;
+        }
+        self.inner.flush()
+    }
+}
+
+impl<S: ReportSink> Drop for BatchingSink<S> {
+    fn drop(&mut self) {
+        if let Err(err) = self.flush() {
+            log::warn!("Failed to flush report records: {err:?}");
+        }
+    }
+}
+
+fn encode_ndjson(records: &[ReportRecord]) -> Result<Vec<u8>> {
+    let mut lines = Vec::new();
+    for record in records {
+        serde_json::to_writer(&mut lines, record).context("Failed to encode report record")?;
+        lines.push(b'\n');
+    }
+    Ok(lines)
+}
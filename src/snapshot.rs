@@ -0,0 +1,138 @@
+//! Collateral embedded into the binary at build time.
+//!
+//! Static verifiers for reproducible, offline deployments can't fetch collateral, so they carry a
+//! snapshot of it. [`embed_collateral!`](crate::embed_collateral) includes a collateral JSON file,
+//! see [`QuoteCollateralV3::to_json`], and pins the TCB evaluation data number it was exported
+//! at, so a rebuild against a different snapshot is noticed:
+//!
+//! ```
+//! use dcap_qvl::snapshot::StalenessPolicy;
+//!
+//! let snapshot = dcap_qvl::embed_collateral!(
+//!     "../sample/tdx_quote_collateral.json",
+//!     tcb_evaluation_data_number = 17,
+//! );
+//! let quote = include_bytes!("../sample/tdx_quote");
+//! let report = snapshot
+//!     .verify(quote, 1741852249, StalenessPolicy::Reject)
+//!     .unwrap();
+//! assert_eq!(report.status, dcap_qvl::verify::TcbStatus::UpToDate);
+//! ```
+//!
+//! Once the snapshot passes its `nextUpdate`, the [`StalenessPolicy`] decides whether quotes are
+//! still verified against it.
+
+use anyhow::{bail, Context, Result};
+
+use crate::qe_identity::QeIdentity;
+use crate::tcb_info::TcbInfo;
+use crate::utils;
+use crate::verify::{verify, VerifiedReport};
+use crate::QuoteCollateralV3;
+
+/// What to do with quotes verified after the snapshot's `nextUpdate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StalenessPolicy {
+    /// Fail verification
+    Reject,
+    /// Keep verifying for this many seconds past `nextUpdate`
+    GracePeriod(u64),
+    /// Keep verifying; the report still carries a stale collateral warning
+    Allow,
+}
+
+/// Collateral embedded with [`embed_collateral!`](crate::embed_collateral).
+#[derive(Debug, Clone)]
+pub struct CollateralSnapshot {
+    collateral: QuoteCollateralV3,
+    tcb_evaluation_data_number: u32,
+    next_update: u64,
+}
+
+impl CollateralSnapshot {
+    /// Parse a collateral JSON snapshot, checking it was exported at the expected TCB evaluation
+    /// data number.
+    pub fn from_json(json: &str, tcb_evaluation_data_number: u32) -> Result<Self> {
+        let collateral = QuoteCollateralV3::from_json(json)?;
+        let tcb_info = serde_json::from_str::<TcbInfo>(&collateral.tcb_info)
+            .context("Failed to decode TcbInfo")?;
+        let qe_identity = serde_json::from_str::<QeIdentity>(&collateral.qe_identity)
+            .context("Failed to decode QeIdentity")?;
+        if tcb_info.tcb_evaluation_data_number != tcb_evaluation_data_number {
+            bail!(
+                "Snapshot has TCB evaluation data number {}, expected {tcb_evaluation_data_number}",
+                tcb_info.tcb_evaluation_data_number
+            );
+        }
+        let next_update = utils::parse_time(&tcb_info.next_update)?
+            .min(utils::parse_time(&qe_identity.next_update)?);
+        Ok(Self {
+            collateral,
+            tcb_evaluation_data_number,
+            next_update,
+        })
+    }
+
+    pub fn collateral(&self) -> &QuoteCollateralV3 {
+        &self.collateral
+    }
+
+    pub fn tcb_evaluation_data_number(&self) -> u32 {
+        self.tcb_evaluation_data_number
+    }
+
+    /// The earliest `nextUpdate` of the TCB info and QE identity, in seconds since the Unix epoch.
+    pub fn next_update(&self) -> u64 {
+        self.next_update
+    }
+
+    /// Fail if the snapshot is too stale at `now` under `policy`.
+    pub fn check_staleness(&self, now: u64, policy: StalenessPolicy) -> Result<()> {
+        if now < self.next_update {
+            return Ok(());
+        }
+        let expired_for = now - self.next_update;
+        match policy {
+            StalenessPolicy::Reject => bail!("Collateral snapshot expired {expired_for}s ago"),
+            StalenessPolicy::GracePeriod(grace_period) if expired_for > grace_period => {
+                bail!("Collateral snapshot expired {expired_for}s ago, past the grace period")
+            }
+            StalenessPolicy::GracePeriod(_) | StalenessPolicy::Allow => Ok(()),
+        }
+    }
+
+    /// Verify a quote against the snapshot, see [`verify`].
+    ///
+    /// # Arguments
+    ///
+    /// * `raw_quote` - The raw quote to verify
+    /// * `now` - The current time in seconds since the Unix epoch
+    /// * `policy` - Whether to verify once the snapshot is stale
+    pub fn verify(
+        &self,
+        raw_quote: &[u8],
+        now: u64,
+        policy: StalenessPolicy,
+    ) -> Result<VerifiedReport> {
+        self.check_staleness(now, policy)?;
+        verify(raw_quote, &self.collateral, now)
+    }
+}
+
+/// Embed a collateral JSON file into the binary, as a `&'static` [`CollateralSnapshot`].
+///
+/// The path is resolved like [`include_str!`]. The snapshot is parsed on first use, panicking if
+/// it is invalid or its TCB evaluation data number is not the given one.
+///
+/// [`CollateralSnapshot`]: crate::snapshot::CollateralSnapshot
+#[macro_export]
+macro_rules! embed_collateral {
+    ($path:expr, tcb_evaluation_data_number = $number:expr $(,)?) => {{
+        static SNAPSHOT: ::std::sync::OnceLock<$crate::snapshot::CollateralSnapshot> =
+            ::std::sync::OnceLock::new();
+        SNAPSHOT.get_or_init(|| {
+            $crate::snapshot::CollateralSnapshot::from_json(include_str!($path), $number)
+                .expect("Invalid embedded collateral snapshot")
+        })
+    }};
+}
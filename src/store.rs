@@ -0,0 +1,348 @@
+//! Storage backends for [`CollateralCache`](crate::collateral::CollateralCache) and the caches
+//! of `SharedVerifier`.
+//!
+//! The caches only need a byte key-value store. [`MemoryStore`] and [`LruStore`] keep them per
+//! process and [`FileStore`] persists them in a directory. With the `sled` or `redis` features,
+//! [`SledStore`] and [`RedisStore`] persist them or share them between verifier replicas.
+
+use alloc::vec::Vec;
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError, RwLock};
+
+use anyhow::{Context, Result};
+
+/// A byte key-value store.
+pub trait CacheStore: Send + Sync {
+    /// Get the value stored under `key`.
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
+    /// Store `value` under `key`, replacing any previous value.
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()>;
+    /// Remove the value stored under `key`, if any.
+    fn remove(&self, key: &[u8]) -> Result<()>;
+    /// List the keys starting with `prefix`.
+    fn keys(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>>;
+    /// Whether calls block on I/O, so async callers should run them on a blocking thread.
+    fn blocking(&self) -> bool {
+        false
+    }
+}
+
+/// An in-process store.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    entries: RwLock<BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl CacheStore for MemoryStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let entries = self.entries.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(entries.get(key).cloned())
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        entries.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<()> {
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        entries.remove(key);
+        Ok(())
+    }
+
+    fn keys(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let entries = self.entries.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(entries
+            .range(prefix.to_vec()..)
+            .map(|(key, _)| key)
+            .take_while(|key| key.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+}
+
+/// An in-process store holding at most `capacity` entries, evicting the least recently used.
+#[derive(Debug)]
+pub struct LruStore {
+    capacity: usize,
+    inner: Mutex<LruEntries>,
+}
+
+#[derive(Debug, Default)]
+struct LruEntries {
+    /// The values with the clock of their last use
+    entries: BTreeMap<Vec<u8>, (Vec<u8>, u64)>,
+    /// Ticks on every use
+    clock: u64,
+}
+
+impl LruStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::default(),
+        }
+    }
+}
+
+impl CacheStore for LruStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner.clock += 1;
+        let clock = inner.clock;
+        Ok(inner.entries.get_mut(key).map(|(value, used)| {
+            *used = clock;
+            value.clone()
+        }))
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner.clock += 1;
+        let clock = inner.clock;
+        inner.entries.insert(key.to_vec(), (value.to_vec(), clock));
+        while inner.entries.len() > self.capacity {
+            let Some(oldest) = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            inner.entries.remove(&oldest);
+        }
+        Ok(())
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<()> {
+        let mut inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        inner.entries.remove(key);
+        Ok(())
+    }
+
+    fn keys(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let inner = self.inner.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(inner
+            .entries
+            .range(prefix.to_vec()..)
+            .map(|(key, _)| key)
+            .take_while(|key| key.starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+}
+
+/// A store keeping each entry in a file of a directory, named by the hex encoded key.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    root: PathBuf,
+}
+
+impl FileStore {
+    /// Open the store at `root`, creating the directory if needed.
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(&root).context("Failed to create cache directory")?;
+        Ok(Self { root })
+    }
+
+    fn path(&self, key: &[u8]) -> PathBuf {
+        self.root.join(hex::encode(key))
+    }
+}
+
+impl CacheStore for FileStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        match std::fs::read(self.path(key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err).context("Failed to read cache file"),
+        }
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        // Write to a temporary file first so a crash never leaves a truncated entry. Its name is
+        // unique to the process and call, so concurrent writers of a key don't share one.
+        static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+        let tmp = self.root.join(format!(
+            "{}.{}.{}.tmp",
+            hex::encode(key),
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let result = std::fs::write(&tmp, value)
+            .and_then(|_| std::fs::rename(&tmp, self.path(key)))
+            .context("Failed to write cache file");
+        if result.is_err() {
+            let _ = std::fs::remove_file(&tmp);
+        }
+        result
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<()> {
+        match std::fs::remove_file(self.path(key)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                Err(err).context("Failed to remove cache file")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn keys(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let mut keys = Vec::new();
+        for entry in std::fs::read_dir(&self.root).context("Failed to list cache directory")? {
+            let name = entry.context("Failed to list cache directory")?.file_name();
+            // Temporary files don't decode as hex
+            let Some(key) = name.to_str().and_then(|name| hex::decode(name).ok()) else {
+                continue;
+            };
+            if key.starts_with(prefix) {
+                keys.push(key);
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn blocking(&self) -> bool {
+        true
+    }
+}
+
+/// A store backed by a sled database.
+#[cfg(feature = "sled")]
+pub struct SledStore {
+    db: sled::Db,
+}
+
+#[cfg(feature = "sled")]
+impl SledStore {
+    pub fn new(db: sled::Db) -> Self {
+        Self { db }
+    }
+}
+
+#[cfg(feature = "sled")]
+impl CacheStore for SledStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        Ok(self.db.get(key)?.map(|value| value.to_vec()))
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        self.db.insert(key, value)?;
+        Ok(())
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<()> {
+        self.db.remove(key)?;
+        Ok(())
+    }
+
+    fn keys(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>> {
+        self.db
+            .scan_prefix(prefix)
+            .keys()
+            .map(|key| Ok(key?.to_vec()))
+            .collect()
+    }
+
+    fn blocking(&self) -> bool {
+        true
+    }
+}
+
+/// A store backed by a Redis server.
+///
+/// All keys are stored under `namespace`, so several verifier deployments can share a server.
+/// Calls share one connection, opened on first use and reopened after a failure.
+#[cfg(feature = "redis")]
+pub struct RedisStore {
+    client: redis::Client,
+    namespace: Vec<u8>,
+    connection: Mutex<Option<redis::Connection>>,
+}
+
+#[cfg(feature = "redis")]
+impl RedisStore {
+    pub fn new(client: redis::Client, namespace: &str) -> Self {
+        Self {
+            client,
+            namespace: namespace.as_bytes().to_vec(),
+            connection: Mutex::new(None),
+        }
+    }
+
+    fn key(&self, key: &[u8]) -> Vec<u8> {
+        [&self.namespace[..], key].concat()
+    }
+
+    /// Run a command on the shared connection, dropping the connection if it fails.
+    fn with_connection<T>(
+        &self,
+        command: impl FnOnce(&mut redis::Connection) -> redis::RedisResult<T>,
+    ) -> Result<T> {
+        let mut connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let conn = match &mut *connection {
+            Some(conn) => conn,
+            None => connection.insert(
+                self.client
+                    .get_connection()
+                    .context("Failed to connect to Redis")?,
+            ),
+        };
+        let result = command(conn);
+        if result.is_err() {
+            *connection = None;
+        }
+        Ok(result?)
+    }
+}
+
+#[cfg(feature = "redis")]
+impl CacheStore for RedisStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        use redis::Commands;
+        self.with_connection(|conn| conn.get(self.key(key)))
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        use redis::Commands;
+        self.with_connection(|conn| conn.set(self.key(key), value))
+    }
+
+    fn remove(&self, key: &[u8]) -> Result<()> {
+        use redis::Commands;
+        self.with_connection(|conn| conn.del(self.key(key)))
+    }
+
+    fn keys(&self, prefix: &[u8]) -> Result<Vec<Vec<u8>>> {
+        use redis::Commands;
+        let mut pattern = Vec::new();
+        for &byte in &self.key(prefix) {
+            // Match the prefix literally
+            if matches!(byte, b'*' | b'?' | b'[' | b']' | b'\\') {
+                pattern.push(b'\\');
+            }
+            pattern.push(byte);
+        }
+        pattern.push(b'*');
+        let keys: Vec<Vec<u8>> =
+            self.with_connection(|conn| Ok(conn.scan_match::<_, Vec<u8>>(pattern)?.collect()))?;
+        Ok(keys
+            .into_iter()
+            .map(|key| key[self.namespace.len()..].to_vec())
+            .collect())
+    }
+
+    fn blocking(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,89 @@
+//! Verifying an async stream of quotes.
+//!
+//! [`verify_stream`] turns a [`Stream`] of [`QuoteJob`]s, e.g. messages consumed from a queue,
+//! into a stream of [`ReportResult`]s. Quotes are verified through a [`SharedVerifier`], so the
+//! collateral of each platform is fetched once and cached, with at most `concurrency`
+//! verifications in flight. The input is only polled while there is room, so a slow verifier
+//! applies backpressure to the producer.
+//!
+//! The returned stream must be polled within a tokio runtime. Fetches run on the polling task,
+//! while the signature checks are offloaded to tokio's blocking threads, see
+//! [`SharedVerifier::verify_at`].
+//!
+//! For quotes pulled from an iterator with their collateral, on threads rather than tasks, see
+//! [`batch::verify_stream`](crate::batch::verify_stream).
+
+use alloc::vec::Vec;
+
+use anyhow::Result;
+use futures::stream::{Stream, StreamExt};
+
+use crate::shared::SharedVerifier;
+use crate::verify::VerifiedReport;
+
+/// A quote to verify, tagged with an ID passed through to its result, e.g. a message offset.
+#[derive(Debug, Clone)]
+pub struct QuoteJob<T> {
+    pub id: T,
+    pub quote: Vec<u8>,
+}
+
+/// The result of verifying a [`QuoteJob`].
+#[derive(Debug)]
+pub struct ReportResult<T> {
+    /// The ID of the job
+    pub id: T,
+    pub result: Result<VerifiedReport>,
+}
+
+/// Verify a stream of quotes at the current time.
+///
+/// Results are yielded as soon as they are ready, so they may arrive out of order and are
+/// matched to their job by ID. The stream ends once the input has ended and all its quotes are
+/// verified.
+///
+/// # Arguments
+///
+/// * `verifier` - The verifier whose collateral cache is used
+/// * `jobs` - The quotes to verify
+/// * `concurrency` - The most quotes verified at once
+///
+/// # Returns
+///
+/// * The stream of results, one per job
+pub fn verify_stream<T>(
+    verifier: SharedVerifier,
+    jobs: impl Stream<Item = QuoteJob<T>>,
+    concurrency: usize,
+) -> impl Stream<Item = ReportResult<T>> {
+    verify_stream_with_clock(verifier, jobs, concurrency, None)
+}
+
+/// Verify a stream of quotes like [`verify_stream`], at `now` in seconds since the Unix epoch.
+pub fn verify_stream_at<T>(
+    verifier: SharedVerifier,
+    jobs: impl Stream<Item = QuoteJob<T>>,
+    concurrency: usize,
+    now: u64,
+) -> impl Stream<Item = ReportResult<T>> {
+    verify_stream_with_clock(verifier, jobs, concurrency, Some(now))
+}
+
+fn verify_stream_with_clock<T>(
+    verifier: SharedVerifier,
+    jobs: impl Stream<Item = QuoteJob<T>>,
+    concurrency: usize,
+    now: Option<u64>,
+) -> impl Stream<Item = ReportResult<T>> {
+    jobs.map(move |job| {
+        let verifier = verifier.clone();
+        async move {
+            let result = match now {
+                Some(now) => verifier.verify_at(&job.quote, now).await,
+                None => verifier.verify(&job.quote).await,
+            };
+            ReportResult { id: job.id, result }
+        }
+    })
+    .buffer_unordered(concurrency.max(1))
+}
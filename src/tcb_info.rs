@@ -2,7 +2,11 @@ use alloc::string::String;
 use alloc::vec::Vec;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[cfg(feature = "borsh")]
+use borsh::{BorshDeserialize, BorshSerialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[serde(rename_all = "camelCase")]
 pub struct TcbInfo {
     pub id: String,
@@ -13,10 +17,55 @@ pub struct TcbInfo {
     pub pce_id: String,
     pub tcb_type: u32,
     pub tcb_evaluation_data_number: u32,
+    /// The identity of the TDX modules without a TDX module identity, only in TDX TCB info
+    #[serde(default)]
+    pub tdx_module: Option<TdxModule>,
+    /// The identities and TCB levels of the TDX module versions, only in TDX TCB info
+    #[serde(default)]
+    pub tdx_module_identities: Vec<TdxModuleIdentity>,
     pub tcb_levels: Vec<TcbLevel>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[serde(rename_all = "camelCase")]
+pub struct TdxModule {
+    pub mrsigner: String,
+    pub attributes: String,
+    pub attributes_mask: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[serde(rename_all = "camelCase")]
+pub struct TdxModuleIdentity {
+    /// `TDX_` followed by the major version of the module, like `TDX_01`
+    pub id: String,
+    pub mrsigner: String,
+    pub attributes: String,
+    pub attributes_mask: String,
+    pub tcb_levels: Vec<TdxModuleTcbLevel>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[serde(rename_all = "camelCase")]
+pub struct TdxModuleTcbLevel {
+    pub tcb: TdxModuleTcb,
+    pub tcb_date: String,
+    pub tcb_status: String,
+    #[serde(rename = "advisoryIDs", default)]
+    pub advisory_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+pub struct TdxModuleTcb {
+    pub isvsvn: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[serde(rename_all = "camelCase")]
 pub struct TcbLevel {
     pub tcb: Tcb,
@@ -26,7 +75,8 @@ pub struct TcbLevel {
     pub advisory_ids: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[serde(rename_all = "camelCase")]
 pub struct Tcb {
     #[serde(rename = "sgxtcbcomponents")]
@@ -37,7 +87,8 @@ pub struct Tcb {
     pub pce_svn: u16,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
 #[serde(rename_all = "camelCase")]
 pub struct TcbComponents {
     pub svn: u8,
@@ -0,0 +1,167 @@
+//! The steps of a verification, for telling which one failed and how long each took.
+//!
+//! [`verify_traced`](crate::verify::verify_traced) returns a [`VerifyTrace`] next to the result,
+//! whether or not the quote verifies. With the `tracing` feature every step also runs in a
+//! `verify_step` span, and collateral requests in `collateral_fetch` spans, for whatever
+//! subscriber the application installs.
+
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+/// A step of the verification, in the order they run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyStep {
+    /// Checking the certificate chain and signature of the TCB info
+    TcbInfo,
+    /// Checking the certificate chain and signature of the QE identity
+    QeIdentity,
+    /// Checking the collateral against the options, e.g. its evaluation number and age
+    CollateralPolicy,
+    /// Decoding the quote and checking its version, key type and certification data type
+    DecodeQuote,
+    /// Checking the PCK certificate chain of the quote
+    PckCertChain,
+    /// Checking the PCK signature over the QE report
+    QeReportSignature,
+    /// Matching the QE report against the QE identity
+    QeIdentityMatch,
+    /// Checking that the QE report binds the attestation key
+    AttestationKey,
+    /// Checking the attestation key signature over the quote
+    QuoteSignature,
+    /// Matching the platform TCB against the TCB info
+    TcbMatching,
+    /// Checking that the TCB status is allowed
+    StatusPolicy,
+}
+
+impl VerifyStep {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerifyStep::TcbInfo => "tcb_info",
+            VerifyStep::QeIdentity => "qe_identity",
+            VerifyStep::CollateralPolicy => "collateral_policy",
+            VerifyStep::DecodeQuote => "decode_quote",
+            VerifyStep::PckCertChain => "pck_cert_chain",
+            VerifyStep::QeReportSignature => "qe_report_signature",
+            VerifyStep::QeIdentityMatch => "qe_identity_match",
+            VerifyStep::AttestationKey => "attestation_key",
+            VerifyStep::QuoteSignature => "quote_signature",
+            VerifyStep::TcbMatching => "tcb_matching",
+            VerifyStep::StatusPolicy => "status_policy",
+        }
+    }
+}
+
+impl core::fmt::Display for VerifyStep {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A step that ran.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepRecord {
+    pub step: VerifyStep,
+    pub passed: bool,
+    /// How long the step took in microseconds, `None` without a clock, i.e. without `std` or in
+    /// browsers
+    pub duration_us: Option<u64>,
+}
+
+/// The steps a verification ran, up to the one that failed if any.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyTrace {
+    pub steps: Vec<StepRecord>,
+    /// The step that failed, `None` if the quote verified
+    pub failed_step: Option<VerifyStep>,
+}
+
+/// When a step started. Only measured where `std::time::Instant` works.
+struct Started {
+    #[cfg(all(feature = "std", not(feature = "js")))]
+    at: std::time::Instant,
+}
+
+impl Started {
+    fn now() -> Self {
+        Self {
+            #[cfg(all(feature = "std", not(feature = "js")))]
+            at: std::time::Instant::now(),
+        }
+    }
+
+    #[cfg(all(feature = "std", not(feature = "js")))]
+    fn elapsed_us(&self) -> Option<u64> {
+        Some(u64::try_from(self.at.elapsed().as_micros()).unwrap_or(u64::MAX))
+    }
+
+    #[cfg(not(all(feature = "std", not(feature = "js"))))]
+    fn elapsed_us(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Records steps into a trace as they begin.
+///
+/// A step passes when the next one begins or [`Steps::finish`] is called. A step still running
+/// when the recorder is dropped, i.e. when the verification returned early with an error, failed.
+pub(crate) struct Steps<'a> {
+    trace: &'a mut VerifyTrace,
+    current: Option<(VerifyStep, Started)>,
+    #[cfg(feature = "tracing")]
+    span: Option<tracing::span::EnteredSpan>,
+}
+
+impl<'a> Steps<'a> {
+    pub(crate) fn new(trace: &'a mut VerifyTrace) -> Self {
+        Self {
+            trace,
+            current: None,
+            #[cfg(feature = "tracing")]
+            span: None,
+        }
+    }
+
+    pub(crate) fn begin(&mut self, step: VerifyStep) {
+        self.end(true);
+        #[cfg(feature = "tracing")]
+        {
+            self.span = Some(tracing::debug_span!("verify_step", step = step.as_str()).entered());
+        }
+        self.current = Some((step, Started::now()));
+    }
+
+    pub(crate) fn finish(mut self) {
+        self.end(true);
+    }
+
+    fn end(&mut self, passed: bool) {
+        let Some((step, started)) = self.current.take() else {
+            return;
+        };
+        let duration_us = started.elapsed_us();
+        #[cfg(feature = "tracing")]
+        {
+            if !passed {
+                tracing::debug!(step = step.as_str(), "verification step failed");
+            }
+            self.span = None;
+        }
+        self.trace.steps.push(StepRecord {
+            step,
+            passed,
+            duration_us,
+        });
+        if !passed {
+            self.trace.failed_step = Some(step);
+        }
+    }
+}
+
+impl Drop for Steps<'_> {
+    fn drop(&mut self) {
+        self.end(false);
+    }
+}
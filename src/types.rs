@@ -0,0 +1,239 @@
+//! Validated newtypes for the platform and enclave identifiers found in quotes and collateral.
+//!
+//! Each type parses from and displays as hex, and serializes as a hex string, so that an FMSPC
+//! can't be mixed up with a CPUSVN or a measurement of the wrong length.
+
+use alloc::vec::Vec;
+use anyhow::{bail, Context, Result};
+use core::fmt;
+use core::str::FromStr;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+#[cfg(feature = "borsh")]
+use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(feature = "scale-codec")]
+use {scale::Decode, scale::Encode, scale_info::TypeInfo};
+
+/// Implement the hex conversions of a fixed size byte array newtype.
+macro_rules! hex_array_type {
+    ($name:ident, $len:literal, $what:literal, $encode:path) => {
+        impl $name {
+            /// The raw bytes.
+            pub fn as_bytes(&self) -> &[u8; $len] {
+                &self.0
+            }
+        }
+
+        impl From<[u8; $len]> for $name {
+            fn from(bytes: [u8; $len]) -> Self {
+                Self(bytes)
+            }
+        }
+
+        impl TryFrom<&[u8]> for $name {
+            type Error = anyhow::Error;
+
+            fn try_from(bytes: &[u8]) -> Result<Self> {
+                let bytes = bytes.try_into().ok().with_context(|| {
+                    format!("{} must be {} bytes, got {}", $what, $len, bytes.len())
+                })?;
+                Ok(Self(bytes))
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = anyhow::Error;
+
+            fn from_str(s: &str) -> Result<Self> {
+                let mut bytes = [0u8; $len];
+                hex::decode_to_slice(s, &mut bytes).ok().with_context(|| {
+                    format!("{} must be {} hex characters, got {s:?}", $what, 2 * $len)
+                })?;
+                Ok(Self(bytes))
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&$encode(self.0))
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(&$encode(self.0))
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_str(FromStrVisitor::<Self>::new($what))
+            }
+        }
+    };
+}
+
+/// The Family-Model-Stepping-Platform-CustomSKU of a platform, the key of its TCB info.
+///
+/// Displays as upper case hex, the form PCS expects in requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(Encode, Decode, TypeInfo))]
+pub struct Fmspc([u8; 6]);
+
+hex_array_type!(Fmspc, 6, "FMSPC", hex::encode_upper);
+
+/// The CPU security version number of a platform, from its PCK certificate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(Encode, Decode, TypeInfo))]
+pub struct CpuSvn([u8; 16]);
+
+hex_array_type!(CpuSvn, 16, "CPUSVN", hex::encode);
+
+/// The 64 bytes of user data an enclave or TD report carries, to bind a nonce or a key to the
+/// quote.
+///
+/// Values shorter than 64 bytes, like SHA-256 digests, are zero padded at the end, in SGX
+/// reports and TD reports alike.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReportData([u8; 64]);
+
+hex_array_type!(ReportData, 64, "Report data", hex::encode);
+
+impl ReportData {
+    /// Check that the report data is `expected`, zero padded to 64 bytes.
+    pub fn expect(&self, expected: &[u8]) -> Result<()> {
+        if expected.len() > self.0.len() {
+            bail!(
+                "Expected report data is {} bytes, longer than 64",
+                expected.len()
+            );
+        }
+        let (head, padding) = self.0.split_at(expected.len());
+        if head != expected || padding.iter().any(|b| *b != 0) {
+            bail!(
+                "Report data mismatch: expected {}, actual {}",
+                hex::encode(expected),
+                hex::encode(self.0)
+            );
+        }
+        Ok(())
+    }
+
+    /// Check that the report data is the SHA-256 of `payload`, zero padded to 64 bytes.
+    pub fn expect_sha256(&self, payload: &[u8]) -> Result<()> {
+        self.expect(ring::digest::digest(&ring::digest::SHA256, payload).as_ref())
+    }
+
+    /// Check that the report data is the SHA-512 of `payload`.
+    pub fn expect_sha512(&self, payload: &[u8]) -> Result<()> {
+        self.expect(ring::digest::digest(&ring::digest::SHA512, payload).as_ref())
+    }
+}
+
+/// The measurement of an enclave or TD: a 32 byte MRENCLAVE or a 48 byte MRTD.
+///
+/// Equality is checked in constant time, since measurements are compared in access policies.
+#[derive(Debug, Clone)]
+pub struct Measurement(pub(crate) Vec<u8>);
+
+impl Measurement {
+    /// The raw bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl TryFrom<&[u8]> for Measurement {
+    type Error = anyhow::Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != 32 && bytes.len() != 48 {
+            bail!("Measurement must be 32 or 48 bytes, got {}", bytes.len());
+        }
+        Ok(Self(bytes.to_vec()))
+    }
+}
+
+impl AsRef<[u8]> for Measurement {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl PartialEq for Measurement {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self
+                .0
+                .iter()
+                .zip(&other.0)
+                .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+                == 0
+    }
+}
+
+impl Eq for Measurement {}
+
+impl FromStr for Measurement {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let bytes = hex::decode(s)
+            .ok()
+            .with_context(|| format!("Measurement must be hex, got {s:?}"))?;
+        Self::try_from(&bytes[..])
+    }
+}
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(self.as_bytes()))
+    }
+}
+
+impl Serialize for Measurement {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&hex::encode(self.as_bytes()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Measurement {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(FromStrVisitor::<Self>::new("measurement"))
+    }
+}
+
+/// Deserialize a value from a string with its [`FromStr`] implementation.
+struct FromStrVisitor<T> {
+    what: &'static str,
+    _marker: core::marker::PhantomData<T>,
+}
+
+impl<T> FromStrVisitor<T> {
+    fn new(what: &'static str) -> Self {
+        Self {
+            what,
+            _marker: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<T: FromStr<Err = anyhow::Error>> de::Visitor<'_> for FromStrVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a hex encoded {}", self.what)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+        v.parse().map_err(|err: anyhow::Error| E::custom(err))
+    }
+}
@@ -0,0 +1,192 @@
+//! Verification with uniform failures, for verifiers exposed as public endpoints.
+//!
+//! The errors of [`verify`](crate::verify::verify) tell exactly which check failed, and how soon
+//! it returns tells how far verification got. Both help whoever probes a public endpoint with
+//! crafted quotes. [`verify_uniform`] only reports a coarse [`FailureCategory`], and every failure
+//! makes up the signature checks verification skipped by returning early, on a dummy key, so
+//! malformed quotes aren't rejected measurably faster than forged ones. The detailed cause stays
+//! available to the verifier through [`UniformError::trace`], for its own logs.
+//!
+//! The padding counts signatures: the TCB info, QE identity, QE report and quote signatures, and
+//! those of the certificates and CRLs of each certificate chain. Parsing is much cheaper than
+//! either and isn't padded.
+
+use core::cell::Cell;
+use core::fmt;
+
+use anyhow::Result;
+
+use crate::quote::Quote;
+use crate::signature::{RingVerifier, SignatureVerifier};
+use crate::trace::{VerifyStep, VerifyTrace};
+use crate::verify::{verify_impl_traced, VerifiedReport, VerifyOptions};
+use crate::QuoteCollateralV3;
+
+/// The signatures verification checks through its [`SignatureVerifier`]: those over the TCB info,
+/// the QE identity, the QE report and the quote.
+const VERIFIER_SIGNATURES: usize = 4;
+
+/// The certificate and CRL signatures of the chain checked by each step: the signing certificate
+/// and root CRL for the collateral, the PCK and PCK CA certificates and both CRLs for the quote.
+const CHAIN_SIGNATURES: [(VerifyStep, usize); 3] = [
+    (VerifyStep::TcbInfo, 2),
+    (VerifyStep::QeIdentity, 2),
+    (VerifyStep::PckCertChain, 4),
+];
+
+/// The P-256 base point, a valid key that verifies no signature.
+const DUMMY_KEY: [u8; 64] = [
+    0x6b, 0x17, 0xd1, 0xf2, 0xe1, 0x2c, 0x42, 0x47, 0xf8, 0xbc, 0xe6, 0xe5, 0x63, 0xa4, 0x40, 0xf2,
+    0x77, 0x03, 0x7d, 0x81, 0x2d, 0xeb, 0x33, 0xa0, 0xf4, 0xa1, 0x39, 0x45, 0xd8, 0x98, 0xc2, 0x96,
+    0x4f, 0xe3, 0x42, 0xe2, 0xfe, 0x1a, 0x7f, 0x9b, 0x8e, 0xe7, 0xeb, 0x4a, 0x7c, 0x0f, 0x9e, 0x16,
+    0x2b, 0xce, 0x33, 0x57, 0x6b, 0x31, 0x5e, 0xce, 0xcb, 0xb6, 0x40, 0x68, 0x37, 0xbf, 0x51, 0xf5,
+];
+
+/// What kind of failure a [`UniformError`] is, the only detail safe to show callers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureCategory {
+    /// The quote could not be decoded
+    Malformed,
+    /// The quote or collateral failed a check
+    Rejected,
+}
+
+/// A verification failure that shows only its category.
+///
+/// Neither [`Display`](fmt::Display) nor [`Debug`] reveal the cause, so the error can be passed
+/// on to callers as is.
+pub struct UniformError {
+    category: FailureCategory,
+    trace: anyhow::Error,
+}
+
+impl UniformError {
+    pub fn category(&self) -> FailureCategory {
+        self.category
+    }
+
+    /// The detailed cause, for the verifier's logs only.
+    pub fn trace(&self) -> &anyhow::Error {
+        &self.trace
+    }
+
+    pub fn into_trace(self) -> anyhow::Error {
+        self.trace
+    }
+}
+
+impl fmt::Display for UniformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.category {
+            FailureCategory::Malformed => f.write_str("Malformed quote"),
+            FailureCategory::Rejected => f.write_str("Quote rejected"),
+        }
+    }
+}
+
+impl fmt::Debug for UniformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UniformError")
+            .field("category", &self.category)
+            .finish_non_exhaustive()
+    }
+}
+
+impl core::error::Error for UniformError {}
+
+/// Verify a quote with the given options, failing with a [`UniformError`].
+///
+/// Quotes the options reject fail as [`FailureCategory::Rejected`]. See
+/// [`verify_with_options`](crate::verify::verify_with_options) for the arguments.
+pub fn verify_uniform(
+    raw_quote: &[u8],
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+    options: &VerifyOptions,
+) -> Result<VerifiedReport, UniformError> {
+    verify_uniform_with_signature_verifier(raw_quote, quote_collateral, now, options, &RingVerifier)
+}
+
+/// Verify a quote, failing with a [`UniformError`], checking its ECDSA signatures with the given
+/// [`SignatureVerifier`].
+///
+/// The padding goes through `signature_verifier` too, so a failure makes at least as many calls
+/// to it as a successful verification. See [`verify_uniform`] for the other arguments.
+pub fn verify_uniform_with_signature_verifier(
+    raw_quote: &[u8],
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+    options: &VerifyOptions,
+    signature_verifier: &dyn SignatureVerifier,
+) -> Result<VerifiedReport, UniformError> {
+    let counted = Counted {
+        inner: signature_verifier,
+        calls: Cell::new(0),
+    };
+    let mut steps = VerifyTrace::default();
+    let trace = match verify_impl_traced(
+        raw_quote,
+        quote_collateral,
+        now,
+        options,
+        &counted,
+        &mut steps,
+    ) {
+        Ok(report) => return Ok(report),
+        Err(err) => err,
+    };
+    // Quotes failing early would otherwise return before the costly signature checks. The
+    // outcomes don't matter, the cause is in `trace` already.
+    let skipped_chains: usize = CHAIN_SIGNATURES
+        .iter()
+        .filter(|(step, _)| !passed(&steps, *step))
+        .map(|(_, signatures)| signatures)
+        .sum();
+    let padding = VERIFIER_SIGNATURES.saturating_sub(counted.calls.get()) + skipped_chains;
+    for _ in 0..padding {
+        let _ = signature_verifier.verify_p256_sha256(&DUMMY_KEY, raw_quote, &[1; 64]);
+    }
+    let category = if Quote::parse(raw_quote).is_err() {
+        FailureCategory::Malformed
+    } else {
+        FailureCategory::Rejected
+    };
+    Err(UniformError { category, trace })
+}
+
+fn passed(trace: &VerifyTrace, step: VerifyStep) -> bool {
+    trace
+        .steps
+        .iter()
+        .any(|record| record.step == step && record.passed)
+}
+
+/// Counts the signatures verification checked, to know how many it skipped.
+struct Counted<'a> {
+    inner: &'a dyn SignatureVerifier,
+    calls: Cell<usize>,
+}
+
+impl SignatureVerifier for Counted<'_> {
+    fn verify_p256_sha256(
+        &self,
+        public_key: &[u8; 64],
+        message: &[u8],
+        signature: &[u8; 64],
+    ) -> Result<()> {
+        self.calls.set(self.calls.get() + 1);
+        self.inner
+            .verify_p256_sha256(public_key, message, signature)
+    }
+
+    fn verify_p384_sha384(
+        &self,
+        public_key: &[u8; 96],
+        message: &[u8],
+        signature: &[u8; 96],
+    ) -> Result<()> {
+        self.calls.set(self.calls.get() + 1);
+        self.inner
+            .verify_p384_sha384(public_key, message, signature)
+    }
+}
@@ -8,10 +8,33 @@ use webpki::types::CertificateDer;
 use x509_cert::Certificate;
 
 use crate::constants::*;
+use crate::signature::SignatureAlgorithm;
+use crate::verify::CertificateRevoked;
+
+/// [`Context`] for errors of dependencies that implement `std::error::Error` only with their std
+/// feature, like those of `der`, `scale` and `webpki`, keeping the core buildable without std.
+/// The error's message becomes the source of the context.
+pub(crate) trait MsgContext<T> {
+    fn msg_context<C>(self, context: C) -> Result<T>
+    where
+        C: core::fmt::Display + Send + Sync + 'static;
+}
+
+impl<T, E> MsgContext<T> for core::result::Result<T, E>
+where
+    E: core::fmt::Display + core::fmt::Debug + Send + Sync + 'static,
+{
+    fn msg_context<C>(self, context: C) -> Result<T>
+    where
+        C: core::fmt::Display + Send + Sync + 'static,
+    {
+        self.map_err(|err| anyhow::Error::msg(err).context(context))
+    }
+}
 
 pub fn get_intel_extension(der_encoded: &[u8]) -> Result<Vec<u8>> {
     let cert: Certificate =
-        der::Decode::from_der(der_encoded).context("Failed to decode certificate")?;
+        der::Decode::from_der(der_encoded).msg_context("Failed to decode certificate")?;
     let mut extension_iter = cert
         .tbs_certificate
         .extensions
@@ -30,14 +53,14 @@ pub fn get_intel_extension(der_encoded: &[u8]) -> Result<Vec<u8>> {
 }
 
 pub fn find_extension(path: &[&[u8]], raw: &[u8]) -> Result<Vec<u8>> {
-    let obj = DerObject::decode(raw).context("Failed to decode DER object")?;
+    let obj = DerObject::decode(raw).msg_context("Failed to decode DER object")?;
     let subobj = get_obj(path, obj).context("Failed to get subobject")?;
     Ok(subobj.value().to_vec())
 }
 
 fn get_obj<'a>(path: &[&[u8]], mut obj: DerObject<'a>) -> Result<DerObject<'a>> {
     for oid in path {
-        let seq = Sequence::load(obj).context("Failed to load sequence")?;
+        let seq = Sequence::load(obj).msg_context("Failed to load sequence")?;
         obj = sub_obj(oid, seq).context("Failed to get subobject")?;
     }
     Ok(obj)
@@ -45,10 +68,10 @@ fn get_obj<'a>(path: &[&[u8]], mut obj: DerObject<'a>) -> Result<DerObject<'a>>
 
 fn sub_obj<'a>(oid: &[u8], seq: Sequence<'a>) -> Result<DerObject<'a>> {
     for i in 0..seq.len() {
-        let entry = seq.get(i).context("Failed to get entry")?;
-        let entry = Sequence::load(entry).context("Failed to load sequence")?;
-        let name = entry.get(0).context("Failed to get name")?;
-        let value = entry.get(1).context("Failed to get value")?;
+        let entry = seq.get(i).msg_context("Failed to get entry")?;
+        let entry = Sequence::load(entry).msg_context("Failed to load sequence")?;
+        let name = entry.get(0).msg_context("Failed to get name")?;
+        let value = entry.get(1).msg_context("Failed to get value")?;
         if name.value() == oid {
             return Ok(value);
         }
@@ -63,8 +86,7 @@ pub fn get_fmspc(extension_section: &[u8]) -> Result<Fmspc> {
         bail!("Fmspc length mismatch");
     }
 
-    data.try_into()
-        .map_err(|_| anyhow!("Failed to decode Fmspc"))
+    Fmspc::try_from(&data[..]).map_err(|_| anyhow!("Failed to decode Fmspc"))
 }
 
 pub fn get_cpu_svn(extension_section: &[u8]) -> Result<CpuSvn> {
@@ -76,8 +98,7 @@ pub fn get_cpu_svn(extension_section: &[u8]) -> Result<CpuSvn> {
         bail!("CpuSvn length mismatch");
     }
 
-    data.try_into()
-        .map_err(|_| anyhow!("Failed to decode CpuSvn"))
+    CpuSvn::try_from(&data[..]).map_err(|_| anyhow!("Failed to decode CpuSvn"))
 }
 
 pub fn get_pce_svn(extension_section: &[u8]) -> Result<Svn> {
@@ -97,9 +118,123 @@ pub fn get_pce_svn(extension_section: &[u8]) -> Result<Svn> {
     }
 }
 
+pub fn get_ppid(extension_section: &[u8]) -> Result<Vec<u8>> {
+    find_extension(&[oids::PPID.as_bytes()], extension_section).context("Failed to find PPID")
+}
+
+/// Get the Platform Instance ID, only present in certificates issued by the Platform CA.
+pub fn get_platform_instance_id(extension_section: &[u8]) -> Result<Option<[u8; 16]>> {
+    let Ok(data) = find_extension(&[oids::PLATFORM_INSTANCE_ID.as_bytes()], extension_section)
+    else {
+        return Ok(None);
+    };
+    data.try_into()
+        .map(Some)
+        .map_err(|_| anyhow!("Platform Instance ID length mismatch"))
+}
+
+/// Get the uncompressed P-256 public key (X || Y) of a DER encoded certificate.
+pub fn get_public_key(der_encoded: &[u8]) -> Result<[u8; ECDSA_PUBKEY_BYTE_LEN]> {
+    let cert: Certificate =
+        der::Decode::from_der(der_encoded).msg_context("Failed to decode certificate")?;
+    let key = cert
+        .tbs_certificate
+        .subject_public_key_info
+        .subject_public_key
+        .as_bytes()
+        .context("Invalid public key")?;
+    // Uncompressed SEC1 point: 0x04 || X || Y
+    match key {
+        [0x04, point @ ..] => point
+            .try_into()
+            .map_err(|_| anyhow!("Invalid public key length")),
+        _ => bail!("Public key must be uncompressed"),
+    }
+}
+
+/// Get the uncompressed public key (X || Y) of a DER encoded certificate and the ECDSA algorithm
+/// it signs with, given by its curve.
+pub fn get_signing_key(der_encoded: &[u8]) -> Result<(SignatureAlgorithm, Vec<u8>)> {
+    let cert: Certificate =
+        der::Decode::from_der(der_encoded).msg_context("Failed to decode certificate")?;
+    let spki = &cert.tbs_certificate.subject_public_key_info;
+    if spki.algorithm.oid != oids::EC_PUBLIC_KEY {
+        bail!("Public key is not an EC key");
+    }
+    let curve = spki
+        .algorithm
+        .parameters
+        .as_ref()
+        .and_then(|params| params.decode_as::<const_oid::ObjectIdentifier>().ok())
+        .context("Missing EC curve")?;
+    let algorithm = if curve == oids::SECP256R1 {
+        SignatureAlgorithm::EcdsaP256Sha256
+    } else if curve == oids::SECP384R1 {
+        SignatureAlgorithm::EcdsaP384Sha384
+    } else {
+        bail!("Unsupported EC curve {curve}");
+    };
+    let key = spki
+        .subject_public_key
+        .as_bytes()
+        .context("Invalid public key")?;
+    match key {
+        [0x04, point @ ..] if point.len() == algorithm.public_key_len() => {
+            Ok((algorithm, point.to_vec()))
+        }
+        _ => bail!("Public key must be uncompressed"),
+    }
+}
+
+/// Get the DER encoded TBS certificate and the raw (r || s) ECDSA signature over it.
+pub fn get_cert_signature(der_encoded: &[u8]) -> Result<(Vec<u8>, [u8; ECDSA_SIGNATURE_BYTE_LEN])> {
+    let cert: Certificate =
+        der::Decode::from_der(der_encoded).msg_context("Failed to decode certificate")?;
+    let tbs = der::Encode::to_der(&cert.tbs_certificate).msg_context("Failed to encode TBS")?;
+    let signature = cert
+        .signature
+        .as_bytes()
+        .context("Invalid certificate signature")?;
+    Ok((tbs, decode_der_signature(signature)?))
+}
+
+/// Decode a DER encoded ECDSA signature into 32-byte r and s values.
+pub fn decode_der_signature(der_encoded: &[u8]) -> Result<[u8; ECDSA_SIGNATURE_BYTE_LEN]> {
+    let sequence: der::asn1::SequenceOf<der::asn1::UintRef, 2> =
+        der::Decode::from_der(der_encoded).msg_context("Failed to decode signature")?;
+    let mut signature = [0u8; ECDSA_SIGNATURE_BYTE_LEN];
+    for (i, value) in sequence.iter().enumerate() {
+        let bytes = value.as_bytes();
+        if bytes.len() > 32 {
+            bail!("Signature component is too long");
+        }
+        let end = 32 * (i + 1);
+        signature[end - bytes.len()..end].copy_from_slice(bytes);
+    }
+    Ok(signature)
+}
+
+/// Check whether a DER encoded certificate is self-signed, i.e. a root certificate.
+pub fn is_self_signed(der_encoded: &[u8]) -> Result<bool> {
+    let cert: Certificate =
+        der::Decode::from_der(der_encoded).msg_context("Failed to decode certificate")?;
+    Ok(cert.tbs_certificate.subject == cert.tbs_certificate.issuer)
+}
+
+/// The validity period of a DER encoded certificate, in seconds since the Unix epoch.
+pub fn cert_validity(der_encoded: &[u8]) -> Result<(u64, u64)> {
+    let cert: Certificate =
+        der::Decode::from_der(der_encoded).msg_context("Failed to decode certificate")?;
+    let validity = &cert.tbs_certificate.validity;
+    Ok((
+        validity.not_before.to_unix_duration().as_secs(),
+        validity.not_after.to_unix_duration().as_secs(),
+    ))
+}
+
 pub fn extract_raw_certs(cert_chain: &[u8]) -> Result<Vec<Vec<u8>>> {
     Ok(pem::parse_many(cert_chain)
-        .context("Failed to parse certs")?
+        .msg_context("Failed to parse certs")?
         .iter()
         .map(|i| i.contents().to_vec())
         .collect())
@@ -117,51 +252,80 @@ pub fn extract_certs<'a>(cert_chain: &'a [u8]) -> Result<Vec<CertificateDer<'a>>
     Ok(certs)
 }
 
-/// Encode two 32-byte values in DER format
-/// This is meant for 256 bit ECC signatures or public keys
-/// TODO: We may could use `asn1_der` crate reimplement this, so we can remove `der` which overlap with `asn1_der`
-pub fn encode_as_der(data: &[u8]) -> Result<Vec<u8>> {
-    if data.len() != 64 {
-        bail!("Key length is invalid");
-    }
-    let mut sequence = der::asn1::SequenceOf::<der::asn1::UintRef, 2>::new();
-    sequence
-        .add(der::asn1::UintRef::new(&data[0..32]).context("Failed to add first element")?)
-        .context("Failed to add second element")?;
-    sequence
-        .add(der::asn1::UintRef::new(&data[32..]).context("Failed to add third element")?)
-        .context("Failed to add third element")?;
-    // 72 should be enough in all cases. 2 + 2 x (32 + 3)
-    let mut asn1 = alloc::vec![0u8; 72];
-    let mut writer = der::SliceWriter::new(&mut asn1);
-    writer
-        .encode(&sequence)
-        .context("Failed to encode sequence")?;
-    Ok(writer.finish().context("Failed to finish writer")?.to_vec())
-}
-
 /// Verifies that the `leaf_cert` in combination with the `intermediate_certs` establishes
 /// a valid certificate chain that is rooted in one of the trust anchors that was compiled into to the pallet
+/// Verify a certificate chain up to one of `roots`, usually [`DCAP_SERVER_ROOTS`], checking
+/// revocation against `crls`.
+///
+/// With `require_crls`, every certificate in the chain must be covered by a CRL of its issuer.
 pub fn verify_certificate_chain(
     leaf_cert: &webpki::EndEntityCert,
     intermediate_certs: &[CertificateDer],
+    roots: &[webpki::types::TrustAnchor],
     verification_time: u64,
+    crls: &[&[u8]],
+    require_crls: bool,
 ) -> Result<()> {
+    let crls = crls
+        .iter()
+        .map(|der| {
+            webpki::BorrowedCertRevocationList::from_der(der)
+                .map(webpki::CertRevocationList::from)
+                .map_err(|_| anyhow!("Failed to parse CRL"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let crl_refs = crls.iter().collect::<Vec<_>>();
+    let revocation = if crl_refs.is_empty() {
+        if require_crls {
+            bail!("CRLs are required but missing");
+        }
+        None
+    } else {
+        let status_policy = if require_crls {
+            webpki::UnknownStatusPolicy::Deny
+        } else {
+            webpki::UnknownStatusPolicy::Allow
+        };
+        Some(
+            webpki::RevocationOptionsBuilder::new(&crl_refs)
+                .map_err(|_| anyhow!("Failed to build revocation options"))?
+                .with_depth(webpki::RevocationCheckDepth::Chain)
+                .with_status_policy(status_policy)
+                .build(),
+        )
+    };
     let time = webpki::types::UnixTime::since_unix_epoch(core::time::Duration::from_secs(
         verification_time / 1000,
     ));
-    let sig_algs = &[webpki::ring::ECDSA_P256_SHA256];
+    let sig_algs = &[
+        webpki::ring::ECDSA_P256_SHA256,
+        webpki::ring::ECDSA_P256_SHA384,
+        webpki::ring::ECDSA_P384_SHA256,
+        webpki::ring::ECDSA_P384_SHA384,
+    ];
     leaf_cert
         .verify_for_usage(
             sig_algs,
-            DCAP_SERVER_ROOTS,
+            roots,
             intermediate_certs,
             time,
             webpki::KeyUsage::server_auth(),
-            None,
+            revocation,
             None,
         )
+        .map_err(|err| match err {
+            webpki::Error::CertRevoked => anyhow::Error::new(CertificateRevoked),
+            err => anyhow::Error::msg(err),
+        })
         .context("Failed to verify certificate chain")?;
 
     Ok(())
 }
+
+/// Parse an RFC 3339 time of the collateral into seconds since the Unix epoch.
+pub(crate) fn parse_time(time: &str) -> Result<u64> {
+    let time = chrono::DateTime::parse_from_rfc3339(time)
+        .ok()
+        .context("Failed to parse time")?;
+    u64::try_from(time.timestamp()).context("Time is before the Unix epoch")
+}
@@ -0,0 +1,160 @@
+//! Building blocks for a Veraison scheme plugin.
+//!
+//! A Veraison scheme splits verification into evidence handling (extracting claims, checking
+//! integrity, appraising against endorsements) and endorsement handling (decoding provisioned
+//! reference values). This module provides those steps for SGX and TDX quotes, so a plugin only
+//! has to do the wire plumbing:
+//!
+//! - [`extract_claims`] decodes a quote into claims and the reference id to look endorsements up
+//!   by.
+//! - [`validate_evidence_integrity`] fully verifies the quote against its collateral, which acts
+//!   as the trust anchor.
+//! - [`decode_endorsements`] parses provisioned [`ReferenceValue`]s.
+//! - [`appraise_evidence`] matches the verified report against the reference values and produces
+//!   an EAR style [`Appraisal`].
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::quote::{Quote, Report};
+use crate::verify::{verify, TcbStatus, VerifiedReport};
+use crate::QuoteCollateralV3;
+
+/// The media type of SGX quote evidence.
+pub const SGX_MEDIA_TYPE: &str = "application/vnd.intel.sgx.quote";
+/// The media type of TDX quote evidence.
+pub const TDX_MEDIA_TYPE: &str = "application/vnd.intel.tdx.quote";
+
+/// The claims extracted from a quote.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractedClaims {
+    /// The id endorsements for this evidence are stored under
+    pub reference_id: String,
+    pub claims: Map<String, Value>,
+}
+
+/// A provisioned reference value, e.g. an expected build of an enclave.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReferenceValue {
+    /// The reference id, see [`ExtractedClaims::reference_id`]
+    pub reference_id: String,
+    /// A human readable name of the reference value
+    #[serde(default)]
+    pub name: String,
+}
+
+/// The EAR (Entity Attestation Result) status of an appraisal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrustTier {
+    /// Matches a reference value and the platform TCB is up to date
+    Affirming,
+    /// Matches a reference value but the platform TCB needs attention
+    Warning,
+    /// Matches no reference value or the platform TCB is revoked
+    Contraindicated,
+}
+
+/// The result of appraising a verified quote.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Appraisal {
+    pub status: TrustTier,
+    /// The matched reference value, if any
+    pub matched: Option<ReferenceValue>,
+    pub tcb_status: TcbStatus,
+    pub advisory_ids: Vec<String>,
+}
+
+/// Decode a quote to claims without verifying it.
+///
+/// # Arguments
+///
+/// * `media_type` - Either [`SGX_MEDIA_TYPE`] or [`TDX_MEDIA_TYPE`].
+/// * `raw_quote` - The raw quote.
+pub fn extract_claims(media_type: &str, raw_quote: &[u8]) -> Result<ExtractedClaims> {
+    let quote = Quote::parse(raw_quote).context("Failed to parse quote")?;
+    let expected = if quote.report.is_sgx() {
+        SGX_MEDIA_TYPE
+    } else {
+        TDX_MEDIA_TYPE
+    };
+    if media_type != expected {
+        bail!("Media type {media_type} does not match the quote");
+    }
+    claims_of(&quote.report)
+}
+
+/// Verify a quote against its collateral.
+///
+/// See [`verify`] for the arguments.
+pub fn validate_evidence_integrity(
+    raw_quote: &[u8],
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+) -> Result<VerifiedReport> {
+    verify(raw_quote, quote_collateral, now)
+}
+
+/// Decode endorsements provisioned as a JSON array of [`ReferenceValue`]s.
+pub fn decode_endorsements(data: &[u8]) -> Result<Vec<ReferenceValue>> {
+    serde_json::from_slice(data).context("Failed to decode endorsements")
+}
+
+/// Appraise a verified report against reference values.
+pub fn appraise_evidence(
+    report: &VerifiedReport,
+    reference_values: &[ReferenceValue],
+) -> Result<Appraisal> {
+    let reference_id = claims_of(&report.report)?.reference_id;
+    let matched = reference_values
+        .iter()
+        .find(|value| value.reference_id == reference_id)
+        .cloned();
+    let status = match (&matched, report.status) {
+        (None, _) | (_, TcbStatus::Revoked) => TrustTier::Contraindicated,
+        (Some(_), TcbStatus::UpToDate) => TrustTier::Affirming,
+        (Some(_), _) => TrustTier::Warning,
+    };
+    Ok(Appraisal {
+        status,
+        matched,
+        tcb_status: report.status,
+        advisory_ids: report.advisory_ids.clone(),
+    })
+}
+
+fn claims_of(report: &Report) -> Result<ExtractedClaims> {
+    let mut claims = Map::new();
+    let reference_id = match report {
+        Report::SgxEnclave(enclave) => {
+            claims.insert("mr_enclave".into(), hex::encode(enclave.mr_enclave).into());
+            claims.insert("mr_signer".into(), hex::encode(enclave.mr_signer).into());
+            claims.insert("isv_prod_id".into(), enclave.isv_prod_id.into());
+            claims.insert("isv_svn".into(), enclave.isv_svn.into());
+            claims.insert(
+                "report_data".into(),
+                hex::encode(enclave.report_data).into(),
+            );
+            format!("sgx/{}", hex::encode(enclave.mr_enclave))
+        }
+        Report::TD10(_) | Report::TD15(_) => {
+            let td = report.as_td10().context("Failed to get TD10 report")?;
+            claims.insert("mr_td".into(), hex::encode(td.mr_td).into());
+            claims.insert("mr_config_id".into(), hex::encode(td.mr_config_id).into());
+            claims.insert("mr_owner".into(), hex::encode(td.mr_owner).into());
+            claims.insert("rt_mr0".into(), hex::encode(td.rt_mr0).into());
+            claims.insert("rt_mr1".into(), hex::encode(td.rt_mr1).into());
+            claims.insert("rt_mr2".into(), hex::encode(td.rt_mr2).into());
+            claims.insert("rt_mr3".into(), hex::encode(td.rt_mr3).into());
+            claims.insert("report_data".into(), hex::encode(td.report_data).into());
+            format!("tdx/{}", hex::encode(td.mr_td))
+        }
+    };
+    Ok(ExtractedClaims {
+        reference_id,
+        claims,
+    })
+}
@@ -2,28 +2,252 @@ use anyhow::{anyhow, bail, Context, Result};
 use scale::Decode;
 
 use {
-    crate::constants::*, crate::tcb_info::TcbInfo, alloc::borrow::ToOwned, alloc::string::String,
-    alloc::vec::Vec,
+    crate::constants::*, crate::qe_identity::QeIdentity, crate::tcb_info::TcbInfo,
+    alloc::string::String, alloc::sync::Arc, alloc::vec::Vec, webpki::types::CertificateDer,
 };
 
+use crate::commitment::{self, CollateralCommitment};
+use crate::pck::{self, PckConfiguration, SgxType};
+#[cfg(any(feature = "tdx", not(feature = "sgx")))]
+use crate::quote::TDReport10;
 pub use crate::quote::{AuthData, EnclaveReport, Quote};
-use crate::QuoteCollateralV3;
+use crate::trace::{Steps, VerifyStep, VerifyTrace};
 use crate::{
-    quote::Report,
-    utils::{self, encode_as_der, extract_certs, verify_certificate_chain},
+    quote::{Anomaly, Header, Report},
+    signature::{self, RingVerifier, SignatureVerifier},
+    utils::{self, extract_certs, verify_certificate_chain, MsgContext},
 };
+use crate::{CollateralSource, QuoteCollateralV3};
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "borsh")]
+use borsh::{BorshDeserialize, BorshSerialize};
+#[cfg(feature = "scale-codec")]
+use {scale::Encode, scale_info::TypeInfo};
+
 #[cfg(feature = "js")]
-use wasm_bindgen::prelude::*;
+use {crate::QuoteCollateralV4, scale::DecodeAll, wasm_bindgen::prelude::*};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(Encode, Decode, TypeInfo))]
+#[non_exhaustive]
 pub struct VerifiedReport {
-    pub status: String,
+    pub status: TcbStatus,
     pub advisory_ids: Vec<String>,
     pub report: Report,
+    /// Commitment over the collateral this report was verified against.
+    pub collateral_commitment: CollateralCommitment,
+    /// Non-fatal issues the verification proceeded despite.
+    #[serde(default)]
+    pub warnings: Vec<Warning>,
+    /// A stable identifier of the machine the quote was produced on.
+    #[serde(default)]
+    pub platform_id: Option<PlatformId>,
+    /// Details of the TCB evaluation, for policies beyond the TCB status.
+    #[serde(default)]
+    pub supplemental: Option<SupplementalData>,
+    /// Where the collateral was fetched from, empty if unknown.
+    #[serde(default)]
+    pub provenance: Vec<CollateralSource>,
+}
+
+accessors!(VerifiedReport {
+    copy {
+        status: TcbStatus,
+    }
+    ref {
+        advisory_ids: [String],
+        report: Report,
+        collateral_commitment: CollateralCommitment,
+        warnings: [Warning],
+        platform_id: Option<PlatformId>,
+        supplemental: Option<SupplementalData>,
+        provenance: [CollateralSource],
+    }
+});
+
+impl VerifiedReport {
+    /// Check that the report data of the verified enclave or TD is `expected`, zero padded to 64
+    /// bytes. See [`ReportData`](crate::types::ReportData) to check digests of a nonce or key
+    /// instead.
+    pub fn verify_report_data(&self, expected: &[u8]) -> Result<()> {
+        self.report.report_data().expect(expected)
+    }
+}
+
+/// What the verification found out about the platform and collateral besides the TCB status,
+/// similar to the supplemental data of Intel's QVL.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(Encode, Decode, TypeInfo))]
+#[non_exhaustive]
+pub struct SupplementalData {
+    /// The `tcbDate` of the TCB level the platform matched, `None` if it matched none
+    pub tcb_date: Option<String>,
+    /// The TCB evaluation data number of the TCB info
+    pub tcb_evaluation_data_number: u32,
+    /// The advisories of the TCB levels the platform and its QE matched
+    pub advisory_ids: Vec<String>,
+    /// The status of the QE's TCB level in the QE identity
+    pub qe_identity_status: TcbStatus,
+    /// The FMSPC from the PCK certificate
+    pub fmspc: Fmspc,
+    /// The CPUSVN from the PCK certificate
+    pub cpu_svn: CpuSvn,
+    /// The PCESVN from the PCK certificate
+    pub pce_svn: Svn,
+    /// The earliest `nextUpdate` of the TCB info and QE identity, in seconds since the Unix epoch
+    pub earliest_expiration: u64,
+    /// The SGX type from the PCK certificate, `None` if this crate doesn't know it
+    #[serde(default)]
+    pub sgx_type: Option<SgxType>,
+    /// The configuration from the PCK certificate of a multi-package platform
+    #[serde(default)]
+    pub pck_configuration: Option<PckConfiguration>,
+}
+
+accessors!(SupplementalData {
+    copy {
+        tcb_evaluation_data_number: u32,
+        qe_identity_status: TcbStatus,
+        fmspc: Fmspc,
+        cpu_svn: CpuSvn,
+        pce_svn: Svn,
+        earliest_expiration: u64,
+        sgx_type: Option<SgxType>,
+        pck_configuration: Option<PckConfiguration>,
+    }
+    ref {
+        tcb_date: Option<String>,
+        advisory_ids: [String],
+    }
+});
+
+/// The TCB status of a platform, ordered from least to most trustworthy.
+///
+/// Converts to and from the status strings of Intel's TCB info.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(Encode, Decode, TypeInfo))]
+pub enum TcbStatus {
+    /// No TCB level of the TCB info matched the platform, or it had an unrecognized status
+    Unknown,
+    Revoked,
+    OutOfDateConfigurationNeeded,
+    OutOfDate,
+    #[serde(rename = "ConfigurationAndSWHardeningNeeded")]
+    ConfigurationAndSwHardeningNeeded,
+    ConfigurationNeeded,
+    #[serde(rename = "SWHardeningNeeded")]
+    SwHardeningNeeded,
+    UpToDate,
+}
+
+impl TcbStatus {
+    /// The status string as used in Intel's TCB info.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TcbStatus::Unknown => "Unknown",
+            TcbStatus::Revoked => "Revoked",
+            TcbStatus::OutOfDateConfigurationNeeded => "OutOfDateConfigurationNeeded",
+            TcbStatus::OutOfDate => "OutOfDate",
+            TcbStatus::ConfigurationAndSwHardeningNeeded => "ConfigurationAndSWHardeningNeeded",
+            TcbStatus::ConfigurationNeeded => "ConfigurationNeeded",
+            TcbStatus::SwHardeningNeeded => "SWHardeningNeeded",
+            TcbStatus::UpToDate => "UpToDate",
+        }
+    }
+
+    /// Whether the platform is trustworthy only with software hardening against the advisories.
+    pub fn needs_sw_hardening(&self) -> bool {
+        matches!(
+            self,
+            TcbStatus::SwHardeningNeeded | TcbStatus::ConfigurationAndSwHardeningNeeded
+        )
+    }
+}
+
+impl core::fmt::Display for TcbStatus {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl core::str::FromStr for TcbStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let status = match s {
+            "Unknown" => TcbStatus::Unknown,
+            "Revoked" => TcbStatus::Revoked,
+            "OutOfDateConfigurationNeeded" => TcbStatus::OutOfDateConfigurationNeeded,
+            "OutOfDate" => TcbStatus::OutOfDate,
+            "ConfigurationAndSWHardeningNeeded" => TcbStatus::ConfigurationAndSwHardeningNeeded,
+            "ConfigurationNeeded" => TcbStatus::ConfigurationNeeded,
+            "SWHardeningNeeded" => TcbStatus::SwHardeningNeeded,
+            "UpToDate" => TcbStatus::UpToDate,
+            _ => bail!("Unknown TCB status: {s}"),
+        };
+        Ok(status)
+    }
+}
+
+/// A per-machine identifier derived from the PCK certificate.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(Encode, Decode, TypeInfo))]
+#[serde(rename_all = "snake_case")]
+pub enum PlatformId {
+    /// The Platform Instance ID of a multi-package platform, from a Platform CA certificate
+    PlatformInstance(#[serde(with = "serde_bytes")] [u8; 16]),
+    /// The SHA-256 of the PPID, from a Processor CA certificate
+    PpidHash(#[serde(with = "serde_bytes")] [u8; 32]),
+}
+
+/// A non-fatal issue found during verification.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "borsh", derive(BorshSerialize, BorshDeserialize))]
+#[cfg_attr(feature = "scale-codec", derive(Encode, Decode, TypeInfo))]
+#[serde(rename_all = "snake_case")]
+pub enum Warning {
+    /// The collateral was used past its `nextUpdate`.
+    StaleCollateral {
+        /// How long ago the collateral expired, in seconds
+        expired_for: u64,
+    },
+    /// The collateral reaches its `nextUpdate` within [`EXPIRY_WARNING_WINDOW`].
+    CollateralExpiringSoon {
+        /// How long until the collateral expires, in seconds
+        expires_in: u64,
+    },
+    /// The TCB status asks for software hardening against the listed advisories.
+    SwHardeningNeeded,
+    /// The enclave or TD has its DEBUG attribute set, so its memory can be inspected by the host.
+    DebugEnabled,
+    /// The enclave has its PROVISIONKEY attribute set, so it can derive the provisioning key
+    /// ordinary workloads never need.
+    ProvisionKeyEnabled,
+    /// The enclave has its EINITTOKEN_KEY attribute set, so it can derive the launch key
+    /// ordinary workloads never need.
+    EinittokenKeyEnabled,
+    /// A reserved field of the quote is not all zeros.
+    ReservedBitsSet { field: String },
+    /// An expired certificate was accepted by
+    /// [`VerifyOptions::accept_expired_cert`].
+    ExpiredCertificateAccepted {
+        /// The hex encoded SHA-256 fingerprint of the certificate
+        fingerprint: String,
+        /// How long ago the certificate expired, in seconds
+        expired_for: u64,
+    },
+    /// The platform's TCB is out of date, accepted by [`VerifyOptions::out_of_date_as_warning`].
+    TcbOutOfDate,
 }
 
+/// How long before the collateral's `nextUpdate` a [`Warning::CollateralExpiringSoon`] is raised.
+pub const EXPIRY_WARNING_WINDOW: u64 = 7 * 24 * 60 * 60;
+
 #[cfg(feature = "js")]
 #[wasm_bindgen]
 pub fn js_verify(
@@ -35,7 +259,10 @@ pub fn js_verify(
         .map_err(|_| JsValue::from_str("Failed to decode raw_quote"))?;
     let quote_collateral_bytes: Vec<u8> = serde_wasm_bindgen::from_value(quote_collateral)
         .map_err(|_| JsValue::from_str("Failed to decode quote_collateral"))?;
-    let quote_collateral = QuoteCollateralV3::decode(&mut quote_collateral_bytes.as_slice())
+    // A V3 encoding runs out of input as V4, a V4 one has trailing bytes as V3
+    let quote_collateral = QuoteCollateralV4::decode_all(&mut quote_collateral_bytes.as_slice())
+        .map(QuoteCollateralV3::from)
+        .or_else(|_| QuoteCollateralV3::decode_all(&mut quote_collateral_bytes.as_slice()))
         .map_err(|_| JsValue::from_str("Failed to decode quote_collateral_bytes"))?;
 
     let verified_report = verify(&raw_quote, &quote_collateral, now)
@@ -62,56 +289,920 @@ pub fn verify(
     quote_collateral: &QuoteCollateralV3,
     now: u64,
 ) -> Result<VerifiedReport> {
-    // Parse data
-    let mut quote = raw_quote;
-    let quote = Quote::decode(&mut quote).context("Failed to decode quote")?;
-    let signed_quote_len = quote.signed_length();
+    verify_with_signature_verifier(raw_quote, quote_collateral, now, &RingVerifier)
+}
 
-    let tcb_info = serde_json::from_str::<TcbInfo>(&quote_collateral.tcb_info)
-        .context("Failed to decode TcbInfo")?;
+/// Options for [`verify_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct VerifyOptions {
+    /// Fail unless the collateral carries the root CA and PCK CRLs and every certificate of every
+    /// chain is covered by them. By default CRLs are only checked when present.
+    pub require_crls: bool,
+    /// Fail unless both the TCB info and QE identity have at least this
+    /// `tcbEvaluationDataNumber`, to enforce that a TCB recovery has been picked up.
+    pub min_tcb_evaluation_data_number: Option<u32>,
+    /// Accept these expired certificates in the PCK and collateral signing chains, for Intel
+    /// certificate transitions. Each accepted certificate is reported as a
+    /// [`Warning::ExpiredCertificateAccepted`].
+    pub accept_expired_cert: Option<AcceptExpiredCert>,
+    /// Fail if the collateral is used more than this many seconds past its earliest
+    /// `nextUpdate`, allowing for clock skew between the verifier and the PCCS. By default stale
+    /// collateral is only reported as a [`Warning::StaleCollateral`].
+    pub collateral_grace_period: Option<u64>,
+    /// Fail unless the TCB status is one of these, e.g. `UpToDate` and `SWHardeningNeeded`. By
+    /// default every status is returned for the caller to judge.
+    pub allowed_tcb_statuses: Option<Vec<TcbStatus>>,
+    /// Accept `OutOfDate` and `OutOfDateConfigurationNeeded` platforms even if not in
+    /// [`allowed_tcb_statuses`](Self::allowed_tcb_statuses), reporting them as a
+    /// [`Warning::TcbOutOfDate`].
+    pub out_of_date_as_warning: bool,
+    /// The DER encoded root CA certificate to verify the certificate chains up to instead of the
+    /// pinned Intel SGX root CA, for test PCCS deployments with their own PKI.
+    pub root_ca: Option<Vec<u8>>,
+}
 
-    // TODO UNCOMMENT THIS IN PRODUCTION
+/// Decides which expired certificates to accept, by the SHA-256 fingerprint of their DER
+/// encoding.
+#[derive(Clone)]
+pub struct AcceptExpiredCert(Arc<dyn Fn(&[u8; 32]) -> bool + Send + Sync>);
 
-    // let next_update = chrono::DateTime::parse_from_rfc3339(&tcb_info.next_update)
-    //     .ok()
-    //     .context("Failed to parse next update")?;
+impl AcceptExpiredCert {
+    /// Accept the expired certificates `accept` returns true for.
+    pub fn new(accept: impl Fn(&[u8; 32]) -> bool + Send + Sync + 'static) -> Self {
+        Self(Arc::new(accept))
+    }
 
-    // if now > next_update.timestamp() as u64 {
-    //     bail!("TCBInfo expired");
-    // }
+    /// Accept the expired certificates with these fingerprints.
+    pub fn fingerprints(fingerprints: impl IntoIterator<Item = [u8; 32]>) -> Self {
+        let fingerprints = fingerprints.into_iter().collect::<Vec<_>>();
+        Self::new(move |fingerprint| fingerprints.contains(fingerprint))
+    }
+}
 
-    let now_in_milli = now * 1000;
+impl core::fmt::Debug for AcceptExpiredCert {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("AcceptExpiredCert(..)")
+    }
+}
 
-    // Verify enclave
+/// Check the certificate chains and signatures of the TCB info and QE identity in the collateral,
+/// without a quote.
+///
+/// This lets collateral fetchers detect a corrupted mirror at fetch time rather than when a quote
+/// is verified against the collateral.
+///
+/// # Arguments
+///
+/// * `quote_collateral` - The quote collateral to check
+/// * `now` - The current time in seconds since the Unix epoch
+pub fn verify_collateral(quote_collateral: &QuoteCollateralV3, now: u64) -> Result<()> {
+    let revocation = Revocation::new(quote_collateral, &VerifyOptions::default());
+    verify_tcb_info(quote_collateral, now, &revocation, &RingVerifier)?;
+    verify_qe_identity(quote_collateral, now, &revocation, &RingVerifier)?;
+    Ok(())
+}
 
-    // Seems we verify MR_ENCLAVE and MR_SIGNER is enough
-    // skip verify_misc_select_field
-    // skip verify_attributes_field
+/// Check a new QE identity against the one it replaces, so collateral pipelines can reject a
+/// regressive or substituted identity before swapping it into caches.
+///
+/// The new identity must be of the same QE, with the same `id`, MRSIGNER and ISVPRODID, and must
+/// not roll back: its `tcbEvaluationDataNumber`, `issueDate` and highest ISVSVN are at least
+/// those of the previous one. Its signature is not checked, see [`verify_qe_identity_update`].
+///
+/// # Arguments
+///
+/// * `previous` - The QE identity JSON in use, as in [`QuoteCollateralV3::qe_identity`]
+/// * `next` - The new QE identity JSON
+pub fn check_qe_identity_update(previous: &str, next: &str) -> Result<()> {
+    let previous = serde_json::from_str::<QeIdentity>(previous)
+        .msg_context("Failed to decode previous QeIdentity")?;
+    let next =
+        serde_json::from_str::<QeIdentity>(next).msg_context("Failed to decode new QeIdentity")?;
+    if next.id != previous.id {
+        bail!("QE identity id changed from {} to {}", previous.id, next.id);
+    }
+    if !next.mrsigner.eq_ignore_ascii_case(&previous.mrsigner) {
+        bail!(
+            "QE MRSIGNER changed from {} to {}",
+            previous.mrsigner,
+            next.mrsigner
+        );
+    }
+    if next.isvprodid != previous.isvprodid {
+        bail!(
+            "QE ISVPRODID changed from {} to {}",
+            previous.isvprodid,
+            next.isvprodid
+        );
+    }
+    if next.tcb_evaluation_data_number < previous.tcb_evaluation_data_number {
+        bail!(
+            "QE identity tcbEvaluationDataNumber rolled back from {} to {}",
+            previous.tcb_evaluation_data_number,
+            next.tcb_evaluation_data_number
+        );
+    }
+    if utils::parse_time(&next.issue_date)? < utils::parse_time(&previous.issue_date)? {
+        bail!(
+            "QE identity issueDate rolled back from {} to {}",
+            previous.issue_date,
+            next.issue_date
+        );
+    }
+    let max_isvsvn = |identity: &QeIdentity| {
+        identity
+            .tcb_levels
+            .iter()
+            .map(|level| level.tcb.isvsvn)
+            .max()
+            .unwrap_or_default()
+    };
+    if max_isvsvn(&next) < max_isvsvn(&previous) {
+        bail!(
+            "QE identity ISVSVN rolled back from {} to {}",
+            max_isvsvn(&previous),
+            max_isvsvn(&next)
+        );
+    }
+    Ok(())
+}
 
-    // Verify integrity
+/// Check the certificate chain and signature of a new QE identity as [`verify_collateral`] does,
+/// then check it against the one it replaces with [`check_qe_identity_update`].
+///
+/// # Arguments
+///
+/// * `previous` - The collateral in use
+/// * `next` - The newly fetched collateral
+/// * `now` - The current time in seconds since the Unix epoch
+pub fn verify_qe_identity_update(
+    previous: &QuoteCollateralV3,
+    next: &QuoteCollateralV3,
+    now: u64,
+) -> Result<()> {
+    let revocation = Revocation::new(next, &VerifyOptions::default());
+    verify_qe_identity(next, now, &revocation, &RingVerifier)?;
+    check_qe_identity_update(&previous.qe_identity, &next.qe_identity)
+}
+
+/// Verify a quote with the given options.
+///
+/// See [`verify`] for the arguments.
+pub fn verify_with_options(
+    raw_quote: &[u8],
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+    options: &VerifyOptions,
+) -> Result<VerifiedReport> {
+    verify_impl(raw_quote, quote_collateral, now, options, &RingVerifier)
+}
+
+/// Verify a quote, checking its ECDSA signatures with the given [`SignatureVerifier`].
+///
+/// See [`verify`] for the arguments.
+pub fn verify_with_signature_verifier(
+    raw_quote: &[u8],
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+    signature_verifier: &dyn SignatureVerifier,
+) -> Result<VerifiedReport> {
+    verify_impl(
+        raw_quote,
+        quote_collateral,
+        now,
+        &VerifyOptions::default(),
+        signature_verifier,
+    )
+}
+
+/// Verify a quote with the given options, recording the steps run, how long each took and which
+/// failed.
+///
+/// The trace is returned whether or not the quote verifies, to tell where a failure came from.
+/// See [`verify`] for the arguments.
+pub fn verify_traced(
+    raw_quote: &[u8],
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+    options: &VerifyOptions,
+) -> (Result<VerifiedReport>, VerifyTrace) {
+    let mut trace = VerifyTrace::default();
+    let result = verify_impl_traced(
+        raw_quote,
+        quote_collateral,
+        now,
+        options,
+        &RingVerifier,
+        &mut trace,
+    );
+    (result, trace)
+}
+
+fn verify_impl(
+    raw_quote: &[u8],
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+    options: &VerifyOptions,
+    signature_verifier: &dyn SignatureVerifier,
+) -> Result<VerifiedReport> {
+    verify_impl_traced(
+        raw_quote,
+        quote_collateral,
+        now,
+        options,
+        signature_verifier,
+        &mut VerifyTrace::default(),
+    )
+}
+
+pub(crate) fn verify_impl_traced(
+    raw_quote: &[u8],
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+    options: &VerifyOptions,
+    signature_verifier: &dyn SignatureVerifier,
+    trace: &mut VerifyTrace,
+) -> Result<VerifiedReport> {
+    CheckedCollateral::new_traced(quote_collateral, now, options, signature_verifier, trace)?
+        .verify_traced(raw_quote, now, signature_verifier, trace)
+}
+
+/// Collateral with its signatures checked and its JSON parsed, to verify quotes against.
+pub(crate) struct CheckedCollateral<'a> {
+    collateral: &'a QuoteCollateralV3,
+    revocation: Revocation<'a>,
+    tcb_info: TcbInfo,
+    qe_identity: QeIdentity,
+    warnings: Vec<Warning>,
+    commitment: CollateralCommitment,
+    allowed_tcb_statuses: Option<Vec<TcbStatus>>,
+    out_of_date_as_warning: bool,
+}
+
+impl<'a> CheckedCollateral<'a> {
+    pub(crate) fn new(
+        quote_collateral: &'a QuoteCollateralV3,
+        now: u64,
+        options: &VerifyOptions,
+        signature_verifier: &dyn SignatureVerifier,
+    ) -> Result<Self> {
+        Self::new_traced(
+            quote_collateral,
+            now,
+            options,
+            signature_verifier,
+            &mut VerifyTrace::default(),
+        )
+    }
+
+    fn new_traced(
+        quote_collateral: &'a QuoteCollateralV3,
+        now: u64,
+        options: &VerifyOptions,
+        signature_verifier: &dyn SignatureVerifier,
+        trace: &mut VerifyTrace,
+    ) -> Result<Self> {
+        let mut steps = Steps::new(trace);
+        let revocation = Revocation::new(quote_collateral, options);
+        steps.begin(VerifyStep::TcbInfo);
+        let (tcb_info, mut warnings) =
+            verify_tcb_info(quote_collateral, now, &revocation, signature_verifier)?;
+        steps.begin(VerifyStep::QeIdentity);
+        let (qe_identity, qe_identity_warnings) =
+            verify_qe_identity(quote_collateral, now, &revocation, signature_verifier)?;
+        steps.begin(VerifyStep::CollateralPolicy);
+        check_tcb_evaluation_data_number(&tcb_info, &qe_identity, options)?;
+        check_collateral_grace_period(&tcb_info, &qe_identity, now, options)?;
+        // Both documents are usually signed by the same chain
+        for warning in qe_identity_warnings {
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+        warnings.extend(collateral_warnings(&tcb_info, &qe_identity, now)?);
+        steps.finish();
+        Ok(Self {
+            collateral: quote_collateral,
+            revocation,
+            tcb_info,
+            qe_identity,
+            warnings,
+            commitment: commitment::commit(quote_collateral),
+            allowed_tcb_statuses: options.allowed_tcb_statuses.clone(),
+            out_of_date_as_warning: options.out_of_date_as_warning,
+        })
+    }
+
+    /// Verify a quote against the collateral, at the time it was checked at.
+    pub(crate) fn verify(
+        &self,
+        raw_quote: &[u8],
+        now: u64,
+        signature_verifier: &dyn SignatureVerifier,
+    ) -> Result<VerifiedReport> {
+        self.verify_traced(
+            raw_quote,
+            now,
+            signature_verifier,
+            &mut VerifyTrace::default(),
+        )
+    }
+
+    fn verify_traced(
+        &self,
+        raw_quote: &[u8],
+        now: u64,
+        signature_verifier: &dyn SignatureVerifier,
+        trace: &mut VerifyTrace,
+    ) -> Result<VerifiedReport> {
+        let verdict = verify_quote_traced(
+            raw_quote,
+            &self.tcb_info,
+            &self.qe_identity,
+            now,
+            &self.revocation,
+            signature_verifier,
+            trace,
+        )?;
+        let mut steps = Steps::new(trace);
+        steps.begin(VerifyStep::StatusPolicy);
+        let mut warnings = self.warnings.clone();
+        warnings.extend(verdict.warnings);
+        let out_of_date = matches!(
+            verdict.status,
+            TcbStatus::OutOfDate | TcbStatus::OutOfDateConfigurationNeeded
+        );
+        if out_of_date && self.out_of_date_as_warning {
+            warnings.push(Warning::TcbOutOfDate);
+        } else if let Some(allowed) = &self.allowed_tcb_statuses {
+            if !allowed.contains(&verdict.status) {
+                bail!("TCB status {} is not allowed", verdict.status);
+            }
+        }
+        steps.finish();
+        Ok(VerifiedReport {
+            status: verdict.status,
+            advisory_ids: verdict.advisory_ids,
+            report: verdict.report,
+            collateral_commitment: self.commitment.clone(),
+            warnings,
+            platform_id: Some(verdict.platform_id),
+            supplemental: Some(verdict.supplemental),
+            provenance: self.collateral.provenance.clone(),
+        })
+    }
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(ring::digest::digest(&ring::digest::SHA256, data).as_ref());
+    digest
+}
+
+/// The earliest `nextUpdate` of the TCB info and QE identity.
+fn collateral_next_update(tcb_info: &TcbInfo, qe_identity: &QeIdentity) -> Result<u64> {
+    Ok(utils::parse_time(&tcb_info.next_update)?.min(utils::parse_time(&qe_identity.next_update)?))
+}
+
+/// Warn about collateral that expired or is about to.
+fn collateral_warnings(
+    tcb_info: &TcbInfo,
+    qe_identity: &QeIdentity,
+    now: u64,
+) -> Result<Vec<Warning>> {
+    let next_update = collateral_next_update(tcb_info, qe_identity)?;
+    let warning = if now >= next_update {
+        Warning::StaleCollateral {
+            expired_for: now - next_update,
+        }
+    } else if next_update - now < EXPIRY_WARNING_WINDOW {
+        Warning::CollateralExpiringSoon {
+            expires_in: next_update - now,
+        }
+    } else {
+        return Ok(Vec::new());
+    };
+    Ok(vec![warning])
+}
+
+/// Warn about a verified quote that is trustworthy only with caveats.
+pub(crate) fn quote_warnings(
+    raw_quote: &[u8],
+    report: &Report,
+    tcb_status: TcbStatus,
+) -> Result<Vec<Warning>> {
+    let mut warnings = Vec::new();
+    if tcb_status.needs_sw_hardening() {
+        warnings.push(Warning::SwHardeningNeeded);
+    }
+    if report.is_debug() {
+        warnings.push(Warning::DebugEnabled);
+    }
+    if report.has_provision_key() {
+        warnings.push(Warning::ProvisionKeyEnabled);
+    }
+    if report.has_einittoken_key() {
+        warnings.push(Warning::EinittokenKeyEnabled);
+    }
+    for anomaly in Quote::anomalies(raw_quote)? {
+        if let Anomaly::ReservedBitsSet { field } = anomaly {
+            warnings.push(Warning::ReservedBitsSet { field });
+        }
+    }
+    Ok(warnings)
+}
+
+/// The error of a certificate chain with a certificate revoked by the root CA or PCK CRL.
+///
+/// Verification errors carry it as their root cause, so a revoked platform can be told apart from
+/// other failures with `err.root_cause().downcast_ref::<CertificateRevoked>()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CertificateRevoked;
+
+impl core::fmt::Display for CertificateRevoked {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("Certificate is revoked")
+    }
+}
+
+impl core::error::Error for CertificateRevoked {}
+
+/// Check the collateral isn't staler than [`VerifyOptions::collateral_grace_period`].
+fn check_collateral_grace_period(
+    tcb_info: &TcbInfo,
+    qe_identity: &QeIdentity,
+    now: u64,
+    options: &VerifyOptions,
+) -> Result<()> {
+    let Some(grace_period) = options.collateral_grace_period else {
+        return Ok(());
+    };
+    let expired_for = now.saturating_sub(collateral_next_update(tcb_info, qe_identity)?);
+    if expired_for > grace_period {
+        bail!("Collateral expired {expired_for}s ago, beyond the grace period of {grace_period}s");
+    }
+    Ok(())
+}
+
+/// The CRLs to check certificate chains against.
+pub(crate) struct Revocation<'a> {
+    pub root_ca_crl: Option<&'a [u8]>,
+    pub pck_crl: Option<&'a [u8]>,
+    pub require: bool,
+    pub accept_expired: Option<AcceptExpiredCert>,
+    /// The DER encoded root CA replacing the pinned one
+    pub root_ca: Option<Vec<u8>>,
+}
 
-    // Check TCB info cert chain and signature
-    let leaf_certs = extract_certs(quote_collateral.tcb_info_issuer_chain.as_bytes())?;
-    if leaf_certs.len() < 2 {
-        bail!("Certificate chain is too short in quote_collateral");
+impl<'a> Revocation<'a> {
+    pub(crate) fn new(quote_collateral: &'a QuoteCollateralV3, options: &VerifyOptions) -> Self {
+        Self {
+            root_ca_crl: quote_collateral.root_ca_crl.as_deref(),
+            pck_crl: quote_collateral.pck_crl.as_deref(),
+            require: options.require_crls,
+            accept_expired: options.accept_expired_cert.clone(),
+            root_ca: options.root_ca.clone(),
+        }
+    }
+
+    /// Verify a certificate chain at `now` against `crls` up to the root CA, accepting the expired
+    /// certificates of [`VerifyOptions::accept_expired_cert`].
+    ///
+    /// With accepted certificates, the chain is checked at the time the first of them expired, and
+    /// every other certificate is checked to be valid at `now`.
+    fn verify_chain(
+        &self,
+        leaf: &CertificateDer,
+        leaf_cert: &webpki::EndEntityCert,
+        intermediate_certs: &[CertificateDer],
+        now: u64,
+        crls: &[&[u8]],
+    ) -> Result<Vec<Warning>> {
+        let mut warnings = Vec::new();
+        let mut chain_time = now;
+        let mut unexpired = Vec::new();
+        for cert in core::iter::once(leaf).chain(intermediate_certs) {
+            if let Some(accept) = &self.accept_expired {
+                let (_, not_after) = utils::cert_validity(cert)?;
+                if not_after < now {
+                    let fingerprint = sha256(cert);
+                    if (accept.0)(&fingerprint) {
+                        warnings.push(Warning::ExpiredCertificateAccepted {
+                            fingerprint: hex::encode(fingerprint),
+                            expired_for: now - not_after,
+                        });
+                        chain_time = chain_time.min(not_after);
+                        continue;
+                    }
+                }
+            }
+            unexpired.push(cert);
+        }
+        if chain_time < now {
+            for cert in unexpired {
+                let (not_before, not_after) = utils::cert_validity(cert)?;
+                if now < not_before || now > not_after {
+                    bail!("Certificate is not valid at the verification time");
+                }
+            }
+        }
+        let root_ca = self.root_ca.as_deref().map(CertificateDer::from);
+        let custom_roots;
+        let roots: &[webpki::types::TrustAnchor] = match &root_ca {
+            Some(root_ca) => {
+                custom_roots = [webpki::anchor_from_trusted_cert(root_ca)
+                    .map_err(|_| anyhow!("Failed to parse root CA certificate"))?];
+                &custom_roots
+            }
+            None => DCAP_SERVER_ROOTS,
+        };
+        verify_certificate_chain(
+            leaf_cert,
+            intermediate_certs,
+            roots,
+            chain_time * 1000,
+            crls,
+            self.require,
+        )?;
+        Ok(warnings)
+    }
+
+    /// The CRLs covering the collateral signing chains, which are issued by the root CA.
+    fn collateral_crls(&self) -> Result<Vec<&'a [u8]>> {
+        if self.require && self.root_ca_crl.is_none() {
+            bail!("Root CA CRL is required but missing in quote_collateral");
+        }
+        Ok(self.root_ca_crl.into_iter().collect())
+    }
+
+    /// The CRLs covering the PCK chain, issued by the root CA and the PCK CA.
+    fn pck_crls(&self) -> Result<Vec<&'a [u8]>> {
+        if self.require && self.pck_crl.is_none() {
+            bail!("PCK CRL is required but missing in quote_collateral");
+        }
+        let mut crls = self.collateral_crls()?;
+        crls.extend(self.pck_crl);
+        Ok(crls)
+    }
+}
+
+/// Check the collateral is at least as recent as [`VerifyOptions::min_tcb_evaluation_data_number`].
+fn check_tcb_evaluation_data_number(
+    tcb_info: &TcbInfo,
+    qe_identity: &QeIdentity,
+    options: &VerifyOptions,
+) -> Result<()> {
+    let Some(required) = options.min_tcb_evaluation_data_number else {
+        return Ok(());
+    };
+    for (name, number) in [
+        ("TCB info", tcb_info.tcb_evaluation_data_number),
+        ("QE identity", qe_identity.tcb_evaluation_data_number),
+    ] {
+        if number < required {
+            bail!("Collateral too old for policy: {name} tcbEvaluationDataNumber {number} is below {required}");
+        }
+    }
+    Ok(())
+}
+
+/// The outcome of checking a quote against an already verified TCB info.
+pub(crate) struct QuoteVerdict {
+    pub status: TcbStatus,
+    pub advisory_ids: Vec<String>,
+    pub report: Report,
+    pub platform_id: PlatformId,
+    pub warnings: Vec<Warning>,
+    pub supplemental: SupplementalData,
+}
+
+/// Check the TCB info certificate chain and signature in the collateral and parse it, with the
+/// warnings of the chain check.
+pub(crate) fn verify_tcb_info(
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+    revocation: &Revocation,
+    signature_verifier: &dyn SignatureVerifier,
+) -> Result<(TcbInfo, Vec<Warning>)> {
+    let tcb_info = serde_json::from_str::<TcbInfo>(&quote_collateral.tcb_info)
+        .msg_context("Failed to decode TcbInfo")?;
+
+    let warnings = verify_signed_collateral(
+        "tcb_info",
+        &quote_collateral.tcb_info_issuer_chain,
+        &quote_collateral.tcb_info,
+        &quote_collateral.tcb_info_signature,
+        now,
+        revocation,
+        signature_verifier,
+    )?;
+    Ok((tcb_info, warnings))
+}
+
+/// Check the QE identity certificate chain and signature in the collateral and parse it, with the
+/// warnings of the chain check.
+pub(crate) fn verify_qe_identity(
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+    revocation: &Revocation,
+    signature_verifier: &dyn SignatureVerifier,
+) -> Result<(QeIdentity, Vec<Warning>)> {
+    let qe_identity = serde_json::from_str::<QeIdentity>(&quote_collateral.qe_identity)
+        .msg_context("Failed to decode QeIdentity")?;
+    let warnings = verify_signed_collateral(
+        "qe_identity",
+        &quote_collateral.qe_identity_issuer_chain,
+        &quote_collateral.qe_identity,
+        &quote_collateral.qe_identity_signature,
+        now,
+        revocation,
+        signature_verifier,
+    )?;
+    Ok((qe_identity, warnings))
+}
+
+/// Check the issuer chain of a signed collateral document and its signature, returning the
+/// warnings of the chain check.
+fn verify_signed_collateral(
+    name: &str,
+    issuer_chain: &str,
+    body: &str,
+    signature: &[u8],
+    now: u64,
+    revocation: &Revocation,
+    signature_verifier: &dyn SignatureVerifier,
+) -> Result<Vec<Warning>> {
+    // The root CA may be omitted from the chain since it is pinned.
+    let leaf_certs = extract_certs(issuer_chain.as_bytes())?;
+    if leaf_certs.is_empty() {
+        bail!("Certificate chain is empty for {name} in quote_collateral");
     }
     let leaf_cert: webpki::EndEntityCert = webpki::EndEntityCert::try_from(&leaf_certs[0])
-        .context("Failed to parse leaf certificate in quote_collateral")?;
+        .msg_context(format!(
+            "Failed to parse {name} leaf certificate in quote_collateral"
+        ))?;
     let intermediate_certs = &leaf_certs[1..];
-    verify_certificate_chain(&leaf_cert, intermediate_certs, now_in_milli)?;
-    let asn1_signature = encode_as_der(&quote_collateral.tcb_info_signature)?;
-    if leaf_cert
-        .verify_signature(
-            webpki::ring::ECDSA_P256_SHA256,
-            quote_collateral.tcb_info.as_bytes(),
-            &asn1_signature,
+    let warnings = revocation
+        .verify_chain(
+            &leaf_certs[0],
+            &leaf_cert,
+            intermediate_certs,
+            now,
+            &revocation.collateral_crls()?,
         )
-        .is_err()
+        .with_context(|| format!("Invalid {name} certificate chain in quote_collateral"))?;
+    let (algorithm, signing_key) = utils::get_signing_key(&leaf_certs[0])?;
+    if signature.len() != algorithm.signature_len() {
+        bail!("Invalid {name} signature length in quote_collateral");
+    }
+    if signature::verify_with_algorithm(
+        signature_verifier,
+        algorithm,
+        &signing_key,
+        body.as_bytes(),
+        signature,
+    )
+    .is_err()
     {
         return Err(anyhow!(
-            "Rsa signature is invalid for tcb_info in quote_collateral"
+            "Rsa signature is invalid for {name} in quote_collateral"
         ));
     }
+    Ok(warnings)
+}
+
+/// Match a QE report against the QE identity, naming the first field that differs.
+fn match_qe_identity(qe_report: &EnclaveReport, qe_identity: &QeIdentity) -> Result<()> {
+    let mrsigner = hex::decode(&qe_identity.mrsigner)
+        .ok()
+        .context("Failed to decode QE identity mrsigner")?;
+    if qe_report.mr_signer[..] != mrsigner[..] {
+        bail!(
+            "QE identity mismatch: mrsigner expected {}, actual {}",
+            qe_identity.mrsigner.to_lowercase(),
+            hex::encode(qe_report.mr_signer)
+        );
+    }
+    if qe_report.isv_prod_id != qe_identity.isvprodid {
+        bail!(
+            "QE identity mismatch: isvprodid expected {}, actual {}",
+            qe_identity.isvprodid,
+            qe_report.isv_prod_id
+        );
+    }
+    let miscselect = u32::from_str_radix(&qe_identity.miscselect, 16)
+        .ok()
+        .context("Failed to decode QE identity miscselect")?;
+    let miscselect_mask = u32::from_str_radix(&qe_identity.miscselect_mask, 16)
+        .ok()
+        .context("Failed to decode QE identity miscselect mask")?;
+    if qe_report.misc_select & miscselect_mask != miscselect {
+        bail!(
+            "QE identity mismatch: miscselect under mask {miscselect_mask:08x} expected {miscselect:08x}, actual {:08x}",
+            qe_report.misc_select & miscselect_mask
+        );
+    }
+    let attributes = hex::decode(&qe_identity.attributes)
+        .ok()
+        .context("Failed to decode QE identity attributes")?;
+    let attributes_mask = hex::decode(&qe_identity.attributes_mask)
+        .ok()
+        .context("Failed to decode QE identity attributes mask")?;
+    if attributes.len() != qe_report.attributes.len()
+        || attributes_mask.len() != qe_report.attributes.len()
+    {
+        bail!("Invalid QE identity attributes length");
+    }
+    let masked = qe_report
+        .attributes
+        .iter()
+        .zip(&attributes_mask)
+        .map(|(a, m)| a & m)
+        .collect::<Vec<_>>();
+    if masked != attributes {
+        bail!(
+            "QE identity mismatch: attributes under mask {} expected {}, actual {}",
+            hex::encode(attributes_mask),
+            hex::encode(attributes),
+            hex::encode(masked)
+        );
+    }
+    Ok(())
+}
+
+/// Match the TDX module of a TD report against the TDX TCB info.
+///
+/// Modules of major version 0, or checked against TCB info without module identities, are matched
+/// against `tdxModule` and have no TCB levels of their own. Newer modules are matched against the
+/// `tdxModuleIdentities` entry of their version, whose TCB level status is returned with its
+/// advisory IDs. [`TcbStatus::Unknown`] is returned if the module SVN is below all of its levels.
+#[cfg(any(feature = "tdx", not(feature = "sgx")))]
+fn match_tdx_module<'a>(
+    td_report: &TDReport10,
+    tcb_info: &'a TcbInfo,
+) -> Result<Option<(TcbStatus, &'a [String])>> {
+    let [module_svn, module_version, ..] = td_report.tee_tcb_svn;
+    if module_version == 0 || tcb_info.tdx_module_identities.is_empty() {
+        if let Some(module) = &tcb_info.tdx_module {
+            match_tdx_module_identity(
+                td_report,
+                &module.mrsigner,
+                &module.attributes,
+                &module.attributes_mask,
+            )?;
+        }
+        return Ok(None);
+    }
+    let id = format!("TDX_{module_version:02}");
+    let identity = tcb_info
+        .tdx_module_identities
+        .iter()
+        .find(|identity| identity.id.eq_ignore_ascii_case(&id))
+        .with_context(|| format!("No TDX module identity {id} in the TCB info"))?;
+    match_tdx_module_identity(
+        td_report,
+        &identity.mrsigner,
+        &identity.attributes,
+        &identity.attributes_mask,
+    )?;
+    let level = identity
+        .tcb_levels
+        .iter()
+        .find(|level| module_svn >= level.tcb.isvsvn);
+    Ok(Some(match level {
+        Some(level) => (
+            level.tcb_status.parse().unwrap_or(TcbStatus::Unknown),
+            &level.advisory_ids[..],
+        ),
+        None => (TcbStatus::Unknown, &[][..]),
+    }))
+}
+
+#[cfg(any(feature = "tdx", not(feature = "sgx")))]
+fn match_tdx_module_identity(
+    td_report: &TDReport10,
+    mrsigner: &str,
+    attributes: &str,
+    attributes_mask: &str,
+) -> Result<()> {
+    let mrsigner = hex::decode(mrsigner)
+        .ok()
+        .context("Failed to decode TDX module mrsigner")?;
+    if td_report.mr_signer_seam[..] != mrsigner[..] {
+        bail!(
+            "TDX module identity mismatch: mrsigner expected {}, actual {}",
+            hex::encode(mrsigner),
+            hex::encode(td_report.mr_signer_seam)
+        );
+    }
+    let attributes = hex::decode(attributes)
+        .ok()
+        .context("Failed to decode TDX module attributes")?;
+    let attributes_mask = hex::decode(attributes_mask)
+        .ok()
+        .context("Failed to decode TDX module attributes mask")?;
+    if attributes.len() != td_report.seam_attributes.len()
+        || attributes_mask.len() != td_report.seam_attributes.len()
+    {
+        bail!("Invalid TDX module attributes length");
+    }
+    let masked = td_report
+        .seam_attributes
+        .iter()
+        .zip(&attributes_mask)
+        .map(|(a, m)| a & m)
+        .collect::<Vec<_>>();
+    if masked != attributes {
+        bail!(
+            "TDX module identity mismatch: attributes under mask {} expected {}, actual {}",
+            hex::encode(attributes_mask),
+            hex::encode(attributes),
+            hex::encode(masked)
+        );
+    }
+    Ok(())
+}
+
+/// Combine the TCB status of the platform with the one of its QE or TDX module, as Intel's QVL
+/// does.
+///
+/// An out of date module makes the platform out of date, keeping whether it needs configuration.
+/// A revoked module, or one matching no TCB level, makes the platform so too.
+pub(crate) fn merge_module_status(platform: TcbStatus, module: TcbStatus) -> TcbStatus {
+    match (module, platform) {
+        (TcbStatus::UpToDate, platform) => platform,
+        (
+            TcbStatus::OutOfDate,
+            TcbStatus::ConfigurationNeeded | TcbStatus::ConfigurationAndSwHardeningNeeded,
+        ) => TcbStatus::OutOfDateConfigurationNeeded,
+        (module, platform) => module.min(platform),
+    }
+}
+
+/// Fail for quotes of a TEE left out of this build by the `sgx` and `tdx` features.
+fn check_tee_enabled(quote: &Quote) -> Result<()> {
+    let sgx = cfg!(any(feature = "sgx", not(feature = "tdx")));
+    let tdx = cfg!(any(feature = "tdx", not(feature = "sgx")));
+    match quote.header.is_sgx() {
+        true if !sgx => bail!("SGX quotes are not supported by this build"),
+        false if !tdx => bail!("TDX quotes are not supported by this build"),
+        _ => Ok(()),
+    }
+}
+
+/// Check the signatures of a quote, match its QE against an already verified QE identity and its
+/// TCB against an already verified TCB info.
+pub(crate) fn verify_quote(
+    raw_quote: &[u8],
+    tcb_info: &TcbInfo,
+    qe_identity: &QeIdentity,
+    now: u64,
+    revocation: &Revocation,
+    signature_verifier: &dyn SignatureVerifier,
+) -> Result<QuoteVerdict> {
+    verify_quote_traced(
+        raw_quote,
+        tcb_info,
+        qe_identity,
+        now,
+        revocation,
+        signature_verifier,
+        &mut VerifyTrace::default(),
+    )
+}
+
+/// What the signatures of a quote vouch for.
+pub(crate) struct SignedQuote {
+    pub header: Header,
+    pub report: Report,
+    /// The DER encoded PCK certificate
+    pub pck_cert: Vec<u8>,
+    pub qe_report: EnclaveReport,
+    /// The warnings of the PCK certificate chain
+    pub chain_warnings: Vec<Warning>,
+}
+
+/// Check the signatures of a decoded quote: the PCK certificate chain, the QE report signed by
+/// the PCK key, the attestation key the QE report binds and the quote signed by it. The QE is
+/// matched against `qe_identity` if given.
+pub(crate) fn check_quote_signatures(
+    raw_quote: &[u8],
+    quote: Quote,
+    qe_identity: Option<&QeIdentity>,
+    now: u64,
+    revocation: &Revocation,
+    signature_verifier: &dyn SignatureVerifier,
+    steps: &mut Steps,
+) -> Result<SignedQuote> {
+    let signed_quote_len = quote.signed_length();
+
+    // Verify enclave
+
+    // Seems we verify MR_ENCLAVE and MR_SIGNER is enough
+    // skip verify_misc_select_field
+    // skip verify_attributes_field
+
+    // Verify integrity
 
     // Check quote fields
     if ![3, 4, 5].contains(&quote.header.version) {
@@ -131,23 +1222,33 @@ pub fn verify(
         bail!("Unsupported DCAP PCK cert format");
     }
 
+    steps.begin(VerifyStep::PckCertChain);
     let certification_certs = extract_certs(&certification_data.body.data)?;
     if certification_certs.len() < 2 {
         bail!("Certificate chain is too short in quote");
     }
     // Check certification_data
     let leaf_cert: webpki::EndEntityCert = webpki::EndEntityCert::try_from(&certification_certs[0])
-        .context("Failed to parse leaf certificate in quote")?;
+        .msg_context("Failed to parse leaf certificate in quote")?;
     let intermediate_certs = &certification_certs[1..];
-    verify_certificate_chain(&leaf_cert, intermediate_certs, now_in_milli)?;
+    let chain_warnings = revocation
+        .verify_chain(
+            &certification_certs[0],
+            &leaf_cert,
+            intermediate_certs,
+            now,
+            &revocation.pck_crls()?,
+        )
+        .context("Invalid PCK certificate chain in quote")?;
 
     // Check QE signature
-    let asn1_signature = encode_as_der(&auth_data.qe_report_signature)?;
-    if leaf_cert
-        .verify_signature(
-            webpki::ring::ECDSA_P256_SHA256,
+    steps.begin(VerifyStep::QeReportSignature);
+    let pck_key = utils::get_public_key(&certification_certs[0])?;
+    if signature_verifier
+        .verify_p256_sha256(
+            &pck_key,
             &auth_data.qe_report,
-            &asn1_signature,
+            &auth_data.qe_report_signature,
         )
         .is_err()
     {
@@ -155,10 +1256,16 @@ pub fn verify(
     }
 
     // Extract QE report from quote
+    steps.begin(VerifyStep::QeIdentityMatch);
     let mut qe_report = auth_data.qe_report.as_slice();
-    let qe_report = EnclaveReport::decode(&mut qe_report).context("Failed to decode QE report")?;
+    let qe_report =
+        EnclaveReport::decode(&mut qe_report).msg_context("Failed to decode QE report")?;
+    if let Some(qe_identity) = qe_identity {
+        match_qe_identity(&qe_report, qe_identity)?;
+    }
 
     // Check QE hash
+    steps.begin(VerifyStep::AttestationKey);
     let mut qe_hash_data = [0u8; QE_HASH_DATA_BYTE_LEN];
     qe_hash_data[0..ATTESTATION_KEY_LEN].copy_from_slice(&auth_data.ecdsa_attestation_key);
     qe_hash_data[ATTESTATION_KEY_LEN..].copy_from_slice(&auth_data.qe_auth_data.data);
@@ -168,41 +1275,97 @@ pub fn verify(
     }
 
     // Check signature from auth data
-    let mut pub_key = [0x04u8; 65]; //Prepend 0x04 to specify uncompressed format
-    pub_key[1..].copy_from_slice(&auth_data.ecdsa_attestation_key);
-    let peer_public_key =
-        ring::signature::UnparsedPublicKey::new(&ring::signature::ECDSA_P256_SHA256_FIXED, pub_key);
-    peer_public_key
-        .verify(
-            &raw_quote
+    steps.begin(VerifyStep::QuoteSignature);
+    signature_verifier
+        .verify_p256_sha256(
+            &auth_data.ecdsa_attestation_key,
+            raw_quote
                 .get(..signed_quote_len)
                 .ok_or(anyhow!("Failed to get signed quote"))?,
             &auth_data.ecdsa_signature,
         )
         .map_err(|_| anyhow!("Isv enclave report signature is invalid"))?;
 
-    // Extract information from the quote
+    Ok(SignedQuote {
+        header: quote.header,
+        report: quote.report,
+        pck_cert: certification_certs[0].to_vec(),
+        qe_report,
+        chain_warnings,
+    })
+}
 
-    let extension_section = utils::get_intel_extension(&certification_certs[0])?;
+fn verify_quote_traced(
+    raw_quote: &[u8],
+    tcb_info: &TcbInfo,
+    qe_identity: &QeIdentity,
+    now: u64,
+    revocation: &Revocation,
+    signature_verifier: &dyn SignatureVerifier,
+    trace: &mut VerifyTrace,
+) -> Result<QuoteVerdict> {
+    let mut steps = Steps::new(trace);
+    steps.begin(VerifyStep::DecodeQuote);
+    // Parse data
+    let mut quote = raw_quote;
+    let quote = Quote::decode(&mut quote).msg_context("Failed to decode quote")?;
+    check_tee_enabled(&quote)?;
+    #[cfg_attr(all(feature = "sgx", not(feature = "tdx")), allow(unused_variables))]
+    let SignedQuote {
+        header,
+        report,
+        pck_cert,
+        qe_report,
+        chain_warnings,
+    } = check_quote_signatures(
+        raw_quote,
+        quote,
+        Some(qe_identity),
+        now,
+        revocation,
+        signature_verifier,
+        &mut steps,
+    )?;
+
+    // Extract information from the quote
+    steps.begin(VerifyStep::TcbMatching);
+    let extension_section = utils::get_intel_extension(&pck_cert)?;
     let cpu_svn = utils::get_cpu_svn(&extension_section)?;
     let pce_svn = utils::get_pce_svn(&extension_section)?;
     let fmspc = utils::get_fmspc(&extension_section)?;
+    let platform_id = match utils::get_platform_instance_id(&extension_section)? {
+        Some(instance_id) => PlatformId::PlatformInstance(instance_id),
+        None => {
+            let ppid = utils::get_ppid(&extension_section)?;
+            let ppid_hash = ring::digest::digest(&ring::digest::SHA256, &ppid);
+            let mut hash = [0u8; 32];
+            hash.copy_from_slice(ppid_hash.as_ref());
+            PlatformId::PpidHash(hash)
+        }
+    };
 
-    let tcb_fmspc = hex::decode(&tcb_info.fmspc)
-        .ok()
+    let tcb_fmspc: Fmspc = tcb_info
+        .fmspc
+        .parse()
         .context("Failed to decode TCB FMSPC")?;
-    if fmspc != tcb_fmspc[..] {
+    if fmspc != tcb_fmspc {
         bail!("Fmspc mismatch");
     }
 
-    if quote.header.tee_type == TEE_TYPE_TDX {
+    #[cfg(any(feature = "tdx", not(feature = "sgx")))]
+    let mut tdx_module = None;
+    #[cfg(any(feature = "tdx", not(feature = "sgx")))]
+    if header.tee_type == TEE_TYPE_TDX {
         if tcb_info.version < 3 || tcb_info.id != "TDX" {
             bail!("TDX quote with non-TDX TCB info in the collateral");
         }
+        let td_report = report.as_td10().context("Failed to get TD10 report")?;
+        tdx_module = match_tdx_module(td_report, tcb_info)?;
     }
 
     // TCB status and advisory ids
-    let mut tcb_status = "Unknown".to_owned();
+    let mut tcb_status = TcbStatus::Unknown;
+    let mut tcb_date = None;
     let mut advisory_ids = Vec::<String>::new();
     for tcb_level in &tcb_info.tcb_levels {
         if pce_svn < tcb_level.tcb.pce_svn {
@@ -214,35 +1377,84 @@ pub fn verify(
             .iter()
             .map(|c| c.svn)
             .collect::<Vec<_>>();
-        if cpu_svn[..] < sgx_components[..] {
+        if cpu_svn.as_bytes()[..] < sgx_components[..] {
             continue;
         }
-        if quote.header.tee_type == TEE_TYPE_TDX {
-            let td_report = quote
-                .report
-                .as_td10()
-                .context("Failed to get TD10 report")?;
+        #[cfg(any(feature = "tdx", not(feature = "sgx")))]
+        if header.tee_type == TEE_TYPE_TDX {
+            let td_report = report.as_td10().context("Failed to get TD10 report")?;
+            // The SVN and version of a module with an identity are checked against its own TCB
+            // levels instead
+            let skip = if tdx_module.is_some() { 2 } else { 0 };
             let tdx_components = tcb_level
                 .tcb
                 .tdx_components
                 .iter()
+                .skip(skip)
                 .map(|c| c.svn)
                 .collect::<Vec<_>>();
-            if td_report.tee_tcb_svn[..] < tdx_components[..] {
+            if td_report.tee_tcb_svn[skip..] < tdx_components[..] {
                 continue;
             }
         }
 
-        tcb_status = tcb_level.tcb_status.clone();
+        // Statuses introduced after this crate rank lowest
+        tcb_status = tcb_level.tcb_status.parse().unwrap_or(TcbStatus::Unknown);
+        tcb_date = Some(tcb_level.tcb_date.clone());
         tcb_level
             .advisory_ids
             .iter()
             .for_each(|id| advisory_ids.push(id.clone()));
         break;
     }
-    Ok(VerifiedReport {
+    #[cfg(any(feature = "tdx", not(feature = "sgx")))]
+    if let Some((module_status, module_advisory_ids)) = tdx_module {
+        tcb_status = merge_module_status(tcb_status, module_status);
+        for id in module_advisory_ids {
+            if !advisory_ids.contains(id) {
+                advisory_ids.push(id.clone());
+            }
+        }
+    }
+    // The QE's TCB level, the first one its ISVSVN is not below
+    let mut qe_identity_status = TcbStatus::Unknown;
+    let mut supplemental_advisory_ids = advisory_ids.clone();
+    if let Some(qe_level) = qe_identity
+        .tcb_levels
+        .iter()
+        .find(|level| qe_report.isv_svn >= level.tcb.isvsvn)
+    {
+        qe_identity_status = qe_level.tcb_status.parse().unwrap_or(TcbStatus::Unknown);
+        for id in &qe_level.advisory_ids {
+            if !supplemental_advisory_ids.contains(id) {
+                supplemental_advisory_ids.push(id.clone());
+            }
+        }
+    }
+    tcb_status = merge_module_status(tcb_status, qe_identity_status);
+    let mut warnings = chain_warnings;
+    warnings.extend(quote_warnings(raw_quote, &report, tcb_status)?);
+    let supplemental = SupplementalData {
+        tcb_date,
+        tcb_evaluation_data_number: tcb_info.tcb_evaluation_data_number,
+        advisory_ids: supplemental_advisory_ids,
+        qe_identity_status,
+        fmspc,
+        cpu_svn,
+        pce_svn,
+        earliest_expiration: collateral_next_update(tcb_info, qe_identity)?,
+        // Left for policies to reject if the type is newer than this crate
+        sgx_type: pck::sgx_type(&extension_section).ok(),
+        pck_configuration: pck::configuration(&extension_section)
+            .context("Invalid PCK certificate configuration")?,
+    };
+    steps.finish();
+    Ok(QuoteVerdict {
         status: tcb_status,
         advisory_ids,
-        report: quote.report,
+        report,
+        platform_id,
+        warnings,
+        supplemental,
     })
 }
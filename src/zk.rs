@@ -0,0 +1,248 @@
+//! Witness export for zkSNARK circuits.
+//!
+//! [`export_witness`] verifies a quote and records every check of the verification as a
+//! [`Witness`]: the ECDSA-P256 signatures over SHA-256 message hashes (the certificate chains,
+//! the TCB info, the QE identity, the QE report and the quote) and the QE report data hash binding the attestation
+//! key. A circuit re-doing these checks proves "this quote verified under this collateral" without
+//! revealing the quote.
+//!
+//! Circuits over ~254-bit fields take the 32-byte values as two 128-bit limbs, see
+//! [`Witness::to_field_elements`].
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use anyhow::{anyhow, bail, Result};
+use scale::Decode;
+use serde::{Deserialize, Serialize};
+
+use crate::constants::*;
+use crate::quote::Quote;
+use crate::utils::{extract_certs, get_cert_signature, get_public_key, MsgContext};
+use crate::verify::{verify, VerifiedReport};
+use crate::QuoteCollateralV3;
+
+pub type Hash = [u8; 32];
+
+/// An ECDSA-P256 signature check over a SHA-256 message hash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignatureCheck {
+    /// What is being signed, e.g. `pck_chain[0]` or `quote`
+    pub label: String,
+    #[serde(with = "serde_bytes")]
+    pub message: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pub message_hash: Hash,
+    #[serde(with = "serde_bytes")]
+    pub r: Hash,
+    #[serde(with = "serde_bytes")]
+    pub s: Hash,
+    #[serde(with = "serde_bytes")]
+    pub key_x: Hash,
+    #[serde(with = "serde_bytes")]
+    pub key_y: Hash,
+}
+
+/// A SHA-256 preimage check.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HashCheck {
+    pub label: String,
+    #[serde(with = "serde_bytes")]
+    pub preimage: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pub digest: Hash,
+}
+
+/// The verification transcript of a quote.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Witness {
+    pub signatures: Vec<SignatureCheck>,
+    pub hashes: Vec<HashCheck>,
+    /// The Intel root CA key the certificate chains end in, a public input
+    #[serde(with = "serde_bytes")]
+    pub root_key: [u8; ECDSA_PUBKEY_BYTE_LEN],
+    /// The collateral commitment root, a public input
+    #[serde(with = "serde_bytes")]
+    pub collateral_root: Hash,
+}
+
+impl Witness {
+    /// Flatten the fixed-width values into field elements.
+    ///
+    /// Each 32-byte value is split into a high and a low big-endian 128-bit limb. The order is:
+    /// for each signature check `message_hash, r, s, key_x, key_y`, then for each hash check
+    /// `digest`, then `root_key` (x, y) and `collateral_root`.
+    pub fn to_field_elements(&self) -> Vec<u128> {
+        let mut elements = Vec::new();
+        let mut push = |value: &[u8]| {
+            for limb in value.chunks(16) {
+                let mut buf = [0u8; 16];
+                buf.copy_from_slice(limb);
+                elements.push(u128::from_be_bytes(buf));
+            }
+        };
+        for check in &self.signatures {
+            push(&check.message_hash);
+            push(&check.r);
+            push(&check.s);
+            push(&check.key_x);
+            push(&check.key_y);
+        }
+        for check in &self.hashes {
+            push(&check.digest);
+        }
+        push(&self.root_key);
+        push(&self.collateral_root);
+        elements
+    }
+}
+
+/// Verify a quote and export the witness of its verification.
+///
+/// # Arguments
+///
+/// * `raw_quote` - The raw quote to verify. Supported SGX and TDX quotes.
+/// * `quote_collateral` - The quote collateral to verify against.
+/// * `now` - The current time in seconds since the Unix epoch
+///
+/// # Returns
+///
+/// * `Ok((VerifiedReport, Witness))` - The verified report and its witness
+/// * `Err(Error)` - The error
+pub fn export_witness(
+    raw_quote: &[u8],
+    quote_collateral: &QuoteCollateralV3,
+    now: u64,
+) -> Result<(VerifiedReport, Witness)> {
+    let report = verify(raw_quote, quote_collateral, now)?;
+
+    let mut signatures = Vec::new();
+    let mut hashes = Vec::new();
+
+    // TCB info and its issuer chain
+    let tcb_certs = extract_certs(quote_collateral.tcb_info_issuer_chain.as_bytes())?;
+    let root_key = chain_checks("tcb_chain", &tcb_certs, &mut signatures)?;
+    let tcb_signing_key = get_public_key(&tcb_certs[0])?;
+    let tcb_info_signature: [u8; ECDSA_SIGNATURE_BYTE_LEN] = quote_collateral
+        .tcb_info_signature
+        .as_slice()
+        .try_into()
+        .map_err(|_| anyhow!("Invalid TCB info signature length"))?;
+    signatures.push(signature_check(
+        "tcb_info",
+        quote_collateral.tcb_info.as_bytes().to_vec(),
+        &tcb_info_signature,
+        &tcb_signing_key,
+    ));
+
+    // QE identity and its issuer chain
+    let qe_identity_certs = extract_certs(quote_collateral.qe_identity_issuer_chain.as_bytes())?;
+    if chain_checks("qe_identity_chain", &qe_identity_certs, &mut signatures)? != root_key {
+        bail!("Certificate chains end in different roots");
+    }
+    let qe_identity_signing_key = get_public_key(&qe_identity_certs[0])?;
+    let qe_identity_signature: [u8; ECDSA_SIGNATURE_BYTE_LEN] = quote_collateral
+        .qe_identity_signature
+        .as_slice()
+        .try_into()
+        .map_err(|_| anyhow!("Invalid QE identity signature length"))?;
+    signatures.push(signature_check(
+        "qe_identity",
+        quote_collateral.qe_identity.as_bytes().to_vec(),
+        &qe_identity_signature,
+        &qe_identity_signing_key,
+    ));
+
+    // Quote and its PCK chain
+    let quote = Quote::decode(&mut &raw_quote[..]).msg_context("Failed to decode quote")?;
+    let signed_quote = raw_quote
+        .get(..quote.signed_length())
+        .ok_or(anyhow!("Failed to get signed quote"))?
+        .to_vec();
+    let auth_data = quote.auth_data.into_v3();
+    let pck_certs = extract_certs(&auth_data.certification_data.body.data)?;
+    if chain_checks("pck_chain", &pck_certs, &mut signatures)? != root_key {
+        bail!("Certificate chains end in different roots");
+    }
+    let pck_key = get_public_key(&pck_certs[0])?;
+    signatures.push(signature_check(
+        "qe_report",
+        auth_data.qe_report.to_vec(),
+        &auth_data.qe_report_signature,
+        &pck_key,
+    ));
+
+    let mut qe_hash_data = auth_data.ecdsa_attestation_key.to_vec();
+    qe_hash_data.extend_from_slice(&auth_data.qe_auth_data.data);
+    hashes.push(HashCheck {
+        label: "qe_report_data".into(),
+        digest: sha256(&qe_hash_data),
+        preimage: qe_hash_data,
+    });
+
+    signatures.push(signature_check(
+        "quote",
+        signed_quote,
+        &auth_data.ecdsa_signature,
+        &auth_data.ecdsa_attestation_key,
+    ));
+
+    let witness = Witness {
+        signatures,
+        hashes,
+        root_key,
+        collateral_root: report.collateral_commitment.root,
+    };
+    Ok((report, witness))
+}
+
+/// Record the signature of every certificate in a chain by its issuer, the next certificate.
+/// The last certificate is the self-signed root. Returns the root key.
+fn chain_checks(
+    label: &str,
+    certs: &[webpki::types::CertificateDer],
+    signatures: &mut Vec<SignatureCheck>,
+) -> Result<[u8; ECDSA_PUBKEY_BYTE_LEN]> {
+    if certs.len() < 2 {
+        bail!("Certificate chain is too short");
+    }
+    for (i, cert) in certs.iter().enumerate() {
+        let issuer = certs.get(i + 1).unwrap_or(cert);
+        let (tbs, signature) = get_cert_signature(cert)?;
+        signatures.push(signature_check(
+            &format!("{label}[{i}]"),
+            tbs,
+            &signature,
+            &get_public_key(issuer)?,
+        ));
+    }
+    get_public_key(&certs[certs.len() - 1])
+}
+
+fn signature_check(
+    label: &str,
+    message: Vec<u8>,
+    signature: &[u8; ECDSA_SIGNATURE_BYTE_LEN],
+    key: &[u8; ECDSA_PUBKEY_BYTE_LEN],
+) -> SignatureCheck {
+    let mut check = SignatureCheck {
+        label: label.into(),
+        message_hash: sha256(&message),
+        message,
+        r: [0; 32],
+        s: [0; 32],
+        key_x: [0; 32],
+        key_y: [0; 32],
+    };
+    check.r.copy_from_slice(&signature[..32]);
+    check.s.copy_from_slice(&signature[32..]);
+    check.key_x.copy_from_slice(&key[..32]);
+    check.key_y.copy_from_slice(&key[32..]);
+    check
+}
+
+fn sha256(data: &[u8]) -> Hash {
+    let digest = ring::digest::digest(&ring::digest::SHA256, data);
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(digest.as_ref());
+    hash
+}
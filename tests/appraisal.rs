@@ -0,0 +1,74 @@
+#![cfg(feature = "interop")]
+
+use dcap_qvl::appraisal::{verify_appraised_quote, verify_appraised_quote_with_options};
+use dcap_qvl::verify::{TcbStatus, VerifyOptions, Warning};
+
+/// An SGX quote whose PCK certificate is issued by the test CA
+const QUOTE: &[u8] = include_bytes!("fixtures/appraisal_quote.dat");
+const NOW: u64 = 1699301000;
+/// Signed by the test CA for [`QUOTE`], after the result of another quote
+const TOKEN: &str = include_str!("fixtures/appraisal_token.jws");
+
+fn test_ca() -> VerifyOptions {
+    VerifyOptions {
+        root_ca: Some(include_bytes!("fixtures/appraisal_root_ca.der").to_vec()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn maps_the_result_about_the_quote() {
+    let report = verify_appraised_quote_with_options(QUOTE, TOKEN, NOW, &test_ca()).unwrap();
+    assert_eq!(report.status, TcbStatus::SwHardeningNeeded);
+    assert_eq!(report.advisory_ids, ["INTEL-SA-00615"]);
+    assert_eq!(report.warnings, [Warning::SwHardeningNeeded]);
+    assert!(report.collateral_commitment.leaves.is_empty());
+
+    // Not signed under the Intel SGX Root CA
+    assert!(verify_appraised_quote(QUOTE, TOKEN, NOW).is_err());
+    let (signing_input, signature) = TOKEN.trim().rsplit_once('.').unwrap();
+    let flipped = if signature.starts_with('A') { 'B' } else { 'A' };
+    let forged = format!("{signing_input}.{flipped}{}", &signature[1..]);
+    assert!(verify_appraised_quote_with_options(QUOTE, &forged, NOW, &test_ca()).is_err());
+    assert!(verify_appraised_quote_with_options(QUOTE, TOKEN, 1893456001, &test_ca()).is_err());
+}
+
+#[test]
+fn checks_the_quote_signatures() {
+    // MRENCLAVE
+    let mut forged = QUOTE.to_vec();
+    forged[112] ^= 1;
+    let err = verify_appraised_quote_with_options(&forged, TOKEN, NOW, &test_ca()).unwrap_err();
+    assert!(format!("{err:#}").contains("signature is invalid"));
+
+    // Issued by the Intel SGX Root CA, not the test CA
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let err = verify_appraised_quote_with_options(raw_quote, TOKEN, NOW, &test_ca()).unwrap_err();
+    assert!(format!("{err:#}").contains("Invalid PCK certificate chain"));
+}
+
+#[test]
+fn rejects_tokens_about_other_quotes() {
+    let raw_quote = include_bytes!("fixtures/appraisal_other_quote.dat");
+    let err = verify_appraised_quote_with_options(raw_quote, TOKEN, NOW, &test_ca()).unwrap_err();
+    assert!(err.to_string().contains("different quote"));
+
+    let token = include_str!("fixtures/appraisal_token_wrong_signer.jws");
+    let err = verify_appraised_quote_with_options(QUOTE, token, NOW, &test_ca()).unwrap_err();
+    assert!(err.to_string().contains("sgx_mrsigner does not match"));
+}
+
+#[test]
+fn merges_the_qe_tcb_status() {
+    let token = include_str!("fixtures/appraisal_token_outdated_qe.jws");
+    let report = verify_appraised_quote_with_options(QUOTE, token, NOW, &test_ca()).unwrap();
+    assert_eq!(report.status, TcbStatus::OutOfDate);
+    assert_eq!(report.advisory_ids, ["INTEL-SA-00615"]);
+}
+
+#[test]
+fn rejects_unknown_tcb_statuses() {
+    let token = include_str!("fixtures/appraisal_token_unknown_status.jws");
+    let err = verify_appraised_quote_with_options(QUOTE, token, NOW, &test_ca()).unwrap_err();
+    assert!(format!("{err:#}").contains("Unknown TCB status: Compromised"));
+}
@@ -0,0 +1,61 @@
+#![cfg(all(feature = "report", not(feature = "js")))]
+
+use dcap_qvl::archive::{replay, CollateralArchive, FetchRecord, Manifest};
+use dcap_qvl::QuoteCollateralV3;
+
+#[test]
+fn replays_archived_collateral() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let now = 1741852249u64;
+    let collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+
+    let root = std::env::temp_dir().join(format!("dcap-qvl-archive-{}", std::process::id()));
+    let archive = CollateralArchive::open(&root).unwrap();
+    let record = |url: &str, header: &str, chain: &str, body: String| FetchRecord {
+        url: url.into(),
+        headers: vec![(header.into(), chain.into())],
+        fetched_at: now,
+        body: archive.put(body.as_bytes()).unwrap(),
+    };
+    let manifest = Manifest {
+        tcb_info: record(
+            "https://pccs.example/tcb",
+            "TCB-Info-Issuer-Chain",
+            &collateral.tcb_info_issuer_chain,
+            format!(
+                r#"{{"tcbInfo":{},"signature":"{}"}}"#,
+                collateral.tcb_info,
+                hex::encode(&collateral.tcb_info_signature)
+            ),
+        ),
+        qe_identity: record(
+            "https://pccs.example/qe/identity",
+            "SGX-Enclave-Identity-Issuer-Chain",
+            &collateral.qe_identity_issuer_chain,
+            format!(
+                r#"{{"enclaveIdentity":{},"signature":"{}"}}"#,
+                collateral.qe_identity,
+                hex::encode(&collateral.qe_identity_signature)
+            ),
+        ),
+    };
+    let id = archive
+        .put(&serde_json::to_vec(&manifest).unwrap())
+        .unwrap();
+
+    assert_eq!(archive.manifest(&id).unwrap(), manifest);
+    let rebuilt = archive.collateral(&id).unwrap();
+    assert_eq!(rebuilt.provenance.len(), 2);
+    assert_eq!(rebuilt.provenance[0].url, "https://pccs.example/tcb");
+    assert_eq!(rebuilt.provenance[0].fetched_at, Some(now));
+    assert_eq!(
+        QuoteCollateralV3 {
+            provenance: Vec::new(),
+            ..rebuilt
+        },
+        collateral
+    );
+    replay(&archive, &id, raw_quote, now).unwrap();
+    std::fs::remove_dir_all(root).unwrap();
+}
@@ -0,0 +1,87 @@
+use std::sync::{Arc, Mutex};
+
+use dcap_qvl::batch::{
+    verify_batch, verify_pairs, verify_stream, CancelToken, Deadline, StreamOptions,
+};
+use dcap_qvl::verify::{TcbStatus, VerifyOptions};
+use dcap_qvl::QuoteCollateralV3;
+
+#[test]
+fn cancelled_batch_fails_remaining_quotes() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let now = 1741852249u64;
+    let collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+    let items = [(&raw_quote[..], &collateral), (&raw_quote[..], &collateral)];
+
+    let cancel = CancelToken::new();
+    let deadline = Deadline {
+        at: None,
+        cancel: Some(cancel.clone()),
+    };
+    assert!(verify_pairs(items, now, &deadline)
+        .iter()
+        .all(Result::is_ok));
+
+    cancel.cancel();
+    assert!(verify_pairs(items, now, &deadline)
+        .iter()
+        .all(Result::is_err));
+}
+
+#[test]
+fn could_verify_stream() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let now = 1741852249u64;
+    let collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+    let collateral = Arc::new(collateral);
+
+    let items = (0..8).map(|_| (raw_quote.to_vec(), collateral.clone()));
+    let options = StreamOptions {
+        concurrency: 2,
+        max_quote_size: 64 * 1024,
+        max_in_flight_bytes: 2 * raw_quote.len(),
+    };
+    let statuses = Mutex::new(vec![TcbStatus::Unknown; 8]);
+    verify_stream(
+        items,
+        now,
+        &options,
+        &Deadline::default(),
+        |index, result| {
+            statuses.lock().unwrap()[index] = result.unwrap().status;
+        },
+    );
+    assert!(statuses
+        .into_inner()
+        .unwrap()
+        .iter()
+        .all(|s| *s == TcbStatus::UpToDate));
+}
+
+#[test]
+fn could_verify_a_batch_with_shared_collateral() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let now = 1741852249u64;
+    let collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+
+    let quotes = [&raw_quote[..], &raw_quote[..10], &raw_quote[..]];
+    let options = VerifyOptions::default();
+    let results = verify_batch(&quotes, &collateral, now, &options).unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap().status, TcbStatus::UpToDate);
+    assert!(results[1].is_err());
+    assert!(results[2].is_ok());
+
+    assert!(verify_batch(&quotes, &collateral, 0, &options).is_err());
+
+    let options = VerifyOptions {
+        allowed_tcb_statuses: Some(vec![TcbStatus::Revoked]),
+        ..Default::default()
+    };
+    let results = verify_batch(&quotes, &collateral, now, &options).unwrap();
+    let err = results[0].as_ref().unwrap_err();
+    assert!(err.to_string().contains("is not allowed"));
+}
@@ -0,0 +1,59 @@
+#![cfg(feature = "interop")]
+
+use dcap_qvl::{canonical, verify::verify, QuoteCollateralV3};
+
+#[test]
+fn canonical_json_sorts_keys() {
+    let value = serde_json::json!({
+        "b": [1, "\u{1}", true],
+        "a": { "d": null, "c": -1 },
+    });
+    assert_eq!(
+        canonical::to_string(&value).unwrap(),
+        r#"{"a":{"c":-1,"d":null},"b":[1,"\u0001",true]}"#
+    );
+}
+
+#[test]
+fn canonical_report_is_stable() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let quote_collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+    let report = verify(raw_quote, &quote_collateral, 1741852249).unwrap();
+
+    let first = canonical::to_string(&report).unwrap();
+    let reparsed: serde_json::Value = serde_json::from_str(&first).unwrap();
+    assert_eq!(canonical::to_string(&reparsed).unwrap(), first);
+    assert!(canonical::to_string(&quote_collateral).is_ok());
+}
+
+#[test]
+fn digests_ignore_padding_and_chain_line_endings() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let quote_collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+
+    // The sample quote is padded already
+    let canonical_quote = canonical::canonical_quote(raw_quote).unwrap();
+    assert_eq!(canonical_quote.len(), 4936);
+    assert!(raw_quote[canonical_quote.len()..].iter().all(|b| *b == 0));
+    let mut padded = raw_quote.to_vec();
+    padded.resize(raw_quote.len() + 1024, 0);
+    assert_eq!(
+        canonical::canonical_quote(&padded).unwrap(),
+        canonical_quote
+    );
+    assert_eq!(
+        canonical::quote_digest(&padded).unwrap(),
+        canonical::quote_digest(raw_quote).unwrap()
+    );
+
+    let crlf = QuoteCollateralV3 {
+        tcb_info_issuer_chain: quote_collateral.tcb_info_issuer_chain.replace('\n', "\r\n"),
+        ..quote_collateral.clone()
+    };
+    assert_eq!(
+        canonical::collateral_digest(&crlf).unwrap(),
+        canonical::collateral_digest(&quote_collateral).unwrap()
+    );
+}
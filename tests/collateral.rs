@@ -0,0 +1,102 @@
+#![cfg(feature = "report")]
+
+use dcap_qvl::{
+    collateral::minimize,
+    verify::{verify, TcbStatus},
+    CollateralKind, CollateralSource, QuoteCollateralV3,
+};
+
+#[test]
+fn minimized_collateral_still_verifies() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let mut quote_collateral: QuoteCollateralV3 =
+        serde_json::from_slice(raw_quote_collateral).unwrap();
+    quote_collateral.provenance = vec![CollateralSource {
+        kind: CollateralKind::TcbInfo,
+        url: "https://pccs.example/tdx/certification/v4/tcb?fmspc=00806F050000".into(),
+        fetched_at: Some(1741852249),
+        headers: vec![],
+    }];
+
+    let minimized = minimize(&quote_collateral, raw_quote).unwrap();
+    assert!(minimized.tcb_info_issuer_chain.len() < quote_collateral.tcb_info_issuer_chain.len());
+    assert_eq!(minimized.tcb_info, quote_collateral.tcb_info);
+    assert!(minimized.pck_crl.is_some());
+    assert_eq!(minimized.pck_crl, quote_collateral.pck_crl);
+    assert!(minimized.provenance.is_empty());
+
+    let report = verify(raw_quote, &minimized, 1741852249).unwrap();
+    assert_eq!(report.status, TcbStatus::UpToDate);
+
+    // A CRL of another CA than the PCK certificate's is not checked
+    quote_collateral.pck_crl = quote_collateral.root_ca_crl.clone();
+    let minimized = minimize(&quote_collateral, raw_quote).unwrap();
+    assert_eq!(minimized.pck_crl, None);
+    let report = verify(raw_quote, &minimized, 1741852249).unwrap();
+    assert_eq!(report.status, TcbStatus::UpToDate);
+}
+
+#[test]
+fn egress_policy_blocks_unlisted_hosts() {
+    use dcap_qvl::collateral::{verify_online_blocking, EgressPolicy, OnlineOptions};
+
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let options = OnlineOptions {
+        egress: EgressPolicy {
+            allowed_hosts: Some(vec!["pccs.internal".into()]),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let err = verify_online_blocking(raw_quote, &options).unwrap_err();
+    assert!(err.to_string().contains("not allowed by the egress policy"));
+}
+
+#[test]
+fn pcs_urls_match_the_fetched_ones() {
+    use dcap_qvl::pcs::{self, PckCa, PckCertQuery};
+
+    let fmspc = "00906ED50000".parse().unwrap();
+    assert_eq!(
+        pcs::tcb_info("https://pccs.example/sgx/certification/v4/", &fmspc),
+        "https://pccs.example/sgx/certification/v4/tcb?fmspc=00906ED50000"
+    );
+    assert_eq!(
+        pcs::pck_crl(pcs::SGX_BASE_URL, PckCa::Platform),
+        format!("{}/pckcrl?ca=platform&encoding=der", pcs::SGX_BASE_URL)
+    );
+    assert_eq!(pcs::root_ca_crl(pcs::TDX_BASE_URL), pcs::ROOT_CA_CRL_URL);
+    assert_eq!(
+        pcs::root_ca_crl("https://pccs.example/tdx/certification/v4/"),
+        "https://pccs.example/sgx/certification/v4/rootcacrl"
+    );
+    let query = PckCertQuery {
+        encrypted_ppid: None,
+        qe_id: Some(&[0xab; 16]),
+        cpu_svn: [0x01; 16].into(),
+        pce_svn: 13,
+        pce_id: 0,
+    };
+    assert!(pcs::pck_cert("https://pccs.example", &query)
+        .starts_with("https://pccs.example/pckcert?cpusvn=01010101010101010101010101010101&pcesvn=0d00&pceid=0000&qeid=abab"));
+}
+
+#[test]
+fn thim_urls_follow_the_pccs_layout() {
+    use dcap_qvl::collateral::ThimConfig;
+
+    let config = ThimConfig::default();
+    assert_eq!(
+        config.certification_url(false),
+        "https://global.acccache.azure.net/tdx/certification/v4"
+    );
+    let config = ThimConfig {
+        base_url: Some("https://westus.thim.example/".into()),
+        client_id: None,
+    };
+    assert_eq!(
+        config.certification_url(true),
+        "https://westus.thim.example/sgx/certification/v4"
+    );
+}
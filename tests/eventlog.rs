@@ -0,0 +1,47 @@
+use dcap_qvl::eventlog::{verify_rtmrs, EventLog};
+use dcap_qvl::quote::Quote;
+
+/// A crypto agile event log with SHA-384 digests, and events of `(mr_index, digest)`.
+fn event_log(events: &[(u32, [u8; 48])]) -> Vec<u8> {
+    let mut spec_id = b"Spec ID Event03\0".to_vec();
+    spec_id.extend([0u8; 8]);
+    spec_id.extend(1u32.to_le_bytes());
+    spec_id.extend(0x000cu16.to_le_bytes());
+    spec_id.extend(48u16.to_le_bytes());
+    spec_id.push(0);
+
+    let mut log = Vec::new();
+    log.extend(0u32.to_le_bytes());
+    log.extend(3u32.to_le_bytes());
+    log.extend([0u8; 20]);
+    log.extend((spec_id.len() as u32).to_le_bytes());
+    log.extend(spec_id);
+    for (mr_index, digest) in events {
+        log.extend(mr_index.to_le_bytes());
+        log.extend(0x8000_0001u32.to_le_bytes());
+        log.extend(1u32.to_le_bytes());
+        log.extend(0x000cu16.to_le_bytes());
+        log.extend(digest);
+        log.extend(4u32.to_le_bytes());
+        log.extend(b"data");
+    }
+    log.extend([0xff; 64]);
+    log
+}
+
+#[test]
+fn could_replay_event_log() {
+    let log = EventLog::parse(&event_log(&[(2, [0x11; 48]), (0, [0x22; 48])])).unwrap();
+    assert_eq!(log.events.len(), 2);
+
+    let mut extended = [0u8; 48].to_vec();
+    extended.extend([0x11; 48]);
+    let expected = ring::digest::digest(&ring::digest::SHA384, &extended);
+    let rtmrs = log.replay();
+    assert_eq!(rtmrs[0], [0u8; 48]);
+    assert_eq!(&rtmrs[1][..], expected.as_ref());
+
+    let quote = Quote::parse(include_bytes!("../sample/tdx_quote")).unwrap();
+    let err = verify_rtmrs(&quote, &log).unwrap_err();
+    assert!(err.to_string().contains("RTMR1 is"));
+}
@@ -0,0 +1,37 @@
+use dcap_qvl::formats::{parse_envelope, read_qve_collateral, write_qve_collateral};
+use dcap_qvl::{
+    verify::{verify, TcbStatus},
+    QuoteCollateralV3,
+};
+
+#[test]
+fn could_verify_envelope() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let now = 1741852249u64;
+
+    let collateral: serde_json::Value = serde_json::from_slice(raw_quote_collateral).unwrap();
+    let envelope = serde_json::json!({
+        "evidence": hex::encode(raw_quote),
+        "endorsements": collateral,
+    });
+    let envelope = parse_envelope(envelope.to_string().as_bytes()).unwrap();
+    assert_eq!(envelope.quote, raw_quote);
+
+    let report = envelope.verify(now).unwrap();
+    assert_eq!(report.status, TcbStatus::UpToDate);
+}
+
+#[test]
+fn could_roundtrip_qve_collateral() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let now = 1741852249u64;
+
+    let collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+    let file = write_qve_collateral(&collateral, true);
+    let read = read_qve_collateral(&file).unwrap();
+
+    let report = verify(raw_quote, &read, now).unwrap();
+    assert_eq!(report.status, TcbStatus::UpToDate);
+}
@@ -0,0 +1,30 @@
+use dcap_qvl::freshness::{check_challenge, issue_challenge, report_data};
+
+#[test]
+fn could_check_challenge_age() {
+    let key = b"verifier key";
+    let challenge = issue_challenge(key, 1000, [7; 8]);
+    let report_data = report_data(&challenge, &[1; 32]);
+    let challenge = report_data[..32].try_into().unwrap();
+
+    assert_eq!(check_challenge(challenge, key, 1030, 60).unwrap(), 1000);
+    assert!(check_challenge(challenge, key, 1061, 60).is_err());
+    assert!(check_challenge(challenge, b"other key", 1030, 60).is_err());
+}
+
+#[test]
+fn could_issue_challenge_with_injected_nonces() {
+    use dcap_qvl::freshness::{issue_challenge_with, NonceSource};
+
+    struct Fixed;
+    impl NonceSource for Fixed {
+        fn fill(&self, dest: &mut [u8]) -> anyhow::Result<()> {
+            dest.fill(7);
+            Ok(())
+        }
+    }
+
+    let key = b"verifier key";
+    let challenge = issue_challenge_with(key, 1000, &Fixed).unwrap();
+    assert_eq!(challenge, issue_challenge(key, 1000, [7; 8]));
+}
@@ -0,0 +1,76 @@
+#![cfg(feature = "grpc")]
+
+use dcap_qvl::collateral::CollateralCache;
+use dcap_qvl::grpc::{AttestationInterceptor, AttestedPeer, QuoteInterceptor};
+use dcap_qvl::limits::SizeLimits;
+use dcap_qvl::verify::TcbStatus;
+use dcap_qvl::QuoteCollateralV3;
+use tonic::service::Interceptor;
+use tonic::{Code, Request};
+
+#[test]
+fn attests_requests_carrying_a_quote() {
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let collateral: QuoteCollateralV3 =
+        serde_json::from_slice(include_bytes!("../sample/sgx_quote_collateral.json")).unwrap();
+    let now = 1699301000u64;
+
+    let cache = CollateralCache::in_memory(4);
+    let mut server = AttestationInterceptor::new(cache.clone())
+        .without_key_binding()
+        .with_now(now);
+
+    let request = QuoteInterceptor::new(raw_quote)
+        .call(Request::new(()))
+        .unwrap();
+    let status = server.call(request).unwrap_err();
+    assert_eq!(status.code(), Code::Unavailable);
+
+    cache.put(raw_quote, &collateral).unwrap();
+    let request = QuoteInterceptor::new(raw_quote)
+        .call(Request::new(()))
+        .unwrap();
+    let request = server.call(request).unwrap();
+    let peer = request.extensions().get::<AttestedPeer>().unwrap();
+    assert_eq!(
+        peer.report.status,
+        TcbStatus::ConfigurationAndSwHardeningNeeded
+    );
+
+    let status = server.call(Request::new(())).unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+
+    // The reason a quote fails is logged, not returned
+    let mut forged = raw_quote.to_vec();
+    forged[112] ^= 1;
+    let request = QuoteInterceptor::new(&forged)
+        .call(Request::new(()))
+        .unwrap();
+    let status = server.call(request).unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+    assert_eq!(status.message(), "Quote failed to verify");
+
+    // Bound to the TLS client key by default, which a request outside a TLS channel has not
+    let mut bound = AttestationInterceptor::new(cache).with_now(now);
+    let request = QuoteInterceptor::new(raw_quote)
+        .call(Request::new(()))
+        .unwrap();
+    let status = bound.call(request).unwrap_err();
+    assert_eq!(status.code(), Code::Unauthenticated);
+}
+
+#[test]
+fn rejects_quotes_over_the_size_limit() {
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let mut server = AttestationInterceptor::new(CollateralCache::in_memory(4))
+        .without_key_binding()
+        .with_limits(SizeLimits {
+            max_quote_size: 1024,
+            ..SizeLimits::default()
+        });
+    let request = QuoteInterceptor::new(raw_quote)
+        .call(Request::new(()))
+        .unwrap();
+    let status = server.call(request).unwrap_err();
+    assert_eq!(status.code(), Code::ResourceExhausted);
+}
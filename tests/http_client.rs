@@ -0,0 +1,435 @@
+use anyhow::Result;
+use dcap_qvl::batch::DeadlineError;
+use dcap_qvl::collateral::{
+    block_on, get_collateral_cached_with_client, get_collateral_with_client,
+    get_collateral_with_client_and_options, CollateralCache, CollateralOptions, FetchError,
+    HttpClient, HttpResponse, RetryPolicy, RetryingClient, TcbUpdate,
+};
+use dcap_qvl::limits::{SizeLimits, TooLarge};
+use dcap_qvl::store::{CacheStore, LruStore};
+use dcap_qvl::verify::{verify, TcbStatus};
+use dcap_qvl::{CollateralKind, QuoteCollateralV3};
+use serde_json::Value;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// Serves the sample collateral the way PCCS does.
+struct SamplePccs {
+    collateral: Value,
+}
+
+impl SamplePccs {
+    fn field(&self, name: &str) -> &str {
+        self.collateral[name].as_str().unwrap()
+    }
+
+    fn document(&self, name: &str, key: &str, chain_header: &str) -> HttpResponse {
+        let body = format!(
+            r#"{{"{key}":{},"signature":"{}"}}"#,
+            self.field(name),
+            self.field(&format!("{name}_signature"))
+        );
+        HttpResponse {
+            status: 200,
+            headers: vec![(
+                chain_header.into(),
+                self.field(&format!("{name}_issuer_chain")).into(),
+            )],
+            body: body.into_bytes(),
+        }
+    }
+}
+
+impl HttpClient for SamplePccs {
+    async fn get(&self, url: &str) -> Result<HttpResponse> {
+        let ok = |body: Vec<u8>| HttpResponse {
+            status: 200,
+            headers: vec![("Date".into(), "Mon, 06 Nov 2023 20:03:20 GMT".into())],
+            body,
+        };
+        Ok(if url.contains("/tcb?fmspc=") {
+            self.document("tcb_info", "tcbInfo", "TCB-Info-Issuer-Chain")
+        } else if url.contains("/qe/identity") {
+            self.document(
+                "qe_identity",
+                "enclaveIdentity",
+                "SGX-Enclave-Identity-Issuer-Chain",
+            )
+        } else if url.ends_with("/rootcacrl") {
+            ok(self.field("root_ca_crl").as_bytes().to_vec())
+        } else if url.contains("/pckcrl?") {
+            ok(hex::decode(self.field("pck_crl"))?)
+        } else {
+            HttpResponse {
+                status: 404,
+                ..Default::default()
+            }
+        })
+    }
+}
+
+fn sample_pccs() -> SamplePccs {
+    SamplePccs {
+        collateral: serde_json::from_slice(include_bytes!("../sample/sgx_quote_collateral.json"))
+            .unwrap(),
+    }
+}
+
+#[tokio::test]
+async fn could_fetch_collateral_over_custom_client() {
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let client = sample_pccs();
+    let collateral = get_collateral_with_client(&client, "https://pccs.example", raw_quote)
+        .await
+        .unwrap();
+    assert_eq!(
+        collateral.pck_crl.as_deref(),
+        Some(&hex::decode(client.field("pck_crl")).unwrap()[..])
+    );
+
+    let kinds = collateral
+        .provenance
+        .iter()
+        .map(|source| source.kind)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            CollateralKind::TcbInfo,
+            CollateralKind::QeIdentity,
+            CollateralKind::RootCaCrl,
+            CollateralKind::PckCrl
+        ]
+    );
+    // Issuer chains are not kept, only the headers identifying the response
+    assert!(collateral.provenance[0].headers.is_empty());
+    assert_eq!(collateral.provenance[2].headers[0].0, "Date");
+
+    let report = verify(raw_quote, &collateral, 1699301000).unwrap();
+    assert_eq!(report.status, TcbStatus::ConfigurationAndSwHardeningNeeded);
+    assert_eq!(report.provenance, collateral.provenance);
+}
+
+#[tokio::test]
+async fn blocking_and_async_fetches_agree() {
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let client = sample_pccs();
+    let mut fetched = get_collateral_with_client(&client, "https://pccs.example", raw_quote)
+        .await
+        .unwrap();
+    let mut blocking = block_on(get_collateral_with_client(
+        &client,
+        "https://pccs.example",
+        raw_quote,
+    ))
+    .unwrap();
+    // The fetches may straddle a second
+    for source in fetched
+        .provenance
+        .iter_mut()
+        .chain(&mut blocking.provenance)
+    {
+        source.fetched_at = None;
+    }
+    assert_eq!(blocking, fetched);
+}
+
+#[tokio::test]
+async fn fetches_the_requested_tcb_evaluation() {
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let client = sample_pccs();
+    let options = CollateralOptions {
+        tcb_evaluation_data_number: Some(16),
+        ..Default::default()
+    };
+    let collateral = get_collateral_with_client_and_options(
+        &client,
+        "https://pccs.example",
+        raw_quote,
+        &options,
+    )
+    .await
+    .unwrap();
+    assert!(collateral.provenance[0]
+        .url
+        .ends_with("/tcb?fmspc=00A067110000&tcbEvaluationDataNumber=16"));
+    assert!(collateral.provenance[1]
+        .url
+        .ends_with("/qe/identity?tcbEvaluationDataNumber=16"));
+    let report = verify(raw_quote, &collateral, 1699301000).unwrap();
+    assert_eq!(report.supplemental.unwrap().tcb_evaluation_data_number, 16);
+
+    let options = CollateralOptions {
+        update: TcbUpdate::Early,
+        ..Default::default()
+    };
+    let collateral = get_collateral_with_client_and_options(
+        &client,
+        "https://pccs.example",
+        raw_quote,
+        &options,
+    )
+    .await
+    .unwrap();
+    assert!(collateral.provenance[1]
+        .url
+        .ends_with("/qe/identity?update=early"));
+
+    // The sample server ignores the requested number, so serves the wrong evaluation
+    let options = CollateralOptions {
+        tcb_evaluation_data_number: Some(17),
+        ..Default::default()
+    };
+    let err = get_collateral_with_client_and_options(
+        &client,
+        "https://pccs.example",
+        raw_quote,
+        &options,
+    )
+    .await
+    .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("Requested tcbEvaluationDataNumber 17"));
+}
+
+/// Serves the sample collateral, but the PCK CRL only after some unavailable responses.
+struct FlakyPckCrl {
+    pccs: SamplePccs,
+    failures: AtomicU32,
+}
+
+impl HttpClient for FlakyPckCrl {
+    async fn get(&self, url: &str) -> Result<HttpResponse> {
+        if url.contains("/pckcrl?") && self.failures.load(Ordering::Relaxed) > 0 {
+            self.failures.fetch_sub(1, Ordering::Relaxed);
+            return Ok(HttpResponse {
+                status: 503,
+                ..Default::default()
+            });
+        }
+        self.pccs.get(url).await
+    }
+}
+
+#[tokio::test]
+async fn retries_transient_failures_and_names_the_failed_element() {
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let flaky = |failures| FlakyPckCrl {
+        pccs: sample_pccs(),
+        failures: AtomicU32::new(failures),
+    };
+
+    let client = RetryingClient::new(flaky(2), RetryPolicy::default());
+    let collateral = get_collateral_with_client(&client, "https://pccs.example", raw_quote)
+        .await
+        .unwrap();
+    assert!(collateral.pck_crl.is_some());
+
+    let client = RetryingClient::new(flaky(1), RetryPolicy::none());
+    let err = get_collateral_with_client(&client, "https://pccs.example", raw_quote)
+        .await
+        .unwrap_err();
+    let err = err.downcast_ref::<FetchError>().unwrap();
+    assert_eq!(err.kind, CollateralKind::PckCrl);
+    assert!(err.url.contains("/pckcrl?"));
+}
+
+/// Fails every request with the given error, counting the attempts.
+struct Failing<'a> {
+    error: fn() -> anyhow::Error,
+    attempts: &'a AtomicU32,
+}
+
+impl HttpClient for Failing<'_> {
+    async fn get(&self, _url: &str) -> Result<HttpResponse> {
+        self.attempts.fetch_add(1, Ordering::Relaxed);
+        Err((self.error)())
+    }
+}
+
+#[test]
+fn retries_transport_errors_only() {
+    let attempts = |error: fn() -> anyhow::Error| {
+        let attempts = AtomicU32::new(0);
+        let client = RetryingClient::new(
+            Failing {
+                error,
+                attempts: &attempts,
+            },
+            RetryPolicy {
+                attempts: 3,
+                initial_backoff: Duration::ZERO,
+                max_backoff: Duration::ZERO,
+            },
+        );
+        block_on(client.get("https://pccs.example")).unwrap_err();
+        attempts.load(Ordering::Relaxed)
+    };
+    assert_eq!(attempts(|| anyhow::anyhow!("Connection reset")), 3);
+    assert_eq!(attempts(|| DeadlineError::Exceeded.into()), 1);
+    assert_eq!(
+        attempts(|| anyhow::Error::new(DeadlineError::Cancelled).context("Fetching")),
+        1
+    );
+    #[cfg(feature = "report")]
+    assert_eq!(
+        attempts(|| dcap_qvl::collateral::EgressDenied {
+            host: "pccs.example".into()
+        }
+        .into()),
+        1
+    );
+}
+
+/// Fails every request, to tell cache hits.
+struct Offline;
+
+impl HttpClient for Offline {
+    async fn get(&self, url: &str) -> Result<HttpResponse> {
+        anyhow::bail!("Offline, can't get {url}")
+    }
+}
+
+#[tokio::test]
+async fn serves_cached_collateral_until_next_update() {
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let now = 1699301000;
+    let cache = CollateralCache::in_memory(4);
+    let fetched = get_collateral_cached_with_client(
+        &cache,
+        &sample_pccs(),
+        "https://pccs.example",
+        raw_quote,
+        now,
+    )
+    .await
+    .unwrap();
+    let cached =
+        get_collateral_cached_with_client(&cache, &Offline, "https://pccs.example", raw_quote, now)
+            .await
+            .unwrap();
+    assert_eq!(cached, fetched);
+
+    let next_update = fetched.next_update().unwrap();
+    assert!(cache.get(raw_quote, next_update).unwrap().is_none());
+}
+
+#[cfg(feature = "report")]
+#[tokio::test]
+async fn verifies_online_with_cached_collateral() {
+    use dcap_qvl::collateral::{verify_online, verify_online_with_client, OnlineOptions};
+
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let options = OnlineOptions {
+        pccs_url: Some("https://pccs.example".into()),
+        now: Some(1699301000),
+        cache: Some(CollateralCache::in_memory(4)),
+        ..Default::default()
+    };
+    let online = verify_online_with_client(&sample_pccs(), raw_quote, &options)
+        .await
+        .unwrap();
+    assert_eq!(
+        online.report.status,
+        TcbStatus::ConfigurationAndSwHardeningNeeded
+    );
+    assert_eq!(
+        online.report.advisory_ids,
+        ["INTEL-SA-00289", "INTEL-SA-00615"]
+    );
+
+    // Served from the cache without contacting PCCS
+    let cached = verify_online_with_client(&Offline, raw_quote, &options)
+        .await
+        .unwrap();
+    assert_eq!(cached.collateral, online.collateral);
+    let cached = verify_online(raw_quote, &options).await.unwrap();
+    assert_eq!(cached.report.status, online.report.status);
+
+    let uncached = OnlineOptions {
+        cache: None,
+        ..options.clone()
+    };
+    let err = verify_online_with_client(&Offline, raw_quote, &uncached)
+        .await
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("Offline"));
+    let expired = OnlineOptions {
+        now: Some(online.collateral.next_update().unwrap()),
+        ..options
+    };
+    assert!(verify_online_with_client(&Offline, raw_quote, &expired)
+        .await
+        .is_err());
+}
+
+#[cfg(feature = "report")]
+#[test]
+fn verifies_online_blocking_with_cached_collateral() {
+    use dcap_qvl::batch::{CancelToken, Deadline};
+    use dcap_qvl::collateral::{verify_online_blocking, OnlineOptions};
+
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let collateral: QuoteCollateralV3 =
+        serde_json::from_slice(include_bytes!("../sample/sgx_quote_collateral.json")).unwrap();
+    let cache = CollateralCache::in_memory(4);
+    cache.put(raw_quote, &collateral).unwrap();
+    let options = OnlineOptions {
+        now: Some(1699301000),
+        cache: Some(cache),
+        ..Default::default()
+    };
+    let online = verify_online_blocking(raw_quote, &options).unwrap();
+    assert_eq!(
+        online.report.status,
+        TcbStatus::ConfigurationAndSwHardeningNeeded
+    );
+    assert_eq!(online.collateral, collateral);
+
+    let cancel = CancelToken::new();
+    cancel.cancel();
+    let cancelled = OnlineOptions {
+        deadline: Deadline {
+            at: None,
+            cancel: Some(cancel),
+        },
+        ..options
+    };
+    let err = verify_online_blocking(raw_quote, &cancelled).unwrap_err();
+    assert!(err.chain().any(|err| err.is::<DeadlineError>()));
+}
+
+#[test]
+fn cache_rejects_collateral_over_the_size_limits() {
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let mut collateral: QuoteCollateralV3 =
+        serde_json::from_slice(include_bytes!("../sample/sgx_quote_collateral.json")).unwrap();
+    let limits = SizeLimits {
+        max_collateral_field_size: 64 * 1024,
+        ..SizeLimits::default()
+    };
+    let cache = CollateralCache::in_memory(4).with_limits(limits);
+    cache.put(raw_quote, &collateral).unwrap();
+
+    collateral.pck_crl = Some(vec![0; 64 * 1024 + 1]);
+    let err = cache.put(raw_quote, &collateral).unwrap_err();
+    let too_large = err.root_cause().downcast_ref::<TooLarge>().unwrap();
+    assert_eq!(too_large.field, "pck_crl");
+
+    let small = CollateralCache::in_memory(4).with_limits(SizeLimits {
+        max_quote_size: 100,
+        ..limits
+    });
+    assert!(small.get(raw_quote, 1699301000).is_err());
+}
+
+#[test]
+fn lru_store_evicts_least_recently_used() {
+    let store = LruStore::new(2);
+    store.put(b"a", b"1").unwrap();
+    store.put(b"b", b"2").unwrap();
+    store.get(b"a").unwrap();
+    store.put(b"c", b"3").unwrap();
+    assert_eq!(store.keys(b"").unwrap(), [b"a".to_vec(), b"c".to_vec()]);
+}
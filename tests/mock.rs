@@ -0,0 +1,142 @@
+#![cfg(feature = "mock")]
+
+use dcap_qvl::mock::MockQuoteBuilder;
+use dcap_qvl::quote::TeeType;
+use dcap_qvl::verify::{verify, verify_with_options, CertificateRevoked, TcbStatus};
+
+#[test]
+fn mock_quotes_verify_with_their_root_ca() {
+    let now = 1741852249u64;
+    let mock = MockQuoteBuilder::new(TeeType::Tdx)
+        .mr_td([0x11; 48])
+        .rtmr(3, [0x33; 48])
+        .report_data([0x42; 64])
+        .build(now)
+        .unwrap();
+    assert!(verify(&mock.quote, &mock.collateral, now).is_err());
+    let report =
+        verify_with_options(&mock.quote, &mock.collateral, now, &mock.verify_options()).unwrap();
+    assert_eq!(report.status, TcbStatus::UpToDate);
+    let td_report = report.report.as_td10().unwrap();
+    assert_eq!(td_report.mr_td, [0x11; 48]);
+    assert_eq!(td_report.rt_mr3, [0x33; 48]);
+    report.verify_report_data(&[0x42; 64]).unwrap();
+
+    let mock = MockQuoteBuilder::new(TeeType::Sgx)
+        .mr_enclave([0x22; 32])
+        .tcb_status(TcbStatus::OutOfDate)
+        .build(now)
+        .unwrap();
+    let report =
+        verify_with_options(&mock.quote, &mock.collateral, now, &mock.verify_options()).unwrap();
+    assert_eq!(report.status, TcbStatus::OutOfDate);
+    assert_eq!(report.report.as_sgx().unwrap().mr_enclave, [0x22; 32]);
+}
+
+#[test]
+fn merges_the_qe_identity_status() {
+    let now = 1741852249u64;
+    let status = |tee, tcb_status, qe_tcb_status| {
+        let mock = MockQuoteBuilder::new(tee)
+            .tcb_status(tcb_status)
+            .qe_tcb_status(qe_tcb_status)
+            .build(now)
+            .unwrap();
+        let report =
+            verify_with_options(&mock.quote, &mock.collateral, now, &mock.verify_options())
+                .unwrap();
+        assert_eq!(
+            report.supplemental.unwrap().qe_identity_status,
+            qe_tcb_status
+        );
+        report.status
+    };
+    use TcbStatus::*;
+    assert_eq!(status(TeeType::Sgx, UpToDate, Revoked), Revoked);
+    assert_eq!(status(TeeType::Tdx, UpToDate, Revoked), Revoked);
+    assert_eq!(status(TeeType::Sgx, UpToDate, OutOfDate), OutOfDate);
+    assert_eq!(
+        status(TeeType::Sgx, ConfigurationNeeded, OutOfDate),
+        OutOfDateConfigurationNeeded
+    );
+    assert_eq!(
+        status(TeeType::Tdx, SwHardeningNeeded, UpToDate),
+        SwHardeningNeeded
+    );
+    assert_eq!(status(TeeType::Sgx, UpToDate, Unknown), Unknown);
+}
+
+#[test]
+fn rejects_quotes_with_a_revoked_pck_certificate() {
+    let now = 1741852249u64;
+    let mock = MockQuoteBuilder::new(TeeType::Tdx)
+        .revoke_pck_certificate()
+        .build(now)
+        .unwrap();
+    assert!(mock.collateral.root_ca_crl.is_some());
+    let err = verify_with_options(&mock.quote, &mock.collateral, now, &mock.verify_options())
+        .unwrap_err();
+    assert!(err
+        .root_cause()
+        .downcast_ref::<CertificateRevoked>()
+        .is_some());
+}
+
+#[test]
+fn verifies_reports_signed_by_an_attested_verifier() {
+    use dcap_qvl::signed_report::{SignatureScheme, SignedReport};
+    use ring::rand::SystemRandom;
+    use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+
+    let rng = SystemRandom::new();
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+    let key =
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+    // The SubjectPublicKeyInfo header of a P-256 key, followed by the point
+    let mut public_key =
+        hex::decode("3059301306072a8648ce3d020106082a8648ce3d030107034200").unwrap();
+    public_key.extend_from_slice(key.public_key().as_ref());
+    let report = br#"{"status":"UpToDate"}"#.to_vec();
+    let signature = key.sign(&rng, &report).unwrap().as_ref().to_vec();
+    let mut signed = SignedReport {
+        report,
+        scheme: SignatureScheme::EcdsaNistp256Sha256,
+        signature,
+        public_key: public_key.clone(),
+        verifier_quote: None,
+    };
+    assert_eq!(
+        signed.verify_with_key(&public_key).unwrap()["status"],
+        "UpToDate"
+    );
+
+    let now = 1741852249u64;
+    let mut report_data = [0u8; 64];
+    report_data[..32].copy_from_slice(&signed.key_hash());
+    let verifier = MockQuoteBuilder::new(TeeType::Tdx)
+        .mr_td([0x77; 48])
+        .report_data(report_data)
+        .build(now)
+        .unwrap();
+    let options = verifier.verify_options();
+    assert!(signed
+        .verify_chained(&verifier.collateral, now, &options)
+        .is_err());
+
+    signed.verifier_quote = Some(verifier.quote.clone());
+    let decoded = SignedReport::from_json(&signed.to_json().unwrap()).unwrap();
+    assert_eq!(decoded, signed);
+    let (json, verifier_report) = decoded
+        .verify_chained(&verifier.collateral, now, &options)
+        .unwrap();
+    assert_eq!(json["status"], "UpToDate");
+    assert_eq!(verifier_report.report.as_td10().unwrap().mr_td, [0x77; 48]);
+
+    // A verifier quote binding another key
+    let other = MockQuoteBuilder::new(TeeType::Tdx).build(now).unwrap();
+    signed.verifier_quote = Some(other.quote);
+    let err = signed
+        .verify_chained(&other.collateral, now, &other.verify_options())
+        .unwrap_err();
+    assert!(err.to_string().contains("not bound"));
+}
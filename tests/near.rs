@@ -0,0 +1,90 @@
+#![cfg(feature = "near")]
+
+use dcap_qvl::near::{
+    encode_report, measurement, prevalidate_collateral, verify, verify_with_measurements,
+    PrevalidatedCollateral,
+};
+use dcap_qvl::verify::{TcbStatus, VerifiedReport};
+use dcap_qvl::QuoteCollateralV3;
+
+const SGX_QUOTE: &[u8] = include_bytes!("../sample/sgx_quote");
+const SGX_NOW: u64 = 1699301000;
+const TDX_QUOTE: &[u8] = include_bytes!("../sample/tdx_quote");
+const TDX_NOW: u64 = 1741852249;
+
+fn sgx_collateral() -> QuoteCollateralV3 {
+    serde_json::from_slice(include_bytes!("../sample/sgx_quote_collateral.json")).unwrap()
+}
+
+fn tdx_collateral() -> QuoteCollateralV3 {
+    serde_json::from_slice(include_bytes!("../sample/tdx_quote_collateral.json")).unwrap()
+}
+
+#[test]
+fn verifies_against_prevalidated_collateral() {
+    let collateral = sgx_collateral();
+    let prevalidated = prevalidate_collateral(&collateral, SGX_NOW).unwrap();
+    assert_eq!(prevalidated.validated_at, SGX_NOW);
+    // Passed to the contract Borsh encoded
+    let prevalidated: PrevalidatedCollateral =
+        borsh::from_slice(&borsh::to_vec(&prevalidated).unwrap()).unwrap();
+
+    let report = verify(SGX_QUOTE, &prevalidated, SGX_NOW).unwrap();
+    let full = dcap_qvl::verify::verify(SGX_QUOTE, &collateral, SGX_NOW).unwrap();
+    assert_eq!(report.status, TcbStatus::ConfigurationAndSwHardeningNeeded);
+    assert_eq!(report.status, full.status);
+    assert_eq!(report.advisory_ids, full.advisory_ids);
+    assert_eq!(report.collateral_commitment, full.collateral_commitment);
+
+    let mut forged = SGX_QUOTE.to_vec();
+    let offset = dcap_qvl::quote::Quote::layout(SGX_QUOTE)
+        .unwrap()
+        .report_data
+        .offset;
+    forged[offset] ^= 1;
+    assert!(verify(&forged, &prevalidated, SGX_NOW).is_err());
+}
+
+#[test]
+fn rejects_invalid_collateral_off_chain() {
+    let mut collateral = sgx_collateral();
+    assert!(prevalidate_collateral(&collateral, SGX_NOW + 10 * 365 * 24 * 3600).is_err());
+    collateral.tcb_info_signature[0] ^= 1;
+    assert!(prevalidate_collateral(&collateral, SGX_NOW).is_err());
+}
+
+#[test]
+fn checks_measurements() {
+    let prevalidated = prevalidate_collateral(&tdx_collateral(), TDX_NOW).unwrap();
+    let report = verify(TDX_QUOTE, &prevalidated, TDX_NOW).unwrap();
+    assert_eq!(report.status, TcbStatus::UpToDate);
+    let mr_td = measurement(&report.report).to_vec();
+    assert_eq!(mr_td.len(), 48);
+    assert_eq!(mr_td, report.report.measurement().as_bytes());
+
+    let other = [0u8; 48];
+    let report =
+        verify_with_measurements(TDX_QUOTE, &prevalidated, TDX_NOW, &[&other, &mr_td]).unwrap();
+    assert_eq!(report.status, TcbStatus::UpToDate);
+    let err = verify_with_measurements(TDX_QUOTE, &prevalidated, TDX_NOW, &[&other]).unwrap_err();
+    assert!(err.to_string().contains("Measurement is not allowed"));
+
+    let sgx = prevalidate_collateral(&sgx_collateral(), SGX_NOW).unwrap();
+    let report = verify(SGX_QUOTE, &sgx, SGX_NOW).unwrap();
+    assert_eq!(
+        hex::encode(measurement(&report.report)),
+        "33d8736db756ed4997e04ba358d27833188f1932ff7b1d156904d3f560452fbb"
+    );
+}
+
+#[test]
+fn encoded_reports_decode() {
+    let prevalidated = prevalidate_collateral(&tdx_collateral(), TDX_NOW).unwrap();
+    let report = verify(TDX_QUOTE, &prevalidated, TDX_NOW).unwrap();
+    let encoded = encode_report(&report).unwrap();
+    let decoded: VerifiedReport = borsh::from_slice(&encoded).unwrap();
+    assert_eq!(decoded.status, report.status);
+    assert_eq!(decoded.advisory_ids, report.advisory_ids);
+    assert_eq!(measurement(&decoded.report), measurement(&report.report));
+    assert_eq!(decoded.collateral_commitment, report.collateral_commitment);
+}
@@ -0,0 +1,111 @@
+//! End-to-end tests against a real PCCS running in docker.
+//!
+//! Run with `make pccs_interop`, or `cargo test --features pccs-interop --test pccs_interop`. The
+//! harness needs:
+//!
+//! - `PCCS_IMAGE`: a PCCS image, e.g. built from Intel's `SGXDataCenterAttestationPrimitives`
+//!   `QuoteGeneration/pccs/container`, configured in offline mode (`CachingFillMode: OFFLINE`).
+//! - `PCCS_ADMIN_TOKEN`: the admin token the image is configured with.
+//! - `PCCS_SEED_FILE`: a `pccsadmin fetch` platform collaterals file covering the sample quotes.
+//!
+//! The container is removed when the test ends.
+#![cfg(feature = "pccs-interop")]
+
+use std::process::Command;
+use std::time::Duration;
+
+use dcap_qvl::collateral::get_collateral;
+use dcap_qvl::verify::verify;
+
+const PCCS_PORT: u16 = 18081;
+
+struct Pccs {
+    container: String,
+}
+
+impl Pccs {
+    fn start() -> Self {
+        let image = std::env::var("PCCS_IMAGE").expect("PCCS_IMAGE is not set");
+        let output = Command::new("docker")
+            .args(["run", "-d", "--rm", "-p"])
+            .arg(format!("{PCCS_PORT}:8081"))
+            .arg(image)
+            .output()
+            .expect("failed to run docker");
+        assert!(output.status.success(), "failed to start PCCS container");
+        let pccs = Pccs {
+            container: String::from_utf8(output.stdout).unwrap().trim().to_string(),
+        };
+        pccs.wait_ready();
+        pccs
+    }
+
+    fn url(&self) -> String {
+        format!("https://localhost:{PCCS_PORT}/sgx/certification/v4")
+    }
+
+    fn client() -> reqwest::blocking::Client {
+        reqwest::blocking::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap()
+    }
+
+    fn wait_ready(&self) {
+        for _ in 0..60 {
+            let ready = Self::client()
+                .get(format!("{}/rootcacrl", self.url()))
+                .send()
+                .is_ok();
+            if ready {
+                return;
+            }
+            std::thread::sleep(Duration::from_secs(1));
+        }
+        panic!("PCCS did not become ready");
+    }
+
+    fn seed(&self) {
+        let token = std::env::var("PCCS_ADMIN_TOKEN").expect("PCCS_ADMIN_TOKEN is not set");
+        let seed_file = std::env::var("PCCS_SEED_FILE").expect("PCCS_SEED_FILE is not set");
+        let body = std::fs::read(seed_file).expect("failed to read PCCS_SEED_FILE");
+        let response = Self::client()
+            .put(format!("{}/platformcollateral", self.url()))
+            .header("admin-token", token)
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send()
+            .expect("failed to seed PCCS");
+        assert!(
+            response.status().is_success(),
+            "failed to seed PCCS: {}",
+            response.status()
+        );
+    }
+}
+
+impl Drop for Pccs {
+    fn drop(&mut self) {
+        let _ = Command::new("docker")
+            .args(["rm", "-f", &self.container])
+            .output();
+    }
+}
+
+#[tokio::test]
+async fn could_verify_with_pccs_collateral() {
+    let pccs = Pccs::start();
+    pccs.seed();
+
+    let cases: [(&[u8], u64); 2] = [
+        (include_bytes!("../sample/sgx_quote"), 1699301000),
+        (include_bytes!("../sample/tdx_quote"), 1741852249),
+    ];
+    for (raw_quote, now) in cases {
+        let collateral = get_collateral(&pccs.url(), raw_quote, Duration::from_secs(10))
+            .await
+            .expect("failed to get collateral from PCCS");
+        verify(raw_quote, &collateral, now).expect("failed to verify with PCCS collateral");
+    }
+}
@@ -0,0 +1,133 @@
+use dcap_qvl::pck::SgxType;
+use dcap_qvl::policy::{verify_with_policy, PlatformSetting, PolicyRule, QuotePolicy};
+use dcap_qvl::quote::Report;
+use dcap_qvl::verify::TcbStatus;
+use dcap_qvl::QuoteCollateralV3;
+
+#[test]
+fn reports_outcome_per_rule() {
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let collateral: QuoteCollateralV3 =
+        serde_json::from_slice(include_bytes!("../sample/sgx_quote_collateral.json")).unwrap();
+    let now = 1699301000u64;
+
+    let report = verify_with_policy(raw_quote, &collateral, now, &QuotePolicy::new()).unwrap();
+    assert!(report.passed());
+    let enclave = report.report.report.as_sgx().unwrap().clone();
+
+    let policy = QuotePolicy::new()
+        .allow_mr_enclave(enclave.mr_enclave)
+        .allow_mr_signer([0; 32])
+        .allow_mr_td([0; 48])
+        .accept_tcb_status(TcbStatus::ConfigurationAndSwHardeningNeeded)
+        .tolerate_advisories(["INTEL-SA-00289"])
+        .deny_debug();
+    let report = verify_with_policy(raw_quote, &collateral, now, &policy).unwrap();
+    assert!(!report.passed());
+    let failed = report
+        .failures()
+        .map(|outcome| outcome.rule)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        failed,
+        [
+            PolicyRule::MrSigner,
+            PolicyRule::MrTd,
+            PolicyRule::AdvisoryIds
+        ]
+    );
+    assert_eq!(report.outcomes.len(), 6);
+    assert!(report.into_result().is_err());
+}
+
+#[test]
+fn checks_platform_kind() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let collateral: QuoteCollateralV3 =
+        serde_json::from_slice(include_bytes!("../sample/tdx_quote_collateral.json")).unwrap();
+    let now = 1741852249u64;
+
+    // The sample TDX platform is a multi-package one with every setting enabled
+    let policy = QuotePolicy::new()
+        .allow_sgx_type(SgxType::Scalable)
+        .require_platform_setting(PlatformSetting::MultiPackage, true)
+        .require_platform_setting(PlatformSetting::DynamicPlatform, true)
+        .require_platform_setting(PlatformSetting::CachedKeys, false)
+        .require_platform_setting(PlatformSetting::SmtEnabled, false);
+    let report = verify_with_policy(raw_quote, &collateral, now, &policy).unwrap();
+    let failures = report.failures().collect::<Vec<_>>();
+    assert_eq!(failures.len(), 2);
+    assert_eq!(
+        failures[0].rule,
+        PolicyRule::PlatformSetting(PlatformSetting::CachedKeys)
+    );
+    assert_eq!(
+        failures[0].failure.as_deref(),
+        Some("Cached keys is enabled")
+    );
+    assert_eq!(
+        failures[1].rule,
+        PolicyRule::PlatformSetting(PlatformSetting::SmtEnabled)
+    );
+}
+
+#[test]
+fn denies_privileged_attributes() {
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let collateral: QuoteCollateralV3 =
+        serde_json::from_slice(include_bytes!("../sample/sgx_quote_collateral.json")).unwrap();
+    let policy = QuotePolicy::new().deny_privileged_attributes();
+    let mut report = verify_with_policy(raw_quote, &collateral, 1699301000, &policy).unwrap();
+    assert!(report.passed());
+
+    if let Report::SgxEnclave(enclave) = &mut report.report.report {
+        // PROVISIONKEY and EINITTOKEN_KEY
+        enclave.attributes[0] |= 0x30;
+    }
+    let outcomes = policy.evaluate(&report.report);
+    assert_eq!(outcomes[0].rule, PolicyRule::PrivilegedAttributes);
+    assert_eq!(
+        outcomes[0].failure.as_deref(),
+        Some("PROVISIONKEY and EINITTOKEN_KEY attributes are set")
+    );
+}
+
+#[test]
+fn records_verdict_with_optional_evidence() {
+    use dcap_qvl::record::{Detail, VerdictRecord};
+
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let collateral: QuoteCollateralV3 =
+        serde_json::from_slice(include_bytes!("../sample/sgx_quote_collateral.json")).unwrap();
+    let now = 1699301000u64;
+    let policy = QuotePolicy::new().deny_debug();
+    let report = verify_with_policy(raw_quote, &collateral, now, &policy).unwrap();
+
+    let record =
+        VerdictRecord::of_policy_report(&report, Detail::Verdict).with_policy_hash(&[0xab; 4]);
+    let json = serde_json::to_value(&record).unwrap();
+    assert_eq!(json.as_object().unwrap().len(), 1);
+    assert_eq!(
+        json["verdict"]["status"],
+        "ConfigurationAndSWHardeningNeeded"
+    );
+    assert_eq!(json["verdict"]["accepted"], true);
+    assert_eq!(json["verdict"]["policy_hash"], "abababab");
+    assert_eq!(
+        json["verdict"]["expires_at"],
+        report
+            .report
+            .supplemental
+            .as_ref()
+            .unwrap()
+            .earliest_expiration
+    );
+
+    let record = VerdictRecord::of_policy_report(&report, Detail::Full);
+    let evidence = record.evidence.unwrap();
+    assert_eq!(
+        evidence.collateral_commitment,
+        report.report.collateral_commitment
+    );
+    assert_eq!(evidence.policy_outcomes, report.outcomes);
+}
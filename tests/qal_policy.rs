@@ -0,0 +1,51 @@
+#![cfg(feature = "interop")]
+
+use dcap_qvl::qal_policy::parse_policy_unverified;
+use dcap_qvl::verify::verify;
+use dcap_qvl::QuoteCollateralV3;
+
+#[test]
+fn could_evaluate_qal_policy() {
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let collateral: QuoteCollateralV3 =
+        serde_json::from_slice(include_bytes!("../sample/sgx_quote_collateral.json")).unwrap();
+    let report = verify(raw_quote, &collateral, 1699301000).unwrap();
+    let enclave = report.report.as_sgx().unwrap();
+
+    let policy = |reference: serde_json::Value| {
+        let policy = serde_json::json!({
+            "policy_payload": {
+                "id": "test",
+                "version": "1.0",
+                "policy": [
+                    {
+                        "environment": { "class_id": "platform" },
+                        "reference": {
+                            "accepted_tcb_status": ["UpToDate", "ConfigurationAndSWHardeningNeeded"],
+                            "allow_dynamic_platform": true,
+                        },
+                    },
+                    { "environment": { "class_id": "enclave" }, "reference": reference },
+                ],
+            },
+        });
+        parse_policy_unverified(&policy.to_string())
+    };
+
+    let accepting = policy(serde_json::json!({
+        "sgx_mrsigner": hex::encode(enclave.mr_signer),
+        "sgx_isvprodid": enclave.isv_prod_id,
+        "sgx_isvsvn_min": enclave.isv_svn,
+    }))
+    .unwrap();
+    accepting.evaluate(&report).unwrap();
+
+    let rejecting = policy(serde_json::json!({ "sgx_mrenclave": hex::encode([0u8; 32]) })).unwrap();
+    assert!(rejecting.evaluate(&report).is_err());
+
+    let td_only =
+        policy(serde_json::json!({ "tdx_mrtd": hex::encode(enclave.mr_enclave) })).unwrap();
+    assert!(td_only.evaluate(&report).is_err());
+
+    assert!(policy(serde_json::json!({ "sgx_unknown": 1 })).is_err());
+}
@@ -0,0 +1,94 @@
+#![cfg(feature = "mock")]
+
+use dcap_qvl::mock::MockQuoteBuilder;
+use dcap_qvl::quote::TeeType;
+use dcap_qvl::ra_tls::{
+    check_key_binding, extract_quote, extract_quote_from_cert, verify_ra_tls_cert,
+    verify_ra_tls_cert_with_options,
+};
+use dcap_qvl::verify::TcbStatus;
+use der::asn1::ObjectIdentifier;
+use der::{Decode, Encode};
+use x509_cert::Certificate;
+
+const NOW: u64 = 1741852249;
+const LEGACY_QUOTE_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113741.1337.6");
+
+/// Re-encode a certificate with its extensions changed, leaving the signature as it was.
+fn with_extensions(
+    cert: &[u8],
+    change: impl FnOnce(&mut Vec<x509_cert::ext::Extension>),
+) -> Vec<u8> {
+    let mut cert = Certificate::from_der(cert).unwrap();
+    change(cert.tbs_certificate.extensions.as_mut().unwrap());
+    cert.to_der().unwrap()
+}
+
+#[test]
+fn extracts_the_embedded_quote() {
+    let mock = MockQuoteBuilder::new(TeeType::Sgx)
+        .mr_enclave([0x11; 32])
+        .build_ra_tls_cert(NOW)
+        .unwrap();
+    assert_eq!(extract_quote(&mock.cert).unwrap(), mock.quote.quote);
+    let quote = extract_quote_from_cert(&mock.cert).unwrap();
+    assert_eq!(quote.report.as_sgx().unwrap().mr_enclave, [0x11; 32]);
+
+    // Gramine, Occlum and RATS-TLS use their own OID
+    let legacy = with_extensions(&mock.cert, |extensions| {
+        extensions.last_mut().unwrap().extn_id = LEGACY_QUOTE_OID;
+    });
+    assert_eq!(extract_quote(&legacy).unwrap(), mock.quote.quote);
+
+    let ambiguous = with_extensions(&mock.cert, |extensions| {
+        let mut legacy = extensions.last().unwrap().clone();
+        legacy.extn_id = LEGACY_QUOTE_OID;
+        extensions.push(legacy);
+    });
+    let err = extract_quote(&ambiguous).unwrap_err();
+    assert!(err.to_string().contains("Quote extension ambiguity"));
+
+    let err = extract_quote(&mock.quote.root_ca).unwrap_err();
+    assert!(err.to_string().contains("Quote extension not found"));
+}
+
+#[test]
+fn checks_the_quote_binds_the_certificate_key() {
+    let mock = MockQuoteBuilder::new(TeeType::Tdx)
+        .build_ra_tls_cert(NOW)
+        .unwrap();
+    let other = MockQuoteBuilder::new(TeeType::Tdx)
+        .build_ra_tls_cert(NOW)
+        .unwrap();
+    let report = extract_quote_from_cert(&mock.cert).unwrap().report;
+    check_key_binding(&mock.cert, &report).unwrap();
+    let err = check_key_binding(&other.cert, &report).unwrap_err();
+    assert!(err.to_string().contains("not bound"));
+}
+
+#[test]
+fn verifies_certificates_with_their_quote() {
+    let mock = MockQuoteBuilder::new(TeeType::Tdx)
+        .mr_td([0x22; 48])
+        .build_ra_tls_cert(NOW)
+        .unwrap();
+    let options = mock.quote.verify_options();
+    let report =
+        verify_ra_tls_cert_with_options(&mock.cert, &mock.quote.collateral, NOW, &options).unwrap();
+    assert_eq!(report.status, TcbStatus::UpToDate);
+    assert_eq!(report.report.as_td10().unwrap().mr_td, [0x22; 48]);
+    // Mock quotes don't chain to the Intel SGX root CA
+    assert!(verify_ra_tls_cert(&mock.cert, &mock.quote.collateral, NOW).is_err());
+
+    // A quote moved into a certificate of another key
+    let other = MockQuoteBuilder::new(TeeType::Tdx)
+        .build_ra_tls_cert(NOW)
+        .unwrap();
+    let moved = with_extensions(&other.cert, |extensions| {
+        let quote = extensions.last_mut().unwrap();
+        quote.extn_value = der::asn1::OctetString::new(mock.quote.quote.clone()).unwrap();
+    });
+    let err =
+        verify_ra_tls_cert_with_options(&moved, &mock.quote.collateral, NOW, &options).unwrap_err();
+    assert!(err.to_string().contains("not bound"));
+}
@@ -0,0 +1,96 @@
+use anyhow::Result;
+use dcap_qvl::collateral::{HttpClient, HttpResponse};
+use dcap_qvl::registry::{
+    verify_with_registry, HttpRegistry, StaticRegistry, Workload, WorkloadStatus,
+};
+use dcap_qvl::QuoteCollateralV3;
+
+fn sample() -> (&'static [u8], QuoteCollateralV3) {
+    let collateral =
+        serde_json::from_slice(include_bytes!("../sample/sgx_quote_collateral.json")).unwrap();
+    (include_bytes!("../sample/sgx_quote"), collateral)
+}
+
+fn mr_enclave(raw_quote: &[u8]) -> String {
+    let quote = dcap_qvl::quote::Quote::parse(raw_quote).unwrap();
+    quote.report.measurement().to_string()
+}
+
+#[tokio::test]
+async fn looks_up_workload_in_static_registry() {
+    let (raw_quote, collateral) = sample();
+    let now = 1699301000;
+    let json = format!(
+        r#"[{{"measurement":"{}","name":"key-manager","version":"1.2.0","status":"deprecated","metadata":{{"owner":"infra"}}}}]"#,
+        mr_enclave(raw_quote)
+    );
+    let registry = StaticRegistry::from_json(&json).unwrap();
+    let report = verify_with_registry(raw_quote, &collateral, now, &registry)
+        .await
+        .unwrap();
+    assert_eq!(report.workload.name, "key-manager");
+    assert_eq!(report.workload.version.as_deref(), Some("1.2.0"));
+    assert_eq!(report.workload.status, WorkloadStatus::Deprecated);
+    assert_eq!(report.workload.metadata["owner"], "infra");
+
+    let revoked = Workload {
+        status: WorkloadStatus::Revoked,
+        ..report.workload
+    };
+    let registry = registry.register(report.report.report.measurement(), revoked);
+    let err = verify_with_registry(raw_quote, &collateral, now, &registry)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("revoked workload key-manager"));
+
+    let err = verify_with_registry(raw_quote, &collateral, now, &StaticRegistry::new())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("is not registered"));
+}
+
+/// Serves one workload, under the given measurement.
+struct RegistryService {
+    measurement: String,
+}
+
+impl HttpClient for RegistryService {
+    async fn get(&self, url: &str) -> Result<HttpResponse> {
+        if url == format!("https://registry.example/workloads/{}", self.measurement) {
+            return Ok(HttpResponse {
+                status: 200,
+                body: br#"{"name":"key-manager","status":"allowed"}"#.to_vec(),
+                ..Default::default()
+            });
+        }
+        Ok(HttpResponse {
+            status: 404,
+            ..Default::default()
+        })
+    }
+}
+
+#[tokio::test]
+async fn looks_up_workload_over_http() {
+    let (raw_quote, collateral) = sample();
+    let service = RegistryService {
+        measurement: mr_enclave(raw_quote),
+    };
+    let registry = HttpRegistry::new(service, "https://registry.example/workloads/");
+    let report = verify_with_registry(raw_quote, &collateral, 1699301000, &registry)
+        .await
+        .unwrap();
+    assert_eq!(report.workload.status, WorkloadStatus::Allowed);
+    assert!(report.workload.metadata.is_empty());
+
+    let registry = HttpRegistry::new(
+        RegistryService {
+            measurement: "00".repeat(32),
+        },
+        "https://registry.example/workloads",
+    );
+    let err = verify_with_registry(raw_quote, &collateral, 1699301000, &registry)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("is not registered"));
+}
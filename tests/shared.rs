@@ -0,0 +1,384 @@
+#![cfg(feature = "shared")]
+
+use anyhow::{bail, Result};
+use dcap_qvl::collateral::{get_collateral_for_platform_with_client, HttpClient, HttpResponse};
+use dcap_qvl::pcs::PckCa;
+use dcap_qvl::shared::{CollateralFetcher, SharedVerifier, SharedVerifierOptions};
+use dcap_qvl::store::{CacheStore, MemoryStore};
+use dcap_qvl::verify::{TcbStatus, VerifyOptions};
+use dcap_qvl::QuoteCollateralV3;
+use futures::future::BoxFuture;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const SGX_QUOTE: &[u8] = include_bytes!("../sample/sgx_quote");
+const NOW: u64 = 1699301000;
+const TDX_QUOTE: &[u8] = include_bytes!("../sample/tdx_quote");
+const TDX_NOW: u64 = 1741852249;
+const TDX_FMSPC: &str = "B0C06F000000";
+
+/// Serves the sample collateral the way PCCS does.
+struct SamplePccs {
+    collateral: Value,
+}
+
+impl SamplePccs {
+    fn field(&self, name: &str) -> &str {
+        self.collateral[name].as_str().unwrap()
+    }
+
+    fn document(&self, name: &str, key: &str, chain_header: &str) -> HttpResponse {
+        let body = format!(
+            r#"{{"{key}":{},"signature":"{}"}}"#,
+            self.field(name),
+            self.field(&format!("{name}_signature"))
+        );
+        HttpResponse {
+            status: 200,
+            headers: vec![(
+                chain_header.into(),
+                self.field(&format!("{name}_issuer_chain")).into(),
+            )],
+            body: body.into_bytes(),
+        }
+    }
+}
+
+impl HttpClient for SamplePccs {
+    async fn get(&self, url: &str) -> Result<HttpResponse> {
+        let ok = |body: Vec<u8>| HttpResponse {
+            status: 200,
+            headers: vec![("Date".into(), "Mon, 06 Nov 2023 20:03:20 GMT".into())],
+            body,
+        };
+        Ok(if url.contains("/tcb?fmspc=") {
+            self.document("tcb_info", "tcbInfo", "TCB-Info-Issuer-Chain")
+        } else if url.contains("/qe/identity") {
+            self.document(
+                "qe_identity",
+                "enclaveIdentity",
+                "SGX-Enclave-Identity-Issuer-Chain",
+            )
+        } else if url.ends_with("/rootcacrl") {
+            ok(self.field("root_ca_crl").as_bytes().to_vec())
+        } else if url.contains("/pckcrl?") {
+            ok(hex::decode(self.field("pck_crl"))?)
+        } else {
+            HttpResponse {
+                status: 404,
+                ..Default::default()
+            }
+        })
+    }
+}
+
+/// Fetches the sample collateral from a [`SamplePccs`], counting the fetches.
+struct SampleFetcher {
+    /// The collateral served for SGX, `None` while PCCS is down. The TDX sample collateral is
+    /// always served.
+    collateral: Mutex<Option<Value>>,
+    fetches: AtomicU32,
+    delay: Duration,
+}
+
+impl SampleFetcher {
+    fn new(delay: Duration) -> Arc<Self> {
+        Arc::new(Self {
+            collateral: Mutex::new(Some(sample_collateral())),
+            fetches: AtomicU32::new(0),
+            delay,
+        })
+    }
+
+    fn fetches(&self) -> u32 {
+        self.fetches.load(Ordering::SeqCst)
+    }
+
+    fn serve(&self, collateral: Option<Value>) {
+        *self.collateral.lock().unwrap() = collateral;
+    }
+}
+
+impl CollateralFetcher for SampleFetcher {
+    fn fetch<'a>(
+        &'a self,
+        pccs_url: &'a str,
+        fmspc: &'a str,
+        pck_ca: PckCa,
+    ) -> BoxFuture<'a, Result<QuoteCollateralV3>> {
+        self.fetches.fetch_add(1, Ordering::SeqCst);
+        let collateral = if fmspc == TDX_FMSPC {
+            Some(
+                serde_json::from_slice(include_bytes!("../sample/tdx_quote_collateral.json"))
+                    .unwrap(),
+            )
+        } else {
+            self.collateral.lock().unwrap().clone()
+        };
+        Box::pin(async move {
+            tokio::time::sleep(self.delay).await;
+            let Some(collateral) = collateral else {
+                bail!("PCCS is down");
+            };
+            let pccs = SamplePccs { collateral };
+            get_collateral_for_platform_with_client(&pccs, pccs_url, fmspc, pck_ca).await
+        })
+    }
+}
+
+fn sample_collateral() -> Value {
+    serde_json::from_slice(include_bytes!("../sample/sgx_quote_collateral.json")).unwrap()
+}
+
+fn sample_next_update() -> u64 {
+    let collateral: QuoteCollateralV3 = serde_json::from_value(sample_collateral()).unwrap();
+    collateral.next_update().unwrap()
+}
+
+fn verifier(
+    options: SharedVerifierOptions,
+    store: &Arc<MemoryStore>,
+    fetcher: &Arc<SampleFetcher>,
+) -> SharedVerifier {
+    SharedVerifier::with_fetcher(options, store.clone(), fetcher.clone())
+}
+
+#[tokio::test]
+async fn coalesces_concurrent_fetches() {
+    let store = Arc::new(MemoryStore::default());
+    let fetcher = SampleFetcher::new(Duration::from_millis(50));
+    let verifier = verifier(Default::default(), &store, &fetcher);
+
+    let lookups = (0..8).map(|_| verifier.collateral_for(SGX_QUOTE, NOW));
+    for collateral in futures::future::join_all(lookups).await {
+        assert_eq!(
+            collateral.unwrap().next_update().unwrap(),
+            sample_next_update()
+        );
+    }
+    assert_eq!(fetcher.fetches(), 1);
+
+    let report = verifier.verify_at(SGX_QUOTE, NOW).await.unwrap();
+    assert_eq!(report.status, TcbStatus::ConfigurationAndSwHardeningNeeded);
+    assert_eq!(fetcher.fetches(), 1);
+}
+
+#[tokio::test]
+async fn falls_back_to_stale_collateral() {
+    let store = Arc::new(MemoryStore::default());
+    let fetcher = SampleFetcher::new(Duration::ZERO);
+    let options = SharedVerifierOptions {
+        max_staleness: Some(Duration::from_secs(3600)),
+        ..Default::default()
+    };
+    let tolerant = verifier(options, &store, &fetcher);
+    let strict = verifier(Default::default(), &store, &fetcher);
+    tolerant.collateral_for(SGX_QUOTE, NOW).await.unwrap();
+    fetcher.serve(None);
+
+    let next_update = sample_next_update();
+    let collateral = tolerant
+        .collateral_for(SGX_QUOTE, next_update + 10)
+        .await
+        .unwrap();
+    assert_eq!(collateral.next_update().unwrap(), next_update);
+    assert_eq!(fetcher.fetches(), 2);
+
+    let err = tolerant
+        .collateral_for(SGX_QUOTE, next_update + 3601)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("too stale"));
+    let err = strict
+        .collateral_for(SGX_QUOTE, next_update + 10)
+        .await
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("PCCS is down"));
+}
+
+#[tokio::test]
+async fn drops_results_when_collateral_changes() {
+    let store = Arc::new(MemoryStore::default());
+    let fetcher = SampleFetcher::new(Duration::ZERO);
+    let options = SharedVerifierOptions {
+        result_ttl: Some(Duration::from_secs(3600)),
+        ..Default::default()
+    };
+    let verifier = verifier(options, &store, &fetcher);
+    verifier.verify_at(SGX_QUOTE, NOW).await.unwrap();
+    assert_eq!(store.keys(b"result/").unwrap().len(), 1);
+
+    // Fetched again, but the same
+    let next_update = sample_next_update();
+    verifier
+        .collateral_for(SGX_QUOTE, next_update)
+        .await
+        .unwrap();
+    assert_eq!(fetcher.fetches(), 2);
+    assert_eq!(store.keys(b"result/").unwrap().len(), 1);
+
+    let mut changed = sample_collateral();
+    let signature = changed["tcb_info_signature"].as_str().unwrap();
+    let flipped = if signature.starts_with('0') { "1" } else { "0" };
+    let signature = format!("{flipped}{}", &signature[1..]);
+    changed["tcb_info_signature"] = signature.into();
+    fetcher.serve(Some(changed));
+    verifier
+        .collateral_for(SGX_QUOTE, next_update)
+        .await
+        .unwrap();
+    assert_eq!(fetcher.fetches(), 3);
+    assert!(store.keys(b"result/").unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn verifies_and_caches_results_with_the_options() {
+    let store = Arc::new(MemoryStore::default());
+    let fetcher = SampleFetcher::new(Duration::ZERO);
+    let options = SharedVerifierOptions {
+        result_ttl: Some(Duration::from_secs(3600)),
+        ..Default::default()
+    };
+    let lenient = verifier(options.clone(), &store, &fetcher);
+    lenient.verify_at(SGX_QUOTE, NOW).await.unwrap();
+    assert_eq!(store.keys(b"result/").unwrap().len(), 1);
+
+    // The result cached by the lenient verifier is not served to a stricter one
+    let strict = SharedVerifierOptions {
+        verify_options: VerifyOptions {
+            allowed_tcb_statuses: Some(vec![TcbStatus::UpToDate]),
+            ..Default::default()
+        },
+        ..options.clone()
+    };
+    let strict = verifier(strict, &store, &fetcher);
+    let err = strict.verify_at(SGX_QUOTE, NOW).await.unwrap_err();
+    assert!(err.to_string().contains("is not allowed"));
+
+    let tolerant = SharedVerifierOptions {
+        verify_options: VerifyOptions {
+            out_of_date_as_warning: true,
+            ..Default::default()
+        },
+        ..options
+    };
+    let tolerant = verifier(tolerant, &store, &fetcher);
+    tolerant.verify_at(SGX_QUOTE, NOW).await.unwrap();
+    assert_eq!(store.keys(b"result/").unwrap().len(), 2);
+}
+
+/// The sample collateral with the last hex digit of `field` changed.
+fn changed_collateral(field: &str) -> Value {
+    let mut changed = sample_collateral();
+    let value = changed[field].as_str().unwrap();
+    let flipped = if value.ends_with('0') { "1" } else { "0" };
+    let value = format!("{}{flipped}", &value[..value.len() - 1]);
+    changed[field] = value.into();
+    changed
+}
+
+#[tokio::test]
+async fn polls_crls_and_drops_the_results_they_affect() {
+    let store = Arc::new(MemoryStore::default());
+    let fetcher = SampleFetcher::new(Duration::ZERO);
+    let options = SharedVerifierOptions {
+        result_ttl: Some(Duration::from_secs(3600)),
+        crl_poll_interval: Some(Duration::from_secs(60)),
+        ..Default::default()
+    };
+    let verifier = verifier(options, &store, &fetcher);
+    // The sample quotes are of platforms of different PCK CAs
+    verifier.verify_at(SGX_QUOTE, NOW).await.unwrap();
+    verifier.verify_at(TDX_QUOTE, TDX_NOW).await.unwrap();
+    assert_eq!(store.keys(b"result/").unwrap().len(), 2);
+
+    // Long before the SGX collateral's nextUpdate, the TDX collateral was fetched later
+    assert_eq!(
+        verifier.refresh_expiring(NOW + 30).await,
+        Some(Duration::from_secs(30))
+    );
+    assert_eq!(fetcher.fetches(), 2);
+    verifier.refresh_expiring(NOW + 60).await;
+    assert_eq!(fetcher.fetches(), 3);
+    assert_eq!(store.keys(b"result/").unwrap().len(), 2);
+
+    fetcher.serve(Some(changed_collateral("pck_crl")));
+    verifier.refresh_expiring(NOW + 120).await;
+    assert_eq!(fetcher.fetches(), 4);
+    let results = store.keys(b"result/").unwrap();
+    assert_eq!(results.len(), 1);
+    assert!(results[0].starts_with(b"result/platform/tdx/"));
+
+    // A new root CA CRL affects every platform
+    fetcher.serve(Some(changed_collateral("root_ca_crl")));
+    verifier.refresh_expiring(NOW + 180).await;
+    assert_eq!(fetcher.fetches(), 5);
+    assert!(store.keys(b"result/").unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn refreshes_in_the_background() {
+    let store = Arc::new(MemoryStore::default());
+    let fetcher = SampleFetcher::new(Duration::ZERO);
+    let options = SharedVerifierOptions {
+        result_ttl: Some(Duration::from_secs(3600)),
+        refresh_interval: Duration::from_millis(10),
+        ..Default::default()
+    };
+    let verifier = verifier(options, &store, &fetcher);
+    verifier.verify_at(SGX_QUOTE, NOW).await.unwrap();
+    assert_eq!(store.keys(b"result/").unwrap().len(), 1);
+
+    // The sample collateral and the result verified with it have long expired
+    let refresher = verifier.spawn_refresher();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while fetcher.fetches() < 2 || !store.keys(b"result/").unwrap().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    refresher.abort();
+}
+
+#[tokio::test]
+async fn spreads_refreshes_across_replicas() {
+    let store = Arc::new(MemoryStore::default());
+    let fetcher = SampleFetcher::new(Duration::ZERO);
+    let margin = 24 * 3600;
+    let jitter = 3600;
+    let options = SharedVerifierOptions {
+        refresh_margin: Duration::from_secs(margin),
+        refresh_jitter: Duration::from_secs(jitter),
+        crl_poll_interval: None,
+        ..Default::default()
+    };
+    let replicas = [
+        verifier(options.clone(), &store, &fetcher),
+        verifier(options.clone(), &store, &fetcher),
+    ];
+    assert_eq!(replicas[0].next_refresh(SGX_QUOTE).await.unwrap(), None);
+    replicas[0].collateral_for(SGX_QUOTE, NOW).await.unwrap();
+
+    let latest = sample_next_update() - margin;
+    for replica in &replicas {
+        let due = replica.next_refresh(SGX_QUOTE).await.unwrap().unwrap();
+        assert!((latest - jitter..=latest).contains(&due));
+        assert_eq!(replica.next_refresh(SGX_QUOTE).await.unwrap(), Some(due));
+    }
+    // The second replica found the collateral cached by the first
+    replicas[1].collateral_for(SGX_QUOTE, NOW).await.unwrap();
+    assert_eq!(fetcher.fetches(), 1);
+
+    let options = SharedVerifierOptions {
+        refresh_jitter: Duration::ZERO,
+        ..options
+    };
+    let unjittered = verifier(options, &store, &fetcher);
+    assert_eq!(
+        unjittered.next_refresh(SGX_QUOTE).await.unwrap(),
+        Some(latest)
+    );
+}
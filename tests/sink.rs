@@ -0,0 +1,65 @@
+#![cfg(feature = "interop")]
+
+use std::sync::Mutex;
+
+use dcap_qvl::record::{Detail, VerdictRecord};
+use dcap_qvl::sink::{BatchingSink, NdjsonSink, Outcome, ReportRecord, ReportSink};
+use dcap_qvl::verify::{verify, verify_traced, VerifyOptions};
+use dcap_qvl::QuoteCollateralV3;
+
+#[derive(Default)]
+struct Batches(Mutex<Vec<usize>>);
+
+impl ReportSink for &Batches {
+    fn write(&self, records: &[ReportRecord]) -> anyhow::Result<()> {
+        self.0.lock().unwrap().push(records.len());
+        Ok(())
+    }
+}
+
+#[test]
+fn batches_and_persists_records() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let collateral: QuoteCollateralV3 =
+        serde_json::from_slice(include_bytes!("../sample/tdx_quote_collateral.json")).unwrap();
+    let now = 1741852249u64;
+    let report = verify(raw_quote, &collateral, now);
+    let accepted = ReportRecord::new(raw_quote, now, &report);
+    let rejected = ReportRecord::new(b"garbage", now, &verify(b"garbage", &collateral, now));
+    let (traced, trace) = verify_traced(raw_quote, &collateral, now, &VerifyOptions::default());
+    let verdict = VerdictRecord::new(&traced.unwrap(), Detail::Full).with_trace(trace.clone());
+    let recorded = ReportRecord::of_verdict(raw_quote, now, verdict);
+    assert!(matches!(accepted.outcome, Outcome::Verified(_)));
+    assert!(matches!(rejected.outcome, Outcome::Rejected { .. }));
+    assert!(matches!(recorded.outcome, Outcome::Recorded(_)));
+    assert_eq!(recorded.name(), accepted.name());
+
+    let batches = Batches::default();
+    {
+        let sink = BatchingSink::new(&batches, 2);
+        for _ in 0..5 {
+            sink.write(&[accepted.clone()]).unwrap();
+        }
+    }
+    assert_eq!(*batches.0.lock().unwrap(), [2, 2, 1]);
+
+    let path = std::env::temp_dir().join(format!("dcap-qvl-sink-{}.ndjson", std::process::id()));
+    let sink = NdjsonSink::open(&path).unwrap();
+    sink.write(&[accepted, rejected, recorded]).unwrap();
+    sink.flush().unwrap();
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written.lines().count(), 3);
+    let recorded: serde_json::Value =
+        serde_json::from_str(written.lines().last().unwrap()).unwrap();
+    assert_eq!(
+        recorded["outcome"]["recorded"]["verdict"]["status"],
+        "UpToDate"
+    );
+    let evidence: dcap_qvl::record::Evidence =
+        serde_json::from_value(recorded["outcome"]["recorded"]["evidence"].clone()).unwrap();
+    assert_eq!(evidence.trace, Some(trace.clone()));
+    assert!(trace.failed_step.is_none());
+    assert!(!trace.steps.is_empty());
+    assert!(trace.steps.iter().all(|step| step.passed));
+}
@@ -0,0 +1,30 @@
+use dcap_qvl::snapshot::{CollateralSnapshot, StalenessPolicy};
+
+#[test]
+fn could_verify_against_embedded_snapshot() {
+    let snapshot = dcap_qvl::embed_collateral!(
+        "../sample/tdx_quote_collateral.json",
+        tcb_evaluation_data_number = 17,
+    );
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    snapshot
+        .verify(raw_quote, 1741852249, StalenessPolicy::Reject)
+        .unwrap();
+
+    let stale = snapshot.next_update() + 3600;
+    assert!(snapshot
+        .check_staleness(stale, StalenessPolicy::Reject)
+        .is_err());
+    assert!(snapshot
+        .check_staleness(stale, StalenessPolicy::GracePeriod(60))
+        .is_err());
+    snapshot
+        .check_staleness(stale, StalenessPolicy::GracePeriod(7200))
+        .unwrap();
+    snapshot
+        .check_staleness(stale, StalenessPolicy::Allow)
+        .unwrap();
+
+    let json = include_str!("../sample/tdx_quote_collateral.json");
+    assert!(CollateralSnapshot::from_json(json, 16).is_err());
+}
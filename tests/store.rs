@@ -0,0 +1,102 @@
+use dcap_qvl::store::{CacheStore, FileStore, MemoryStore};
+
+/// Check a store gets, replaces, lists and removes entries, with keys as the caches use them.
+fn exercise(store: &dyn CacheStore) {
+    assert_eq!(store.get(b"collateral/sgx/a").unwrap(), None);
+    store.put(b"collateral/sgx/a", b"1").unwrap();
+    store.put(b"collateral/sgx/a", b"2").unwrap();
+    store.put(b"collateral/tdx/b", b"3").unwrap();
+    store.put(b"result/c", b"4").unwrap();
+    // Glob characters in keys are matched literally
+    store.put(b"result*/d", b"5").unwrap();
+    assert_eq!(store.get(b"collateral/sgx/a").unwrap(), Some(b"2".to_vec()));
+
+    let mut keys = store.keys(b"collateral/").unwrap();
+    keys.sort();
+    assert_eq!(
+        keys,
+        [b"collateral/sgx/a".to_vec(), b"collateral/tdx/b".to_vec()]
+    );
+    assert_eq!(store.keys(b"result/").unwrap(), [b"result/c".to_vec()]);
+    assert_eq!(store.keys(b"result*").unwrap(), [b"result*/d".to_vec()]);
+
+    store.remove(b"collateral/sgx/a").unwrap();
+    store.remove(b"collateral/sgx/a").unwrap();
+    assert_eq!(store.get(b"collateral/sgx/a").unwrap(), None);
+    assert_eq!(
+        store.keys(b"collateral/").unwrap(),
+        [b"collateral/tdx/b".to_vec()]
+    );
+}
+
+#[test]
+fn memory_store_works() {
+    exercise(&MemoryStore::default());
+}
+
+#[test]
+fn file_store_works() {
+    let dir = std::env::temp_dir().join(format!("dcap-qvl-file-store-{}", std::process::id()));
+    let store = FileStore::open(&dir).unwrap();
+    exercise(&store);
+    assert!(store.blocking());
+
+    // Concurrent writers of a key each write their own temporary file
+    std::thread::scope(|scope| {
+        for writer in 0..8u8 {
+            let store = &store;
+            scope.spawn(move || {
+                for _ in 0..20 {
+                    store.put(b"collateral/sgx/e", &[writer; 64]).unwrap();
+                }
+            });
+        }
+    });
+    let value = store.get(b"collateral/sgx/e").unwrap().unwrap();
+    assert!(value.len() == 64 && value.iter().all(|byte| *byte == value[0]));
+    assert_eq!(
+        std::fs::read_dir(&dir).unwrap().count(),
+        store.keys(b"").unwrap().len()
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "sled")]
+#[test]
+fn sled_store_works() {
+    use dcap_qvl::store::SledStore;
+
+    let db = sled::Config::new().temporary(true).open().unwrap();
+    let store = SledStore::new(db.clone());
+    exercise(&store);
+
+    // Entries outlive the store, which other handles on the database see
+    let reopened = SledStore::new(db);
+    assert_eq!(
+        reopened.get(b"collateral/tdx/b").unwrap(),
+        Some(b"3".to_vec())
+    );
+}
+
+/// Runs against the Redis server at `REDIS_URL`, and is skipped if it is not set.
+#[cfg(feature = "redis")]
+#[test]
+fn redis_store_works() {
+    use dcap_qvl::store::RedisStore;
+
+    let Ok(url) = std::env::var("REDIS_URL") else {
+        eprintln!("REDIS_URL is not set, skipping");
+        return;
+    };
+    let client = redis::Client::open(url).unwrap();
+    let namespace = format!("dcap-qvl-test-{}/", std::process::id());
+    let store = RedisStore::new(client.clone(), &namespace);
+    exercise(&store);
+
+    // Namespaces keep deployments sharing a server apart
+    let other = RedisStore::new(client, "dcap-qvl-test-other/");
+    assert_eq!(other.get(b"collateral/tdx/b").unwrap(), None);
+    for key in store.keys(b"").unwrap() {
+        store.remove(&key).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "stream")]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+use dcap_qvl::pcs::PckCa;
+use dcap_qvl::shared::{CollateralFetcher, SharedVerifier};
+use dcap_qvl::store::MemoryStore;
+use dcap_qvl::stream::{verify_stream_at, QuoteJob};
+use dcap_qvl::verify::TcbStatus;
+use dcap_qvl::QuoteCollateralV3;
+use futures::future::BoxFuture;
+use futures::stream::{self, StreamExt};
+
+const SGX_QUOTE: &[u8] = include_bytes!("../sample/sgx_quote");
+const NOW: u64 = 1699301000;
+
+/// Serves the sample collateral of the SGX quote.
+struct SampleFetcher;
+
+impl CollateralFetcher for SampleFetcher {
+    fn fetch<'a>(
+        &'a self,
+        _pccs_url: &'a str,
+        _fmspc: &'a str,
+        _pck_ca: PckCa,
+    ) -> BoxFuture<'a, Result<QuoteCollateralV3>> {
+        Box::pin(async {
+            let collateral = include_bytes!("../sample/sgx_quote_collateral.json");
+            Ok(serde_json::from_slice(collateral)?)
+        })
+    }
+}
+
+fn verifier() -> SharedVerifier {
+    SharedVerifier::with_fetcher(
+        Default::default(),
+        Arc::new(MemoryStore::default()),
+        Arc::new(SampleFetcher),
+    )
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn yields_one_result_per_job() {
+    let jobs = (0..8).map(|id| QuoteJob {
+        id,
+        // Jobs 1, 4 and 7 carry a truncated quote
+        quote: if id % 3 == 1 {
+            SGX_QUOTE[..100].to_vec()
+        } else {
+            SGX_QUOTE.to_vec()
+        },
+    });
+    let mut results = verify_stream_at(verifier(), stream::iter(jobs), 3, NOW)
+        .collect::<Vec<_>>()
+        .await;
+    results.sort_by_key(|result| result.id);
+
+    assert_eq!(results.len(), 8);
+    for (id, result) in results.iter().enumerate() {
+        assert_eq!(result.id, id);
+        match &result.result {
+            Ok(report) if id % 3 != 1 => {
+                assert_eq!(report.status, TcbStatus::ConfigurationAndSwHardeningNeeded)
+            }
+            Err(_) if id % 3 == 1 => {}
+            result => panic!("unexpected result for job {id}: {result:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn polls_jobs_while_there_is_room() {
+    let pulled = AtomicUsize::new(0);
+    let jobs = stream::iter((0..8).map(|id| QuoteJob {
+        id,
+        quote: SGX_QUOTE.to_vec(),
+    }))
+    .inspect(|_| {
+        pulled.fetch_add(1, Ordering::SeqCst);
+    });
+    let mut results = Box::pin(verify_stream_at(verifier(), jobs, 2, NOW));
+
+    assert!(results.next().await.unwrap().result.is_ok());
+    assert!(pulled.load(Ordering::SeqCst) <= 3);
+    assert_eq!(results.count().await, 7);
+    assert_eq!(pulled.load(Ordering::SeqCst), 8);
+}
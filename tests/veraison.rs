@@ -0,0 +1,104 @@
+#![cfg(feature = "interop")]
+
+use dcap_qvl::veraison::{
+    appraise_evidence, decode_endorsements, extract_claims, validate_evidence_integrity,
+    ReferenceValue, TrustTier, SGX_MEDIA_TYPE, TDX_MEDIA_TYPE,
+};
+use dcap_qvl::verify::TcbStatus;
+use dcap_qvl::QuoteCollateralV3;
+
+const SGX_QUOTE: &[u8] = include_bytes!("../sample/sgx_quote");
+const SGX_NOW: u64 = 1699301000;
+const TDX_QUOTE: &[u8] = include_bytes!("../sample/tdx_quote");
+const TDX_NOW: u64 = 1741852249;
+const SGX_REFERENCE_ID: &str =
+    "sgx/33d8736db756ed4997e04ba358d27833188f1932ff7b1d156904d3f560452fbb";
+
+fn sgx_collateral() -> QuoteCollateralV3 {
+    serde_json::from_slice(include_bytes!("../sample/sgx_quote_collateral.json")).unwrap()
+}
+
+fn tdx_collateral() -> QuoteCollateralV3 {
+    serde_json::from_slice(include_bytes!("../sample/tdx_quote_collateral.json")).unwrap()
+}
+
+#[test]
+fn extracts_claims_of_the_media_type() {
+    let extracted = extract_claims(SGX_MEDIA_TYPE, SGX_QUOTE).unwrap();
+    assert_eq!(extracted.reference_id, SGX_REFERENCE_ID);
+    assert_eq!(
+        extracted.claims["mr_enclave"],
+        SGX_REFERENCE_ID.trim_start_matches("sgx/")
+    );
+    assert!(extracted.claims.contains_key("mr_signer"));
+
+    let extracted = extract_claims(TDX_MEDIA_TYPE, TDX_QUOTE).unwrap();
+    let mr_td = extracted.claims["mr_td"].as_str().unwrap();
+    assert_eq!(extracted.reference_id, format!("tdx/{mr_td}"));
+    assert_eq!(mr_td.len(), 96);
+    assert!(extracted.claims.contains_key("rt_mr3"));
+
+    let err = extract_claims(TDX_MEDIA_TYPE, SGX_QUOTE).unwrap_err();
+    assert!(err.to_string().contains("does not match the quote"));
+    assert!(extract_claims(SGX_MEDIA_TYPE, &SGX_QUOTE[..100]).is_err());
+}
+
+#[test]
+fn validates_evidence_against_its_collateral() {
+    let report = validate_evidence_integrity(SGX_QUOTE, &sgx_collateral(), SGX_NOW).unwrap();
+    assert_eq!(report.status, TcbStatus::ConfigurationAndSwHardeningNeeded);
+
+    let mut forged = TDX_QUOTE.to_vec();
+    let offset = dcap_qvl::quote::Quote::layout(TDX_QUOTE)
+        .unwrap()
+        .report_data
+        .offset;
+    forged[offset] ^= 1;
+    assert!(validate_evidence_integrity(&forged, &tdx_collateral(), TDX_NOW).is_err());
+}
+
+#[test]
+fn decodes_endorsements() {
+    let json = format!(
+        r#"[{{"reference_id":"{SGX_REFERENCE_ID}","name":"sample"}},{{"reference_id":"tdx/00"}}]"#
+    );
+    let endorsements = decode_endorsements(json.as_bytes()).unwrap();
+    assert_eq!(endorsements.len(), 2);
+    assert_eq!(endorsements[0].name, "sample");
+    assert_eq!(endorsements[1].name, "");
+    assert!(decode_endorsements(br#"{"reference_id":"tdx/00"}"#).is_err());
+}
+
+#[test]
+fn appraises_verified_evidence() {
+    let report = validate_evidence_integrity(TDX_QUOTE, &tdx_collateral(), TDX_NOW).unwrap();
+    let tdx = ReferenceValue {
+        reference_id: extract_claims(TDX_MEDIA_TYPE, TDX_QUOTE)
+            .unwrap()
+            .reference_id,
+        name: "sample TD".into(),
+    };
+    let sgx = ReferenceValue {
+        reference_id: SGX_REFERENCE_ID.into(),
+        name: "sample enclave".into(),
+    };
+
+    let appraisal = appraise_evidence(&report, &[sgx.clone(), tdx.clone()]).unwrap();
+    assert_eq!(appraisal.status, TrustTier::Affirming);
+    assert_eq!(appraisal.matched, Some(tdx.clone()));
+    assert_eq!(appraisal.tcb_status, TcbStatus::UpToDate);
+
+    let appraisal = appraise_evidence(&report, &[sgx.clone()]).unwrap();
+    assert_eq!(appraisal.status, TrustTier::Contraindicated);
+    assert_eq!(appraisal.matched, None);
+
+    let mut revoked = report.clone();
+    revoked.status = TcbStatus::Revoked;
+    let appraisal = appraise_evidence(&revoked, &[tdx]).unwrap();
+    assert_eq!(appraisal.status, TrustTier::Contraindicated);
+
+    let report = validate_evidence_integrity(SGX_QUOTE, &sgx_collateral(), SGX_NOW).unwrap();
+    let appraisal = appraise_evidence(&report, &[sgx]).unwrap();
+    assert_eq!(appraisal.status, TrustTier::Warning);
+    assert_eq!(appraisal.advisory_ids, ["INTEL-SA-00289", "INTEL-SA-00615"]);
+}
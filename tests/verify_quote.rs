@@ -1,4 +1,14 @@
-use dcap_qvl::{quote::Quote, verify::verify, QuoteCollateralV3};
+use dcap_qvl::commitment::{commit, verify_proof, CollateralComponent};
+use dcap_qvl::{
+    pck::SgxType,
+    quote::{Anomaly, ClassifiedQuote, Quote, TeeType},
+    trace::VerifyStep,
+    verify::{
+        check_qe_identity_update, verify, verify_collateral, verify_qe_identity_update,
+        verify_traced, verify_with_options, AcceptExpiredCert, TcbStatus, VerifyOptions, Warning,
+    },
+    QuoteCollateralV3, QuoteCollateralV4,
+};
 use scale::Decode;
 
 #[test]
@@ -14,11 +24,15 @@ fn could_parse_sgx_quote() {
         serde_json::from_slice(raw_quote_collateral).expect("decodable");
     let tcb_status = verify(&raw_quote, &quote_collateral, now).expect("verify");
 
-    assert_eq!(tcb_status.status, "ConfigurationAndSWHardeningNeeded");
+    assert_eq!(
+        tcb_status.status,
+        TcbStatus::ConfigurationAndSwHardeningNeeded
+    );
     assert_eq!(
         tcb_status.advisory_ids,
         ["INTEL-SA-00289", "INTEL-SA-00615"]
     );
+    assert_eq!(tcb_status.warnings, [Warning::SwHardeningNeeded]);
 }
 
 #[test]
@@ -29,9 +43,706 @@ fn could_parse_tdx_quote() {
 
     let quote = Quote::decode(&mut &raw_quote[..]).unwrap();
     insta::assert_debug_snapshot!(quote);
+    assert!(!quote.header().is_sgx());
+    assert!(quote.report().as_td10().is_some());
+    assert_eq!(quote.tee_type(), TeeType::Tdx);
+    assert!(quote.as_tdx().is_some() && quote.as_sgx().is_none());
 
     let quote_collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
     let tcb_status = verify(raw_quote, &quote_collateral, now).unwrap();
-    assert_eq!(tcb_status.status, "UpToDate");
+    assert_eq!(tcb_status.status, TcbStatus::UpToDate);
     assert!(tcb_status.advisory_ids.is_empty());
+    assert!(tcb_status.platform_id.is_some());
+
+    assert_eq!(tcb_status.status(), TcbStatus::UpToDate);
+    let supplemental = tcb_status.supplemental().clone().unwrap();
+    assert_eq!(supplemental.tcb_evaluation_data_number, 17);
+    assert_eq!(supplemental.qe_identity_status, TcbStatus::UpToDate);
+    assert!(supplemental.tcb_date.is_some());
+    assert!(supplemental.earliest_expiration > now);
+}
+
+#[test]
+fn could_redact_tdx_quote() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+
+    let redacted = Quote::redact(raw_quote).unwrap();
+    assert_eq!(redacted.len(), raw_quote.len());
+
+    let quote = Quote::parse(&redacted).unwrap();
+    let td_report = quote.report.as_td10().unwrap();
+    assert_eq!(td_report.report_data, [0u8; 64]);
+    assert!(quote
+        .auth_data
+        .into_v3()
+        .qe_auth_data
+        .data
+        .iter()
+        .all(|b| *b == 0));
+}
+
+#[test]
+fn could_locate_quote_fields() {
+    for raw_quote in [
+        &include_bytes!("../sample/sgx_quote")[..],
+        &include_bytes!("../sample/tdx_quote")[..],
+    ] {
+        let quote = Quote::parse(raw_quote).unwrap();
+        let layout = Quote::layout(raw_quote).unwrap();
+
+        assert_eq!(layout.signed.end(), quote.signed_length());
+        assert_eq!(layout.ecdsa_signature.offset, quote.signed_length() + 4);
+        assert_eq!(
+            raw_quote[layout.report_data.range()],
+            *quote.report.report_data().as_bytes()
+        );
+        assert_eq!(
+            &raw_quote[layout.qe_auth_data.range()],
+            quote.qe_auth_data()
+        );
+        assert_eq!(
+            &raw_quote[layout.cert_data.range()],
+            quote.raw_cert_chain().unwrap()
+        );
+        let auth_data = quote.auth_data.into_v3();
+        assert_eq!(
+            raw_quote[layout.ecdsa_signature.range()],
+            auth_data.ecdsa_signature
+        );
+        assert_eq!(raw_quote[layout.qe_report.range()], auth_data.qe_report);
+        assert_eq!(layout.auth_data.end(), layout.cert_data.end());
+    }
+}
+
+#[test]
+fn could_report_quote_anomalies() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+
+    let anomalies = Quote::anomalies(raw_quote).unwrap();
+    assert!(anomalies.contains(&Anomaly::TrailingBytes { len: 70 }));
+}
+
+#[test]
+fn could_prove_collateral_components() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let quote_collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+    let report = verify(raw_quote, &quote_collateral, 1741852249).unwrap();
+
+    let commitment = &report.collateral_commitment;
+    for component in CollateralComponent::ALL {
+        let proof = commitment.proof(component);
+        let data = component.data(&quote_collateral);
+        assert!(verify_proof(&commitment.root, component, data, &proof));
+        assert!(!verify_proof(
+            &commitment.root,
+            component,
+            b"tampered",
+            &proof
+        ));
+    }
+
+    // The CRLs are committed, as empty leaves if absent
+    let mut with_crl = quote_collateral.clone();
+    with_crl.pck_crl = Some(b"crl".to_vec());
+    let committed = commit(&with_crl);
+    assert_ne!(committed.root, commitment.root);
+    let proof = committed.proof(CollateralComponent::PckCrl);
+    assert!(verify_proof(
+        &committed.root,
+        CollateralComponent::PckCrl,
+        b"crl",
+        &proof
+    ));
+    assert!(!verify_proof(
+        &commitment.root,
+        CollateralComponent::PckCrl,
+        b"crl",
+        &proof
+    ));
+}
+
+#[cfg(feature = "borsh")]
+#[test]
+fn could_borsh_roundtrip_quote() {
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let quote = Quote::parse(raw_quote).unwrap();
+
+    let encoded = borsh::to_vec(&quote).unwrap();
+    let decoded: Quote = borsh::from_slice(&encoded).unwrap();
+    assert_eq!(format!("{decoded:?}"), format!("{quote:?}"));
+}
+
+#[cfg(feature = "borsh")]
+#[test]
+fn could_borsh_roundtrip_verified_report() {
+    use dcap_qvl::verify::VerifiedReport;
+
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let quote_collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+    let report = verify(raw_quote, &quote_collateral, 1741852249).unwrap();
+
+    let encoded = borsh::to_vec(&report).unwrap();
+    let decoded: VerifiedReport = borsh::from_slice(&encoded).unwrap();
+    assert_eq!(format!("{decoded:?}"), format!("{report:?}"));
+}
+
+#[cfg(feature = "scale-codec")]
+#[test]
+fn could_scale_roundtrip_verified_report() {
+    use dcap_qvl::verify::VerifiedReport;
+    use scale::Encode;
+
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let quote_collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+    let report = verify(raw_quote, &quote_collateral, 1741852249).unwrap();
+
+    let encoded = report.encode();
+    let decoded = VerifiedReport::decode(&mut &encoded[..]).unwrap();
+    assert_eq!(format!("{decoded:?}"), format!("{report:?}"));
+}
+
+#[test]
+fn could_decode_collateral_encoded_before_crls() {
+    use scale::{DecodeAll, Encode};
+
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let quote_collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+    assert!(quote_collateral.pck_crl.is_some());
+    // The encoding of the six fields collateral had before CRLs
+    let old = (
+        &quote_collateral.tcb_info_issuer_chain,
+        &quote_collateral.tcb_info,
+        &quote_collateral.tcb_info_signature,
+        &quote_collateral.qe_identity_issuer_chain,
+        &quote_collateral.qe_identity,
+        &quote_collateral.qe_identity_signature,
+    )
+        .encode();
+    assert_eq!(quote_collateral.encode(), old);
+    let decoded = QuoteCollateralV3::decode_all(&mut &old[..]).unwrap();
+    assert_eq!(decoded.tcb_info, quote_collateral.tcb_info);
+    assert_eq!(decoded.pck_crl, None);
+    assert!(QuoteCollateralV4::decode_all(&mut &old[..]).is_err());
+
+    let encoded = QuoteCollateralV4::from(quote_collateral.clone()).encode();
+    assert!(encoded.starts_with(&old));
+    let decoded =
+        QuoteCollateralV3::from(QuoteCollateralV4::decode_all(&mut &encoded[..]).unwrap());
+    assert_eq!(decoded, quote_collateral);
+}
+
+#[test]
+fn could_abi_encode_evm_proof() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let quote_collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+
+    let (report, proof) = dcap_qvl::evm::prove(raw_quote, &quote_collateral, 1741852249).unwrap();
+    assert_eq!(proof.signed_quote, raw_quote[..proof.signed_quote.len()]);
+    assert_eq!(
+        proof.tcb_assertion,
+        dcap_qvl::evm::tcb_assertion(
+            &proof.pck_key,
+            &report.collateral_commitment.root,
+            report.status.as_str()
+        )
+    );
+    let other_key = [0x11; 64];
+    assert_ne!(
+        proof.tcb_assertion,
+        dcap_qvl::evm::tcb_assertion(
+            &other_key,
+            &report.collateral_commitment.root,
+            report.status.as_str()
+        )
+    );
+
+    // Decode every field as `abi.decode` in the contract does
+    let encoded = proof.abi_encode();
+    let word = |index: usize| &encoded[index * 32..(index + 1) * 32];
+    let bytes = |index: usize| {
+        let offset = abi_usize(word(index));
+        let len = abi_usize(&encoded[offset..offset + 32]);
+        &encoded[offset + 32..offset + 32 + len]
+    };
+    assert_eq!(bytes(0), proof.signed_quote);
+    assert_eq!(word(1), &proof.quote_signature[..32]);
+    assert_eq!(word(2), &proof.quote_signature[32..]);
+    assert_eq!(word(3), &proof.attestation_key[..32]);
+    assert_eq!(word(4), &proof.attestation_key[32..]);
+    assert_eq!(bytes(5), proof.qe_report);
+    assert_eq!(word(6), &proof.qe_report_signature[..32]);
+    assert_eq!(word(7), &proof.qe_report_signature[32..]);
+    assert_eq!(bytes(8), proof.qe_auth_data);
+    assert_eq!(word(9), &proof.pck_key[..32]);
+    assert_eq!(word(10), &proof.pck_key[32..]);
+    assert_eq!(word(11), proof.tcb_assertion);
+    // The dynamic fields follow the 12 head words in order, each padded to whole words
+    assert_eq!(abi_usize(word(0)), 12 * 32);
+    let padded = |len: usize| 32 + len.next_multiple_of(32);
+    let qe_auth_data_end = abi_usize(word(8)) + padded(proof.qe_auth_data.len());
+    assert_eq!(qe_auth_data_end, encoded.len());
+}
+
+fn abi_usize(word: &[u8]) -> usize {
+    assert!(word[..24].iter().all(|b| *b == 0));
+    u64::from_be_bytes(word[24..].try_into().unwrap()) as usize
+}
+
+#[test]
+fn could_export_zk_witness() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let quote_collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+
+    let (report, witness) =
+        dcap_qvl::zk::export_witness(raw_quote, &quote_collateral, 1741852249).unwrap();
+    assert_eq!(witness.collateral_root, report.collateral_commitment.root);
+    assert_eq!(witness.signatures.last().unwrap().label, "quote");
+    assert_eq!(
+        witness.to_field_elements().len(),
+        witness.signatures.len() * 10 + witness.hashes.len() * 2 + 6
+    );
+}
+
+#[cfg(feature = "interop")]
+#[test]
+fn could_map_maa_claims() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let now = 1741852249u64;
+
+    let quote_collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+    let report = verify(raw_quote, &quote_collateral, now).unwrap();
+    let claims = dcap_qvl::maa::claims(&report);
+
+    let td_report = report.report.as_td10().unwrap();
+    assert_eq!(claims["x-ms-attestation-type"], "tdx");
+    assert_eq!(claims["x-ms-tdx-mrtd"], hex::encode(td_report.mr_td));
+    assert_eq!(claims["x-ms-tcb-status"], "UpToDate");
+}
+
+#[test]
+fn could_require_crls() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let now = 1741852249u64;
+
+    let quote_collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+    let options = VerifyOptions {
+        require_crls: true,
+        ..Default::default()
+    };
+    let err = verify_with_options(raw_quote, &quote_collateral, now, &options).unwrap_err();
+    assert!(err.to_string().contains("CRL is required"));
+}
+
+#[test]
+fn could_require_tcb_evaluation_data_number() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let now = 1741852249u64;
+
+    let quote_collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+    let options = |min| VerifyOptions {
+        min_tcb_evaluation_data_number: Some(min),
+        ..Default::default()
+    };
+    verify_with_options(raw_quote, &quote_collateral, now, &options(17)).unwrap();
+    let err = verify_with_options(raw_quote, &quote_collateral, now, &options(18)).unwrap_err();
+    assert!(err.to_string().contains("Collateral too old for policy"));
+}
+
+#[test]
+fn could_accept_expired_certificate() {
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/sgx_quote_collateral.json");
+    let quote_collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+    // The TCB signing certificate of the sample collateral expired at 1747824610
+    let now = 1750000000u64;
+    let fingerprint = "ec37d98107bfb7dab6ff8de4d4dda6fde30a6f50b0d7b03eefb5652f1e652969";
+
+    assert!(verify(raw_quote, &quote_collateral, now).is_err());
+    let accepted: [u8; 32] = hex::decode(fingerprint).unwrap().try_into().unwrap();
+    let options = VerifyOptions {
+        accept_expired_cert: Some(AcceptExpiredCert::fingerprints([accepted])),
+        ..Default::default()
+    };
+    let report = verify_with_options(raw_quote, &quote_collateral, now, &options).unwrap();
+    let accepted = Warning::ExpiredCertificateAccepted {
+        fingerprint: fingerprint.into(),
+        expired_for: now - 1747824610,
+    };
+    assert_eq!(
+        report.warnings.iter().filter(|w| **w == accepted).count(),
+        1
+    );
+}
+
+#[test]
+fn could_restrict_verification_with_options() {
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/sgx_quote_collateral.json");
+    let quote_collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+    let now = 1699301000u64;
+
+    let statuses = |allowed: &[TcbStatus]| VerifyOptions {
+        allowed_tcb_statuses: Some(allowed.to_vec()),
+        ..Default::default()
+    };
+    let err = verify_with_options(
+        raw_quote,
+        &quote_collateral,
+        now,
+        &statuses(&[TcbStatus::UpToDate]),
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("is not allowed"));
+    let allowed = statuses(&[TcbStatus::ConfigurationAndSwHardeningNeeded]);
+    verify_with_options(raw_quote, &quote_collateral, now, &allowed).unwrap();
+
+    // The collateral's earliest nextUpdate is 2023-12-21T00:06:09Z
+    let grace_period = |seconds| VerifyOptions {
+        collateral_grace_period: Some(seconds),
+        ..Default::default()
+    };
+    let stale = 1703117169 + 100;
+    verify_with_options(raw_quote, &quote_collateral, stale, &grace_period(200)).unwrap();
+    let err =
+        verify_with_options(raw_quote, &quote_collateral, stale, &grace_period(50)).unwrap_err();
+    assert!(err.to_string().contains("beyond the grace period"));
+
+    let chain = pem::parse_many(&quote_collateral.tcb_info_issuer_chain).unwrap();
+    let root_ca = |cert: &pem::Pem| VerifyOptions {
+        root_ca: Some(cert.contents().to_vec()),
+        ..Default::default()
+    };
+    verify_with_options(raw_quote, &quote_collateral, now, &root_ca(&chain[1])).unwrap();
+    assert!(verify_with_options(raw_quote, &quote_collateral, now, &root_ca(&chain[0])).is_err());
+}
+
+#[test]
+fn could_check_report_data() {
+    let raw_quote = include_bytes!("../sample/sgx_quote");
+    let raw_quote_collateral = include_bytes!("../sample/sgx_quote_collateral.json");
+    let quote_collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+    let report = verify(raw_quote, &quote_collateral, 1699301000).unwrap();
+
+    report.verify_report_data(b"Hello, world!").unwrap();
+    assert!(report.verify_report_data(b"Hello").is_err());
+    assert!(report.verify_report_data(&[0; 65]).is_err());
+    let report_data = report.report.report_data();
+    assert!(report_data.expect_sha256(b"Hello, world!").is_err());
+    assert!(report_data.expect_sha512(b"Hello, world!").is_err());
+}
+
+#[test]
+fn tcb_status_orders_and_round_trips() {
+    assert!(TcbStatus::UpToDate > TcbStatus::SwHardeningNeeded);
+    assert!(TcbStatus::ConfigurationNeeded > TcbStatus::OutOfDate);
+    assert!(TcbStatus::OutOfDate > TcbStatus::Revoked);
+    for status in [
+        "UpToDate",
+        "SWHardeningNeeded",
+        "ConfigurationAndSWHardeningNeeded",
+    ] {
+        let parsed: TcbStatus = status.parse().unwrap();
+        assert_eq!(parsed.to_string(), status);
+        assert_eq!(
+            serde_json::to_string(&parsed).unwrap(),
+            format!("\"{status}\"")
+        );
+    }
+    assert!("Bogus".parse::<TcbStatus>().is_err());
+}
+
+#[test]
+fn identifier_newtypes_round_trip() {
+    use dcap_qvl::types::{Fmspc, Measurement};
+
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let quote = Quote::parse(raw_quote).unwrap();
+    let fmspc = quote.fmspc().unwrap();
+    assert_eq!(fmspc.to_string().parse::<Fmspc>().unwrap(), fmspc);
+    assert_eq!(
+        serde_json::to_string(&fmspc).unwrap(),
+        format!("\"{fmspc}\"")
+    );
+    assert!("00906ED500".parse::<Fmspc>().is_err());
+
+    let measurement = quote.report.measurement();
+    assert_eq!(measurement.as_bytes().len(), 48);
+    let parsed: Measurement = measurement.to_string().parse().unwrap();
+    assert_eq!(parsed, measurement);
+}
+
+#[test]
+fn could_parse_pck_extension() {
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let quote = Quote::parse(raw_quote).unwrap();
+    let pck = quote.pck_extension().unwrap();
+    assert_eq!(pck.fmspc, quote.fmspc().unwrap());
+    assert_eq!(pck.pce_id, [0, 0]);
+    assert_eq!(&pck.tcb_components, pck.cpu_svn.as_bytes());
+    assert_eq!(pck.sgx_type, SgxType::Scalable);
+    assert!(pck.is_multi_package());
+    assert_eq!(pck.configuration.unwrap().smt_enabled, Some(true));
+}
+
+#[test]
+fn could_detect_corrupted_collateral() {
+    let raw_quote_collateral = include_bytes!("../sample/tdx_quote_collateral.json");
+    let now = 1741852249u64;
+
+    let mut quote_collateral: QuoteCollateralV3 =
+        serde_json::from_slice(raw_quote_collateral).unwrap();
+    verify_collateral(&quote_collateral, now).unwrap();
+    quote_collateral.tcb_info.push(' ');
+    assert!(verify_collateral(&quote_collateral, now).is_err());
+}
+
+#[test]
+fn rejects_regressive_qe_identity_updates() {
+    let raw_quote_collateral = include_bytes!("../sample/sgx_quote_collateral.json");
+    let now = 1699301000u64;
+
+    let quote_collateral: QuoteCollateralV3 = serde_json::from_slice(raw_quote_collateral).unwrap();
+    verify_qe_identity_update(&quote_collateral, &quote_collateral, now).unwrap();
+
+    let previous = &quote_collateral.qe_identity;
+    let update = |edit: fn(&mut serde_json::Value)| {
+        let mut identity: serde_json::Value = serde_json::from_str(previous).unwrap();
+        edit(&mut identity);
+        check_qe_identity_update(previous, &identity.to_string())
+    };
+    update(|identity| identity["tcbEvaluationDataNumber"] = serde_json::json!(17)).unwrap();
+    let err = update(|identity| identity["mrsigner"] = "00".repeat(32).into()).unwrap_err();
+    assert!(err.to_string().contains("MRSIGNER changed"));
+    let err =
+        update(|identity| identity["tcbEvaluationDataNumber"] = serde_json::json!(15)).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("tcbEvaluationDataNumber rolled back"));
+    let err = update(|identity| {
+        for level in identity["tcbLevels"].as_array_mut().unwrap() {
+            level["tcb"]["isvsvn"] = serde_json::json!(1);
+        }
+    })
+    .unwrap_err();
+    assert!(err.to_string().contains("ISVSVN rolled back"));
+
+    let mut tampered = quote_collateral.clone();
+    tampered.qe_identity = tampered.qe_identity.replace("UpToDate", "OutOfDate");
+    assert!(verify_qe_identity_update(&quote_collateral, &tampered, now).is_err());
+}
+
+#[test]
+fn traces_the_failed_step() {
+    let mut raw_quote = include_bytes!("../sample/sgx_quote").to_vec();
+    let quote_collateral: QuoteCollateralV3 =
+        serde_json::from_slice(include_bytes!("../sample/sgx_quote_collateral.json")).unwrap();
+    let now = 1699301000u64;
+
+    let (result, trace) = verify_traced(
+        &raw_quote,
+        &quote_collateral,
+        now,
+        &VerifyOptions::default(),
+    );
+    result.unwrap();
+    assert_eq!(trace.failed_step, None);
+    assert_eq!(trace.steps.len(), 11);
+    assert!(trace.steps.iter().all(|step| step.passed));
+    assert!(trace.steps.iter().all(|step| step.duration_us.is_some()));
+
+    // Corrupt the ECDSA signature over the quote
+    raw_quote[440] ^= 1;
+    let (result, trace) = verify_traced(
+        &raw_quote,
+        &quote_collateral,
+        now,
+        &VerifyOptions::default(),
+    );
+    assert!(result.is_err());
+    assert_eq!(trace.failed_step, Some(VerifyStep::QuoteSignature));
+    let last = trace.steps.last().unwrap();
+    assert_eq!(last.step, VerifyStep::QuoteSignature);
+    assert!(!last.passed);
+}
+
+#[test]
+fn could_classify_unknown_tee_quote() {
+    let mut raw_quote = include_bytes!("../sample/tdx_quote").to_vec();
+    assert!(matches!(
+        Quote::classify(&raw_quote).unwrap(),
+        ClassifiedQuote::Known(_)
+    ));
+
+    raw_quote[4..8].copy_from_slice(&0x99u32.to_le_bytes());
+    assert!(Quote::parse(&raw_quote).is_err());
+    let ClassifiedQuote::UnknownTee(unknown) = Quote::classify(&raw_quote).unwrap() else {
+        panic!("expected an unknown TEE");
+    };
+    assert_eq!(unknown.tee_type, 0x99);
+    assert_eq!(unknown.header, raw_quote[..48]);
+    assert_eq!(unknown.body, raw_quote[48..]);
+}
+
+#[cfg(feature = "interop")]
+#[test]
+fn could_interpret_qe_auth_data() {
+    use dcap_qvl::qe_auth::QeAuthDataRegistry;
+
+    let quote = Quote::parse(include_bytes!("../sample/tdx_quote")).unwrap();
+    assert_eq!(quote.qe_auth_data(), (0u8..32).collect::<Vec<_>>());
+
+    assert!(QeAuthDataRegistry::new().interpret(&quote).is_none());
+    let interpreted = QeAuthDataRegistry::with_defaults()
+        .interpret(&quote)
+        .unwrap()
+        .unwrap();
+    assert_eq!(interpreted["default"], true);
+
+    let mut registry = QeAuthDataRegistry::with_defaults();
+    registry.register(quote.header.qe_vendor_id, |data: &[u8]| {
+        Ok(serde_json::json!(data.len()))
+    });
+    assert_eq!(registry.interpret(&quote).unwrap().unwrap(), 32);
+}
+
+#[cfg(feature = "platform-families")]
+#[test]
+fn could_name_platform_family() {
+    use dcap_qvl::platform_family::{platform_family, PlatformFamilies};
+    use dcap_qvl::types::Fmspc;
+
+    let fmspc: Fmspc = "B0C06F000000".parse().unwrap();
+    assert_eq!(platform_family(&fmspc), Some("Emerald Rapids Xeon SP"));
+
+    let unknown: Fmspc = "00123F000000".parse().unwrap();
+    let mut families = PlatformFamilies::builtin();
+    assert_eq!(families.family(&unknown), None);
+    families
+        .extend_from_json(r#"{"123F": "Future Lake"}"#)
+        .unwrap();
+    assert_eq!(families.family(&unknown), Some("Future Lake"));
+}
+
+#[test]
+fn collateral_json_round_trips() {
+    let json = include_str!("../sample/sgx_quote_collateral.json");
+    let collateral = QuoteCollateralV3::from_json(json).unwrap();
+    assert!(collateral.pck_crl.is_some());
+
+    let encoded = collateral.to_json().unwrap();
+    let value: serde_json::Value = serde_json::from_str(&encoded).unwrap();
+    assert_eq!(
+        value["tcb_info_signature"],
+        hex::encode(&collateral.tcb_info_signature)
+    );
+    assert_eq!(QuoteCollateralV3::from_json(&encoded).unwrap(), collateral);
+}
+
+#[test]
+fn uniform_errors_hide_the_cause() {
+    use dcap_qvl::uniform::{verify_uniform, FailureCategory};
+
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let collateral =
+        QuoteCollateralV3::from_json(include_str!("../sample/tdx_quote_collateral.json")).unwrap();
+    let now = 1741852249;
+    let options = VerifyOptions::default();
+    verify_uniform(raw_quote, &collateral, now, &options).unwrap();
+
+    let err = verify_uniform(&raw_quote[..100], &collateral, now, &options).unwrap_err();
+    assert_eq!(err.category(), FailureCategory::Malformed);
+    assert_eq!(err.to_string(), "Malformed quote");
+
+    let err = verify_uniform(raw_quote, &collateral, 4102444800, &options).unwrap_err();
+    assert_eq!(err.category(), FailureCategory::Rejected);
+    assert_eq!(err.to_string(), "Quote rejected");
+    assert!(!format!("{err:?}").contains("certificate"));
+    assert!(format!("{:#}", err.trace()).contains("certificate"));
+
+    let mut forged = raw_quote.to_vec();
+    forged[Quote::layout(raw_quote).unwrap().report_data.offset] ^= 1;
+    let err = verify_uniform(&forged, &collateral, now, &options).unwrap_err();
+    assert_eq!(err.category(), FailureCategory::Rejected);
+
+    // Rejected by the options
+    let options = VerifyOptions {
+        allowed_tcb_statuses: Some(vec![TcbStatus::SwHardeningNeeded]),
+        ..Default::default()
+    };
+    let err = verify_uniform(raw_quote, &collateral, now, &options).unwrap_err();
+    assert_eq!(err.category(), FailureCategory::Rejected);
+    assert_eq!(err.to_string(), "Quote rejected");
+    let options = VerifyOptions {
+        require_crls: true,
+        ..Default::default()
+    };
+    let err = verify_uniform(raw_quote, &collateral, now, &options).unwrap_err();
+    assert_eq!(err.category(), FailureCategory::Rejected);
+}
+
+#[test]
+fn uniform_failures_check_as_many_signatures() {
+    use dcap_qvl::signature::{RingVerifier, SignatureVerifier};
+    use dcap_qvl::uniform::verify_uniform_with_signature_verifier;
+    use std::cell::Cell;
+
+    /// Counts the signatures checked, and those on the dummy key, the P-256 base point
+    #[derive(Default)]
+    struct Counting {
+        calls: Cell<usize>,
+        dummy: Cell<usize>,
+    }
+
+    impl SignatureVerifier for Counting {
+        fn verify_p256_sha256(
+            &self,
+            public_key: &[u8; 64],
+            message: &[u8],
+            signature: &[u8; 64],
+        ) -> anyhow::Result<()> {
+            self.calls.set(self.calls.get() + 1);
+            if public_key.starts_with(&[0x6b, 0x17, 0xd1, 0xf2]) {
+                self.dummy.set(self.dummy.get() + 1);
+            }
+            RingVerifier.verify_p256_sha256(public_key, message, signature)
+        }
+    }
+
+    let calls = |raw_quote: &[u8], now: u64| {
+        let collateral =
+            QuoteCollateralV3::from_json(include_str!("../sample/tdx_quote_collateral.json"))
+                .unwrap();
+        let verifier = Counting::default();
+        let _ = verify_uniform_with_signature_verifier(
+            raw_quote,
+            &collateral,
+            now,
+            &VerifyOptions::default(),
+            &verifier,
+        );
+        (verifier.calls.get(), verifier.dummy.get())
+    };
+    let raw_quote = include_bytes!("../sample/tdx_quote");
+    let now = 1741852249;
+    let valid = 4;
+    assert_eq!(calls(raw_quote, now), (valid, 0));
+
+    let mut forged = raw_quote.to_vec();
+    forged[Quote::layout(raw_quote).unwrap().report_data.offset] ^= 1;
+    assert_eq!(calls(&forged, now), (valid, 0));
+    // Padded on the dummy key, with the certificate chain signatures webpki checks too
+    let (malformed, dummy) = calls(&raw_quote[..100], now);
+    assert!(malformed > valid);
+    assert!(dummy > 0);
+    let (expired, dummy) = calls(raw_quote, 4102444800);
+    assert!(expired > valid);
+    assert!(dummy > 0);
 }